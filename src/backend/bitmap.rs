@@ -0,0 +1,569 @@
+use super::scan::Entry;
+use super::{map::bytes_mut, Backend, BackendError, Value};
+use crate::BulkString;
+
+// bitmaps are plain strings, bit 0 is the most significant bit of the first byte
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// Unit of the start / end range of BITCOUNT and BITPOS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitUnit {
+    Byte,
+    Bit,
+}
+
+/// Integer type of a BITFIELD sub-command, e.g. i5 or u16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOverflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitFieldOp {
+    Get {
+        ty: BitFieldType,
+        offset: usize,
+    },
+    Set {
+        ty: BitFieldType,
+        offset: usize,
+        value: i64,
+        overflow: BitFieldOverflow,
+    },
+    IncrBy {
+        ty: BitFieldType,
+        offset: usize,
+        increment: i64,
+        overflow: BitFieldOverflow,
+    },
+}
+
+impl Backend {
+    pub fn setbit(&self, key: Vec<u8>, offset: usize, bit: bool) -> Result<u8, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let mut entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::String(BulkString::new(Vec::new()).into()));
+
+        let data = bytes_mut(entry.value_mut())?;
+        let byte = offset >> 3;
+        if data.len() <= byte {
+            data.resize(byte + 1, 0);
+        }
+        let mask = 1 << (7 - (offset & 7));
+        let old = (data[byte] & mask != 0) as u8;
+        if bit {
+            data[byte] |= mask;
+        } else {
+            data[byte] &= !mask;
+        }
+        Ok(old)
+    }
+
+    pub fn getbit(&self, key: &[u8], offset: usize) -> Result<u8, BackendError> {
+        let data = self.get_bytes(key)?;
+        Ok(read_bit(&data, offset))
+    }
+
+    pub fn bitcount(
+        &self,
+        key: &[u8],
+        range: Option<(i64, i64, BitUnit)>,
+    ) -> Result<i64, BackendError> {
+        let data = self.get_bytes(key)?;
+        let (start, end) = match range {
+            Some((start, end, unit)) => match bit_range(data.len(), start, end, unit) {
+                Some(range) => range,
+                None => return Ok(0),
+            },
+            None => (0, data.len() * 8),
+        };
+        Ok(count_bits(&data, start, end) as i64)
+    }
+
+    /// Position of the first bit set to `bit`, -1 if there is none.
+    /// Looking for a clear bit without an explicit end considers the string zero-padded on the right.
+    pub fn bitpos(
+        &self,
+        key: &[u8],
+        bit: bool,
+        start: Option<i64>,
+        end: Option<i64>,
+        unit: BitUnit,
+    ) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        if !self.keyspace.contains_key(key) {
+            return Ok(if bit { -1 } else { 0 });
+        }
+
+        let data = self.get_bytes(key)?;
+        let total = match unit {
+            BitUnit::Byte => data.len() as i64,
+            BitUnit::Bit => data.len() as i64 * 8,
+        };
+        let (first, last) = match bit_range(
+            data.len(),
+            start.unwrap_or(0),
+            end.unwrap_or(total - 1),
+            unit,
+        ) {
+            Some(range) => range,
+            None => return Ok(-1),
+        };
+
+        match (first..last).find(|i| read_bit(&data, *i) == bit as u8) {
+            Some(pos) => Ok(pos as i64),
+            None if !bit && end.is_none() => Ok(last as i64),
+            None => Ok(-1),
+        }
+    }
+
+    /// Store the result of the operation in `dest`, returns the length of the stored string.
+    pub fn bitop(
+        &self,
+        op: BitOperation,
+        dest: Vec<u8>,
+        keys: &[Vec<u8>],
+    ) -> Result<usize, BackendError> {
+        let _guard = self.locks.write(keys.iter().chain([&dest]));
+        let values = keys
+            .iter()
+            .map(|key| self.get_bytes(key))
+            .collect::<Result<Vec<Vec<u8>>, BackendError>>()?;
+
+        let len = values.iter().map(|v| v.len()).max().unwrap_or(0);
+        let byte = |v: &Vec<u8>, i: usize| v.get(i).copied().unwrap_or(0);
+        let result = (0..len)
+            .map(|i| {
+                let mut bytes = values.iter().map(|v| byte(v, i));
+                let first = bytes.next().unwrap_or(0);
+                match op {
+                    BitOperation::And => bytes.fold(first, |acc, b| acc & b),
+                    BitOperation::Or => bytes.fold(first, |acc, b| acc | b),
+                    BitOperation::Xor => bytes.fold(first, |acc, b| acc ^ b),
+                    BitOperation::Not => !first,
+                }
+            })
+            .collect::<Vec<u8>>();
+
+        self.expire.remove(&dest);
+        if result.is_empty() {
+            self.keyspace.remove(&dest);
+        } else {
+            self.keyspace
+                .insert(dest, Value::String(BulkString::new(result).into()));
+        }
+        Ok(len)
+    }
+
+    /// Run the sub-commands in order, None is returned for a write refused by OVERFLOW FAIL.
+    pub fn bitfield(
+        &self,
+        key: Vec<u8>,
+        ops: &[BitFieldOp],
+    ) -> Result<Vec<Option<i64>>, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+
+        let read_only = ops.iter().all(|op| matches!(op, BitFieldOp::Get { .. }));
+        if read_only {
+            let data = self.get_bytes(&key)?;
+            return Ok(ops
+                .iter()
+                .map(|op| match op {
+                    BitFieldOp::Get { ty, offset } => Some(ty.read(&data, *offset)),
+                    _ => None,
+                })
+                .collect());
+        }
+
+        let mut entry = match self.keyspace.entry(key) {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(Value::String(BulkString::new(Vec::new()).into())),
+        };
+        let data = bytes_mut(entry.value_mut())?;
+
+        let results = ops
+            .iter()
+            .map(|op| match *op {
+                BitFieldOp::Get { ty, offset } => Some(ty.read(data, offset)),
+                BitFieldOp::Set {
+                    ty,
+                    offset,
+                    value,
+                    overflow,
+                } => {
+                    let old = ty.read(data, offset);
+                    let value = ty.overflow(value as i128, overflow)?;
+                    ty.write(data, offset, value);
+                    Some(old)
+                }
+                BitFieldOp::IncrBy {
+                    ty,
+                    offset,
+                    increment,
+                    overflow,
+                } => {
+                    let old = ty.read(data, offset);
+                    let value = ty.overflow(old as i128 + increment as i128, overflow)?;
+                    ty.write(data, offset, value);
+                    Some(value)
+                }
+            })
+            .collect();
+        Ok(results)
+    }
+}
+
+impl BitFieldType {
+    fn min(&self) -> i128 {
+        if self.signed {
+            -(1i128 << (self.bits - 1))
+        } else {
+            0
+        }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed {
+            (1i128 << (self.bits - 1)) - 1
+        } else {
+            (1i128 << self.bits) - 1
+        }
+    }
+
+    fn read(&self, data: &[u8], offset: usize) -> i64 {
+        let mut value = 0u64;
+        for i in 0..self.bits as usize {
+            value = (value << 1) | read_bit(data, offset + i) as u64;
+        }
+        if self.signed && self.bits < 64 && value >> (self.bits - 1) & 1 == 1 {
+            // sign extend
+            value |= u64::MAX << self.bits;
+        }
+        value as i64
+    }
+
+    fn write(&self, data: &mut Vec<u8>, offset: usize, value: i64) {
+        let last_byte = (offset + self.bits as usize - 1) >> 3;
+        if data.len() <= last_byte {
+            data.resize(last_byte + 1, 0);
+        }
+        for i in 0..self.bits as usize {
+            let bit = (value as u64 >> (self.bits as usize - 1 - i)) & 1;
+            let pos = offset + i;
+            let mask = 1 << (7 - (pos & 7));
+            if bit == 1 {
+                data[pos >> 3] |= mask;
+            } else {
+                data[pos >> 3] &= !mask;
+            }
+        }
+    }
+
+    // fit the value into the type according to the overflow policy, None means FAIL
+    fn overflow(&self, value: i128, overflow: BitFieldOverflow) -> Option<i64> {
+        if value >= self.min() && value <= self.max() {
+            return Some(value as i64);
+        }
+        match overflow {
+            BitFieldOverflow::Wrap => {
+                let mut wrapped = value & ((1i128 << self.bits) - 1);
+                if self.signed && wrapped > self.max() {
+                    wrapped -= 1i128 << self.bits;
+                }
+                Some(wrapped as i64)
+            }
+            BitFieldOverflow::Sat => Some(if value > self.max() {
+                self.max() as i64
+            } else {
+                self.min() as i64
+            }),
+            BitFieldOverflow::Fail => None,
+        }
+    }
+}
+
+// popcount of the bits in [start, end), whole bytes at a time where possible
+fn count_bits(data: &[u8], start: usize, end: usize) -> usize {
+    let mut count = 0;
+    let mut i = start;
+    while i < end {
+        if i & 7 == 0 && i + 8 <= end {
+            count += data[i >> 3].count_ones() as usize;
+            i += 8;
+        } else {
+            count += read_bit(data, i) as usize;
+            i += 1;
+        }
+    }
+    count
+}
+
+fn read_bit(data: &[u8], offset: usize) -> u8 {
+    match data.get(offset >> 3) {
+        Some(byte) => (byte >> (7 - (offset & 7))) & 1,
+        None => 0,
+    }
+}
+
+// resolve an inclusive start / end range (negative values count from the end) into a
+// half-open range of bit offsets, None if the range is empty
+fn bit_range(len: usize, start: i64, end: i64, unit: BitUnit) -> Option<(usize, usize)> {
+    let total = match unit {
+        BitUnit::Byte => len as i64,
+        BitUnit::Bit => len as i64 * 8,
+    };
+    if (start < 0 && end < 0 && start > end) || total == 0 {
+        return None;
+    }
+    let start = if start < 0 {
+        (total + start).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 {
+        (total + end).max(0)
+    } else {
+        end.min(total - 1)
+    };
+    if start > end {
+        return None;
+    }
+    match unit {
+        BitUnit::Byte => Some((start as usize * 8, (end as usize + 1) * 8)),
+        BitUnit::Bit => Some((start as usize, end as usize + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn i(bits: u32) -> BitFieldType {
+        BitFieldType { signed: true, bits }
+    }
+
+    fn u(bits: u32) -> BitFieldType {
+        BitFieldType {
+            signed: false,
+            bits,
+        }
+    }
+
+    #[test]
+    fn test_setbit_getbit() {
+        let backend = Backend::new();
+        assert_eq!(backend.setbit(b"bits".to_vec(), 7, true), Ok(0));
+        assert_eq!(backend.setbit(b"bits".to_vec(), 7, true), Ok(1));
+        assert_eq!(backend.getbit(b"bits", 7), Ok(1));
+        assert_eq!(backend.getbit(b"bits", 6), Ok(0));
+        assert_eq!(backend.getbit(b"bits", 100), Ok(0));
+        assert_eq!(
+            backend.get(b"bits"),
+            Ok(Some(BulkString::from(b"\x01").into()))
+        );
+
+        assert_eq!(backend.setbit(b"bits".to_vec(), 7, false), Ok(1));
+        assert_eq!(
+            backend.get(b"bits"),
+            Ok(Some(BulkString::from(b"\x00").into()))
+        );
+    }
+
+    #[test]
+    fn test_bitcount() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("foobar").into());
+        assert_eq!(backend.bitcount(b"key", None), Ok(26));
+        assert_eq!(backend.bitcount(b"key", Some((0, 0, BitUnit::Byte))), Ok(4));
+        assert_eq!(backend.bitcount(b"key", Some((1, 1, BitUnit::Byte))), Ok(6));
+        assert_eq!(backend.bitcount(b"key", Some((1, 1, BitUnit::Bit))), Ok(1));
+        assert_eq!(
+            backend.bitcount(b"key", Some((5, 30, BitUnit::Bit))),
+            Ok(17)
+        );
+        assert_eq!(
+            backend.bitcount(b"key", Some((-2, -1, BitUnit::Byte))),
+            Ok(7)
+        );
+        assert_eq!(backend.bitcount(b"missing", None), Ok(0));
+    }
+
+    #[test]
+    fn test_bitpos() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from(b"\xff\xf0\x00").into());
+        assert_eq!(
+            backend.bitpos(b"key", false, None, None, BitUnit::Byte),
+            Ok(12)
+        );
+        assert_eq!(
+            backend.bitpos(b"key", true, Some(2), None, BitUnit::Byte),
+            Ok(-1)
+        );
+        assert_eq!(
+            backend.bitpos(b"key", true, Some(2), Some(-1), BitUnit::Byte),
+            Ok(-1)
+        );
+        assert_eq!(
+            backend.bitpos(b"key", true, Some(7), Some(15), BitUnit::Bit),
+            Ok(7)
+        );
+
+        backend.set(b"ones".to_vec(), BulkString::from(b"\xff\xff").into());
+        assert_eq!(
+            backend.bitpos(b"ones", false, None, None, BitUnit::Byte),
+            Ok(16)
+        );
+        assert_eq!(
+            backend.bitpos(b"ones", false, Some(0), Some(-1), BitUnit::Byte),
+            Ok(-1)
+        );
+
+        assert_eq!(
+            backend.bitpos(b"missing", false, None, None, BitUnit::Byte),
+            Ok(0)
+        );
+        assert_eq!(
+            backend.bitpos(b"missing", true, None, None, BitUnit::Byte),
+            Ok(-1)
+        );
+    }
+
+    #[test]
+    fn test_bitop() {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("foobar").into());
+        backend.set(b"key2".to_vec(), BulkString::from("abcdef").into());
+
+        let keys = [b"key1".to_vec(), b"key2".to_vec()];
+        assert_eq!(
+            backend.bitop(BitOperation::And, b"dest".to_vec(), &keys),
+            Ok(6)
+        );
+        assert_eq!(
+            backend.get(b"dest"),
+            Ok(Some(BulkString::from("`bc`ab").into()))
+        );
+
+        assert_eq!(
+            backend.bitop(BitOperation::Not, b"dest".to_vec(), &keys[..1]),
+            Ok(6)
+        );
+        assert_eq!(
+            backend.get(b"dest"),
+            Ok(Some(BulkString::from(b"\x99\x90\x90\x9d\x9e\x8d").into()))
+        );
+
+        assert_eq!(
+            backend.bitop(BitOperation::Or, b"dest".to_vec(), &[b"missing".to_vec()]),
+            Ok(0)
+        );
+        assert_eq!(backend.get(b"dest"), Ok(None));
+    }
+
+    #[test]
+    fn test_bitfield() {
+        let backend = Backend::new();
+        let ops = [
+            BitFieldOp::IncrBy {
+                ty: i(5),
+                offset: 100,
+                increment: 1,
+                overflow: BitFieldOverflow::Wrap,
+            },
+            BitFieldOp::Get {
+                ty: u(4),
+                offset: 0,
+            },
+        ];
+        assert_eq!(
+            backend.bitfield(b"key".to_vec(), &ops),
+            Ok(vec![Some(1), Some(0)])
+        );
+
+        let ops = [BitFieldOp::Set {
+            ty: i(8),
+            offset: 0,
+            value: -100,
+            overflow: BitFieldOverflow::Wrap,
+        }];
+        assert_eq!(backend.bitfield(b"key".to_vec(), &ops), Ok(vec![Some(0)]));
+        let ops = [
+            BitFieldOp::Get {
+                ty: i(8),
+                offset: 0,
+            },
+            BitFieldOp::Get {
+                ty: u(8),
+                offset: 0,
+            },
+        ];
+        assert_eq!(
+            backend.bitfield(b"key".to_vec(), &ops),
+            Ok(vec![Some(-100), Some(156)])
+        );
+    }
+
+    #[test]
+    fn test_bitfield_overflow() {
+        let backend = Backend::new();
+        let incr = |overflow| BitFieldOp::IncrBy {
+            ty: u(2),
+            offset: 102,
+            increment: 1,
+            overflow,
+        };
+
+        let results = (0..4)
+            .map(|_| {
+                backend
+                    .bitfield(
+                        b"key".to_vec(),
+                        &[incr(BitFieldOverflow::Wrap), incr(BitFieldOverflow::Sat)],
+                    )
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            vec![
+                vec![Some(1), Some(2)],
+                vec![Some(3), Some(3)],
+                vec![Some(0), Some(1)],
+                vec![Some(2), Some(3)],
+            ]
+        );
+
+        assert_eq!(
+            backend.bitfield(b"key".to_vec(), &[incr(BitFieldOverflow::Fail)]),
+            Ok(vec![None])
+        );
+
+        let ops = [BitFieldOp::IncrBy {
+            ty: i(8),
+            offset: 0,
+            increment: 200,
+            overflow: BitFieldOverflow::Sat,
+        }];
+        assert_eq!(backend.bitfield(b"key".to_vec(), &ops), Ok(vec![Some(127)]));
+    }
+}
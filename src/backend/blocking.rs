@@ -0,0 +1,370 @@
+use super::Backend;
+use crate::RespFrame;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// Try to serve a blocked client from one of its keys, None if the key holds nothing for it.
+pub type ServeFn = Box<dyn Fn(&Backend, &[u8]) -> Option<RespFrame> + Send + Sync>;
+
+// A client blocked on its keys. It is queued on every key, and whoever serves it first takes
+// the sender: the mutex makes the serve and the take one step, so it is served only once.
+struct Waiter {
+    keys: Vec<Vec<u8>>,
+    serve: ServeFn,
+    sender: Mutex<Option<oneshot::Sender<RespFrame>>>,
+}
+
+/// Clients blocked on keys (BLPOP and friends), one FIFO queue per key.
+///
+/// Blocked clients never serve themselves once queued: a write that may unblock them calls
+/// `wake`, which serves the queue of the key in order. Serving runs without any key lock or
+/// the registry lock held, the serve function takes the locks it needs like any command.
+#[derive(Default)]
+pub(crate) struct Waiters {
+    queues: Mutex<HashMap<Vec<u8>, VecDeque<Arc<Waiter>>>>,
+}
+
+impl std::fmt::Debug for Waiters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys = self.queues.lock().map(|queues| queues.len()).unwrap_or(0);
+        f.debug_struct("Waiters").field("keys", &keys).finish()
+    }
+}
+
+// removes its waiter from every queue once the command is done, whether it was served, timed
+// out, or the connection was dropped while blocked
+struct WaiterGuard<'a> {
+    backend: &'a Backend,
+    waiter: Arc<Waiter>,
+}
+
+impl Drop for WaiterGuard<'_> {
+    fn drop(&mut self) {
+        // no wake can serve it from now on
+        self.waiter.take_sender();
+        let mut queues = self.backend.waiters.lock();
+        for key in &self.waiter.keys {
+            if let Some(queue) = queues.get_mut(key) {
+                queue.retain(|waiter| !Arc::ptr_eq(waiter, &self.waiter));
+                if queue.is_empty() {
+                    queues.remove(key);
+                }
+            }
+        }
+    }
+}
+
+thread_local! {
+    // the keys written while a wake on this thread serves a client, None outside of a wake
+    static WAKING: RefCell<Option<Vec<Vec<u8>>>> = const { RefCell::new(None) };
+}
+
+// ends the wake of the thread, even if serving a client panicked
+struct WakingGuard;
+
+impl Drop for WakingGuard {
+    fn drop(&mut self) {
+        WAKING.with_borrow_mut(|waking| *waking = None);
+    }
+}
+
+impl Waiter {
+    // taken by whoever serves the waiter, or by the waiter giving up; None once either did
+    fn take_sender(&self) -> Option<oneshot::Sender<RespFrame>> {
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+impl Waiters {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, VecDeque<Arc<Waiter>>>> {
+        self.queues.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend {
+    /// Serve the keys in order, and wait for a write to one of them if none can be served.
+    /// Returns None once the timeout (None for no timeout) expires.
+    pub async fn block_on(
+        &self,
+        keys: Vec<Vec<u8>>,
+        timeout: Option<Duration>,
+        serve: ServeFn,
+    ) -> Option<RespFrame> {
+        if let Some(frame) = keys.iter().find_map(|key| serve(self, key)) {
+            return Some(frame);
+        }
+
+        let (sender, mut receiver) = oneshot::channel();
+        let waiter = Arc::new(Waiter {
+            keys,
+            serve,
+            sender: Mutex::new(Some(sender)),
+        });
+        {
+            let mut queues = self.waiters.lock();
+            for key in &waiter.keys {
+                queues
+                    .entry(key.clone())
+                    .or_default()
+                    .push_back(waiter.clone());
+            }
+        }
+        let _guard = WaiterGuard {
+            backend: self,
+            waiter: waiter.clone(),
+        };
+        // a write between the first try and the registration found no waiter to wake
+        for key in &waiter.keys {
+            self.wake(key);
+        }
+
+        match timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, &mut receiver).await {
+                Ok(frame) => frame.ok(),
+                // a wake serving it right as the timeout expired holds the sender until the
+                // frame is sent, the frame is then replied rather than lost
+                Err(_) => match waiter.take_sender() {
+                    Some(_) => None,
+                    None => receiver.try_recv().ok(),
+                },
+            },
+            None => receiver.await.ok(),
+        }
+    }
+
+    /// Serve the clients blocked on key, oldest first. A client it cannot serve keeps its
+    /// place in the queue, and the clients behind it still get their turn: a reader of a
+    /// stream or of another consumer group may be served by the same write.
+    pub(crate) fn wake(&self, key: &[u8]) {
+        // serving a client may write another key, e.g. the destination of BLMOVE, whose
+        // clients are served once this wake is done: serving them right away would lock the
+        // sender of a client being served again, a BLMOVE from a list to itself or two
+        // BLMOVE crossing each other would never return
+        let nested = WAKING.with_borrow_mut(|waking| match waking {
+            Some(pending) => {
+                pending.push(key.to_vec());
+                true
+            }
+            None => {
+                *waking = Some(vec![]);
+                false
+            }
+        });
+        if nested {
+            return;
+        }
+
+        let _waking = WakingGuard;
+        self.wake_key(key);
+        let next = || {
+            WAKING.with_borrow_mut(|waking| {
+                let pending = waking.as_mut()?;
+                (!pending.is_empty()).then(|| pending.remove(0))
+            })
+        };
+        while let Some(key) = next() {
+            self.wake_key(&key);
+        }
+    }
+
+    fn wake_key(&self, key: &[u8]) {
+        // the waiters stay queued while they are tried, a concurrent wake still sees them and
+        // the sender decides which of the two serves them
+        let queued = match self.waiters.lock().get(key) {
+            Some(queue) => queue.iter().cloned().collect::<Vec<_>>(),
+            None => return,
+        };
+
+        let mut done = vec![];
+        for waiter in queued {
+            let mut sender = waiter.sender.lock().unwrap_or_else(|e| e.into_inner());
+            // already served from another key, or its connection is gone
+            if sender.as_ref().is_none_or(|sender| sender.is_closed()) {
+                drop(sender);
+                done.push(waiter);
+                continue;
+            }
+            if let Some(frame) = (waiter.serve)(self, key) {
+                if let Some(sender) = sender.take() {
+                    let _ = sender.send(frame);
+                }
+                drop(sender);
+                done.push(waiter);
+            }
+        }
+
+        if done.is_empty() {
+            return;
+        }
+        let mut queues = self.waiters.lock();
+        if let Some(queue) = queues.get_mut(key) {
+            queue.retain(|waiter| !done.iter().any(|done| Arc::ptr_eq(waiter, done)));
+            if queue.is_empty() {
+                queues.remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, ListEnd};
+
+    fn pop_left() -> ServeFn {
+        Box::new(|backend, key| match backend.pop(key, ListEnd::Left, 1) {
+            Ok(Some(mut elements)) => elements.pop().map(|e| BulkString::from(e).into()),
+            _ => None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_block_on_served_in_fifo_order() {
+        let backend = Backend::new();
+        let mut clients = vec![];
+        for _ in 0..3 {
+            let client = backend.clone();
+            clients.push(tokio::spawn(async move {
+                client
+                    .block_on(vec![b"list".to_vec()], None, pop_left())
+                    .await
+            }));
+            // let the client block before the next one comes
+            while backend
+                .waiters
+                .lock()
+                .get(b"list".as_slice())
+                .map(|q| q.len())
+                != Some(clients.len())
+            {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        let elements = vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        backend
+            .push(b"list".to_vec(), ListEnd::Right, elements, false)
+            .unwrap();
+        for (client, expected) in clients.into_iter().zip(["a", "b", "c"]) {
+            assert_eq!(
+                client.await.unwrap(),
+                Some(BulkString::from(expected).into())
+            );
+        }
+        assert!(backend.waiters.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_on_timeout_and_drop_clean_up() {
+        let backend = Backend::new();
+        let keys = vec![b"a".to_vec(), b"b".to_vec()];
+        let timeout = Some(Duration::from_millis(10));
+        assert_eq!(
+            backend.block_on(keys.clone(), timeout, pop_left()).await,
+            None
+        );
+        assert!(backend.waiters.lock().is_empty());
+
+        // a disconnected client drops its pending command
+        let blocked = backend.block_on(keys, None, pop_left());
+        let result = tokio::time::timeout(Duration::from_millis(10), blocked).await;
+        assert!(result.is_err());
+        assert!(backend.waiters.lock().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_block_on_timeout_racing_a_push() {
+        let backend = Backend::new();
+        let client = backend.clone();
+        // the timeout expires while the push is serving the client
+        let slow_pop: ServeFn = Box::new(|backend, key| {
+            let frame = pop_left()(backend, key);
+            if frame.is_some() {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            frame
+        });
+        let blocked = tokio::spawn(async move {
+            let timeout = Some(Duration::from_millis(20));
+            client
+                .block_on(vec![b"list".to_vec()], timeout, slow_pop)
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend
+            .push(b"list".to_vec(), ListEnd::Right, vec![b"a".to_vec()], false)
+            .unwrap();
+
+        // the popped element is replied rather than lost
+        assert_eq!(blocked.await.unwrap(), Some(BulkString::from("a").into()));
+        assert!(backend.waiters.lock().is_empty());
+    }
+
+    fn move_to(destination: &'static [u8]) -> ServeFn {
+        Box::new(move |backend, key| {
+            match backend.lmove(key, destination, ListEnd::Left, ListEnd::Right) {
+                Ok(element) => element.map(|e| BulkString::from(e).into()),
+                Err(e) => Some(e.into()),
+            }
+        })
+    }
+
+    // the push runs on a thread of its own, so a deadlock reports a failure rather than only
+    // hanging the test
+    fn push_within(backend: &Backend, key: &'static [u8]) -> bool {
+        let (backend, (sender, receiver)) = (backend.clone(), std::sync::mpsc::channel());
+        std::thread::spawn(move || {
+            let pushed = backend.push(key.to_vec(), ListEnd::Right, vec![b"x".to_vec()], false);
+            let _ = sender.send(pushed);
+        });
+        matches!(receiver.recv_timeout(Duration::from_secs(1)), Ok(Ok(_)))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_block_on_lmove_to_the_same_list() {
+        let backend = Backend::new();
+        let client = backend.clone();
+        let blocked = tokio::spawn(async move {
+            client
+                .block_on(vec![b"a".to_vec()], None, move_to(b"a"))
+                .await
+        });
+        while backend.waiters.lock().is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(push_within(&backend, b"a"));
+        assert_eq!(blocked.await.unwrap(), Some(BulkString::from("x").into()));
+        assert_eq!(backend.llen(b"a"), Ok(1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_block_on_crossed_lmoves() {
+        let backend = Backend::new();
+        let mut clients = vec![];
+        for (source, destination) in [(b"a", b"b"), (b"b", b"a")] {
+            let client = backend.clone();
+            clients.push(tokio::spawn(async move {
+                client
+                    .block_on(vec![source.to_vec()], None, move_to(destination))
+                    .await
+            }));
+        }
+        while backend.waiters.lock().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        // x moves from a to b, then back to a
+        assert!(push_within(&backend, b"a"));
+        for client in clients {
+            assert_eq!(client.await.unwrap(), Some(BulkString::from("x").into()));
+        }
+        assert_eq!(backend.llen(b"a"), Ok(1));
+        assert!(backend.waiters.lock().is_empty());
+    }
+}
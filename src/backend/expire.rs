@@ -0,0 +1,237 @@
+use super::Backend;
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+// keys with a deadline sampled by a round of active expiry
+const ACTIVE_EXPIRE_SAMPLE: usize = 20;
+// the percentage of expired keys in a sample above which another round follows
+const ACTIVE_EXPIRE_STALE_PERCENT: usize = 25;
+
+// current unix time in milliseconds
+pub(crate) fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+impl Backend {
+    // lazily drop the key if its deadline has passed, or the expired fields of a hash.
+    // Returns true if the key was removed.
+    pub(crate) fn expire_if_needed(&self, key: &[u8]) -> bool {
+        let now = now_ms();
+        let expired = self.expire.get(key).is_some_and(|at| *at <= now);
+        // the deadline is checked again under the entry of the key, so a value written
+        // meanwhile, which replaced or cleared the deadline too, is never removed with it.
+        // Like set_with_options, the expire map is only ever locked inside a keyspace entry.
+        if expired
+            && self
+                .keyspace
+                .remove_if(key, |key, _| {
+                    self.expire.remove_if(key, |_, at| *at <= now).is_some()
+                })
+                .is_some()
+        {
+            return true;
+        }
+        if expired && !self.keyspace.contains_key(key) {
+            // no value left to carry the deadline, a new one is set after now
+            self.expire.remove_if(key, |_, at| *at <= now);
+        }
+        self.expire_fields_if_needed(key, now)
+    }
+
+    pub(crate) fn remove_key(&self, key: &[u8]) -> bool {
+        self.expire.remove(key);
+        self.keyspace.remove(key).is_some()
+    }
+
+    pub fn exists(&self, key: &[u8]) -> bool {
+        self.expire_if_needed(key);
+        self.keyspace.contains_key(key)
+    }
+
+    /// Set the absolute deadline (unix time in milliseconds) of a key.
+    /// Returns false if the key does not exist. A deadline in the past deletes the key.
+    pub fn expire_at(&self, key: &[u8], at: i64) -> bool {
+        // a DEL in between would leave the deadline to the next value of the key
+        let _guard = self.locks.write([key]);
+        if !self.exists(key) {
+            return false;
+        }
+
+        if at <= now_ms() {
+            self.remove_key(key);
+        } else {
+            self.expire.insert(key.to_vec(), at);
+        }
+        true
+    }
+
+    /// Remove the deadline of a key, returns true if the key had one.
+    pub fn persist(&self, key: &[u8]) -> bool {
+        let _guard = self.locks.write([key]);
+        if !self.exists(key) {
+            return false;
+        }
+        self.expire.remove(key).is_some()
+    }
+
+    /// Remaining time to live in milliseconds, -2 if the key does not exist and -1 if it has no deadline.
+    pub fn pttl(&self, key: &[u8]) -> i64 {
+        if !self.exists(key) {
+            return -2;
+        }
+        match self.expire.get(key) {
+            Some(at) => (*at - now_ms()).max(0),
+            None => -1,
+        }
+    }
+
+    /// One cycle of active expiry, like the one of redis: a round samples the next 20 keys
+    /// with a deadline and removes the expired ones, and another round follows while more
+    /// than 25% of the sample had expired. Rounds resume the walk where the previous one
+    /// stopped, so every key is sampled in turn. Returns the number of removed keys.
+    pub fn purge_expired(&self) -> usize {
+        let mut removed = 0;
+        loop {
+            let cursor = self.expire_cursor.load(Ordering::Relaxed);
+            let page = self
+                .expire
+                .scan(cursor, ACTIVE_EXPIRE_SAMPLE, |key, _| Some(key.clone()));
+            self.expire_cursor.store(page.cursor, Ordering::Relaxed);
+            let expired = page
+                .items
+                .iter()
+                .filter(|key| self.expire_if_needed(key))
+                .count();
+            removed += expired;
+            if expired * 100 <= page.items.len() * ACTIVE_EXPIRE_STALE_PERCENT {
+                return removed;
+            }
+        }
+    }
+
+    /// Active expiry: periodically reclaim the memory of keys nobody accesses anymore.
+    pub async fn run_active_expire(self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let count = self.purge_expired();
+            if count > 0 {
+                info!("Active expire removed {} keys", count);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespFrame};
+
+    #[test]
+    fn test_expire_at_and_pttl() {
+        let backend = Backend::new();
+        assert!(!backend.expire_at(b"hello", now_ms() + 1000));
+        assert_eq!(backend.pttl(b"hello"), -2);
+
+        backend.set(b"hello".to_vec(), BulkString::from("world").into());
+        assert_eq!(backend.pttl(b"hello"), -1);
+
+        assert!(backend.expire_at(b"hello", now_ms() + 10_000));
+        let ttl = backend.pttl(b"hello");
+        assert!(ttl > 9_000 && ttl <= 10_000);
+
+        assert!(backend.persist(b"hello"));
+        assert_eq!(backend.pttl(b"hello"), -1);
+        assert!(!backend.persist(b"hello"));
+    }
+
+    #[test]
+    fn test_expired_keys_are_invisible() {
+        let backend = Backend::new();
+        backend.set(b"str".to_vec(), BulkString::from("world").into());
+        backend
+            .hset(
+                b"hash".to_vec(),
+                vec![(b"field".to_vec(), BulkString::from("value").into())],
+            )
+            .unwrap();
+        backend
+            .sadd(b"myset".to_vec(), vec![b"member".to_vec()])
+            .unwrap();
+
+        backend.expire.insert(b"str".to_vec(), now_ms() - 1);
+        backend.expire.insert(b"hash".to_vec(), now_ms() - 1);
+        backend.expire.insert(b"myset".to_vec(), now_ms() - 1);
+
+        assert_eq!(backend.get(b"str"), Ok(None));
+        assert_eq!(backend.hget(b"hash", b"field"), Ok(None));
+        assert_eq!(backend.sismember(b"myset", b"member"), Ok(0));
+        assert!(backend.expire.is_empty());
+    }
+
+    #[test]
+    fn test_purge_expired() {
+        let backend = Backend::new();
+        backend.set(b"a".to_vec(), RespFrame::Integer(1));
+        backend.set(b"b".to_vec(), RespFrame::Integer(2));
+        backend.set(b"c".to_vec(), RespFrame::Integer(3));
+        backend.expire.insert(b"a".to_vec(), now_ms() - 1);
+        backend.expire.insert(b"b".to_vec(), now_ms() + 10_000);
+
+        assert_eq!(backend.purge_expired(), 1);
+        assert!(!backend.keyspace.contains_key(b"a".as_slice()));
+        assert!(backend.keyspace.contains_key(b"b".as_slice()));
+        assert!(backend.keyspace.contains_key(b"c".as_slice()));
+    }
+
+    #[test]
+    fn test_purge_expired_samples_the_deadlines() {
+        let backend = Backend::new();
+        for i in 0..200 {
+            let key = format!("gone:{}", i).into_bytes();
+            backend.set(key.clone(), RespFrame::Integer(i));
+            backend.expire.insert(key, now_ms() - 1);
+        }
+        // every round finds only expired keys, so the cycle goes on until none is left
+        assert_eq!(backend.purge_expired(), 200);
+        assert!(backend.keyspace.is_empty());
+
+        for i in 0..100 {
+            let key = format!("live:{}", i).into_bytes();
+            backend.set(key.clone(), RespFrame::Integer(i));
+            backend.expire.insert(key, now_ms() + 10_000);
+        }
+        backend.set(b"gone".to_vec(), RespFrame::Integer(0));
+        backend.expire.insert(b"gone".to_vec(), now_ms() - 1);
+        // a round of 20 keys with at most one expired ends the cycle, the next cycles walk on
+        let mut removed = backend.purge_expired();
+        assert_ne!(backend.expire_cursor.load(Ordering::Relaxed), 0);
+        for _ in 0..5 {
+            removed += backend.purge_expired();
+        }
+        assert_eq!(removed, 1);
+        assert_eq!(backend.keyspace.len(), 100);
+    }
+
+    #[test]
+    fn test_expire_without_a_key_is_dropped() {
+        let backend = Backend::new();
+        backend.expire.insert(b"gone".to_vec(), now_ms() - 1);
+        assert!(!backend.exists(b"gone"));
+        assert!(backend.expire.is_empty());
+
+        // a deadline does not outlive its key for the next value to inherit
+        backend.set(b"key".to_vec(), RespFrame::Integer(1));
+        assert!(backend.expire_at(b"key", now_ms() + 10_000));
+        backend.del(&[b"key".to_vec()]);
+        assert!(!backend.expire_at(b"key", now_ms() + 10_000));
+        backend.set(b"key".to_vec(), RespFrame::Integer(2));
+        assert_eq!(backend.pttl(b"key"), -1);
+    }
+}
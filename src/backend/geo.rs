@@ -0,0 +1,526 @@
+use super::{Backend, BackendError, SortedSet, ZAddOptions};
+use std::f64::consts::FRAC_PI_2;
+
+// like redis, positions are members of a sorted set scored by their 52 bits geohash: 26 bits
+// of latitude interleaved with 26 bits of longitude, so GEO keys are plain sorted sets
+
+const STEP: u32 = 26;
+const LONGITUDE: (f64, f64) = (-180.0, 180.0);
+// the latitudes of the web mercator projection, the poles cannot be indexed
+const LATITUDE: (f64, f64) = (-85.05112878, 85.05112878);
+const EARTH_RADIUS: f64 = 6372797.560856;
+// half the circumference of the earth in the web mercator projection
+const MERCATOR_MAX: f64 = 20037726.37;
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Where GEOSEARCH measures distances from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(Vec<u8>),
+    // longitude, latitude
+    LonLat(f64, f64),
+}
+
+/// The area GEOSEARCH looks into, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+/// The order of GEOSEARCH results, by distance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoSort {
+    Unsorted,
+    Asc,
+    Desc,
+}
+
+/// A GEOSEARCH query. With `any` the search stops at the first `count` matches found,
+/// otherwise the `count` nearest (or farthest) are returned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    pub sort: GeoSort,
+    pub count: Option<usize>,
+    pub any: bool,
+}
+
+/// A member found by GEOSEARCH, its distance in meters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: Vec<u8>,
+    pub dist: f64,
+    pub hash: u64,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+impl Backend {
+    /// Add or move members to their positions, given as (longitude, latitude, member), with
+    /// the NX / XX and CH options of ZADD.
+    pub fn geoadd(
+        &self,
+        key: Vec<u8>,
+        positions: Vec<(f64, f64, Vec<u8>)>,
+        options: ZAddOptions,
+    ) -> Result<i64, BackendError> {
+        let pairs = positions
+            .into_iter()
+            .map(|(lon, lat, member)| (geohash(lon, lat) as f64, member))
+            .collect();
+        self.zadd(key, pairs, options)
+    }
+
+    /// Distance in meters between two members, None if one of them is missing.
+    pub fn geodist(
+        &self,
+        key: &[u8],
+        member1: &[u8],
+        member2: &[u8],
+    ) -> Result<Option<f64>, BackendError> {
+        let positions = self.geopos(key, &[member1.to_vec(), member2.to_vec()])?;
+        match positions.as_slice() {
+            [Some((lon1, lat1)), Some((lon2, lat2))] => {
+                Ok(Some(distance(*lon1, *lat1, *lon2, *lat2)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The (longitude, latitude) of each member, as decoded from its geohash.
+    pub fn geopos(
+        &self,
+        key: &[u8],
+        members: &[Vec<u8>],
+    ) -> Result<Vec<Option<(f64, f64)>>, BackendError> {
+        Ok(self
+            .zmscore(key, members)?
+            .into_iter()
+            .map(|score| score.map(|score| decode(score as u64)))
+            .collect())
+    }
+
+    /// The standard 11 characters geohash of each member.
+    pub fn geohash(
+        &self,
+        key: &[u8],
+        members: &[Vec<u8>],
+    ) -> Result<Vec<Option<String>>, BackendError> {
+        Ok(self
+            .geopos(key, members)?
+            .into_iter()
+            .map(|position| position.map(|(lon, lat)| geohash_string(lon, lat)))
+            .collect())
+    }
+
+    pub fn geosearch(&self, key: &[u8], search: &GeoQuery) -> Result<Vec<GeoMatch>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(v) => v,
+            None => return Ok(vec![]),
+        };
+        search.run(entry.as_zset()?)
+    }
+
+    /// Store the members GEOSEARCH finds in destination, scored by their geohash, or by their
+    /// distance divided by `dist_unit` (meters) with STOREDIST. Returns how many were stored,
+    /// an empty result deletes destination.
+    pub fn geosearchstore(
+        &self,
+        destination: Vec<u8>,
+        key: &[u8],
+        search: &GeoQuery,
+        dist_unit: Option<f64>,
+    ) -> Result<i64, BackendError> {
+        let count = {
+            let _guard = self.locks.write([key, destination.as_slice()]);
+            let mut zset = SortedSet::default();
+            for found in self.geosearch(key, search)? {
+                let score = match dist_unit {
+                    Some(unit) => found.dist / unit,
+                    None => found.hash as f64,
+                };
+                zset.insert(found.member, score);
+            }
+            self.store_zset(destination.clone(), zset)
+        };
+        self.wake(&destination);
+        Ok(count)
+    }
+}
+
+impl GeoQuery {
+    // like redis, only the members of the geohash cell of the center and of its 8 neighbours
+    // are checked against the exact shape, in the order of their geohashes
+    fn run(&self, zset: &SortedSet) -> Result<Vec<GeoMatch>, BackendError> {
+        let (lon, lat) = match &self.origin {
+            GeoOrigin::LonLat(lon, lat) => (*lon, *lat),
+            GeoOrigin::Member(member) => match zset.score(member) {
+                Some(score) => decode(score as u64),
+                None => return Err(BackendError::NoGeoMember),
+            },
+        };
+
+        let mut matches = vec![];
+        let candidates = self
+            .shape
+            .cells(lon, lat)
+            .into_iter()
+            .flat_map(|(min, max)| zset.range_by_score(min as f64, max as f64));
+        for (member, score) in candidates {
+            if self.any && Some(matches.len()) == self.count {
+                break;
+            }
+            let hash = score as u64;
+            let (member_lon, member_lat) = decode(hash);
+            if let Some(dist) = self.shape.distance(lon, lat, member_lon, member_lat) {
+                matches.push(GeoMatch {
+                    member,
+                    dist,
+                    hash,
+                    lon: member_lon,
+                    lat: member_lat,
+                });
+            }
+        }
+
+        // COUNT without ANY keeps the nearest members
+        let sort = match self.sort {
+            GeoSort::Unsorted if self.count.is_some() && !self.any => GeoSort::Asc,
+            sort => sort,
+        };
+        match sort {
+            GeoSort::Unsorted => {}
+            GeoSort::Asc => matches.sort_by(|a, b| a.dist.total_cmp(&b.dist)),
+            GeoSort::Desc => matches.sort_by(|a, b| b.dist.total_cmp(&a.dist)),
+        }
+        if let Some(count) = self.count {
+            matches.truncate(count);
+        }
+        Ok(matches)
+    }
+}
+
+impl GeoShape {
+    // the score ranges of the geohash cells covering the shape around the center: the cell
+    // of the center and its neighbours, at the smallest step they cover the shape with
+    fn cells(&self, lon: f64, lat: f64) -> Vec<(u64, u64)> {
+        // the bounding box of the shape, in degrees: the farthest longitude a point within
+        // the shape can have is found at the latitude nearest to a pole
+        let (half_width, half_height) = match *self {
+            GeoShape::Radius(radius) => (radius, radius),
+            GeoShape::Box { width, height } => (width / 2.0, height / 2.0),
+        };
+        let lat_delta = (half_height / EARTH_RADIUS).to_degrees();
+        let lon_sin = match *self {
+            GeoShape::Radius(radius) => {
+                (radius / EARTH_RADIUS).min(FRAC_PI_2).sin() / lat.to_radians().cos()
+            }
+            GeoShape::Box { .. } => {
+                (half_width / EARTH_RADIUS / 2.0).min(FRAC_PI_2).sin()
+                    / (lat.abs() + lat_delta).min(90.0).to_radians().cos()
+            }
+        };
+        // around a pole every longitude is in the box
+        if lat.abs() + lat_delta >= 90.0 || lon_sin >= 1.0 {
+            return vec![(0, 1 << (STEP * 2))];
+        }
+        let lon_delta = match *self {
+            GeoShape::Radius(_) => lon_sin.asin().to_degrees(),
+            GeoShape::Box { .. } => 2.0 * lon_sin.asin().to_degrees(),
+        };
+        let lat_bounds = (
+            (lat - lat_delta).max(LATITUDE.0),
+            (lat + lat_delta).min(LATITUDE.1),
+        );
+
+        let mut step = estimate_step(half_width.hypot(half_height), lat);
+        let (mut x, mut y);
+        loop {
+            x = cell(lon, LONGITUDE) >> (STEP - step);
+            y = cell(lat, LATITUDE) >> (STEP - step);
+            // the neighbours reach one cell further on each side
+            let lon_cell = (LONGITUDE.1 - LONGITUDE.0) / (1u64 << step) as f64;
+            let lat_cell = (LATITUDE.1 - LATITUDE.0) / (1u64 << step) as f64;
+            let lon_low = LONGITUDE.0 + (x as f64 - 1.0) * lon_cell;
+            let lat_low = LATITUDE.0 + (y as f64 - 1.0) * lat_cell;
+            let covered = lon - lon_delta >= lon_low
+                && lon + lon_delta <= lon_low + 3.0 * lon_cell
+                && lat_bounds.0 >= lat_low
+                && lat_bounds.1 <= lat_low + 3.0 * lat_cell;
+            if covered || step == 1 {
+                break;
+            }
+            step -= 1;
+        }
+
+        let cells = 1i64 << step;
+        let shift = (STEP - step) * 2;
+        let mut ranges = vec![];
+        for dy in -1..=1 {
+            let ny = y as i64 + dy;
+            if !(0..cells).contains(&ny) {
+                continue;
+            }
+            for dx in -1..=1 {
+                // longitudes wrap around at 180 degrees
+                let nx = (x as i64 + dx).rem_euclid(cells);
+                let hash = spread(ny as u64) | spread(nx as u64) << 1;
+                ranges.push((hash << shift, (hash + 1) << shift));
+            }
+        }
+        ranges.sort_unstable();
+        ranges.dedup();
+        ranges
+    }
+
+    // the distance of the point from the center, None if it is outside the shape
+    fn distance(&self, lon: f64, lat: f64, point_lon: f64, point_lat: f64) -> Option<f64> {
+        match *self {
+            GeoShape::Radius(radius) => {
+                Some(distance(lon, lat, point_lon, point_lat)).filter(|dist| *dist <= radius)
+            }
+            GeoShape::Box { width, height } => {
+                // the latitude distance is the cheaper one, checked first
+                let lat_dist = EARTH_RADIUS * (point_lat.to_radians() - lat.to_radians()).abs();
+                if lat_dist > height / 2.0 {
+                    return None;
+                }
+                // the longitude distance is measured along the latitude of the point
+                if distance(lon, point_lat, point_lon, point_lat) > width / 2.0 {
+                    return None;
+                }
+                Some(distance(lon, lat, point_lon, point_lat))
+            }
+        }
+    }
+}
+
+/// Whether the position can be indexed.
+pub fn valid_position(lon: f64, lat: f64) -> bool {
+    (LONGITUDE.0..=LONGITUDE.1).contains(&lon) && (LATITUDE.0..=LATITUDE.1).contains(&lat)
+}
+
+// the haversine distance in meters
+fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let v = ((lon2.to_radians() - lon1.to_radians()) / 2.0).sin();
+    2.0 * EARTH_RADIUS * (u * u + lat1.cos() * lat2.cos() * v * v).sqrt().asin()
+}
+
+// the number of bits per coordinate of the cells large enough for the radius, as redis
+// estimates it: cells get narrower toward the poles
+fn estimate_step(radius: f64, lat: f64) -> u32 {
+    if radius == 0.0 {
+        return STEP;
+    }
+    let mut step: i32 = 1;
+    let mut range = radius;
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+    if lat.abs() > 66.0 {
+        step -= 1;
+        if lat.abs() > 80.0 {
+            step -= 1;
+        }
+    }
+    step.clamp(1, STEP as i32) as u32
+}
+
+fn geohash(lon: f64, lat: f64) -> u64 {
+    encode(lon, lat, LONGITUDE, LATITUDE)
+}
+
+// the cell of each coordinate within its range, latitude bits at even positions
+fn encode(lon: f64, lat: f64, lon_range: (f64, f64), lat_range: (f64, f64)) -> u64 {
+    spread(cell(lat, lat_range)) | spread(cell(lon, lon_range)) << 1
+}
+
+// the cell of the value within the range, out of 2^STEP
+fn cell(value: f64, (min, max): (f64, f64)) -> u64 {
+    let offset = (value - min) / (max - min) * (1u64 << STEP) as f64;
+    // the maximum falls in the last cell
+    (offset as u64).min((1 << STEP) - 1)
+}
+
+// the center of the cell of the geohash, within the ranges
+fn decode(hash: u64) -> (f64, f64) {
+    let center = |cell: u64, (min, max): (f64, f64)| {
+        let scale = (max - min) / (1u64 << STEP) as f64;
+        let low = min + cell as f64 * scale;
+        let high = min + (cell + 1) as f64 * scale;
+        ((low + high) / 2.0).clamp(min, max)
+    };
+    let lon = center(squash(hash >> 1), LONGITUDE);
+    let lat = center(squash(hash), LATITUDE);
+    (lon, lat)
+}
+
+// the standard geohash covers latitudes from -90 to 90, the 11th character has no bits
+fn geohash_string(lon: f64, lat: f64) -> String {
+    let hash = encode(lon, lat, LONGITUDE, (-90.0, 90.0));
+    (0..11)
+        .map(|i| {
+            let index = match i {
+                10 => 0,
+                i => (hash >> (52 - (i + 1) * 5)) & 0x1f,
+            };
+            GEOHASH_ALPHABET[index as usize] as char
+        })
+        .collect()
+}
+
+// the 32 low bits of x at the even bit positions
+fn spread(x: u64) -> u64 {
+    let mut x = x & 0xffff_ffff;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    (x | (x << 1)) & 0x5555_5555_5555_5555
+}
+
+// the bits at even positions, packed
+fn squash(x: u64) -> u64 {
+    let mut x = x & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    (x | (x >> 16)) & 0x0000_0000_ffff_ffff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example of the redis documentation
+    fn sicily() -> Result<Backend, BackendError> {
+        let backend = Backend::new();
+        let positions = vec![
+            (13.361389, 38.115556, b"Palermo".to_vec()),
+            (15.087269, 37.502669, b"Catania".to_vec()),
+        ];
+        backend.geoadd(b"Sicily".to_vec(), positions, ZAddOptions::default())?;
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_geohash_scores() -> Result<(), BackendError> {
+        let backend = sicily()?;
+        assert_eq!(
+            backend.zscore(b"Sicily", b"Palermo"),
+            Ok(Some(3479099956230698.0))
+        );
+        let (lon, lat) = backend.geopos(b"Sicily", &[b"Palermo".to_vec()])?[0].unwrap();
+        assert!((lon - 13.361389).abs() < 1e-5 && (lat - 38.115556).abs() < 1e-5);
+
+        let dist = backend.geodist(b"Sicily", b"Palermo", b"Catania")?.unwrap();
+        assert!((dist - 166274.1516).abs() < 0.01);
+        assert_eq!(
+            backend.geohash(b"Sicily", &[b"Palermo".to_vec(), b"none".to_vec()])?,
+            vec![Some("sqc8b49rny0".to_string()), None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch() -> Result<(), BackendError> {
+        let backend = sicily()?;
+        let mut search = GeoQuery {
+            origin: GeoOrigin::LonLat(15.0, 37.0),
+            shape: GeoShape::Radius(200_000.0),
+            sort: GeoSort::Desc,
+            count: None,
+            any: false,
+        };
+        let found = backend.geosearch(b"Sicily", &search)?;
+        let members = found.iter().map(|m| m.member.clone()).collect::<Vec<_>>();
+        assert_eq!(members, vec![b"Palermo".to_vec(), b"Catania".to_vec()]);
+        assert!((found[1].dist / 1000.0 - 56.4413).abs() < 1e-4);
+
+        // COUNT alone keeps the nearest
+        search.sort = GeoSort::Unsorted;
+        search.count = Some(1);
+        assert_eq!(backend.geosearch(b"Sicily", &search)?[0].member, b"Catania");
+
+        search.shape = GeoShape::Box {
+            width: 400_000.0,
+            height: 200_000.0,
+        };
+        search.count = None;
+        assert_eq!(backend.geosearch(b"Sicily", &search)?.len(), 1);
+
+        search.origin = GeoOrigin::Member(b"none".to_vec());
+        assert_eq!(
+            backend.geosearch(b"Sicily", &search),
+            Err(BackendError::NoGeoMember)
+        );
+        search.origin = GeoOrigin::Member(b"Palermo".to_vec());
+        assert_eq!(
+            backend.geosearchstore(b"dest".to_vec(), b"Sicily", &search, Some(1000.0)),
+            Ok(2)
+        );
+        assert_eq!(backend.zscore(b"dest", b"Palermo"), Ok(Some(0.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_scans_only_nearby_cells() -> Result<(), BackendError> {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let backend = Backend::new();
+        let positions = (0..2000)
+            .map(|i| {
+                let lon = rng.gen_range(-180.0..=180.0);
+                let lat = rng.gen_range(LATITUDE.0..=LATITUDE.1);
+                (lon, lat, format!("m{}", i).into_bytes())
+            })
+            .collect::<Vec<_>>();
+        backend.geoadd(b"key".to_vec(), positions.clone(), ZAddOptions::default())?;
+
+        // a 5 km radius is looked for in a few small cells
+        let cells = GeoShape::Radius(5000.0).cells(13.361389, 38.115556);
+        let span: u64 = cells.iter().map(|(min, max)| max - min).sum();
+        assert!(span < 1 << 40);
+
+        // still the same members as a check of every one of them, near the poles and across
+        // the 180th meridian too
+        for _ in 0..200 {
+            let lon = rng.gen_range(-180.0..=180.0);
+            let lat = rng.gen_range(LATITUDE.0..=LATITUDE.1);
+            let shape = if rng.gen_bool(0.5) {
+                GeoShape::Radius(rng.gen_range(0.0..3_000_000.0))
+            } else {
+                GeoShape::Box {
+                    width: rng.gen_range(0.0..6_000_000.0),
+                    height: rng.gen_range(0.0..6_000_000.0),
+                }
+            };
+            let search = GeoQuery {
+                origin: GeoOrigin::LonLat(lon, lat),
+                shape,
+                sort: GeoSort::Asc,
+                count: None,
+                any: false,
+            };
+            let found = backend.geosearch(b"key", &search)?;
+            let expected = positions
+                .iter()
+                .filter(|(member_lon, member_lat, _)| {
+                    let (member_lon, member_lat) = decode(geohash(*member_lon, *member_lat));
+                    shape.distance(lon, lat, member_lon, member_lat).is_some()
+                })
+                .count();
+            assert_eq!(found.len(), expected, "{:?} at {} {}", shape, lon, lat);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+// redis glob-style matching: `*`, `?`, `[abc]`, `[^a-z]` and `\` to escape a special character
+//
+// Patterns come from clients, so this never recurses: on a mismatch only the last `*` seen
+// takes one more byte, the earlier ones can't do better. Matching is O(pattern * string).
+pub(crate) fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // pattern index after the last star, and the string index it is retried from
+    let mut star = None;
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            // consecutive stars are one star
+            while pattern.get(p) == Some(&b'*') {
+                p += 1;
+            }
+            star = Some((p, s));
+            continue;
+        }
+        if let Some(next) = match_one(pattern, p, string[s]) {
+            p = next;
+            s += 1;
+            continue;
+        }
+        match star {
+            Some((star_p, star_s)) => {
+                star = Some((star_p, star_s + 1));
+                p = star_p;
+                s = star_s + 1;
+            }
+            None => return false,
+        }
+    }
+    // trailing stars match the empty rest of the string
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+// match c against the element of the pattern at p (not a star), returns the index after it
+fn match_one(pattern: &[u8], p: usize, c: u8) -> Option<usize> {
+    match *pattern.get(p)? {
+        b'?' => Some(p + 1),
+        b'[' => {
+            let (matched, end) = match_class(pattern, p + 1, c);
+            matched.then_some(end + 1)
+        }
+        b'\\' if p + 1 < pattern.len() => (pattern[p + 1] == c).then_some(p + 2),
+        literal => (literal == c).then_some(p + 1),
+    }
+}
+
+// match c against the class starting after `[`, returns the result and the index of the closing `]`
+fn match_class(pattern: &[u8], mut p: usize, c: u8) -> (bool, usize) {
+    let negate = pattern.get(p) == Some(&b'^');
+    if negate {
+        p += 1;
+    }
+
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            p += 1;
+            matched |= pattern[p] == c;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' && pattern[p + 2] != b']' {
+            let (start, end) = (pattern[p], pattern[p + 2]);
+            let (start, end) = (start.min(end), start.max(end));
+            matched |= (start..=end).contains(&c);
+            p += 2;
+        } else {
+            matched |= pattern[p] == c;
+        }
+        p += 1;
+    }
+    // like redis, an unterminated class ends at the end of the pattern
+    (matched != negate, p.min(pattern.len() - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"*", b"anything"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(!glob_match(b"h?llo", b"hllo"));
+        assert!(glob_match(b"h*llo", b"heeeello"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[ae]llo", b"hillo"));
+        assert!(glob_match(b"h[^e]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-b]llo", b"hbllo"));
+        assert!(glob_match(b"h[b-a]llo", b"hallo"));
+        assert!(!glob_match(b"h[a-b]llo", b"hcllo"));
+        assert!(glob_match(b"user:*:name", b"user:42:name"));
+        assert!(!glob_match(b"user:*:name", b"user:42:age"));
+    }
+
+    #[test]
+    fn test_glob_match_escapes() {
+        assert!(glob_match(b"h\\*llo", b"h*llo"));
+        assert!(!glob_match(b"h\\*llo", b"hello"));
+        assert!(glob_match(b"h[\\]]llo", b"h]llo"));
+        assert!(glob_match(b"a\\?", b"a?"));
+        assert!(!glob_match(b"a\\?", b"ab"));
+    }
+
+    #[test]
+    fn test_glob_match_many_stars() {
+        // backtracking over every star would take exponential time here
+        let string = [b'a'; 40];
+        assert!(!glob_match(b"*a*a*a*a*a*a*a*a*a*a*a*a*b", &string));
+        assert!(glob_match(b"*a*a*a*a*a*a*a*a*a*a*a*a*", &string));
+        assert!(glob_match(b"a*b*c", b"axxbyyc"));
+        assert!(!glob_match(b"a*b*c", b"axxbyyd"));
+        assert!(glob_match(b"**", b""));
+    }
+}
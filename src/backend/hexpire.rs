@@ -0,0 +1,411 @@
+use super::{
+    now_ms, Backend, BackendError, ExpireCondition, HashValue, KeyExpire, SetCondition, Value,
+};
+use crate::RespFrame;
+
+// per field replies of the HEXPIRE family
+const NO_FIELD: i64 = -2;
+const NO_DEADLINE: i64 = -1;
+const NOT_SET: i64 = 0;
+const SET: i64 = 1;
+const DELETED: i64 = 2;
+
+impl ExpireCondition {
+    // like redis, a field without deadline counts as one that never expires
+    fn allows(self, current: Option<i64>, at: i64) -> bool {
+        match self {
+            ExpireCondition::Always => true,
+            ExpireCondition::NoDeadline => current.is_none(),
+            ExpireCondition::HasDeadline => current.is_some(),
+            ExpireCondition::Greater => current.is_some_and(|current| at > current),
+            ExpireCondition::Less => current.is_none_or(|current| at < current),
+        }
+    }
+}
+
+impl HashValue {
+    // a deadline already passed deletes the field right away, returns whether it was deleted
+    fn expire_field(&self, field: &[u8], at: i64, now: i64) -> bool {
+        if at <= now {
+            self.remove(field);
+            return true;
+        }
+        self.expire.insert(field.to_vec(), at);
+        false
+    }
+
+    fn deadline(&self, field: &[u8]) -> Option<i64> {
+        self.expire.get(field).map(|v| *v.value())
+    }
+}
+
+impl Backend {
+    /// Set the deadline (unix time in milliseconds) of each field. Replies per field with -2 if
+    /// the field does not exist, 0 if the condition is not met, 1 if the deadline is set and 2 if
+    /// the field is deleted because the deadline has already passed.
+    pub fn hexpire_at(
+        &self,
+        key: &[u8],
+        at: i64,
+        condition: ExpireCondition,
+        fields: &[Vec<u8>],
+    ) -> Result<Vec<i64>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let now = now_ms();
+        let (codes, empty) = match self.keyspace.get(key) {
+            Some(v) => {
+                let hash = v.as_hash()?;
+                let codes = fields
+                    .iter()
+                    .map(|field| {
+                        if !hash.contains_key(field) {
+                            NO_FIELD
+                        } else if !condition.allows(hash.deadline(field), at) {
+                            NOT_SET
+                        } else if hash.expire_field(field, at, now) {
+                            DELETED
+                        } else {
+                            SET
+                        }
+                    })
+                    .collect();
+                (codes, hash.is_empty())
+            }
+            None => return Ok(vec![NO_FIELD; fields.len()]),
+        };
+        // the ref is released by now, removing the key while holding it would deadlock
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(codes)
+    }
+
+    /// Deadline of each field in unix milliseconds, -2 if the field does not exist and -1 if it
+    /// has no deadline.
+    pub fn hpexpire_time(&self, key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<i64>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let hash = v.as_hash()?;
+                Ok(fields
+                    .iter()
+                    .map(|field| match hash.deadline(field) {
+                        Some(at) => at,
+                        None if hash.contains_key(field) => NO_DEADLINE,
+                        None => NO_FIELD,
+                    })
+                    .collect())
+            }
+            None => Ok(vec![NO_FIELD; fields.len()]),
+        }
+    }
+
+    /// Remove the deadline of each field. Replies per field with -2 if the field does not exist,
+    /// -1 if it has no deadline and 1 if the deadline was removed.
+    pub fn hpersist(&self, key: &[u8], fields: &[Vec<u8>]) -> Result<Vec<i64>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let hash = v.as_hash()?;
+                Ok(fields
+                    .iter()
+                    .map(|field| match hash.expire.remove(field) {
+                        Some(_) => SET,
+                        None if hash.contains_key(field) => NO_DEADLINE,
+                        None => NO_FIELD,
+                    })
+                    .collect())
+            }
+            None => Ok(vec![NO_FIELD; fields.len()]),
+        }
+    }
+
+    /// Values of the fields, then the deadline of the existing ones is updated.
+    pub fn hgetex(
+        &self,
+        key: &[u8],
+        fields: &[Vec<u8>],
+        expire: KeyExpire,
+    ) -> Result<Vec<Option<RespFrame>>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let now = now_ms();
+        let (values, empty) = match self.keyspace.get(key) {
+            Some(v) => {
+                let hash = v.as_hash()?;
+                let values = fields
+                    .iter()
+                    .map(|field| {
+                        let value = hash.get(field).map(|v| v.value().clone());
+                        if value.is_some() {
+                            match expire {
+                                KeyExpire::Persist => {
+                                    hash.expire.remove(field);
+                                }
+                                KeyExpire::Keep => {}
+                                KeyExpire::At(at) => {
+                                    hash.expire_field(field, at, now);
+                                }
+                            }
+                        }
+                        value
+                    })
+                    .collect();
+                (values, hash.is_empty())
+            }
+            None => return Ok(vec![None; fields.len()]),
+        };
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(values)
+    }
+
+    /// Set the fields together with their deadline. With `NotExists` none of the fields may
+    /// exist yet, with `Exists` all of them must. Returns whether the fields were set.
+    pub fn hsetex(
+        &self,
+        key: Vec<u8>,
+        pairs: Vec<(Vec<u8>, RespFrame)>,
+        condition: SetCondition,
+        expire: KeyExpire,
+    ) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        // checked before the entry is created, so a failed condition leaves no empty hash behind
+        let applies = match self.keyspace.get(&key) {
+            Some(v) => {
+                let hash = v.as_hash()?;
+                match condition {
+                    SetCondition::Always => true,
+                    SetCondition::NotExists => pairs.iter().all(|(f, _)| !hash.contains_key(f)),
+                    SetCondition::Exists => pairs.iter().all(|(f, _)| hash.contains_key(f)),
+                }
+            }
+            None => condition != SetCondition::Exists,
+        };
+        if !applies {
+            return Ok(false);
+        }
+
+        let now = now_ms();
+        let empty = {
+            let entry = self
+                .keyspace
+                .entry(key.clone())
+                .or_insert_with(|| Value::Hash(HashValue::default()));
+            let hash = entry.as_hash()?;
+            for (field, value) in pairs {
+                let at = match expire {
+                    KeyExpire::Persist => None,
+                    KeyExpire::Keep => hash.deadline(&field),
+                    KeyExpire::At(at) => Some(at),
+                };
+                hash.insert(field.clone(), value);
+                if let Some(at) = at {
+                    hash.expire_field(&field, at, now);
+                }
+            }
+            hash.is_empty()
+        };
+        if empty {
+            self.remove_key(&key);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn fields(names: &[&str]) -> Vec<Vec<u8>> {
+        names.iter().map(|name| name.as_bytes().to_vec()).collect()
+    }
+
+    fn fill(backend: &Backend) -> Result<(), BackendError> {
+        backend.hset(
+            b"hash".to_vec(),
+            vec![
+                (b"a".to_vec(), BulkString::from("1").into()),
+                (b"b".to_vec(), BulkString::from("2").into()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexpire_conditions() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend)?;
+        let at = now_ms() + 10_000;
+        let names = fields(&["a", "missing"]);
+
+        assert_eq!(
+            backend.hexpire_at(b"hash", at, ExpireCondition::HasDeadline, &names),
+            Ok(vec![0, -2])
+        );
+        assert_eq!(
+            backend.hexpire_at(b"hash", at, ExpireCondition::NoDeadline, &names),
+            Ok(vec![1, -2])
+        );
+        assert_eq!(
+            backend.hexpire_at(b"hash", at - 1, ExpireCondition::Greater, &names),
+            Ok(vec![0, -2])
+        );
+        assert_eq!(
+            backend.hexpire_at(b"hash", at - 1, ExpireCondition::Less, &names),
+            Ok(vec![1, -2])
+        );
+        // no deadline is an infinite one: GT never applies, LT always does
+        let names = fields(&["b"]);
+        assert_eq!(
+            backend.hexpire_at(b"hash", at, ExpireCondition::Greater, &names),
+            Ok(vec![0])
+        );
+        assert_eq!(
+            backend.hexpire_at(b"hash", at, ExpireCondition::Less, &names),
+            Ok(vec![1])
+        );
+
+        assert_eq!(
+            backend.hpexpire_time(b"hash", &fields(&["a", "b", "missing"])),
+            Ok(vec![at - 1, at, -2])
+        );
+        assert_eq!(
+            backend.hexpire_at(b"missing", at, ExpireCondition::Always, &names),
+            Ok(vec![-2])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_fields_are_hidden() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend)?;
+        if let Some(v) = backend.keyspace.get(b"hash".as_slice()) {
+            v.as_hash()?.expire.insert(b"a".to_vec(), now_ms() - 1);
+        }
+
+        assert_eq!(backend.hget(b"hash", b"a"), Ok(None));
+        assert_eq!(
+            backend.hmget(b"hash", &fields(&["a", "b"])),
+            Ok(vec![None, Some(BulkString::from("2").into())])
+        );
+        assert_eq!(backend.hgetall(b"hash")?.map(|v| v.len()), Some(1));
+        assert_eq!(backend.hlen(b"hash"), Ok(1));
+
+        // a deadline in the past deletes the field, and the hash with its last field
+        assert_eq!(
+            backend.hexpire_at(b"hash", 0, ExpireCondition::Always, &fields(&["b"])),
+            Ok(vec![2])
+        );
+        assert!(!backend.exists(b"hash"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_field_expiring_removes_the_hash() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend)?;
+        if let Some(v) = backend.keyspace.get(b"hash".as_slice()) {
+            let hash = v.as_hash()?;
+            hash.expire.insert(b"a".to_vec(), now_ms() - 1);
+            hash.expire.insert(b"b".to_vec(), now_ms() - 1);
+        }
+
+        assert_eq!(backend.key_type(b"hash"), "none");
+        assert!(backend.keyspace.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hpersist_and_hset_clear_deadlines() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend)?;
+        let at = now_ms() + 10_000;
+        backend.hexpire_at(b"hash", at, ExpireCondition::Always, &fields(&["a", "b"]))?;
+
+        assert_eq!(
+            backend.hpersist(b"hash", &fields(&["a", "a", "missing"])),
+            Ok(vec![1, -1, -2])
+        );
+        // a new value replaces the field with its deadline
+        backend.hset(
+            b"hash".to_vec(),
+            vec![(b"b".to_vec(), RespFrame::Integer(3))],
+        )?;
+        assert_eq!(
+            backend.hpexpire_time(b"hash", &fields(&["a", "b"])),
+            Ok(vec![-1, -1])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hgetex_hsetex() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let at = now_ms() + 10_000;
+        let pairs = vec![(b"a".to_vec(), BulkString::from("1").into())];
+
+        assert_eq!(
+            backend.hsetex(
+                b"hash".to_vec(),
+                pairs.clone(),
+                SetCondition::Exists,
+                KeyExpire::At(at)
+            ),
+            Ok(false)
+        );
+        assert!(!backend.exists(b"hash"));
+        assert_eq!(
+            backend.hsetex(
+                b"hash".to_vec(),
+                pairs.clone(),
+                SetCondition::NotExists,
+                KeyExpire::At(at)
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            backend.hsetex(
+                b"hash".to_vec(),
+                pairs.clone(),
+                SetCondition::NotExists,
+                KeyExpire::Keep
+            ),
+            Ok(false)
+        );
+        assert_eq!(
+            backend.hsetex(
+                b"hash".to_vec(),
+                pairs,
+                SetCondition::Exists,
+                KeyExpire::Keep
+            ),
+            Ok(true)
+        );
+        assert_eq!(
+            backend.hpexpire_time(b"hash", &fields(&["a"])),
+            Ok(vec![at])
+        );
+
+        assert_eq!(
+            backend.hgetex(b"hash", &fields(&["a", "missing"]), KeyExpire::Persist),
+            Ok(vec![Some(BulkString::from("1").into()), None])
+        );
+        assert_eq!(
+            backend.hpexpire_time(b"hash", &fields(&["a"])),
+            Ok(vec![-1])
+        );
+        assert_eq!(
+            backend.hgetex(b"hash", &fields(&["a"]), KeyExpire::At(0)),
+            Ok(vec![Some(BulkString::from("1").into())])
+        );
+        assert!(!backend.exists(b"hash"));
+        Ok(())
+    }
+}
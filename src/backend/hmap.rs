@@ -0,0 +1,510 @@
+use super::scan::{Entry, ScanMap};
+use super::{
+    map::{parse_float, parse_integer},
+    set::{pick, random_positions},
+    Backend, BackendError, Value,
+};
+use crate::{format_incr_float, BulkString, RespFrame};
+use dashmap::DashMap;
+use std::ops::Deref;
+
+/// The fields of a hash, each of them may have its own deadline.
+#[derive(Debug, Clone, Default)]
+pub struct HashValue {
+    fields: ScanMap<RespFrame>,
+    // field -> deadline in unix milliseconds
+    pub(crate) expire: DashMap<Vec<u8>, i64>,
+}
+
+// reads go straight to the fields, expired ones are purged before any command looks at them
+impl Deref for HashValue {
+    type Target = ScanMap<RespFrame>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fields
+    }
+}
+
+impl HashValue {
+    // like redis, a new value replaces the field together with its time to live
+    pub(crate) fn insert(&self, field: Vec<u8>, value: RespFrame) -> Option<RespFrame> {
+        self.expire.remove(&field);
+        self.fields.insert(field, value)
+    }
+
+    pub(crate) fn remove(&self, field: &[u8]) -> Option<(Vec<u8>, RespFrame)> {
+        self.expire.remove(field);
+        self.fields.remove(field)
+    }
+
+    // drop the fields whose deadline has passed, returns whether any was dropped
+    pub(crate) fn purge_expired(&self, now: i64) -> bool {
+        if self.expire.is_empty() {
+            return false;
+        }
+        let expired = self
+            .expire
+            .iter()
+            .filter(|v| *v.value() <= now)
+            .map(|v| v.key().clone())
+            .collect::<Vec<Vec<u8>>>();
+
+        let mut purged = false;
+        for field in expired {
+            if self.expire.remove_if(&field, |_, at| *at <= now).is_some() {
+                purged |= self.fields.remove(&field).is_some();
+            }
+        }
+        purged
+    }
+}
+
+// like redis, a hash only exists while it has fields: the writers removing fields delete the key
+// once the last one is gone, so readers never see an empty hash
+impl Backend {
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Result<Option<RespFrame>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_hash()?.get(field).map(|v| v.value().clone())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn hmget(
+        &self,
+        key: &[u8],
+        fields: &[Vec<u8>],
+    ) -> Result<Vec<Option<RespFrame>>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = self.keyspace.get(key);
+        let hmap = match entry {
+            Some(ref v) => Some(v.as_hash()?),
+            None => None,
+        };
+
+        Ok(fields
+            .iter()
+            .map(|field| hmap.and_then(|hmap| hmap.get(field).map(|v| v.value().clone())))
+            .collect())
+    }
+
+    /// Set every field/value pair, returns how many fields were created.
+    /// The keyspace entry is held for the whole update, so readers see all the pairs or none.
+    pub fn hset(
+        &self,
+        key: Vec<u8>,
+        pairs: Vec<(Vec<u8>, RespFrame)>,
+    ) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::Hash(HashValue::default()));
+        let hmap = entry.as_hash()?;
+        Ok(pairs
+            .into_iter()
+            .map(|(field, value)| hmap.insert(field, value).is_none() as i64)
+            .sum())
+    }
+
+    pub fn hgetall(&self, key: &[u8]) -> Result<Option<DashMap<Vec<u8>, RespFrame>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(Some((*v.as_hash()?.fields).clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Set the field only if it does not exist yet, returns whether it was set.
+    pub fn hsetnx(
+        &self,
+        key: Vec<u8>,
+        field: Vec<u8>,
+        value: RespFrame,
+    ) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::Hash(HashValue::default()));
+        let inserted = match entry.as_hash()?.entry(field) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                true
+            }
+        };
+        Ok(inserted)
+    }
+
+    /// Add delta to the integer stored in the field, a missing field counts as 0.
+    /// The read-modify-write happens under the field's entry lock, so no update gets lost.
+    pub fn hincr_by(&self, key: Vec<u8>, field: Vec<u8>, delta: i64) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::Hash(HashValue::default()));
+        let mut field = entry
+            .as_hash()?
+            .entry(field)
+            .or_insert_with(|| BulkString::from("0").into());
+
+        let current = parse_integer(field.value()).map_err(|_| BackendError::HashNotInteger)?;
+        let value = current.checked_add(delta).ok_or(BackendError::Overflow)?;
+        *field.value_mut() = BulkString::from(value.to_string()).into();
+        Ok(value)
+    }
+
+    pub fn hincr_by_float(
+        &self,
+        key: Vec<u8>,
+        field: Vec<u8>,
+        delta: f64,
+    ) -> Result<f64, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::Hash(HashValue::default()));
+        let mut field = entry
+            .as_hash()?
+            .entry(field)
+            .or_insert_with(|| BulkString::from("0").into());
+
+        let value = parse_float(field.value()).map_err(|_| BackendError::HashNotFloat)? + delta;
+        if !value.is_finite() {
+            return Err(BackendError::NanOrInfinity);
+        }
+        *field.value_mut() = BulkString::from(format_incr_float(value)).into();
+        Ok(value)
+    }
+
+    // drop the expired fields of a hash, and the hash itself once none is left.
+    // Returns true if the key was removed.
+    pub(crate) fn expire_fields_if_needed(&self, key: &[u8], now: i64) -> bool {
+        let emptied = match self.keyspace.get(key) {
+            Some(v) => match v.value() {
+                Value::Hash(hash) => hash.purge_expired(now) && hash.is_empty(),
+                _ => false,
+            },
+            None => false,
+        };
+        // the ref is released by now, so a writer may have refilled the hash meanwhile
+        if emptied
+            && self
+                .keyspace
+                .remove_if(
+                    key,
+                    |_, v| matches!(v, Value::Hash(hash) if hash.is_empty()),
+                )
+                .is_some()
+        {
+            self.expire.remove(key);
+            return true;
+        }
+        false
+    }
+
+    /// Remove the given fields, returns how many of them existed.
+    pub fn hdel(&self, key: &[u8], fields: &[Vec<u8>]) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let (count, empty) = match self.keyspace.get(key) {
+            Some(v) => {
+                let hmap = v.as_hash()?;
+                let count = fields
+                    .iter()
+                    .filter(|field| hmap.remove(field).is_some())
+                    .count();
+                (count as i64, hmap.is_empty())
+            }
+            None => return Ok(0),
+        };
+        // the ref is released by now, removing the key while holding it would deadlock
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(count)
+    }
+
+    pub fn hexists(&self, key: &[u8], field: &[u8]) -> Result<bool, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_hash()?.contains_key(field)),
+            None => Ok(false),
+        }
+    }
+
+    pub fn hlen(&self, key: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_hash()?.len() as i64),
+            None => Ok(0),
+        }
+    }
+
+    pub fn hkeys(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_hash()?.iter().map(|v| v.key().clone()).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn hvals(&self, key: &[u8]) -> Result<Vec<RespFrame>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_hash()?.iter().map(|v| v.value().clone()).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Length of the value of the field, 0 if the field does not exist.
+    pub fn hstrlen(&self, key: &[u8], field: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_hash()?.get(field).map_or(0, |v| value_len(v.value())) as i64),
+            None => Ok(0),
+        }
+    }
+
+    /// Random fields with their values. A positive count returns distinct fields, at most the
+    /// whole hash, a negative count returns exactly -count fields that may repeat, up to
+    /// 1 << 20.
+    pub fn hrandfield(
+        &self,
+        key: &[u8],
+        count: i64,
+    ) -> Result<Vec<(Vec<u8>, RespFrame)>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let hash = v.as_hash()?;
+                let positions = random_positions(hash.len(), count)?;
+                Ok(pick(hash.iter(), &positions, |v| {
+                    (v.key().clone(), v.value().clone())
+                }))
+            }
+            None => Ok(vec![]),
+        }
+    }
+}
+
+// values are normally BulkString, other frames count the length of their text form
+fn value_len(value: &RespFrame) -> usize {
+    match value {
+        RespFrame::BulkString(s) => s.as_ref().len(),
+        RespFrame::SimpleString(s) => s.len(),
+        RespFrame::Integer(n) => n.to_string().len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::set::MAX_RANDOM_COUNT, BulkString};
+    use std::collections::HashSet;
+
+    fn fill(backend: &Backend, key: &[u8], n: usize) -> Result<(), BackendError> {
+        for i in 0..n {
+            backend.hset(
+                key.to_vec(),
+                vec![(
+                    format!("field:{}", i).into_bytes(),
+                    BulkString::from(format!("value:{}", i)).into(),
+                )],
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hdel_removes_empty_hash() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend, b"hash", 2)?;
+
+        let fields = [b"field:0".to_vec(), b"missing".to_vec()];
+        assert_eq!(backend.hdel(b"hash", &fields), Ok(1));
+        assert_eq!(backend.hlen(b"hash"), Ok(1));
+        assert_eq!(backend.hdel(b"hash", &[b"field:1".to_vec()]), Ok(1));
+        assert!(!backend.exists(b"hash"));
+        assert_eq!(backend.key_type(b"hash"), "none");
+        assert_eq!(backend.hdel(b"hash", &[b"field:1".to_vec()]), Ok(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_readers() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend, b"hash", 3)?;
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+
+        assert_eq!(backend.hexists(b"hash", b"field:2"), Ok(true));
+        assert_eq!(backend.hexists(b"hash", b"field:3"), Ok(false));
+        assert_eq!(backend.hstrlen(b"hash", b"field:1"), Ok(7));
+        assert_eq!(backend.hstrlen(b"hash", b"missing"), Ok(0));
+
+        let mut keys = backend.hkeys(b"hash")?;
+        keys.sort();
+        assert_eq!(keys, vec![b"field:0", b"field:1", b"field:2"]);
+        assert_eq!(backend.hvals(b"hash")?.len(), 3);
+        assert_eq!(backend.hkeys(b"missing"), Ok(vec![]));
+
+        assert_eq!(backend.hlen(b"str"), Err(BackendError::WrongType));
+        assert_eq!(
+            backend.hdel(b"str", &[b"field".to_vec()]),
+            Err(BackendError::WrongType)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hset_is_atomic_for_readers() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let writer = {
+            let backend = backend.clone();
+            std::thread::spawn(move || {
+                for i in 0..1000 {
+                    let pairs = vec![
+                        (b"a".to_vec(), RespFrame::Integer(i)),
+                        (b"b".to_vec(), RespFrame::Integer(i)),
+                    ];
+                    backend.hset(b"hash".to_vec(), pairs).unwrap();
+                }
+            })
+        };
+
+        // both fields are always written together
+        while !writer.is_finished() {
+            let values = backend.hmget(b"hash", &[b"a".to_vec(), b"b".to_vec()])?;
+            assert_eq!(values[0], values[1]);
+        }
+        writer.join().unwrap();
+        assert_eq!(backend.hlen(b"hash"), Ok(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincr_by() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(backend.hincr_by(b"hash".to_vec(), b"n".to_vec(), 5), Ok(5));
+        assert_eq!(
+            backend.hincr_by(b"hash".to_vec(), b"n".to_vec(), -7),
+            Ok(-2)
+        );
+        assert_eq!(
+            backend.hget(b"hash", b"n"),
+            Ok(Some(BulkString::from("-2").into()))
+        );
+        assert_eq!(
+            backend.hincr_by_float(b"hash".to_vec(), b"n".to_vec(), 0.5),
+            Ok(-1.5)
+        );
+
+        backend.hset(
+            b"hash".to_vec(),
+            vec![(b"s".to_vec(), BulkString::from("abc").into())],
+        )?;
+        assert_eq!(
+            backend.hincr_by(b"hash".to_vec(), b"s".to_vec(), 1),
+            Err(BackendError::HashNotInteger)
+        );
+        assert_eq!(
+            backend.hincr_by_float(b"hash".to_vec(), b"s".to_vec(), 1.0),
+            Err(BackendError::HashNotFloat)
+        );
+        // a float is not an integer
+        assert_eq!(
+            backend.hincr_by(b"hash".to_vec(), b"n".to_vec(), 1),
+            Err(BackendError::HashNotInteger)
+        );
+
+        backend.hset(
+            b"hash".to_vec(),
+            vec![(b"max".to_vec(), RespFrame::Integer(i64::MAX))],
+        )?;
+        assert_eq!(
+            backend.hincr_by(b"hash".to_vec(), b"max".to_vec(), 1),
+            Err(BackendError::Overflow)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincr_by_is_atomic() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let handles = (0..4)
+            .map(|_| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..250 {
+                        backend
+                            .hincr_by(b"hash".to_vec(), b"n".to_vec(), 1)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(
+            backend.hincr_by(b"hash".to_vec(), b"n".to_vec(), 0),
+            Ok(1000)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hsetnx() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.hsetnx(b"hash".to_vec(), b"field".to_vec(), RespFrame::Integer(1)),
+            Ok(true)
+        );
+        assert_eq!(
+            backend.hsetnx(b"hash".to_vec(), b"field".to_vec(), RespFrame::Integer(2)),
+            Ok(false)
+        );
+        assert_eq!(
+            backend.hget(b"hash", b"field"),
+            Ok(Some(RespFrame::Integer(1)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        fill(&backend, b"hash", 5)?;
+
+        let fields = backend.hrandfield(b"hash", 3)?;
+        let distinct = fields.iter().map(|(f, _)| f).collect::<HashSet<_>>();
+        assert_eq!(distinct.len(), 3);
+
+        // a positive count never returns more than the whole hash
+        assert_eq!(backend.hrandfield(b"hash", 10)?.len(), 5);
+        // a negative one returns exactly that many, repeating fields
+        assert_eq!(backend.hrandfield(b"hash", -10)?.len(), 10);
+        assert_eq!(backend.hrandfield(b"hash", 0)?.len(), 0);
+        assert_eq!(backend.hrandfield(b"missing", -3)?.len(), 0);
+
+        for (field, value) in backend.hrandfield(b"hash", -20)? {
+            assert_eq!(backend.hget(b"hash", &field), Ok(Some(value)));
+        }
+
+        // unlike redis, a negative count is capped since the reply is built in memory
+        let max = MAX_RANDOM_COUNT as i64;
+        assert_eq!(
+            backend.hrandfield(b"hash", -max - 1),
+            Err(BackendError::CountOutOfRange)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,442 @@
+use super::{
+    map::{bytes, bytes_mut},
+    Backend, BackendError, Value,
+};
+use crate::BulkString;
+
+// HyperLogLogs are strings laid out like the ones of redis, so they can be moved between
+// servers with GET / SET: a 16 bytes header, then 2^14 registers of 6 bits, either packed
+// (dense) or run-length encoded (sparse).
+//
+// header: "HYLL", encoding (0 dense, 1 sparse), 3 unused bytes, the cached cardinality as a
+// little endian u64, its most significant bit set when the cache is stale
+
+const MAGIC: &[u8] = b"HYLL";
+const HEADER_LEN: usize = 16;
+const DENSE: u8 = 0;
+const SPARSE: u8 = 1;
+
+// 14 bits of the hash pick a register, the other 50 give the count stored in it
+const P: usize = 14;
+const Q: usize = 64 - P;
+const REGISTERS: usize = 1 << P;
+const REGISTER_BITS: usize = 6;
+const DENSE_LEN: usize = HEADER_LEN + (REGISTERS * REGISTER_BITS).div_ceil(8);
+
+// sparse opcodes: ZERO 00xxxxxx and XZERO 01xxxxxx yyyyyyyy for runs of empty registers,
+// VAL 1vvvvvxx for up to 4 registers holding the same count of at most 32
+const ZERO_MAX_LEN: usize = 64;
+const XZERO_MAX_LEN: usize = 16384;
+const VAL_MAX_VALUE: u8 = 32;
+const VAL_MAX_LEN: usize = 4;
+// like hll-sparse-max-bytes, a longer sparse string is stored dense
+const SPARSE_MAX_BYTES: usize = 3000;
+
+const SEED: u64 = 0xadc83b19;
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+// the registers of a HyperLogLog, decoded from either encoding
+struct Hll {
+    sparse: bool,
+    card: [u8; 8],
+    registers: Vec<u8>,
+}
+
+impl Hll {
+    // new ones start sparse, with a cached cardinality of 0
+    fn new() -> Self {
+        Self {
+            sparse: true,
+            card: [0; 8],
+            registers: vec![0; REGISTERS],
+        }
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, BackendError> {
+        if data.len() < HEADER_LEN || &data[..4] != MAGIC || data[4] > SPARSE {
+            return Err(BackendError::NotHll);
+        }
+        let sparse = data[4] == SPARSE;
+        if !sparse && data.len() != DENSE_LEN {
+            return Err(BackendError::NotHll);
+        }
+        let mut card = [0; 8];
+        card.copy_from_slice(&data[8..HEADER_LEN]);
+        let registers = match sparse {
+            true => decode_sparse(&data[HEADER_LEN..])?,
+            false => decode_dense(&data[HEADER_LEN..]),
+        };
+        Ok(Self {
+            sparse,
+            card,
+            registers,
+        })
+    }
+
+    // once dense, a HyperLogLog stays dense
+    fn encode(&self) -> Vec<u8> {
+        let sparse = match self.sparse {
+            true => encode_sparse(&self.registers)
+                .filter(|data| HEADER_LEN + data.len() <= SPARSE_MAX_BYTES),
+            false => None,
+        };
+        let mut out = Vec::with_capacity(DENSE_LEN);
+        out.extend_from_slice(MAGIC);
+        out.push(if sparse.is_some() { SPARSE } else { DENSE });
+        out.extend_from_slice(&[0; 3]);
+        out.extend_from_slice(&self.card);
+        match sparse {
+            Some(data) => out.extend_from_slice(&data),
+            None => out.extend_from_slice(&encode_dense(&self.registers)),
+        }
+        out
+    }
+
+    // whether a register was raised
+    fn add(&mut self, element: &[u8]) -> bool {
+        let hash = murmur_hash64a(element, SEED);
+        let index = hash as usize & (REGISTERS - 1);
+        // the run of zeros of the other bits, the extra bit ends it after Q bits at most
+        let count = ((hash >> P) | (1 << Q)).trailing_zeros() as u8 + 1;
+        if self.registers[index] >= count {
+            return false;
+        }
+        self.registers[index] = count;
+        true
+    }
+
+    fn cached(&self) -> Option<u64> {
+        match self.card[7] & 0x80 {
+            0 => Some(u64::from_le_bytes(self.card)),
+            _ => None,
+        }
+    }
+
+    fn set_cached(&mut self, count: u64) {
+        self.card = count.to_le_bytes();
+    }
+
+    fn invalidate(&mut self) {
+        self.card[7] |= 0x80;
+    }
+}
+
+impl Backend {
+    /// Add the elements to the HyperLogLog at key, created if needed. Returns whether its
+    /// estimate may have changed, i.e. a register was updated or the key created.
+    pub fn pfadd(&self, key: Vec<u8>, elements: &[Vec<u8>]) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let (mut hll, created) = match self.keyspace.get(&key) {
+            Some(v) => (Hll::decode(bytes(&v)?)?, false),
+            None => (Hll::new(), true),
+        };
+        // every element is added, even after one raised a register
+        let updated = elements.iter().filter(|element| hll.add(element)).count() > 0;
+        if updated {
+            hll.invalidate();
+        }
+        if updated || created {
+            self.store_hll(key, &hll)?;
+        }
+        Ok(updated || created)
+    }
+
+    /// The estimated cardinality of one HyperLogLog, cached in it, or of the union of several.
+    /// Missing keys count as empty ones.
+    pub fn pfcount(&self, keys: &[Vec<u8>]) -> Result<u64, BackendError> {
+        let [key] = keys else {
+            let _guard = self.locks.read(keys);
+            return Ok(estimate(&self.merge_registers(keys)?.0));
+        };
+
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let mut hll = match self.keyspace.get(key) {
+            Some(v) => Hll::decode(bytes(&v)?)?,
+            None => return Ok(0),
+        };
+        if let Some(count) = hll.cached() {
+            return Ok(count);
+        }
+        let count = estimate(&hll.registers);
+        hll.set_cached(count);
+        self.store_hll(key.clone(), &hll)?;
+        Ok(count)
+    }
+
+    /// Store the union of dest and the keys in dest. It is stored dense if any of them was.
+    pub fn pfmerge(&self, dest: Vec<u8>, keys: &[Vec<u8>]) -> Result<(), BackendError> {
+        let _guard = self.locks.write(keys.iter().chain([&dest]));
+        let sources = std::iter::once(dest.clone())
+            .chain(keys.iter().cloned())
+            .collect::<Vec<_>>();
+        let (registers, dense) = self.merge_registers(&sources)?;
+
+        let mut hll = match self.keyspace.get(&dest) {
+            Some(v) => Hll::decode(bytes(&v)?)?,
+            None => Hll::new(),
+        };
+        hll.registers = registers;
+        hll.sparse &= !dense;
+        hll.invalidate();
+        self.store_hll(dest, &hll)
+    }
+
+    // the highest register of all the keys, and whether one of them is dense
+    fn merge_registers(&self, keys: &[Vec<u8>]) -> Result<(Vec<u8>, bool), BackendError> {
+        let (mut registers, mut dense) = (vec![0; REGISTERS], false);
+        for key in keys {
+            self.expire_if_needed(key);
+            let hll = match self.keyspace.get(key) {
+                Some(v) => Hll::decode(bytes(&v)?)?,
+                None => continue,
+            };
+            dense |= !hll.sparse;
+            for (max, register) in registers.iter_mut().zip(hll.registers) {
+                *max = (*max).max(register);
+            }
+        }
+        Ok((registers, dense))
+    }
+
+    // the time to live of an existing key is kept
+    fn store_hll(&self, key: Vec<u8>, hll: &Hll) -> Result<(), BackendError> {
+        match self.keyspace.get_mut(&key) {
+            Some(mut v) => *bytes_mut(&mut v)? = hll.encode(),
+            None => {
+                self.keyspace
+                    .insert(key, Value::String(BulkString::new(hll.encode()).into()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// registers are packed from the least significant bits of each byte up
+fn decode_dense(data: &[u8]) -> Vec<u8> {
+    (0..REGISTERS)
+        .map(|i| {
+            let (byte, shift) = (i * REGISTER_BITS / 8, i * REGISTER_BITS % 8);
+            let low = data[byte] as u16;
+            let high = data.get(byte + 1).copied().unwrap_or(0) as u16;
+            ((low | high << 8) >> shift) as u8 & 0x3f
+        })
+        .collect()
+}
+
+fn encode_dense(registers: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; DENSE_LEN - HEADER_LEN];
+    for (i, register) in registers.iter().enumerate() {
+        let (byte, shift) = (i * REGISTER_BITS / 8, i * REGISTER_BITS % 8);
+        let bits = (*register as u16) << shift;
+        data[byte] |= bits as u8;
+        if let Some(next) = data.get_mut(byte + 1) {
+            *next |= (bits >> 8) as u8;
+        }
+    }
+    data
+}
+
+fn decode_sparse(data: &[u8]) -> Result<Vec<u8>, BackendError> {
+    let mut registers = vec![0; REGISTERS];
+    let (mut i, mut ops) = (0, data.iter());
+    while let Some(op) = ops.next() {
+        let (len, value) = match op >> 6 {
+            0b00 => ((op & 0x3f) as usize + 1, 0),
+            0b01 => {
+                let low = *ops.next().ok_or(BackendError::HllCorrupted)? as usize;
+                ((((op & 0x3f) as usize) << 8 | low) + 1, 0)
+            }
+            _ => ((op & 0x03) as usize + 1, (op >> 2 & 0x1f) + 1),
+        };
+        if i + len > REGISTERS {
+            return Err(BackendError::HllCorrupted);
+        }
+        registers[i..i + len].fill(value);
+        i += len;
+    }
+    if i != REGISTERS {
+        return Err(BackendError::HllCorrupted);
+    }
+    Ok(registers)
+}
+
+// None when a register is too high for a VAL opcode
+fn encode_sparse(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    let mut i = 0;
+    while i < REGISTERS {
+        let value = registers[i];
+        let run = registers[i..].iter().take_while(|r| **r == value).count();
+        i += run;
+
+        let mut len = run;
+        while len > 0 {
+            match value {
+                0 if len > ZERO_MAX_LEN => {
+                    let n = len.min(XZERO_MAX_LEN) - 1;
+                    data.extend_from_slice(&[0x40 | (n >> 8) as u8, n as u8]);
+                    len -= n + 1;
+                }
+                0 => {
+                    data.push((len - 1) as u8);
+                    len = 0;
+                }
+                value if value > VAL_MAX_VALUE => return None,
+                value => {
+                    let n = len.min(VAL_MAX_LEN);
+                    data.push(0x80 | (value - 1) << 2 | (n - 1) as u8);
+                    len -= n;
+                }
+            }
+        }
+    }
+    Some(data)
+}
+
+// the estimator of redis (Otmar Ertl's), from the histogram of the registers
+fn estimate(registers: &[u8]) -> u64 {
+    let mut histogram = [0.0; Q + 2];
+    for register in registers {
+        histogram[*register as usize] += 1.0;
+    }
+    let m = REGISTERS as f64;
+    let mut z = m * tau((m - histogram[Q + 1]) / m);
+    for count in histogram[1..=Q].iter().rev() {
+        z += count;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] / m);
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+// MurmurHash64A, the hash redis gives elements
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("8 bytes chunk"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, byte) in rest.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    fn get(backend: &Backend, key: &[u8]) -> Vec<u8> {
+        match backend.get(key) {
+            Ok(Some(RespFrame::BulkString(s))) => s.as_ref().to_vec(),
+            other => panic!("expected a string, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pfadd_encodes_like_redis() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert!(backend.pfadd(b"hll".to_vec(), &[])?);
+        assert!(!backend.pfadd(b"hll".to_vec(), &[])?);
+        // an empty sparse HyperLogLog is one XZERO of all the registers
+        let empty = get(&backend, b"hll");
+        assert_eq!(&empty[..5], b"HYLL\x01");
+        assert_eq!(&empty[HEADER_LEN..], &[0x7f, 0xff]);
+
+        let elements = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        assert!(backend.pfadd(b"hll".to_vec(), &elements)?);
+        assert!(!backend.pfadd(b"hll".to_vec(), &elements)?);
+        assert_eq!(backend.pfcount(&[b"hll".to_vec()]), Ok(3));
+        // the count is now cached in the header
+        assert_eq!(
+            &get(&backend, b"hll")[8..HEADER_LEN],
+            &[3, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        backend.set(b"s".to_vec(), BulkString::from("HYLL").into());
+        assert_eq!(backend.pfcount(&[b"s".to_vec()]), Err(BackendError::NotHll));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparse_and_dense_estimates() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let elements = |from: usize, to: usize| {
+            (from..to)
+                .map(|i| format!("element:{}", i).into_bytes())
+                .collect::<Vec<_>>()
+        };
+        backend.pfadd(b"a".to_vec(), &elements(0, 100))?;
+        backend.pfadd(b"b".to_vec(), &elements(50, 10050))?;
+        assert_eq!(get(&backend, b"a")[4], SPARSE);
+        assert_eq!(get(&backend, b"b")[4], DENSE);
+        assert_eq!(get(&backend, b"b").len(), DENSE_LEN);
+
+        let close = |count: u64, expected: f64| (count as f64 - expected).abs() / expected < 0.02;
+        assert!(close(backend.pfcount(&[b"a".to_vec()])?, 100.0));
+        assert!(close(backend.pfcount(&[b"b".to_vec()])?, 10000.0));
+        let union = backend.pfcount(&[b"a".to_vec(), b"b".to_vec(), b"none".to_vec()])?;
+        assert!(close(union, 10050.0));
+
+        // a dense source makes the destination dense
+        backend.pfmerge(b"a".to_vec(), &[b"b".to_vec()])?;
+        assert_eq!(get(&backend, b"a")[4], DENSE);
+        assert_eq!(backend.pfcount(&[b"a".to_vec()]), Ok(union));
+
+        // the registers survive both encodings
+        let hll = Hll::decode(&get(&backend, b"b"))?;
+        let sparse = encode_sparse(&hll.registers).expect("registers of at most 32");
+        assert_eq!(decode_sparse(&sparse)?, hll.registers);
+        Ok(())
+    }
+}
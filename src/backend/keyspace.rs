@@ -0,0 +1,244 @@
+use super::{Backend, BackendError};
+
+impl Backend {
+    /// Remove the given keys, returns how many of them existed.
+    pub fn del(&self, keys: &[Vec<u8>]) -> i64 {
+        let _guard = self.locks.write(keys);
+        keys.iter()
+            .filter(|key| !self.expire_if_needed(key) && self.remove_key(key))
+            .count() as i64
+    }
+
+    /// Number of the given keys that exist, a key mentioned twice is counted twice.
+    pub fn exists_count(&self, keys: &[Vec<u8>]) -> i64 {
+        let _guard = self.locks.read(keys);
+        keys.iter().filter(|key| self.exists(key)).count() as i64
+    }
+
+    /// Name of the type stored at key, "none" if the key does not exist.
+    pub fn key_type(&self, key: &[u8]) -> &'static str {
+        self.expire_if_needed(key);
+        self.keyspace
+            .get(key)
+            .map_or("none", |v| v.value().type_name())
+    }
+
+    /// Move the value and the time to live of key to newkey, replacing what newkey held.
+    /// With `nx`, nothing happens if newkey exists. Returns whether the key was renamed.
+    pub fn rename(&self, key: &[u8], newkey: &[u8], nx: bool) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([key, newkey]);
+        if !self.exists(key) {
+            return Err(BackendError::NoSuchKey);
+        }
+        if key == newkey {
+            return Ok(!nx);
+        }
+        if nx && self.exists(newkey) {
+            return Ok(false);
+        }
+
+        self.remove_key(newkey);
+        if let Some((_, value)) = self.keyspace.remove(key) {
+            self.keyspace.insert(newkey.to_vec(), value);
+        }
+        if let Some((_, at)) = self.expire.remove(key) {
+            self.expire.insert(newkey.to_vec(), at);
+        }
+        // clients blocked on newkey pop with its lock, release it first
+        drop(_guard);
+        self.wake(newkey);
+        Ok(true)
+    }
+
+    /// Copy the value and the time to live of source to destination.
+    /// Returns false if source does not exist, or destination exists and `replace` is not set.
+    pub fn copy(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        replace: bool,
+    ) -> Result<bool, BackendError> {
+        if source == destination {
+            return Err(BackendError::SameObject);
+        }
+
+        let _guard = self.locks.write([source, destination]);
+        if !self.exists(source) {
+            return Ok(false);
+        }
+        if self.exists(destination) {
+            if !replace {
+                return Ok(false);
+            }
+            self.remove_key(destination);
+        }
+
+        // clone before inserting, holding a ref while writing to the same shard would deadlock
+        let value = self.keyspace.get(source).map(|v| v.value().clone());
+        let at = self.expire.get(source).map(|v| *v.value());
+        if let Some(value) = value {
+            self.keyspace.insert(destination.to_vec(), value);
+        }
+        if let Some(at) = at {
+            self.expire.insert(destination.to_vec(), at);
+        }
+        drop(_guard);
+        self.wake(destination);
+        Ok(true)
+    }
+
+    /// A random key of any type, None if the keyspace is empty.
+    pub fn random_key(&self) -> Option<Vec<u8>> {
+        loop {
+            let key = self.keyspace.random_name()?;
+            // an expired key is dropped here, so the loop ends once only live keys are left
+            if !self.expire_if_needed(&key) {
+                return Some(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::now_ms, BulkString, RespFrame};
+
+    #[test]
+    fn test_del_and_exists_across_types() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+        backend.hset(
+            b"hash".to_vec(),
+            vec![(b"field".to_vec(), BulkString::from("value").into())],
+        )?;
+        backend.sadd(b"myset".to_vec(), vec![b"member".to_vec()])?;
+
+        let keys = ["str", "hash", "myset", "missing", "str"].map(|key| key.as_bytes().to_vec());
+        assert_eq!(backend.exists_count(&keys), 4);
+        assert_eq!(backend.key_type(b"hash"), "hash");
+        assert_eq!(backend.key_type(b"missing"), "none");
+
+        assert_eq!(backend.del(&keys), 3);
+        assert_eq!(backend.exists_count(&keys), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_one_value_per_key() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.hset(
+            b"key".to_vec(),
+            vec![(b"field".to_vec(), BulkString::from("value").into())],
+        )?;
+
+        assert_eq!(backend.get(b"key"), Err(BackendError::WrongType));
+        assert_eq!(
+            backend.sismember(b"key", b"field"),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(
+            backend.sadd(b"key".to_vec(), vec![b"member".to_vec()]),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(
+            backend.incr_by(b"key".to_vec(), 1),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(backend.mget(&[b"key".to_vec()]), vec![None]);
+
+        // SET replaces a value of any type
+        backend.set(b"key".to_vec(), BulkString::from("value").into());
+        assert_eq!(backend.key_type(b"key"), "string");
+        assert_eq!(backend.hget(b"key", b"field"), Err(BackendError::WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_moves_value_and_ttl() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"src".to_vec(), vec![b"member".to_vec()])?;
+        backend.set(b"dst".to_vec(), BulkString::from("old").into());
+        let at = now_ms() + 10_000;
+        backend.expire_at(b"src", at);
+
+        assert_eq!(backend.rename(b"src", b"dst", true), Ok(false));
+        assert_eq!(backend.rename(b"src", b"dst", false), Ok(true));
+        assert!(!backend.exists(b"src"));
+        assert_eq!(backend.key_type(b"dst"), "set");
+        assert_eq!(backend.sismember(b"dst", b"member"), Ok(1));
+        assert_eq!(backend.expire.get(b"dst".as_slice()).map(|v| *v), Some(at));
+
+        assert_eq!(
+            backend.rename(b"src", b"dst", false),
+            Err(BackendError::NoSuchKey)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_keeps_source() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.hset(
+            b"src".to_vec(),
+            vec![(b"field".to_vec(), BulkString::from("value").into())],
+        )?;
+        backend.set(b"dst".to_vec(), BulkString::from("old").into());
+
+        assert_eq!(backend.copy(b"src", b"dst", false), Ok(false));
+        assert_eq!(backend.copy(b"src", b"dst", true), Ok(true));
+        assert_eq!(backend.key_type(b"dst"), "hash");
+
+        // the copy is independent from the source
+        backend.hset(
+            b"dst".to_vec(),
+            vec![(b"other".to_vec(), BulkString::from("value").into())],
+        )?;
+        assert_eq!(backend.hget(b"src", b"other"), Ok(None));
+
+        assert_eq!(
+            backend.copy(b"src", b"src", true),
+            Err(BackendError::SameObject)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_key_skips_expired_keys() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(backend.random_key(), None);
+
+        backend.set(b"a".to_vec(), RespFrame::Integer(1));
+        backend.sadd(b"b".to_vec(), vec![b"member".to_vec()])?;
+        backend.expire.insert(b"a".to_vec(), now_ms() - 1);
+
+        for _ in 0..10 {
+            assert_eq!(backend.random_key(), Some(b"b".to_vec()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_key_draws_every_key() {
+        let backend = Backend::new();
+        for i in 0..20 {
+            backend.set(format!("key:{}", i).into_bytes(), RespFrame::Integer(i));
+        }
+        // keys deleted afterwards are never drawn
+        for i in 0..1000 {
+            backend.set(format!("gone:{}", i).into_bytes(), RespFrame::Integer(i));
+        }
+        let gone = (0..1000)
+            .map(|i| format!("gone:{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        backend.del(&gone);
+
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..1000 {
+            let key = backend.random_key().unwrap();
+            assert!(key.starts_with(b"key:"));
+            seen.insert(key);
+        }
+        assert_eq!(seen.len(), 20);
+    }
+}
@@ -0,0 +1,515 @@
+use super::scan::Entry;
+use super::{Backend, BackendError, Value};
+use std::collections::VecDeque;
+
+// like hashes and sets, a list is deleted as soon as its last element is removed
+
+/// The end of a list an element is pushed to or popped from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+// the key a multi-key pop was served from, with the popped elements
+type KeyElements = (Vec<u8>, Vec<Vec<u8>>);
+
+/// Where LINSERT puts the element, relative to the pivot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    Before,
+    After,
+}
+
+impl Backend {
+    /// Push the elements one after the other, so LPUSH key a b leaves b first.
+    /// With `only_existing` (LPUSHX / RPUSHX) nothing happens if the list does not exist.
+    /// Returns the length of the list after the push.
+    pub fn push(
+        &self,
+        key: Vec<u8>,
+        end: ListEnd,
+        elements: Vec<Vec<u8>>,
+        only_existing: bool,
+    ) -> Result<i64, BackendError> {
+        let len = {
+            let _guard = self.locks.write([&key]);
+            self.expire_if_needed(&key);
+            let mut entry = match self.keyspace.entry(key.clone()) {
+                Entry::Occupied(entry) => entry.into_ref(),
+                Entry::Vacant(_) if only_existing => return Ok(0),
+                Entry::Vacant(entry) => entry.insert(Value::List(VecDeque::new())),
+            };
+            let list = entry.as_list_mut()?;
+            for element in elements {
+                push_to(list, end, element);
+            }
+            list.len() as i64
+        };
+        // blocked clients pop with the lock of key, it must be released by now
+        self.wake(&key);
+        Ok(len)
+    }
+
+    /// Remove and return up to `count` elements, None if the list does not exist.
+    pub fn pop(
+        &self,
+        key: &[u8],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<Vec<u8>>>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.pop_locked(key, end, count)
+    }
+
+    /// Pop up to `count` elements from the first non-empty list among keys,
+    /// returns its key together with the elements.
+    pub fn lmpop(
+        &self,
+        keys: &[Vec<u8>],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<KeyElements>, BackendError> {
+        let _guard = self.locks.write(keys);
+        for key in keys {
+            if let Some(popped) = self.pop_locked(key, end, count)? {
+                return Ok(Some((key.clone(), popped)));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn llen(&self, key: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_list()?.len() as i64),
+            None => Ok(0),
+        }
+    }
+
+    /// Elements from start to stop, both included, negative indices count from the end.
+    pub fn lrange(&self, key: &[u8], start: i64, stop: i64) -> Result<Vec<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(v) => v,
+            None => return Ok(vec![]),
+        };
+        let list = entry.as_list()?;
+        match list_range(list.len(), start, stop) {
+            Some((start, stop)) => Ok(list.range(start..=stop).cloned().collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn lindex(&self, key: &[u8], index: i64) -> Result<Option<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let list = entry.as_list()?;
+        Ok(list_index(list.len(), index).and_then(|i| list.get(i).cloned()))
+    }
+
+    pub fn lset(&self, key: &[u8], index: i64, element: Vec<u8>) -> Result<(), BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let mut entry = self.keyspace.get_mut(key).ok_or(BackendError::NoSuchKey)?;
+        let list = entry.as_list_mut()?;
+        let i = list_index(list.len(), index).ok_or(BackendError::IndexOutOfRange)?;
+        list[i] = element;
+        Ok(())
+    }
+
+    /// Insert the element next to the first occurrence of pivot. Returns the length of the
+    /// list after the insert, 0 if the list does not exist and -1 if pivot is not found.
+    pub fn linsert(
+        &self,
+        key: &[u8],
+        position: InsertPosition,
+        pivot: &[u8],
+        element: Vec<u8>,
+    ) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let mut entry = match self.keyspace.get_mut(key) {
+            Some(v) => v,
+            None => return Ok(0),
+        };
+        let list = entry.as_list_mut()?;
+        let i = match list.iter().position(|v| v == pivot) {
+            Some(i) => i,
+            None => return Ok(-1),
+        };
+        match position {
+            InsertPosition::Before => list.insert(i, element),
+            InsertPosition::After => list.insert(i + 1, element),
+        }
+        Ok(list.len() as i64)
+    }
+
+    /// Remove the first `count` occurrences of element from the head, or from the tail if
+    /// count is negative, all of them if count is 0. Returns how many were removed.
+    pub fn lrem(&self, key: &[u8], count: i64, element: &[u8]) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let (removed, empty) = match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let list = v.as_list_mut()?;
+                let limit = match count {
+                    0 => usize::MAX,
+                    count => count.unsigned_abs() as usize,
+                };
+                let mut matches = list
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| v.as_slice() == element)
+                    .map(|(i, _)| i)
+                    .collect::<Vec<usize>>();
+                if count < 0 {
+                    matches.reverse();
+                }
+                matches.truncate(limit);
+                // remove from the back so the indices left are still valid
+                matches.sort_unstable();
+                for i in matches.iter().rev() {
+                    list.remove(*i);
+                }
+                (matches.len() as i64, list.is_empty())
+            }
+            None => return Ok(0),
+        };
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(removed)
+    }
+
+    /// Keep the elements from start to stop only, both included.
+    pub fn ltrim(&self, key: &[u8], start: i64, stop: i64) -> Result<(), BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let empty = match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let list = v.as_list_mut()?;
+                match list_range(list.len(), start, stop) {
+                    Some((start, stop)) => {
+                        list.truncate(stop + 1);
+                        list.drain(..start);
+                        false
+                    }
+                    None => true,
+                }
+            }
+            None => return Ok(()),
+        };
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(())
+    }
+
+    /// Indices of the element. RANK skips the first rank - 1 matches, a negative rank searches
+    /// from the tail. Up to `count` indices are returned (0 for all of them), and only the
+    /// first `maxlen` elements in the search direction are compared (0 for the whole list).
+    pub fn lpos(
+        &self,
+        key: &[u8],
+        element: &[u8],
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Result<Vec<i64>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(v) => v,
+            None => return Ok(vec![]),
+        };
+        let list = entry.as_list()?;
+
+        let maxlen = if maxlen == 0 { list.len() } else { maxlen };
+        let count = if count == 0 { usize::MAX } else { count };
+        let skip = rank.unsigned_abs() as usize - 1;
+        let indices: Box<dyn Iterator<Item = usize>> = if rank > 0 {
+            Box::new(0..list.len())
+        } else {
+            Box::new((0..list.len()).rev())
+        };
+        Ok(indices
+            .take(maxlen)
+            .filter(|i| list[*i] == element)
+            .skip(skip)
+            .take(count)
+            .map(|i| i as i64)
+            .collect())
+    }
+
+    /// Pop an element from one end of source and push it to one end of destination,
+    /// None if source does not exist. Source and destination may be the same list.
+    pub fn lmove(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<Vec<u8>>, BackendError> {
+        let element = self.move_element(source, destination, from, to)?;
+        if element.is_some() {
+            self.wake(destination);
+        }
+        Ok(element)
+    }
+
+    fn move_element(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Result<Option<Vec<u8>>, BackendError> {
+        let _guard = self.locks.write([source, destination]);
+        self.expire_if_needed(source);
+        self.expire_if_needed(destination);
+        // both types are checked before anything moves
+        match self.keyspace.get(source) {
+            Some(v) => {
+                v.as_list()?;
+            }
+            None => return Ok(None),
+        }
+        if let Some(v) = self.keyspace.get(destination) {
+            v.as_list()?;
+        }
+
+        let element = match self.pop_locked(source, from, 1)? {
+            Some(mut popped) => popped.pop(),
+            None => None,
+        };
+        if let Some(element) = &element {
+            let mut entry = self
+                .keyspace
+                .entry(destination.to_vec())
+                .or_insert_with(|| Value::List(VecDeque::new()));
+            push_to(entry.as_list_mut()?, to, element.clone());
+        }
+        Ok(element)
+    }
+
+    // pop for callers already holding the lock of key
+    fn pop_locked(
+        &self,
+        key: &[u8],
+        end: ListEnd,
+        count: usize,
+    ) -> Result<Option<Vec<Vec<u8>>>, BackendError> {
+        self.expire_if_needed(key);
+        let (popped, empty) = match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let list = v.as_list_mut()?;
+                let popped = (0..count)
+                    .map_while(|_| pop_from(list, end))
+                    .collect::<Vec<Vec<u8>>>();
+                (popped, list.is_empty())
+            }
+            None => return Ok(None),
+        };
+        // the ref is released by now, removing the key while holding it would deadlock
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(Some(popped))
+    }
+}
+
+fn push_to(list: &mut VecDeque<Vec<u8>>, end: ListEnd, element: Vec<u8>) {
+    match end {
+        ListEnd::Left => list.push_front(element),
+        ListEnd::Right => list.push_back(element),
+    }
+}
+
+fn pop_from(list: &mut VecDeque<Vec<u8>>, end: ListEnd) -> Option<Vec<u8>> {
+    match end {
+        ListEnd::Left => list.pop_front(),
+        ListEnd::Right => list.pop_back(),
+    }
+}
+
+// resolve a possibly negative index, None if it is out of the list
+fn list_index(len: usize, index: i64) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+// resolve an inclusive start / stop range like LRANGE and LTRIM, None if it is empty
+fn list_range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn elements(names: &[&str]) -> Vec<Vec<u8>> {
+        names.iter().map(|name| name.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_push_pop() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.push(b"list".to_vec(), ListEnd::Left, elements(&["a"]), true),
+            Ok(0)
+        );
+        assert!(!backend.exists(b"list"));
+
+        let pushed = backend.push(
+            b"list".to_vec(),
+            ListEnd::Left,
+            elements(&["a", "b"]),
+            false,
+        );
+        assert_eq!(pushed, Ok(2));
+        let pushed = backend.push(b"list".to_vec(), ListEnd::Right, elements(&["c"]), true);
+        assert_eq!(pushed, Ok(3));
+        assert_eq!(backend.lrange(b"list", 0, -1)?, elements(&["b", "a", "c"]));
+
+        assert_eq!(
+            backend.pop(b"list", ListEnd::Right, 2)?,
+            Some(elements(&["c", "a"]))
+        );
+        assert_eq!(
+            backend.pop(b"list", ListEnd::Left, 5)?,
+            Some(elements(&["b"]))
+        );
+        assert!(!backend.exists(b"list"));
+        assert_eq!(backend.pop(b"list", ListEnd::Left, 1), Ok(None));
+
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+        assert_eq!(backend.llen(b"str"), Err(BackendError::WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_negative_indices() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let list = elements(&["a", "b", "c", "d"]);
+        backend.push(b"list".to_vec(), ListEnd::Right, list, false)?;
+
+        assert_eq!(backend.lrange(b"list", -2, -1)?, elements(&["c", "d"]));
+        assert_eq!(backend.lrange(b"list", -100, 1)?, elements(&["a", "b"]));
+        assert_eq!(backend.lrange(b"list", 2, 100)?, elements(&["c", "d"]));
+        assert!(backend.lrange(b"list", 3, 1)?.is_empty());
+        assert!(backend.lrange(b"list", 5, 10)?.is_empty());
+        assert_eq!(backend.lindex(b"list", -1)?, Some(b"d".to_vec()));
+        assert_eq!(backend.lindex(b"list", 4)?, None);
+
+        backend.lset(b"list", -4, b"z".to_vec())?;
+        assert_eq!(backend.lindex(b"list", 0)?, Some(b"z".to_vec()));
+        assert_eq!(
+            backend.lset(b"list", 4, b"z".to_vec()),
+            Err(BackendError::IndexOutOfRange)
+        );
+        assert_eq!(
+            backend.lset(b"missing", 0, b"z".to_vec()),
+            Err(BackendError::NoSuchKey)
+        );
+
+        backend.ltrim(b"list", 1, -2)?;
+        assert_eq!(backend.lrange(b"list", 0, -1)?, elements(&["b", "c"]));
+        backend.ltrim(b"list", 5, 10)?;
+        assert!(!backend.exists(b"list"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_linsert_lrem() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let list = elements(&["a", "x", "b", "x", "c", "x"]);
+        backend.push(b"list".to_vec(), ListEnd::Right, list, false)?;
+
+        let insert =
+            |position, pivot: &[u8]| backend.linsert(b"list", position, pivot, b"new".to_vec());
+        assert_eq!(insert(InsertPosition::Before, b"a"), Ok(7));
+        assert_eq!(insert(InsertPosition::After, b"c"), Ok(8));
+        assert_eq!(insert(InsertPosition::After, b"missing"), Ok(-1));
+        assert_eq!(
+            backend.linsert(b"missing", InsertPosition::After, b"a", b"new".to_vec()),
+            Ok(0)
+        );
+
+        assert_eq!(backend.lrem(b"list", -2, b"x"), Ok(2));
+        assert_eq!(
+            backend.lrange(b"list", 0, -1)?,
+            elements(&["new", "a", "x", "b", "c", "new"])
+        );
+        assert_eq!(backend.lrem(b"list", 0, b"new"), Ok(2));
+        assert_eq!(backend.lrem(b"list", 1, b"x"), Ok(1));
+        assert_eq!(backend.lrange(b"list", 0, -1)?, elements(&["a", "b", "c"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let list = elements(&["a", "b", "c", "1", "2", "3", "c", "c"]);
+        backend.push(b"list".to_vec(), ListEnd::Right, list, false)?;
+
+        assert_eq!(backend.lpos(b"list", b"c", 1, 1, 0), Ok(vec![2]));
+        assert_eq!(backend.lpos(b"list", b"c", 2, 1, 0), Ok(vec![6]));
+        assert_eq!(backend.lpos(b"list", b"c", -1, 1, 0), Ok(vec![7]));
+        assert_eq!(backend.lpos(b"list", b"c", 1, 0, 0), Ok(vec![2, 6, 7]));
+        assert_eq!(backend.lpos(b"list", b"c", -1, 2, 0), Ok(vec![7, 6]));
+        assert_eq!(backend.lpos(b"list", b"c", 1, 0, 3), Ok(vec![2]));
+        assert_eq!(backend.lpos(b"list", b"missing", 1, 0, 0), Ok(vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmove_lmpop() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let list = elements(&["a", "b", "c"]);
+        backend.push(b"src".to_vec(), ListEnd::Right, list, false)?;
+
+        let moved = backend.lmove(b"src", b"dst", ListEnd::Right, ListEnd::Left)?;
+        assert_eq!(moved, Some(b"c".to_vec()));
+        // rotating a list on itself
+        let moved = backend.lmove(b"src", b"src", ListEnd::Left, ListEnd::Right)?;
+        assert_eq!(moved, Some(b"a".to_vec()));
+        assert_eq!(backend.lrange(b"src", 0, -1)?, elements(&["b", "a"]));
+        assert_eq!(
+            backend.lmove(b"missing", b"dst", ListEnd::Left, ListEnd::Left),
+            Ok(None)
+        );
+
+        let keys = elements(&["missing", "dst", "src"]);
+        assert_eq!(
+            backend.lmpop(&keys, ListEnd::Left, 10)?,
+            Some((b"dst".to_vec(), elements(&["c"])))
+        );
+        assert_eq!(
+            backend.lmpop(&keys, ListEnd::Right, 1)?,
+            Some((b"src".to_vec(), elements(&["a"])))
+        );
+        assert_eq!(
+            backend.lmpop(&elements(&["missing"]), ListEnd::Left, 1),
+            Ok(None)
+        );
+        Ok(())
+    }
+}
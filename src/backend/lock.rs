@@ -0,0 +1,94 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+const LOCK_STRIPES: usize = 1024;
+
+/// Striped locks for commands touching several keys at once (e.g. MSET / MGET).
+///
+/// Every key is mapped to one stripe. Multi-key commands acquire the stripes of all their keys
+/// in ascending order, so two commands can never wait on each other. DashMap only locks one
+/// shard at a time, these locks are what make a multi-key update atomic for other connections.
+/// Single-key writes take the stripe of their key too, so a RENAME or MSET never interleaves
+/// with them. They must be taken before any DashMap reference is held, and never twice in the
+/// same call.
+#[derive(Debug)]
+pub(crate) struct KeyLocks {
+    stripes: Vec<RwLock<()>>,
+    hasher: RandomState,
+}
+
+impl KeyLocks {
+    pub(crate) fn new() -> Self {
+        Self {
+            stripes: (0..LOCK_STRIPES).map(|_| RwLock::new(())).collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub(crate) fn read<'a, K: AsRef<[u8]> + ?Sized + 'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a K>,
+    ) -> Vec<RwLockReadGuard<'_, ()>> {
+        self.stripes_of(keys)
+            .into_iter()
+            .map(|i| self.stripes[i].read().unwrap_or_else(|e| e.into_inner()))
+            .collect()
+    }
+
+    pub(crate) fn write<'a, K: AsRef<[u8]> + ?Sized + 'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a K>,
+    ) -> Vec<RwLockWriteGuard<'_, ()>> {
+        self.stripes_of(keys)
+            .into_iter()
+            .map(|i| self.stripes[i].write().unwrap_or_else(|e| e.into_inner()))
+            .collect()
+    }
+
+    // sorted and deduplicated, which gives every caller the same locking order
+    fn stripes_of<'a, K: AsRef<[u8]> + ?Sized + 'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a K>,
+    ) -> Vec<usize> {
+        let mut stripes = keys
+            .into_iter()
+            .map(|key| self.hasher.hash_one(key.as_ref()) as usize % LOCK_STRIPES)
+            .collect::<Vec<usize>>();
+        stripes.sort_unstable();
+        stripes.dedup();
+        stripes
+    }
+}
+
+impl Default for KeyLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stripes_are_sorted_and_unique() {
+        let locks = KeyLocks::new();
+        let keys = ["a", "b", "c", "a", "b"];
+        let stripes = locks.stripes_of(keys.iter());
+        assert!(stripes.windows(2).all(|w| w[0] < w[1]));
+        assert!(stripes.len() <= 3);
+    }
+
+    #[test]
+    fn test_write_then_read_same_keys() {
+        let locks = KeyLocks::new();
+        let keys = ["a".to_string(), "b".to_string()];
+        let guards = locks.write(keys.iter());
+        drop(guards);
+        let guards = locks.read(keys.iter());
+        assert!(!guards.is_empty());
+    }
+}
@@ -0,0 +1,557 @@
+use super::scan::Entry;
+use super::{now_ms, Backend, BackendError, KeyExpire, Value};
+use crate::{format_incr_float, BulkString, RespFrame};
+
+// same limit as redis' proto-max-bulk-len
+const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+/// Dynamic programming table of the longest common subsequence of two strings.
+#[derive(Debug)]
+pub struct LcsTable {
+    a: Vec<u8>,
+    b: Vec<u8>,
+    // table[i * (b.len() + 1) + j] is the LCS length of a[..i] and b[..j]
+    table: Vec<u32>,
+}
+
+/// A contiguous range shared by both strings, bounds are inclusive.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LcsMatch {
+    pub a: (usize, usize),
+    pub b: (usize, usize),
+}
+
+// counters are stored as BulkString like redis does, so GET returns them as plain strings.
+// The read-modify-write happens while holding the entry lock, concurrent updates never get lost.
+impl Backend {
+    pub fn incr_by(&self, key: Vec<u8>, delta: i64) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        match self.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let current = parse_integer(entry.get().as_string()?)?;
+                let value = current.checked_add(delta).ok_or(BackendError::Overflow)?;
+                *entry.get_mut() = Value::String(BulkString::from(value.to_string()).into());
+                Ok(value)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Value::String(BulkString::from(delta.to_string()).into()));
+                Ok(delta)
+            }
+        }
+    }
+
+    pub fn incr_by_float(&self, key: Vec<u8>, delta: f64) -> Result<f64, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let mut entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::String(BulkString::from("0").into()));
+
+        let value = parse_float(entry.as_string()?)? + delta;
+        if !value.is_finite() {
+            return Err(BackendError::NanOrInfinity);
+        }
+        *entry.value_mut() = Value::String(BulkString::from(format_incr_float(value)).into());
+        Ok(value)
+    }
+
+    pub fn mget(&self, keys: &[Vec<u8>]) -> Vec<Option<RespFrame>> {
+        let _guard = self.locks.read(keys);
+        keys.iter()
+            .map(|key| {
+                self.expire_if_needed(key);
+                // like redis, keys of another type read as nil
+                self.keyspace
+                    .get(key)
+                    .and_then(|v| v.as_string().ok().cloned())
+            })
+            .collect()
+    }
+
+    pub fn mset(&self, pairs: Vec<(Vec<u8>, RespFrame)>) {
+        let _guard = self.locks.write(pairs.iter().map(|(key, _)| key));
+        for (key, value) in pairs {
+            self.expire.remove(&key);
+            self.keyspace.insert(key, Value::String(value));
+        }
+    }
+
+    // all or nothing: no key is written if any of them already exists
+    pub fn msetnx(&self, pairs: Vec<(Vec<u8>, RespFrame)>) -> bool {
+        let _guard = self.locks.write(pairs.iter().map(|(key, _)| key));
+        if pairs.iter().any(|(key, _)| self.exists(key)) {
+            return false;
+        }
+        for (key, value) in pairs {
+            self.expire.remove(&key);
+            self.keyspace.insert(key, Value::String(value));
+        }
+        true
+    }
+
+    pub fn append(&self, key: Vec<u8>, value: &[u8]) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let mut entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::String(BulkString::new(Vec::new()).into()));
+
+        let data = bytes_mut(entry.value_mut())?;
+        check_string_len(data.len() + value.len())?;
+        data.extend_from_slice(value);
+        Ok(data.len())
+    }
+
+    pub fn strlen(&self, key: &[u8]) -> Result<usize, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(bytes(v.value())?.len()),
+            None => Ok(0),
+        }
+    }
+
+    // start and end are inclusive, negative offsets count from the end of the string
+    pub fn getrange(&self, key: &[u8], start: i64, end: i64) -> Result<Vec<u8>, BackendError> {
+        self.expire_if_needed(key);
+        let value = match self.keyspace.get(key) {
+            Some(v) => bytes(v.value())?.to_vec(),
+            None => return Ok(vec![]),
+        };
+
+        let len = value.len() as i64;
+        if (start < 0 && end < 0 && start > end) || len == 0 {
+            return Ok(vec![]);
+        }
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let end = if end < 0 {
+            (len + end).max(0)
+        } else {
+            end.min(len - 1)
+        };
+        if start > end {
+            return Ok(vec![]);
+        }
+        Ok(value[start as usize..=end as usize].to_vec())
+    }
+
+    // overwrite part of the string, zero-padding it if the offset is past the end
+    pub fn setrange(
+        &self,
+        key: Vec<u8>,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<usize, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        match self.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let data = bytes_mut(entry.get_mut())?;
+                if value.is_empty() {
+                    return Ok(data.len());
+                }
+                let end = offset + value.len();
+                check_string_len(end)?;
+                if data.len() < end {
+                    data.resize(end, 0);
+                }
+                data[offset..end].copy_from_slice(value);
+                Ok(data.len())
+            }
+            Entry::Vacant(entry) => {
+                if value.is_empty() {
+                    return Ok(0);
+                }
+                let end = offset + value.len();
+                check_string_len(end)?;
+                let mut data = vec![0; end];
+                data[offset..].copy_from_slice(value);
+                entry.insert(Value::String(BulkString::new(data).into()));
+                Ok(end)
+            }
+        }
+    }
+
+    pub fn getdel(&self, key: &[u8]) -> Result<Option<RespFrame>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let value = match self.keyspace.get(key) {
+            Some(v) => {
+                bytes(v.value())?;
+                v.as_string()?.clone()
+            }
+            None => return Ok(None),
+        };
+        self.remove_key(key);
+        Ok(Some(value))
+    }
+
+    pub fn getex(&self, key: &[u8], expire: KeyExpire) -> Result<Option<RespFrame>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let value = match self.keyspace.get(key) {
+            Some(v) => {
+                bytes(v.value())?;
+                v.as_string()?.clone()
+            }
+            None => return Ok(None),
+        };
+
+        match expire {
+            KeyExpire::At(at) if at <= now_ms() => {
+                self.remove_key(key);
+            }
+            expire => self.update_expire(key, expire),
+        }
+        Ok(Some(value))
+    }
+
+    // longest common subsequence of two strings, missing keys are treated as empty strings
+    pub fn lcs(&self, key1: &[u8], key2: &[u8]) -> Result<LcsTable, BackendError> {
+        let a = self.get_bytes(key1)?;
+        let b = self.get_bytes(key2)?;
+        LcsTable::new(a, b)
+    }
+
+    pub(crate) fn get_bytes(&self, key: &[u8]) -> Result<Vec<u8>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(bytes(v.value())?.to_vec()),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+pub(crate) fn bytes(value: &Value) -> Result<&[u8], BackendError> {
+    match value {
+        Value::String(RespFrame::BulkString(s)) => Ok(s.as_ref()),
+        _ => Err(BackendError::WrongType),
+    }
+}
+
+pub(crate) fn bytes_mut(value: &mut Value) -> Result<&mut Vec<u8>, BackendError> {
+    match value {
+        Value::String(RespFrame::BulkString(s)) => Ok(s.0.get_or_insert_with(Vec::new)),
+        _ => Err(BackendError::WrongType),
+    }
+}
+
+fn check_string_len(len: usize) -> Result<(), BackendError> {
+    if len > MAX_STRING_LEN {
+        return Err(BackendError::StringTooLong);
+    }
+    Ok(())
+}
+
+pub(crate) fn parse_integer(frame: &RespFrame) -> Result<i64, BackendError> {
+    match frame {
+        RespFrame::Integer(n) => Ok(*n),
+        RespFrame::BulkString(s) if is_integer_text(s.as_ref()) => std::str::from_utf8(s.as_ref())
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(BackendError::NotInteger),
+        _ => Err(BackendError::NotInteger),
+    }
+}
+
+// like string2ll of redis, only the exact text of an integer: no `+` and no leading zero
+// (e.g. "0123" or "-0"), so a value that INCR accepts reads back the same
+fn is_integer_text(s: &[u8]) -> bool {
+    let digits = s.strip_prefix(b"-").unwrap_or(s);
+    match digits {
+        [] => false,
+        [b'0'] => digits.len() == s.len(),
+        [b'0', ..] => false,
+        _ => digits.iter().all(u8::is_ascii_digit),
+    }
+}
+
+pub(crate) fn parse_float(frame: &RespFrame) -> Result<f64, BackendError> {
+    let n = match frame {
+        RespFrame::Integer(n) => Some(*n as f64),
+        RespFrame::Double(n) => Some(*n),
+        RespFrame::BulkString(s) => std::str::from_utf8(s.as_ref())
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok()),
+        _ => None,
+    };
+    n.filter(|n| n.is_finite()).ok_or(BackendError::NotFloat)
+}
+
+impl LcsTable {
+    // like redis, the table must fit in proto-max-bulk-len, two large strings would otherwise
+    // take gigabytes and keep the thread busy for minutes
+    fn new(a: Vec<u8>, b: Vec<u8>) -> Result<Self, BackendError> {
+        let width = b.len() + 1;
+        let cells = (a.len() + 1)
+            .checked_mul(width)
+            .filter(|cells| {
+                cells
+                    .checked_mul(std::mem::size_of::<u32>())
+                    .is_some_and(|size| size <= MAX_STRING_LEN)
+            })
+            .ok_or(BackendError::LcsTooLarge)?;
+        let mut table = vec![0u32; cells];
+        for i in 1..=a.len() {
+            for j in 1..=b.len() {
+                table[i * width + j] = if a[i - 1] == b[j - 1] {
+                    table[(i - 1) * width + j - 1] + 1
+                } else {
+                    table[(i - 1) * width + j].max(table[i * width + j - 1])
+                };
+            }
+        }
+        Ok(Self { a, b, table })
+    }
+
+    fn at(&self, i: usize, j: usize) -> u32 {
+        self.table[i * (self.b.len() + 1) + j]
+    }
+
+    pub fn len(&self) -> usize {
+        self.at(self.a.len(), self.b.len()) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Walk the table back from the end and collect the subsequence with its matching ranges,
+    /// ranges are reported from the end of the strings like redis does.
+    pub fn matches(&self) -> (Vec<u8>, Vec<LcsMatch>) {
+        let mut result = vec![0u8; self.len()];
+        let mut matches = vec![];
+        let (mut i, mut j, mut idx) = (self.a.len(), self.b.len(), self.len());
+        let mut current: Option<LcsMatch> = None;
+
+        while i > 0 && j > 0 {
+            let mut emit = false;
+            if self.a[i - 1] == self.b[j - 1] {
+                result[idx - 1] = self.a[i - 1];
+                match current {
+                    // extend the current range backward, it is contiguous
+                    Some(ref mut m) if m.a.0 == i && m.b.0 == j => {
+                        m.a.0 -= 1;
+                        m.b.0 -= 1;
+                    }
+                    Some(_) => emit = true,
+                    None => {
+                        current = Some(LcsMatch {
+                            a: (i - 1, i - 1),
+                            b: (j - 1, j - 1),
+                        })
+                    }
+                }
+                if current.as_ref().is_some_and(|m| m.a.0 == 0 || m.b.0 == 0) {
+                    emit = true;
+                }
+                idx -= 1;
+                i -= 1;
+                j -= 1;
+            } else {
+                if self.at(i - 1, j) > self.at(i, j - 1) {
+                    i -= 1;
+                } else {
+                    j -= 1;
+                }
+                emit = current.is_some();
+            }
+
+            if emit {
+                matches.extend(current.take());
+            }
+        }
+        (result, matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_incr_by() {
+        let backend = Backend::new();
+        assert_eq!(backend.incr_by(b"counter".to_vec(), 5), Ok(5));
+        assert_eq!(backend.incr_by(b"counter".to_vec(), -7), Ok(-2));
+        assert_eq!(
+            backend.get(b"counter"),
+            Ok(Some(BulkString::from("-2").into()))
+        );
+
+        backend.set(b"counter".to_vec(), BulkString::from("abc").into());
+        assert_eq!(
+            backend.incr_by(b"counter".to_vec(), 1),
+            Err(BackendError::NotInteger)
+        );
+
+        backend.set(
+            b"counter".to_vec(),
+            BulkString::from(i64::MAX.to_string()).into(),
+        );
+        assert_eq!(
+            backend.incr_by(b"counter".to_vec(), 1),
+            Err(BackendError::Overflow)
+        );
+
+        // only the exact text of an integer
+        for text in ["0123", "+5", "-0", "", "-", " 1"] {
+            backend.set(b"counter".to_vec(), BulkString::from(text).into());
+            assert_eq!(
+                backend.incr_by(b"counter".to_vec(), 1),
+                Err(BackendError::NotInteger)
+            );
+        }
+        backend.set(b"counter".to_vec(), BulkString::from("0").into());
+        assert_eq!(backend.incr_by(b"counter".to_vec(), -1), Ok(-1));
+    }
+
+    #[test]
+    fn test_incr_by_float() {
+        let backend = Backend::new();
+        backend.set(b"price".to_vec(), BulkString::from("10.50").into());
+        assert_eq!(backend.incr_by_float(b"price".to_vec(), 0.1), Ok(10.6));
+        assert_eq!(
+            backend.get(b"price"),
+            Ok(Some(BulkString::from("10.6").into()))
+        );
+
+        assert_eq!(backend.incr_by_float(b"price".to_vec(), -5.6), Ok(5.0));
+        assert_eq!(
+            backend.get(b"price"),
+            Ok(Some(BulkString::from("5").into()))
+        );
+
+        backend.set(b"price".to_vec(), BulkString::from("abc").into());
+        assert_eq!(
+            backend.incr_by_float(b"price".to_vec(), 1.0),
+            Err(BackendError::NotFloat)
+        );
+    }
+
+    #[test]
+    fn test_concurrent_incr_by() {
+        let backend = Backend::new();
+        let handles = (0..8)
+            .map(|_| {
+                let backend = backend.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        backend.incr_by(b"counter".to_vec(), 1).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(backend.incr_by(b"counter".to_vec(), 0), Ok(8000));
+    }
+
+    #[test]
+    fn test_append_setrange_getrange() {
+        let backend = Backend::new();
+        assert_eq!(backend.append(b"key".to_vec(), b"Hello"), Ok(5));
+        assert_eq!(backend.append(b"key".to_vec(), b" World"), Ok(11));
+        assert_eq!(backend.strlen(b"key"), Ok(11));
+        assert_eq!(backend.strlen(b"missing"), Ok(0));
+
+        assert_eq!(backend.getrange(b"key", 0, 4), Ok(b"Hello".to_vec()));
+        assert_eq!(backend.getrange(b"key", -5, -1), Ok(b"World".to_vec()));
+        assert_eq!(backend.getrange(b"key", 0, -1), Ok(b"Hello World".to_vec()));
+        assert_eq!(backend.getrange(b"key", 10, 100), Ok(b"d".to_vec()));
+        assert_eq!(backend.getrange(b"key", 5, 3), Ok(vec![]));
+        assert_eq!(backend.getrange(b"key", -1, -5), Ok(vec![]));
+
+        assert_eq!(backend.setrange(b"key".to_vec(), 6, b"Redis"), Ok(11));
+        assert_eq!(
+            backend.get(b"key"),
+            Ok(Some(BulkString::from("Hello Redis").into()))
+        );
+
+        assert_eq!(backend.setrange(b"pad".to_vec(), 3, b"abc"), Ok(6));
+        assert_eq!(
+            backend.get(b"pad"),
+            Ok(Some(BulkString::from(b"\0\0\0abc").into()))
+        );
+        assert_eq!(backend.setrange(b"empty".to_vec(), 3, b""), Ok(0));
+        assert_eq!(backend.get(b"empty"), Ok(None));
+
+        backend.set(b"int".to_vec(), RespFrame::Integer(1));
+        assert_eq!(
+            backend.append(b"int".to_vec(), b"1"),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(backend.strlen(b"int"), Err(BackendError::WrongType));
+    }
+
+    #[test]
+    fn test_getdel_getex() {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("value").into());
+
+        assert_eq!(
+            backend.getex(b"key", KeyExpire::At(now_ms() + 10_000)),
+            Ok(Some(BulkString::from("value").into()))
+        );
+        assert!(backend.pttl(b"key") > 0);
+        assert_eq!(
+            backend.getex(b"key", KeyExpire::Persist),
+            Ok(Some(BulkString::from("value").into()))
+        );
+        assert_eq!(backend.pttl(b"key"), -1);
+
+        assert_eq!(
+            backend.getdel(b"key"),
+            Ok(Some(BulkString::from("value").into()))
+        );
+        assert_eq!(backend.getdel(b"key"), Ok(None));
+        assert_eq!(backend.get(b"key"), Ok(None));
+    }
+
+    #[test]
+    fn test_lcs() {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("ohmytext").into());
+        backend.set(b"key2".to_vec(), BulkString::from("mynewtext").into());
+
+        let lcs = backend.lcs(b"key1", b"key2").unwrap();
+        assert_eq!(lcs.len(), 6);
+
+        let (result, matches) = lcs.matches();
+        assert_eq!(result, b"mytext");
+        assert_eq!(
+            matches,
+            vec![
+                LcsMatch {
+                    a: (4, 7),
+                    b: (5, 8)
+                },
+                LcsMatch {
+                    a: (2, 3),
+                    b: (0, 1)
+                },
+            ]
+        );
+
+        let lcs = backend.lcs(b"key1", b"missing").unwrap();
+        assert!(lcs.is_empty());
+        assert_eq!(lcs.matches(), (vec![], vec![]));
+
+        // 100 KB each would take a 40 GB table
+        let long = "a".repeat(100 * 1024);
+        backend.set(b"key1".to_vec(), BulkString::from(long.as_str()).into());
+        backend.set(b"key2".to_vec(), BulkString::from(long.as_str()).into());
+        assert!(matches!(
+            backend.lcs(b"key1", b"key2"),
+            Err(BackendError::LcsTooLarge)
+        ));
+    }
+}
@@ -1,16 +1,167 @@
-use crate::RespFrame;
-use dashmap::{DashMap, DashSet};
+mod bitmap;
+mod blocking;
+mod expire;
+mod geo;
+mod glob;
+mod hexpire;
+mod hmap;
+mod hyperloglog;
+mod keyspace;
+mod list;
+mod lock;
+mod map;
+mod pubsub;
+mod rank_tree;
+mod scan;
+mod set;
+mod stream;
+mod stream_group;
+mod zset;
+
+pub use bitmap::{BitFieldOp, BitFieldOverflow, BitFieldType, BitOperation, BitUnit};
+pub use blocking::ServeFn;
+pub(crate) use expire::now_ms;
+pub use geo::{valid_position, GeoMatch, GeoOrigin, GeoQuery, GeoShape, GeoSort};
+pub use hmap::HashValue;
+pub use list::{InsertPosition, ListEnd};
+pub use map::{LcsMatch, LcsTable};
+pub use pubsub::{PubSubMessage, Subscriber};
+pub use scan::{ScanMap, ScanPage, ScanSet};
+pub use set::SetOperation;
+pub use stream::{Stream, StreamEntry, StreamFields, StreamId, StreamTrim, TrimStrategy, XAddId};
+pub use stream_group::{
+    AutoClaim, ClaimOptions, ConsumerGroup, ConsumerInfo, GroupEntry, GroupInfo, PendingInfo,
+    PendingSummary, StreamInfo,
+};
+pub use zset::{
+    Aggregate, LexBound, ScoreBound, ScoreEnd, ScoreUpdate, SortedSet, ZAddOptions, ZRangeBy,
+};
+
+use crate::{RespFrame, SimpleError};
+use blocking::Waiters;
+use lock::KeyLocks;
+use pubsub::PubSub;
+use scan::Entry;
+use std::collections::VecDeque;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{atomic::AtomicU64, Arc};
+use thiserror::Error;
 
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
+// errors are sent back to the client as they are, so messages carry the redis error prefix
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BackendError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+    #[error("ERR value is not an integer or out of range")]
+    NotInteger,
+    #[error("ERR value is not a valid float")]
+    NotFloat,
+    #[error("ERR hash value is not an integer")]
+    HashNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashNotFloat,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NanOrInfinity,
+    #[error("ERR string exceeds maximum allowed size (proto-max-bulk-len)")]
+    StringTooLong,
+    #[error("ERR Insufficient memory, transient memory for LCS exceeds proto-max-bulk-len")]
+    LcsTooLarge,
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("ERR source and destination objects are the same")]
+    SameObject,
+    #[error("ERR index out of range")]
+    IndexOutOfRange,
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNaN,
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+    #[error("ERR The stream has exhausted the last possible ID, unable to add more items")]
+    StreamExhausted,
+    #[error("NOGROUP No such key '{key}' or consumer group '{group}'")]
+    NoGroup { key: String, group: String },
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    XGroupNoKey,
+    #[error("WRONGTYPE Key is not a valid HyperLogLog string value.")]
+    NotHll,
+    #[error("INVALIDOBJ Corrupted HLL object detected")]
+    HllCorrupted,
+    #[error("ERR could not decode requested zset member")]
+    NoGeoMember,
+    #[error("ERR value is out of range")]
+    CountOutOfRange,
+}
+
+/// When a SET should be applied, depending on whether the key already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    Always,
+    NotExists,
+    Exists,
+}
+
+/// What happens to the time to live of a key when its value is replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyExpire {
+    Persist,
+    Keep,
+    // deadline in unix milliseconds
+    At(i64),
+}
+
+/// When a new deadline replaces the current one, from the NX / XX / GT / LT options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    Always,
+    // NX
+    NoDeadline,
+    // XX
+    HasDeadline,
+    // GT
+    Greater,
+    // LT
+    Less,
+}
+
+/// A value stored in the keyspace, one variant per data type.
+#[derive(Debug, Clone)]
+pub enum Value {
+    String(RespFrame),
+    Hash(HashValue),
+    Set(ScanSet),
+    List(VecDeque<Vec<u8>>),
+    ZSet(SortedSet),
+    Stream(Stream),
+}
+
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub(crate) set: DashMap<String, DashSet<String>>,
+    // a key holds exactly one value, whatever its type
+    pub(crate) keyspace: ScanMap<Value>,
+    // key -> deadline in unix milliseconds
+    pub(crate) expire: ScanMap<i64>,
+    // where the next round of active expiry resumes the walk of the deadlines
+    pub(crate) expire_cursor: AtomicU64,
+    pub(crate) locks: KeyLocks,
+    // clients blocked until a key receives data
+    pub(crate) waiters: Waiters,
+    // channels and patterns clients are subscribed to
+    pub(crate) pubsub: PubSub,
+}
+
+impl From<BackendError> for RespFrame {
+    fn from(e: BackendError) -> Self {
+        SimpleError::new(e.to_string()).into()
+    }
 }
 
 impl Deref for Backend {
@@ -30,81 +181,162 @@ impl Default for Backend {
 impl Default for BackendInner {
     fn default() -> Self {
         Self {
-            map: DashMap::new(),
-            hmap: DashMap::new(),
-            set: DashMap::new(),
+            keyspace: ScanMap::new(),
+            expire: ScanMap::new(),
+            expire_cursor: AtomicU64::new(0),
+            locks: KeyLocks::new(),
+            waiters: Waiters::default(),
+            pubsub: PubSub::default(),
         }
     }
 }
 
-impl Backend {
-    pub fn new() -> Self {
-        Self::default()
+impl Value {
+    /// Name of the type as reported by TYPE.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::List(_) => "list",
+            Value::ZSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
     }
 
-    pub fn sadd(&self, key: String, members: Vec<String>) -> i64 {
-        let mut count = 0;
-        let set = self.set.entry(key).or_default();
+    pub(crate) fn as_string(&self) -> Result<&RespFrame, BackendError> {
+        match self {
+            Value::String(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
+    }
 
-        members.into_iter().for_each(|member| {
-            if set.insert(member) {
-                count += 1
-            }
-        });
-        count
+    pub(crate) fn as_hash(&self) -> Result<&HashValue, BackendError> {
+        match self {
+            Value::Hash(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
     }
 
-    pub fn sismember(&self, key: &str, member: &str) -> i64 {
-        if self.set.get(key).map_or(false, |set| set.contains(member)) {
-            1
-        } else {
-            0
+    pub(crate) fn as_set(&self) -> Result<&ScanSet, BackendError> {
+        match self {
+            Value::Set(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
         }
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    pub(crate) fn as_list(&self) -> Result<&VecDeque<Vec<u8>>, BackendError> {
+        match self {
+            Value::List(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    // lists, sorted sets and streams are updated through a mutable ref, the others share
+    // interior maps
+    pub(crate) fn as_list_mut(&mut self) -> Result<&mut VecDeque<Vec<u8>>, BackendError> {
+        match self {
+            Value::List(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    pub(crate) fn as_zset(&self) -> Result<&SortedSet, BackendError> {
+        match self {
+            Value::ZSet(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
     }
 
-    pub fn hmget(&self, key: &str, fields: &Vec<String>) -> Vec<Option<RespFrame>> {
-        let mut resp: Vec<Option<RespFrame>> = vec![];
+    pub(crate) fn as_zset_mut(&mut self) -> Result<&mut SortedSet, BackendError> {
+        match self {
+            Value::ZSet(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
+    }
 
-        for field in fields {
-            if let Some(hashmap) = self.hmap.get(key) {
-                if let Some(value) = hashmap.get(field) {
-                    resp.push(Some(value.clone()));
-                } else {
-                    resp.push(None);
-                }
-            } else {
-                resp.push(None);
-            }
+    pub(crate) fn as_stream(&self) -> Result<&Stream, BackendError> {
+        match self {
+            Value::Stream(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
         }
+    }
 
-        resp
+    pub(crate) fn as_stream_mut(&mut self) -> Result<&mut Stream, BackendError> {
+        match self {
+            Value::Stream(v) => Ok(v),
+            _ => Err(BackendError::WrongType),
+        }
+    }
+}
+
+impl Backend {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) -> i64 {
-        let hmap = self.hmap.entry(key).or_default();
-        if hmap.get(&field).is_some() {
-            hmap.insert(field, value);
-            return 0;
+    pub fn get(&self, key: &[u8]) -> Result<Option<RespFrame>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(Some(v.as_string()?.clone())),
+            None => Ok(None),
         }
-        hmap.insert(field, value);
-        1
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    // like redis, a plain SET discards any previous time to live and value of any type
+    pub fn set(&self, key: Vec<u8>, value: RespFrame) {
+        let _guard = self.locks.write([&key]);
+        self.expire.remove(&key);
+        self.keyspace.insert(key, Value::String(value));
+    }
+
+    /// Set the value under the entry lock so the condition check and the write are atomic.
+    /// Returns whether the value was written, together with the previous value.
+    /// With `get` the previous value must be a string, otherwise nothing is written.
+    pub fn set_with_options(
+        &self,
+        key: Vec<u8>,
+        value: RespFrame,
+        condition: SetCondition,
+        expire: KeyExpire,
+        get: bool,
+    ) -> Result<(bool, Option<RespFrame>), BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        match self.keyspace.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let old = match entry.get().as_string() {
+                    Ok(old) => Some(old.clone()),
+                    Err(e) if get => return Err(e),
+                    Err(_) => None,
+                };
+                if condition == SetCondition::NotExists {
+                    return Ok((false, old));
+                }
+                self.update_expire(entry.key(), expire);
+                entry.insert(Value::String(value));
+                Ok((true, old))
+            }
+            Entry::Vacant(entry) => {
+                if condition == SetCondition::Exists {
+                    return Ok((false, None));
+                }
+                self.update_expire(entry.key(), expire);
+                entry.insert(Value::String(value));
+                Ok((true, None))
+            }
+        }
+    }
+
+    fn update_expire(&self, key: &[u8], expire: KeyExpire) {
+        match expire {
+            KeyExpire::Persist => {
+                self.expire.remove(key);
+            }
+            KeyExpire::Keep => {}
+            KeyExpire::At(at) => {
+                self.expire.insert(key.to_vec(), at);
+            }
+        }
     }
 }
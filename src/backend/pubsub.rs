@@ -0,0 +1,324 @@
+use super::{glob::glob_match, Backend};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+use tokio::sync::mpsc;
+
+static NEXT_SUBSCRIBER_ID: AtomicU64 = AtomicU64::new(1);
+
+// messages queued to a subscriber not reading them fast enough before it is disconnected, the
+// counterpart of the pubsub client-output-buffer-limit of redis
+const SUBSCRIBER_QUEUE_LEN: usize = 4096;
+
+/// A message published to a channel, as delivered to one subscriber.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PubSubMessage {
+    // to a subscriber of the channel itself
+    Message {
+        channel: Vec<u8>,
+        payload: Vec<u8>,
+    },
+    // to a subscriber of a pattern matching the channel
+    PMessage {
+        pattern: Vec<u8>,
+        channel: Vec<u8>,
+        payload: Vec<u8>,
+    },
+}
+
+// where the messages of one subscriber go
+#[derive(Clone)]
+struct Mailbox {
+    sender: mpsc::Sender<PubSubMessage>,
+    // set once a message did not fit, the subscriber is then disconnected
+    overflowed: Arc<AtomicBool>,
+}
+
+impl Mailbox {
+    fn send(&self, message: PubSubMessage) -> bool {
+        match self.sender.try_send(message) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.overflowed.store(true, Ordering::Relaxed);
+                false
+            }
+            // the receiver is only gone with its subscriber, which then unsubscribes
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+// channel or pattern -> subscriber id -> its mailbox
+type Subscribers = HashMap<Vec<u8>, HashMap<u64, Mailbox>>;
+
+/// Channels and patterns clients are subscribed to, shared by all connections.
+///
+/// Publishing never waits on a subscriber: messages are queued to each connection, which
+/// writes them to its client as they arrive. A subscriber whose queue is full misses the
+/// message and is disconnected.
+#[derive(Default)]
+pub(crate) struct PubSub {
+    channels: Mutex<Subscribers>,
+    patterns: Mutex<Subscribers>,
+}
+
+impl std::fmt::Debug for PubSub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PubSub")
+            .field("channels", &lock(&self.channels).len())
+            .field("patterns", &lock(&self.patterns).len())
+            .finish()
+    }
+}
+
+fn lock(subscribers: &Mutex<Subscribers>) -> MutexGuard<'_, Subscribers> {
+    subscribers.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Backend {
+    /// Send the message to the subscribers of the channel and of every matching pattern,
+    /// returns the number of deliveries, a client subscribed twice receiving it twice.
+    pub fn publish(&self, channel: &[u8], payload: &[u8]) -> i64 {
+        let mut count = 0;
+        if let Some(subscribers) = lock(&self.pubsub.channels).get(channel) {
+            for mailbox in subscribers.values() {
+                let message = PubSubMessage::Message {
+                    channel: channel.to_vec(),
+                    payload: payload.to_vec(),
+                };
+                if mailbox.send(message) {
+                    count += 1;
+                }
+            }
+        }
+        for (pattern, subscribers) in lock(&self.pubsub.patterns).iter() {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+            for mailbox in subscribers.values() {
+                let message = PubSubMessage::PMessage {
+                    pattern: pattern.clone(),
+                    channel: channel.to_vec(),
+                    payload: payload.to_vec(),
+                };
+                if mailbox.send(message) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Channels with at least one subscriber, only those matching the pattern if any.
+    pub fn pubsub_channels(&self, pattern: Option<&[u8]>) -> Vec<Vec<u8>> {
+        lock(&self.pubsub.channels)
+            .keys()
+            .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel)))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of subscribers of each channel, patterns are not counted.
+    pub fn pubsub_numsub(&self, channels: &[Vec<u8>]) -> Vec<i64> {
+        let subscribers = lock(&self.pubsub.channels);
+        channels
+            .iter()
+            .map(|channel| subscribers.get(channel).map_or(0, |s| s.len() as i64))
+            .collect()
+    }
+
+    /// Number of patterns with at least one subscriber.
+    pub fn pubsub_numpat(&self) -> i64 {
+        lock(&self.pubsub.patterns).len() as i64
+    }
+}
+
+/// The channels and patterns one connection is subscribed to, and the queue its messages
+/// arrive in. Dropping it, e.g. when the connection closes, unsubscribes from all of them.
+pub struct Subscriber {
+    id: u64,
+    backend: Backend,
+    mailbox: Mailbox,
+    receiver: mpsc::Receiver<PubSubMessage>,
+    channels: BTreeSet<Vec<u8>>,
+    patterns: BTreeSet<Vec<u8>>,
+}
+
+impl std::fmt::Debug for Subscriber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscriber")
+            .field("id", &self.id)
+            .field("channels", &self.channels)
+            .field("patterns", &self.patterns)
+            .finish()
+    }
+}
+
+impl Subscriber {
+    pub fn new(backend: &Backend) -> Self {
+        let (sender, receiver) = mpsc::channel(SUBSCRIBER_QUEUE_LEN);
+        Self {
+            id: NEXT_SUBSCRIBER_ID.fetch_add(1, Ordering::Relaxed),
+            backend: backend.clone(),
+            mailbox: Mailbox {
+                sender,
+                overflowed: Arc::new(AtomicBool::new(false)),
+            },
+            receiver,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+        }
+    }
+
+    /// Number of channels and patterns subscribed to, the count replied to (un)subscribes.
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    pub fn channels(&self) -> Vec<Vec<u8>> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<Vec<u8>> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    pub fn subscribe(&mut self, channel: Vec<u8>) {
+        if self.channels.insert(channel.clone()) {
+            lock(&self.backend.pubsub.channels)
+                .entry(channel)
+                .or_default()
+                .insert(self.id, self.mailbox.clone());
+        }
+    }
+
+    pub fn unsubscribe(&mut self, channel: &[u8]) {
+        if self.channels.remove(channel) {
+            remove(&self.backend.pubsub.channels, channel, self.id);
+        }
+    }
+
+    pub fn psubscribe(&mut self, pattern: Vec<u8>) {
+        if self.patterns.insert(pattern.clone()) {
+            lock(&self.backend.pubsub.patterns)
+                .entry(pattern)
+                .or_default()
+                .insert(self.id, self.mailbox.clone());
+        }
+    }
+
+    pub fn punsubscribe(&mut self, pattern: &[u8]) {
+        if self.patterns.remove(pattern) {
+            remove(&self.backend.pubsub.patterns, pattern, self.id);
+        }
+    }
+
+    /// The next message of a channel or pattern still subscribed to. Messages queued before
+    /// an unsubscribe are dropped rather than delivered after its confirmation.
+    /// None once a message was missed because the queue was full, the client must then be
+    /// disconnected.
+    pub async fn recv(&mut self) -> Option<PubSubMessage> {
+        loop {
+            // the subscriber holds a sender itself, so the queue is never closed
+            let Some(message) = self.receiver.recv().await else {
+                return std::future::pending().await;
+            };
+            if self.mailbox.overflowed.load(Ordering::Relaxed) {
+                return None;
+            }
+            let subscribed = match &message {
+                PubSubMessage::Message { channel, .. } => self.channels.contains(channel),
+                PubSubMessage::PMessage { pattern, .. } => self.patterns.contains(pattern),
+            };
+            if subscribed {
+                return Some(message);
+            }
+        }
+    }
+}
+
+// the channel or pattern is forgotten along with its last subscriber
+fn remove(subscribers: &Mutex<Subscribers>, name: &[u8], id: u64) {
+    let mut subscribers = lock(subscribers);
+    if let Some(ids) = subscribers.get_mut(name) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            subscribers.remove(name);
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        for channel in std::mem::take(&mut self.channels) {
+            remove(&self.backend.pubsub.channels, &channel, self.id);
+        }
+        for pattern in std::mem::take(&mut self.patterns) {
+            remove(&self.backend.pubsub.patterns, &pattern, self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_to_channels_and_patterns() {
+        let backend = Backend::new();
+        let mut subscriber = Subscriber::new(&backend);
+        subscriber.subscribe(b"news.tech".to_vec());
+        subscriber.psubscribe(b"news.*".to_vec());
+        assert_eq!(backend.pubsub_numsub(&[b"news.tech".to_vec()]), vec![1]);
+        assert_eq!(backend.pubsub_numpat(), 1);
+
+        // delivered once for the channel and once for the pattern
+        assert_eq!(backend.publish(b"news.tech", b"hello"), 2);
+        assert_eq!(backend.publish(b"weather", b"sunny"), 0);
+        assert_eq!(
+            subscriber.recv().await,
+            Some(PubSubMessage::Message {
+                channel: b"news.tech".to_vec(),
+                payload: b"hello".to_vec(),
+            })
+        );
+        assert_eq!(
+            subscriber.recv().await,
+            Some(PubSubMessage::PMessage {
+                pattern: b"news.*".to_vec(),
+                channel: b"news.tech".to_vec(),
+                payload: b"hello".to_vec(),
+            })
+        );
+
+        // already queued messages of a dropped channel are not delivered
+        backend.publish(b"news.tech", b"again");
+        subscriber.unsubscribe(b"news.tech");
+        assert_eq!(backend.pubsub_channels(None), Vec::<Vec<u8>>::new());
+        assert!(matches!(
+            subscriber.recv().await,
+            Some(PubSubMessage::PMessage { payload, .. }) if payload == b"again"
+        ));
+
+        drop(subscriber);
+        assert_eq!(backend.pubsub_numpat(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_disconnected() {
+        let backend = Backend::new();
+        let mut subscriber = Subscriber::new(&backend);
+        subscriber.subscribe(b"news".to_vec());
+
+        for _ in 0..SUBSCRIBER_QUEUE_LEN {
+            assert_eq!(backend.publish(b"news", b"hello"), 1);
+        }
+        // memory is bounded, the message that does not fit is not delivered
+        assert_eq!(backend.publish(b"news", b"hello"), 0);
+        assert_eq!(subscriber.recv().await, None);
+    }
+}
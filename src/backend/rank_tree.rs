@@ -0,0 +1,194 @@
+use std::cmp::Ordering;
+
+// An ordered set with O(log n) rank queries: a treap whose nodes know the size of their
+// subtree. Priorities are random, so the tree stays balanced in expectation whatever the
+// order keys are inserted in, and recursion never goes deeper than its expected height.
+
+type Link<K> = Option<Box<Node<K>>>;
+
+#[derive(Debug, Clone)]
+struct Node<K> {
+    key: K,
+    priority: u32,
+    size: usize,
+    left: Link<K>,
+    right: Link<K>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RankTree<K> {
+    root: Link<K>,
+}
+
+impl<K> Default for RankTree<K> {
+    fn default() -> Self {
+        Self { root: None }
+    }
+}
+
+impl<K: Ord> RankTree<K> {
+    pub(crate) fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Insert a key that is not in the tree yet.
+    pub(crate) fn insert(&mut self, key: K) {
+        let node = Box::new(Node {
+            key,
+            priority: rand::random(),
+            size: 1,
+            left: None,
+            right: None,
+        });
+        let (left, right) = split(self.root.take(), &node.key);
+        self.root = merge(merge(left, Some(node)), right);
+    }
+
+    pub(crate) fn remove(&mut self, key: &K) -> bool {
+        remove(&mut self.root, key)
+    }
+
+    /// Number of keys for which `below` is true, `below` must be true for a prefix of the
+    /// keys only (e.g. "score < min"). It is the rank of the first key it is false for.
+    pub(crate) fn count_while(&self, below: impl Fn(&K) -> bool) -> usize {
+        let mut count = 0;
+        let mut link = &self.root;
+        while let Some(node) = link {
+            if below(&node.key) {
+                count += size(&node.left) + 1;
+                link = &node.right;
+            } else {
+                link = &node.left;
+            }
+        }
+        count
+    }
+
+    /// Rank of a key in the tree, 0 for the smallest.
+    pub(crate) fn rank(&self, key: &K) -> usize {
+        self.count_while(|k| k < key)
+    }
+
+    /// Keys of rank start (included) to end (excluded), in order.
+    pub(crate) fn range(&self, start: usize, end: usize) -> Vec<&K> {
+        let mut keys = Vec::with_capacity(end.saturating_sub(start));
+        collect_range(&self.root, start, end, &mut keys);
+        keys
+    }
+}
+
+fn size<K>(link: &Link<K>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+fn update<K>(node: &mut Node<K>) {
+    node.size = size(&node.left) + size(&node.right) + 1;
+}
+
+// keys lower than key on the left, the others on the right
+fn split<K: Ord>(link: Link<K>, key: &K) -> (Link<K>, Link<K>) {
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            if node.key < *key {
+                let (left, right) = split(node.right.take(), key);
+                node.right = left;
+                update(&mut node);
+                (Some(node), right)
+            } else {
+                let (left, right) = split(node.left.take(), key);
+                node.left = right;
+                update(&mut node);
+                (left, Some(node))
+            }
+        }
+    }
+}
+
+// every key of left is lower than every key of right
+fn merge<K>(left: Link<K>, right: Link<K>) -> Link<K> {
+    match (left, right) {
+        (None, right) => right,
+        (left, None) => left,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                update(&mut left);
+                Some(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                update(&mut right);
+                Some(right)
+            }
+        }
+    }
+}
+
+fn remove<K: Ord>(link: &mut Link<K>, key: &K) -> bool {
+    let node = match link {
+        Some(node) => node,
+        None => return false,
+    };
+    let removed = match key.cmp(&node.key) {
+        Ordering::Less => remove(&mut node.left, key),
+        Ordering::Greater => remove(&mut node.right, key),
+        Ordering::Equal => {
+            let node = link.take().expect("node exists");
+            *link = merge(node.left, node.right);
+            return true;
+        }
+    };
+    if removed {
+        node.size -= 1;
+    }
+    removed
+}
+
+fn collect_range<'a, K>(link: &'a Link<K>, start: usize, end: usize, keys: &mut Vec<&'a K>) {
+    let node = match link {
+        Some(node) if start < end => node,
+        _ => return,
+    };
+    let left = size(&node.left);
+    if start < left {
+        collect_range(&node.left, start, end.min(left), keys);
+    }
+    if start <= left && left < end {
+        keys.push(&node.key);
+    }
+    if end > left + 1 {
+        collect_range(
+            &node.right,
+            start.saturating_sub(left + 1),
+            end - left - 1,
+            keys,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_tree() {
+        let mut tree = RankTree::default();
+        // inserted in order, the worst case of an unbalanced tree
+        for i in 0..1000 {
+            tree.insert(i * 2);
+        }
+        assert_eq!(tree.len(), 1000);
+        assert_eq!(tree.rank(&0), 0);
+        assert_eq!(tree.rank(&500), 250);
+        assert_eq!(tree.count_while(|k| *k < 501), 251);
+        assert_eq!(tree.range(10, 13), vec![&20, &22, &24]);
+        assert_eq!(tree.range(998, 2000), vec![&1996, &1998]);
+        assert!(tree.range(5, 5).is_empty());
+
+        assert!(tree.remove(&500));
+        assert!(!tree.remove(&500));
+        assert_eq!(tree.len(), 999);
+        assert_eq!(tree.rank(&502), 250);
+        assert_eq!(tree.range(249, 251), vec![&498, &502]);
+    }
+}
@@ -0,0 +1,603 @@
+use super::{glob::glob_match, Backend, BackendError};
+use crate::RespFrame;
+use dashmap::{
+    mapref::{entry, one::RefMut},
+    DashMap,
+};
+use rand::Rng;
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::BuildHasher,
+    ops::Deref,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// One page of a SCAN-like iteration, the cursor is 0 once the iteration is complete.
+#[derive(Debug, PartialEq)]
+pub struct ScanPage<T> {
+    pub cursor: u64,
+    pub items: Vec<T>,
+}
+
+// the names are spread over stripes, so writers of different names rarely share a lock
+const STRIPES: usize = 16;
+
+// the low bits of a cursor are the position within a stripe, the high bits the stripe
+const POSITION_BITS: u32 = 48;
+// the position of a stripe not walked yet, whatever its length
+const TOP: u64 = (1 << POSITION_BITS) - 1;
+
+/// A map of names (keys, hash fields, set members) that SCAN-like commands walk with a cursor
+/// and RANDOMKEY draws from.
+///
+/// Next to the map, every name is listed in a vector of the stripe its hash picks. A removed
+/// name is replaced by the last one of its vector, and a new name goes last. A scan walks each
+/// vector from its end down to its start: the cursor is the position where it stops, names
+/// below it are not visited yet, and only names from visited positions ever move there. A name
+/// present for the whole iteration is therefore returned, though a removal may move a visited
+/// name below the cursor, where it is returned again, like after a rehash in redis.
+///
+/// The vectors are updated while the map holds the lock of the name, so they list the names
+/// of the map: insert, remove, remove_if and entry are those of the wrapper, the map itself is read only.
+#[derive(Debug)]
+pub struct ScanMap<V> {
+    map: DashMap<Vec<u8>, V>,
+    index: NameIndex,
+}
+
+#[derive(Debug, Default)]
+struct Stripe {
+    names: Vec<Arc<[u8]>>,
+    positions: HashMap<Arc<[u8]>, usize>,
+}
+
+#[derive(Debug)]
+struct NameIndex {
+    stripes: [Mutex<Stripe>; STRIPES],
+    hasher: RandomState,
+}
+
+impl Default for NameIndex {
+    fn default() -> Self {
+        Self {
+            stripes: std::array::from_fn(|_| Mutex::default()),
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl NameIndex {
+    // a stripe lock is never held while taking another lock
+    fn lock(&self, stripe: usize) -> MutexGuard<'_, Stripe> {
+        self.stripes[stripe]
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stripe_of(&self, name: &[u8]) -> usize {
+        self.hasher.hash_one(name) as usize % STRIPES
+    }
+
+    fn add(&self, name: &[u8]) {
+        let mut stripe = self.lock(self.stripe_of(name));
+        if !stripe.positions.contains_key(name) {
+            let name: Arc<[u8]> = name.into();
+            let position = stripe.names.len();
+            stripe.positions.insert(name.clone(), position);
+            stripe.names.push(name);
+        }
+    }
+
+    fn remove(&self, name: &[u8]) {
+        let mut stripe = self.lock(self.stripe_of(name));
+        if let Some(position) = stripe.positions.remove(name) {
+            stripe.names.swap_remove(position);
+            if let Some(moved) = stripe.names.get(position).cloned() {
+                stripe.positions.insert(moved, position);
+            }
+        }
+    }
+
+    // up to count names below position, the position moves down past them
+    fn walk(&self, stripe: usize, position: &mut u64, count: usize) -> Vec<Arc<[u8]>> {
+        let stripe = self.lock(stripe);
+        let end = (*position as usize).min(stripe.names.len());
+        let start = end.saturating_sub(count);
+        *position = start as u64;
+        stripe.names[start..end].iter().rev().cloned().collect()
+    }
+
+    fn random(&self) -> Option<Arc<[u8]>> {
+        let mut rng = rand::thread_rng();
+        loop {
+            let lens = (0..STRIPES)
+                .map(|stripe| self.lock(stripe).names.len())
+                .collect::<Vec<_>>();
+            let len = lens.iter().sum::<usize>();
+            if len == 0 {
+                return None;
+            }
+            let mut index = rng.gen_range(0..len);
+            let mut stripe = 0;
+            while index >= lens[stripe] {
+                index -= lens[stripe];
+                stripe += 1;
+            }
+            // the stripe may have shrunk since it was counted
+            if let Some(name) = self.lock(stripe).names.get(index) {
+                return Some(name.clone());
+            }
+        }
+    }
+}
+
+impl<V> Deref for ScanMap<V> {
+    type Target = DashMap<Vec<u8>, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<V> Default for ScanMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone> Clone for ScanMap<V> {
+    fn clone(&self) -> Self {
+        let map = self.map.clone();
+        let index = NameIndex::default();
+        for v in map.iter() {
+            index.add(v.key());
+        }
+        Self { map, index }
+    }
+}
+
+/// The entry of a name in a [`ScanMap`], a vacant one lists its name once a value is inserted.
+pub enum Entry<'a, V> {
+    Occupied(entry::OccupiedEntry<'a, Vec<u8>, V>),
+    Vacant(VacantEntry<'a, V>),
+}
+
+pub struct VacantEntry<'a, V> {
+    entry: entry::VacantEntry<'a, Vec<u8>, V>,
+    index: &'a NameIndex,
+}
+
+impl<'a, V> VacantEntry<'a, V> {
+    pub fn key(&self) -> &Vec<u8> {
+        self.entry.key()
+    }
+
+    pub fn insert(self, value: V) -> RefMut<'a, Vec<u8>, V> {
+        self.index.add(self.entry.key());
+        self.entry.insert(value)
+    }
+}
+
+impl<'a, V> Entry<'a, V> {
+    pub fn or_insert_with(self, value: impl FnOnce() -> V) -> RefMut<'a, Vec<u8>, V> {
+        match self {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(entry) => entry.insert(value()),
+        }
+    }
+}
+
+impl<V> ScanMap<V> {
+    pub fn new() -> Self {
+        Self {
+            map: DashMap::new(),
+            index: NameIndex::default(),
+        }
+    }
+
+    pub fn insert(&self, name: Vec<u8>, value: V) -> Option<V> {
+        match self.map.entry(name) {
+            entry::Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            entry::Entry::Vacant(entry) => {
+                self.index.add(entry.key());
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&self, name: &[u8]) -> Option<(Vec<u8>, V)> {
+        self.remove_if(name, |_, _| true)
+    }
+
+    pub fn remove_if(
+        &self,
+        name: &[u8],
+        f: impl FnOnce(&Vec<u8>, &V) -> bool,
+    ) -> Option<(Vec<u8>, V)> {
+        self.map.remove_if(name, |name, value| {
+            let removed = f(name, value);
+            if removed {
+                self.index.remove(name);
+            }
+            removed
+        })
+    }
+
+    pub fn entry(&self, name: Vec<u8>) -> Entry<'_, V> {
+        match self.map.entry(name) {
+            entry::Entry::Occupied(entry) => Entry::Occupied(entry),
+            entry::Entry::Vacant(entry) => Entry::Vacant(VacantEntry {
+                entry,
+                index: &self.index,
+            }),
+        }
+    }
+
+    /// About `count` names from the cursor on, those `item` keeps. Each name costs one unit of
+    /// `count` whether it is kept or not.
+    pub(crate) fn scan<T>(
+        &self,
+        cursor: u64,
+        count: usize,
+        mut item: impl FnMut(&Vec<u8>, &V) -> Option<T>,
+    ) -> ScanPage<T> {
+        // cursor 0 starts the walk at the top of the first stripe
+        let (mut stripe, mut position) = match cursor {
+            0 => (0, TOP),
+            cursor => ((cursor >> POSITION_BITS) as usize, cursor & TOP),
+        };
+        let count = count.max(1);
+        let (mut examined, mut items) = (0, vec![]);
+        while stripe < STRIPES && examined < count {
+            let names = self.index.walk(stripe, &mut position, count - examined);
+            examined += names.len();
+            // the map is read once the stripe is released, a name removed meanwhile is skipped
+            for name in names {
+                if let Some(v) = self.map.get(&*name) {
+                    items.extend(item(v.key(), v.value()));
+                }
+            }
+            if position == 0 {
+                (stripe, position) = (stripe + 1, TOP);
+            }
+        }
+        // a cursor past the last stripe, which no scan returns, ends the iteration too
+        let cursor = match stripe {
+            stripe if stripe < STRIPES => (stripe as u64) << POSITION_BITS | position,
+            _ => 0,
+        };
+        ScanPage { cursor, items }
+    }
+
+    /// A random name, every one equally likely, None if the map is empty.
+    pub(crate) fn random_name(&self) -> Option<Vec<u8>> {
+        loop {
+            let name = self.index.random()?;
+            // removed since it was drawn
+            if self.map.contains_key(&*name) {
+                return Some(name.to_vec());
+            }
+        }
+    }
+}
+
+/// The members of a set, a [`ScanMap`] without values.
+#[derive(Debug, Clone, Default)]
+pub struct ScanSet(ScanMap<()>);
+
+impl Deref for ScanSet {
+    type Target = ScanMap<()>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ScanSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether the member was new.
+    pub fn insert(&self, member: Vec<u8>) -> bool {
+        self.0.insert(member, ()).is_none()
+    }
+
+    pub fn remove(&self, member: &[u8]) -> Option<Vec<u8>> {
+        self.0.remove(member).map(|(member, _)| member)
+    }
+
+    pub fn contains(&self, member: &[u8]) -> bool {
+        self.0.contains_key(member)
+    }
+}
+
+fn matches(pattern: Option<&[u8]>, name: &[u8]) -> bool {
+    pattern.is_none_or(|pattern| glob_match(pattern, name))
+}
+
+impl Backend {
+    /// All the keys matching a glob-style pattern.
+    pub fn keys(&self, pattern: &[u8]) -> Vec<Vec<u8>> {
+        let keys = self
+            .keyspace
+            .iter()
+            .filter(|v| glob_match(pattern, v.key()))
+            .map(|v| v.key().clone())
+            .collect::<Vec<Vec<u8>>>();
+        // expired keys are removed once the iterator no longer holds the shards
+        keys.into_iter()
+            .filter(|key| !self.expire_if_needed(key))
+            .collect()
+    }
+
+    /// About `count` keys from the cursor on. MATCH and TYPE filter the page afterwards,
+    /// so a page may be empty while the iteration is not complete yet.
+    pub fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        key_type: Option<&str>,
+    ) -> ScanPage<Vec<u8>> {
+        let page = self.keyspace.scan(cursor, count, |key, value| {
+            let wanted = matches(pattern, key) && key_type.is_none_or(|ty| value.type_name() == ty);
+            wanted.then(|| key.clone())
+        });
+        // expired keys are removed once the scan no longer holds the shards
+        ScanPage {
+            cursor: page.cursor,
+            items: page
+                .items
+                .into_iter()
+                .filter(|key| !self.expire_if_needed(key))
+                .collect(),
+        }
+    }
+
+    pub fn hscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<ScanPage<(Vec<u8>, RespFrame)>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(entry) => entry,
+            None => return Ok(empty_page()),
+        };
+        let hmap = entry.as_hash()?;
+        Ok(hmap.scan(cursor, count, |field, value| {
+            matches(pattern, field).then(|| (field.clone(), value.clone()))
+        }))
+    }
+
+    pub fn sscan(
+        &self,
+        key: &[u8],
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+    ) -> Result<ScanPage<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(entry) => entry,
+            None => return Ok(empty_page()),
+        };
+        let set = entry.as_set()?;
+        Ok(set.scan(cursor, count, |member, _| {
+            matches(pattern, member).then(|| member.clone())
+        }))
+    }
+}
+
+fn empty_page<T>() -> ScanPage<T> {
+    ScanPage {
+        cursor: 0,
+        items: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_keys() {
+        let backend = Backend::new();
+        for key in ["user:1", "user:2", "order:1"] {
+            backend.set(key.as_bytes().to_vec(), BulkString::from("value").into());
+        }
+
+        let mut keys = backend.keys(b"user:*");
+        keys.sort();
+        assert_eq!(keys, vec![b"user:1".to_vec(), b"user:2".to_vec()]);
+        assert_eq!(backend.keys(b"*").len(), 3);
+        assert!(backend.keys(b"missing*").is_empty());
+    }
+
+    #[test]
+    fn test_scan_returns_every_key_once() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("key:{}", i).into_bytes(), RespFrame::Integer(i));
+        }
+
+        let mut seen = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            // each page walks 7 keys of the index
+            let page = backend.scan(cursor, 7, None, None);
+            for key in page.items {
+                assert!(seen.insert(key));
+            }
+            // keys added or removed while iterating do not disturb the others
+            backend.set(
+                format!("new:{}", cursor).into_bytes(),
+                RespFrame::Integer(0),
+            );
+            backend.del(&[format!("new:{}", cursor).into_bytes()]);
+            cursor = page.cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 100);
+    }
+
+    #[test]
+    fn test_scan_while_the_keyspace_grows() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("key:{}", i).into_bytes(), RespFrame::Integer(i));
+        }
+
+        // keys added meanwhile go last in their stripe, where the walk already was, so they
+        // neither skip nor repeat a key
+        let mut seen = HashSet::new();
+        let (mut cursor, mut added) = (0, 0);
+        loop {
+            let page = backend.scan(cursor, 5, Some(b"key:*"), None);
+            for key in page.items {
+                assert!(seen.insert(key));
+            }
+            for _ in 0..10 {
+                backend.set(format!("new:{}", added).into_bytes(), RespFrame::Integer(0));
+                added += 1;
+            }
+            cursor = page.cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 100);
+    }
+
+    #[test]
+    fn test_scan_while_names_move() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.set(format!("key:{}", i).into_bytes(), RespFrame::Integer(i));
+        }
+        for i in 0..2000 {
+            backend.set(format!("tmp:{}", i).into_bytes(), RespFrame::Integer(i));
+        }
+
+        // every page removes names, whose places are taken by names from the end of their
+        // stripe, and adds as many new names
+        let mut seen = HashSet::new();
+        let (mut cursor, mut page_number) = (0, 0);
+        loop {
+            let page = backend.scan(cursor, 10, Some(b"key:*"), None);
+            seen.extend(page.items);
+            for i in 0..50 {
+                let i = page_number * 50 + i;
+                backend.del(&[format!("tmp:{}", i).into_bytes()]);
+                backend.set(format!("new:{}", i).into_bytes(), RespFrame::Integer(0));
+            }
+            page_number += 1;
+            cursor = page.cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        // a key may come twice in a scan, but none present all along is missed
+        assert_eq!(seen.len(), 100);
+    }
+
+    #[test]
+    fn test_scan_map_lists_its_names() {
+        let map = ScanMap::new();
+        for i in 0..100u8 {
+            map.insert(vec![i], i);
+        }
+        for i in (0..100u8).step_by(3) {
+            map.remove(&[i]);
+        }
+        if let Entry::Vacant(entry) = map.entry(vec![200]) {
+            entry.insert(200);
+        }
+        map.entry(vec![201]).or_insert_with(|| 201);
+
+        let mut names = map.scan(0, usize::MAX, |name, _| Some(name.clone())).items;
+        names.sort();
+        let mut expected = map.iter().map(|v| v.key().clone()).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(names, expected);
+        assert_eq!(names.len(), 68);
+        assert_eq!(map.scan(u64::MAX, 10, |_, _| Some(())).cursor, 0);
+        assert!(map
+            .random_name()
+            .is_some_and(|name| map.contains_key(&name)));
+    }
+
+    #[test]
+    fn test_scan_filters() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+        backend.sadd(b"set:1".to_vec(), vec![b"member".to_vec()])?;
+        backend.sadd(b"set:2".to_vec(), vec![b"member".to_vec()])?;
+
+        let page = backend.scan(0, 100, None, Some("set"));
+        assert_eq!(page.cursor, 0);
+        assert_eq!(page.items.len(), 2);
+
+        let page = backend.scan(0, 100, Some(b"s?r"), None);
+        assert_eq!(page.items, vec![b"str".to_vec()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hscan_sscan() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for i in 0..20 {
+            backend.hset(
+                b"hash".to_vec(),
+                vec![(format!("field:{}", i).into_bytes(), RespFrame::Integer(i))],
+            )?;
+        }
+        backend.sadd(b"set".to_vec(), vec![b"a".to_vec(), b"b".to_vec()])?;
+
+        let mut fields = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            let page = backend.hscan(b"hash", cursor, 3, Some(b"field:1*"))?;
+            fields.extend(page.items.into_iter().map(|(field, _)| field));
+            cursor = page.cursor;
+            if cursor == 0 {
+                break;
+            }
+        }
+        // field:1 and field:10 to field:19
+        assert_eq!(fields.len(), 11);
+
+        let page = backend.sscan(b"set", 0, 10, None)?;
+        assert_eq!(page.cursor, 0);
+        assert_eq!(page.items.len(), 2);
+
+        assert_eq!(backend.sscan(b"missing", 0, 10, None)?, empty_page());
+        assert_eq!(
+            backend.sscan(b"hash", 0, 10, None),
+            Err(BackendError::WrongType)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_with_huge_count() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), BulkString::from("value").into());
+        backend.sadd(b"set".to_vec(), vec![b"a".to_vec(), b"b".to_vec()])?;
+
+        // COUNT is not an allocation size
+        let page = backend.scan(0, usize::MAX, None, None);
+        assert_eq!((page.cursor, page.items.len()), (0, 2));
+        let page = backend.sscan(b"set", 0, i64::MAX as usize, None)?;
+        assert_eq!((page.cursor, page.items.len()), (0, 2));
+        Ok(())
+    }
+}
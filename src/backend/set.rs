@@ -0,0 +1,499 @@
+use super::scan::ScanSet;
+use super::{Backend, BackendError, Value};
+use rand::{seq::SliceRandom, Rng};
+use std::collections::HashSet;
+
+// The most members a negative count of SRANDMEMBER or HRANDFIELD may ask for. Unlike redis,
+// which streams any count into the output buffer, a reply is built in memory as one frame,
+// so a larger count replies "value is out of range" rather than allocating gigabytes.
+pub(crate) const MAX_RANDOM_COUNT: u64 = 1 << 20;
+
+/// How SINTER, SUNION and SDIFF combine their sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Inter,
+    Union,
+    Diff,
+}
+
+// like hashes, a set is deleted as soon as its last member is removed
+impl Backend {
+    pub fn sadd(&self, key: Vec<u8>, members: Vec<Vec<u8>>) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let entry = self
+            .keyspace
+            .entry(key)
+            .or_insert_with(|| Value::Set(ScanSet::new()));
+        let set = entry.as_set()?;
+
+        let mut count = 0;
+        members.into_iter().for_each(|member| {
+            if set.insert(member) {
+                count += 1
+            }
+        });
+        Ok(count)
+    }
+
+    pub fn sismember(&self, key: &[u8], member: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_set()?.contains(member) as i64),
+            None => Ok(0),
+        }
+    }
+
+    /// Remove the given members, returns how many of them existed.
+    pub fn srem(&self, key: &[u8], members: &[Vec<u8>]) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let (count, empty) = match self.keyspace.get(key) {
+            Some(v) => {
+                let set = v.as_set()?;
+                let count = members
+                    .iter()
+                    .filter(|member| set.remove(member).is_some())
+                    .count();
+                (count as i64, set.is_empty())
+            }
+            None => return Ok(0),
+        };
+        // the ref is released by now, removing the key while holding it would deadlock
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(count)
+    }
+
+    pub fn smembers(&self, key: &[u8]) -> Result<Vec<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_set()?.iter().map(|v| v.key().clone()).collect()),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn scard(&self, key: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_set()?.len() as i64),
+            None => Ok(0),
+        }
+    }
+
+    /// Whether each of the members belongs to the set, as 1 or 0.
+    pub fn smismember(&self, key: &[u8], members: &[Vec<u8>]) -> Result<Vec<i64>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let set = v.as_set()?;
+                Ok(members
+                    .iter()
+                    .map(|member| set.contains(member) as i64)
+                    .collect())
+            }
+            None => Ok(vec![0; members.len()]),
+        }
+    }
+
+    /// Remove and return up to `count` random members.
+    pub fn spop(&self, key: &[u8], count: usize) -> Result<Vec<Vec<u8>>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let (popped, empty) = match self.keyspace.get(key) {
+            Some(v) => {
+                let set = v.as_set()?;
+                let positions = random_positions(set.len(), count.min(set.len()) as i64)?;
+                let popped = pick(set.iter(), &positions, |v| v.key().clone());
+                for member in &popped {
+                    set.remove(member);
+                }
+                (popped, set.is_empty())
+            }
+            None => return Ok(vec![]),
+        };
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(popped)
+    }
+
+    /// Random members. A positive count returns distinct members, at most the whole set,
+    /// a negative count returns exactly -count members that may repeat, up to 1 << 20.
+    pub fn srandmember(&self, key: &[u8], count: i64) -> Result<Vec<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let set = v.as_set()?;
+                let positions = random_positions(set.len(), count)?;
+                Ok(pick(set.iter(), &positions, |v| v.key().clone()))
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Move a member from source to destination, returns false if source does not hold it.
+    /// Both keys stay locked, so no other client sees the member in both sets or in none.
+    pub fn smove(
+        &self,
+        source: &[u8],
+        destination: &[u8],
+        member: &[u8],
+    ) -> Result<bool, BackendError> {
+        let _guard = self.locks.write([source, destination]);
+        self.expire_if_needed(source);
+        self.expire_if_needed(destination);
+        // both types are checked before anything moves
+        match self.keyspace.get(source) {
+            Some(v) => {
+                v.as_set()?;
+            }
+            None => return Ok(false),
+        }
+        if let Some(v) = self.keyspace.get(destination) {
+            v.as_set()?;
+        }
+        if source == destination {
+            return Ok(self.sismember(source, member)? == 1);
+        }
+
+        let (removed, empty) = match self.keyspace.get(source) {
+            Some(v) => {
+                let set = v.as_set()?;
+                (set.remove(member).is_some(), set.is_empty())
+            }
+            None => (false, false),
+        };
+        if !removed {
+            return Ok(false);
+        }
+        if empty {
+            self.remove_key(source);
+        }
+        self.keyspace
+            .entry(destination.to_vec())
+            .or_insert_with(|| Value::Set(ScanSet::new()))
+            .as_set()?
+            .insert(member.to_vec());
+        Ok(true)
+    }
+    /// Combine the sets at keys, a missing key counts as an empty set.
+    pub fn scombine(
+        &self,
+        op: SetOperation,
+        keys: &[Vec<u8>],
+    ) -> Result<Vec<Vec<u8>>, BackendError> {
+        let _guard = self.locks.read(keys);
+        Ok(self.combine(op, keys)?.into_iter().collect())
+    }
+
+    /// Store the combination of the sets at keys in destination, replacing what it held.
+    /// Returns the size of the resulting set, an empty result deletes destination.
+    pub fn scombine_store(
+        &self,
+        op: SetOperation,
+        destination: Vec<u8>,
+        keys: &[Vec<u8>],
+    ) -> Result<i64, BackendError> {
+        // the sources and destination stay locked, so no client sees a partial result
+        let _guard = self.locks.write(keys.iter().chain([&destination]));
+        let members = self.combine(op, keys)?;
+        let count = members.len() as i64;
+
+        self.remove_key(&destination);
+        if count > 0 {
+            let set = ScanSet::new();
+            members.into_iter().for_each(|member| {
+                set.insert(member);
+            });
+            self.keyspace.insert(destination, Value::Set(set));
+        }
+        Ok(count)
+    }
+
+    /// Size of the intersection of the sets at keys, counting stops at `limit` unless it is 0.
+    pub fn sintercard(&self, keys: &[Vec<u8>], limit: usize) -> Result<i64, BackendError> {
+        let _guard = self.locks.read(keys);
+        Ok(self.inter(keys, limit)?.len() as i64)
+    }
+
+    fn combine(
+        &self,
+        op: SetOperation,
+        keys: &[Vec<u8>],
+    ) -> Result<HashSet<Vec<u8>>, BackendError> {
+        match op {
+            SetOperation::Inter => self.inter(keys, 0),
+            SetOperation::Union => {
+                let mut members = HashSet::new();
+                for key in keys {
+                    members.extend(self.set_members(key)?);
+                }
+                Ok(members)
+            }
+            SetOperation::Diff => {
+                let mut members = match keys.first() {
+                    Some(key) => self.set_members(key)?,
+                    None => return Ok(HashSet::new()),
+                };
+                for key in &keys[1..] {
+                    // an expired key subtracts nothing
+                    self.expire_if_needed(key);
+                    if let Some(v) = self.keyspace.get(key.as_slice()) {
+                        let set = v.as_set()?;
+                        members.retain(|member| !set.contains(member));
+                    }
+                }
+                Ok(members)
+            }
+        }
+    }
+
+    // Members of the smallest set are looked up in the others, from the smallest to the
+    // largest, so the work is bounded by the smallest set whatever the size of the others.
+    fn inter(&self, keys: &[Vec<u8>], limit: usize) -> Result<HashSet<Vec<u8>>, BackendError> {
+        let mut sizes = Vec::with_capacity(keys.len());
+        let mut missing = false;
+        // every type is checked, even once a missing key makes the result empty
+        for key in keys {
+            self.expire_if_needed(key);
+            match self.keyspace.get(key.as_slice()) {
+                Some(v) => sizes.push((v.as_set()?.len(), key)),
+                None => missing = true,
+            }
+        }
+        if missing || sizes.is_empty() {
+            return Ok(HashSet::new());
+        }
+        sizes.sort_by_key(|(len, _)| *len);
+
+        // one ref at a time, holding two could deadlock on a shard a writer waits for
+        let mut members = HashSet::new();
+        for member in self.set_members(sizes[0].1)? {
+            let in_all = sizes[1..].iter().all(|(_, key)| {
+                self.keyspace
+                    .get(key.as_slice())
+                    .is_some_and(|v| v.as_set().is_ok_and(|set| set.contains(&member)))
+            });
+            if in_all {
+                members.insert(member);
+                if members.len() == limit {
+                    break;
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    fn set_members(&self, key: &[u8]) -> Result<HashSet<Vec<u8>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_set()?.iter().map(|v| v.key().clone()).collect()),
+            None => Ok(HashSet::new()),
+        }
+    }
+}
+
+// Sorted positions of random items among len: a positive count gives distinct positions, at
+// most len of them, a negative count exactly -count positions that may repeat.
+pub(crate) fn random_positions(len: usize, count: i64) -> Result<Vec<usize>, BackendError> {
+    let mut rng = rand::thread_rng();
+    let mut positions = if count >= 0 {
+        let count = (count as u64).min(len as u64) as usize;
+        rand::seq::index::sample(&mut rng, len, count).into_vec()
+    } else if len == 0 {
+        vec![]
+    } else if count.unsigned_abs() > MAX_RANDOM_COUNT {
+        return Err(BackendError::CountOutOfRange);
+    } else {
+        (0..count.unsigned_abs())
+            .map(|_| rng.gen_range(0..len))
+            .collect()
+    };
+    positions.sort_unstable();
+    Ok(positions)
+}
+
+// The items at the sorted positions, in random order, going through the items once and
+// copying only the picked ones rather than the whole collection.
+pub(crate) fn pick<I, T>(items: I, positions: &[usize], copy: impl Fn(&I::Item) -> T) -> Vec<T>
+where
+    I: Iterator,
+{
+    let mut picked = Vec::with_capacity(positions.len());
+    let mut positions = positions.iter().peekable();
+    for (i, item) in items.enumerate() {
+        while positions.next_if(|&&position| position == i).is_some() {
+            picked.push(copy(&item));
+        }
+        if positions.peek().is_none() {
+            break;
+        }
+    }
+    picked.shuffle(&mut rand::thread_rng());
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::now_ms, BulkString};
+
+    fn members(names: &[&str]) -> Vec<Vec<u8>> {
+        names.iter().map(|name| name.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_srem_removes_empty_set() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"set".to_vec(), members(&["a", "b"]))?;
+
+        assert_eq!(backend.srem(b"set", &members(&["a", "missing"])), Ok(1));
+        assert_eq!(backend.scard(b"set"), Ok(1));
+        assert_eq!(backend.smembers(b"set"), Ok(members(&["b"])));
+        assert_eq!(backend.srem(b"set", &members(&["b"])), Ok(1));
+        assert!(!backend.exists(b"set"));
+        assert_eq!(
+            backend.smismember(b"set", &members(&["a", "b"])),
+            Ok(vec![0, 0])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"set".to_vec(), members(&["a", "b", "c", "d"]))?;
+
+        let random = backend.srandmember(b"set", 3)?;
+        assert_eq!(random.iter().collect::<HashSet<_>>().len(), 3);
+        assert_eq!(backend.srandmember(b"set", 10)?.len(), 4);
+        assert_eq!(backend.srandmember(b"set", -10)?.len(), 10);
+        // the reply of a huge negative count would not fit in memory
+        let max = MAX_RANDOM_COUNT as i64;
+        assert_eq!(backend.srandmember(b"set", -max)?.len(), max as usize);
+        assert_eq!(
+            backend.srandmember(b"set", -max - 1),
+            Err(BackendError::CountOutOfRange)
+        );
+        assert_eq!(
+            backend.srandmember(b"set", -i64::MAX),
+            Err(BackendError::CountOutOfRange)
+        );
+        assert_eq!(backend.srandmember(b"set", i64::MAX)?.len(), 4);
+
+        let popped = backend.spop(b"set", 3)?;
+        assert_eq!(popped.len(), 3);
+        assert_eq!(backend.scard(b"set"), Ok(1));
+        assert_eq!(backend.smismember(b"set", &popped), Ok(vec![0, 0, 0]));
+        assert_eq!(backend.spop(b"set", 3)?.len(), 1);
+        assert!(!backend.exists(b"set"));
+        assert_eq!(backend.spop(b"set", 1), Ok(vec![]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_smove() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"src".to_vec(), members(&["a"]))?;
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+
+        assert_eq!(
+            backend.smove(b"src", b"str", b"a"),
+            Err(BackendError::WrongType)
+        );
+        assert_eq!(backend.smove(b"src", b"dst", b"missing"), Ok(false));
+        assert_eq!(backend.smove(b"src", b"src", b"a"), Ok(true));
+        assert_eq!(backend.smove(b"src", b"dst", b"a"), Ok(true));
+        assert!(!backend.exists(b"src"));
+        assert_eq!(backend.sismember(b"dst", b"a"), Ok(1));
+        assert_eq!(backend.smove(b"src", b"dst", b"a"), Ok(false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scombine() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"a".to_vec(), members(&["1", "2", "3", "4"]))?;
+        backend.sadd(b"b".to_vec(), members(&["2", "3", "5"]))?;
+        backend.sadd(b"c".to_vec(), members(&["3", "4"]))?;
+        let sorted = |mut members: Vec<Vec<u8>>| {
+            members.sort();
+            members
+        };
+
+        let keys = members(&["a", "b", "c"]);
+        let result = backend.scombine(SetOperation::Inter, &keys)?;
+        assert_eq!(result, members(&["3"]));
+        let result = backend.scombine(SetOperation::Union, &keys)?;
+        assert_eq!(sorted(result), members(&["1", "2", "3", "4", "5"]));
+        let result = backend.scombine(SetOperation::Diff, &keys)?;
+        assert_eq!(result, members(&["1"]));
+
+        let keys = members(&["a", "missing"]);
+        assert_eq!(backend.scombine(SetOperation::Inter, &keys), Ok(vec![]));
+        let result = backend.scombine(SetOperation::Diff, &keys)?;
+        assert_eq!(result.len(), 4);
+
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+        let keys = members(&["missing", "str"]);
+        assert_eq!(
+            backend.scombine(SetOperation::Inter, &keys),
+            Err(BackendError::WrongType)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scombine_store_and_sintercard() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"a".to_vec(), members(&["1", "2", "3"]))?;
+        backend.sadd(b"b".to_vec(), members(&["2", "3"]))?;
+        backend.set(b"dst".to_vec(), BulkString::from("value").into());
+        backend.expire_at(b"dst", now_ms() + 10_000);
+
+        let keys = members(&["a", "b"]);
+        assert_eq!(backend.sintercard(&keys, 0), Ok(2));
+        assert_eq!(backend.sintercard(&keys, 1), Ok(1));
+
+        // the destination is replaced whatever its type, and loses its time to live
+        assert_eq!(
+            backend.scombine_store(SetOperation::Inter, b"dst".to_vec(), &keys),
+            Ok(2)
+        );
+        assert_eq!(backend.key_type(b"dst"), "set");
+        assert!(!backend.expire.contains_key(b"dst".as_slice()));
+
+        // a destination among the sources is read before being replaced
+        let keys = members(&["a", "a"]);
+        assert_eq!(
+            backend.scombine_store(SetOperation::Diff, b"a".to_vec(), &keys),
+            Ok(0)
+        );
+        assert!(!backend.exists(b"a"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sdiff_of_an_expired_key() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.sadd(b"a".to_vec(), members(&["1", "2"]))?;
+        backend.sadd(b"b".to_vec(), members(&["2"]))?;
+        backend.expire.insert(b"b".to_vec(), now_ms() - 1);
+
+        let keys = members(&["a", "b"]);
+        let mut diff = backend.scombine(SetOperation::Diff, &keys)?;
+        diff.sort();
+        assert_eq!(diff, members(&["1", "2"]));
+
+        backend.sadd(b"b".to_vec(), members(&["2"]))?;
+        backend.expire.insert(b"b".to_vec(), now_ms() - 1);
+        assert_eq!(
+            backend.scombine_store(SetOperation::Diff, b"dst".to_vec(), &keys),
+            Ok(2)
+        );
+        assert!(!backend.exists(b"b"));
+        Ok(())
+    }
+}
@@ -0,0 +1,394 @@
+use super::scan::Entry;
+use super::{now_ms, stream_group::ConsumerGroup, Backend, BackendError, Value};
+use std::{collections::BTreeMap, fmt};
+
+// unlike the other collections, a stream stays when its last entry is deleted: it keeps its
+// last ID, so the IDs it hands out never go back
+
+// `~` trims whole nodes of this many entries, like the default stream-node-max-entries of
+// redis, so up to a node's worth of entries more than asked for may stay
+const NODE_ENTRIES: usize = 100;
+
+/// The ID of a stream entry: the milliseconds it was added at, and a sequence number
+/// for the entries added within the same millisecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// The field value pairs of a stream entry, in the order they were given.
+pub type StreamFields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// A stream entry with its ID.
+pub type StreamEntry = (StreamId, StreamFields);
+
+/// The ID XADD gives the new entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    // *
+    Auto,
+    // ms-*
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+/// What XADD and XTRIM trim the stream to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    MinId(StreamId),
+}
+
+/// The MAXLEN / MINID option of XADD and XTRIM. Approximate (`~`) trimming only removes
+/// whole nodes, and at most `limit` entries (LIMIT) when given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    pub approximate: bool,
+    pub limit: Option<usize>,
+}
+
+/// The value of a stream: its entries in ID order, and the consumer groups reading it.
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    pub(crate) entries: BTreeMap<StreamId, StreamFields>,
+    pub(crate) last_id: StreamId,
+    // every entry ever added, deleted or not
+    pub(crate) entries_added: u64,
+    pub(crate) groups: BTreeMap<Vec<u8>, ConsumerGroup>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// The smallest ID greater than this one, None for the last possible ID.
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// The greatest ID lower than this one, None for 0-0.
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn first_entry(&self) -> Option<StreamEntry> {
+        self.entries
+            .first_key_value()
+            .map(|(id, fields)| (*id, fields.clone()))
+    }
+
+    pub fn last_entry(&self) -> Option<StreamEntry> {
+        self.entries
+            .last_key_value()
+            .map(|(id, fields)| (*id, fields.clone()))
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    /// Entries from start to end, both included, in ID order or the reverse with `rev`.
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        rev: bool,
+    ) -> Vec<StreamEntry> {
+        if start > end {
+            return vec![];
+        }
+        let entries = self.entries.range(start..=end);
+        let entries: Box<dyn Iterator<Item = _>> = if rev {
+            Box::new(entries.rev())
+        } else {
+            Box::new(entries)
+        };
+        entries
+            .take(count)
+            .map(|(id, fields)| (*id, fields.clone()))
+            .collect()
+    }
+
+    // the ID the next entry gets, which must be greater than the last one
+    fn next_id(&self, id: XAddId, now: u64) -> Result<StreamId, BackendError> {
+        let last = self.last_id;
+        match id {
+            XAddId::Auto if now > last.ms => Ok(StreamId::new(now, 0)),
+            XAddId::Auto => last.next().ok_or(BackendError::StreamExhausted),
+            XAddId::AutoSeq(ms) if ms == last.ms => last
+                .next()
+                .filter(|id| id.ms == ms)
+                .ok_or(BackendError::StreamIdTooSmall),
+            XAddId::AutoSeq(ms) if ms > last.ms => Ok(StreamId::new(ms, 0)),
+            XAddId::AutoSeq(_) => Err(BackendError::StreamIdTooSmall),
+            XAddId::Explicit(id) if id == StreamId::MIN => Err(BackendError::StreamIdZero),
+            XAddId::Explicit(id) if id <= last => Err(BackendError::StreamIdTooSmall),
+            XAddId::Explicit(id) => Ok(id),
+        }
+    }
+
+    fn add(&mut self, id: XAddId, fields: StreamFields) -> Result<StreamId, BackendError> {
+        let id = self.next_id(id, now_ms().max(0) as u64)?;
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+        Ok(id)
+    }
+
+    fn trim(&mut self, trim: StreamTrim) -> usize {
+        let removable = match trim.strategy {
+            TrimStrategy::MaxLen(len) => self.len().saturating_sub(len),
+            TrimStrategy::MinId(id) => self.entries.range(..id).count(),
+        };
+        let count = if trim.approximate {
+            let count = removable.min(trim.limit.unwrap_or(NODE_ENTRIES * 100));
+            count - count % NODE_ENTRIES
+        } else {
+            removable
+        };
+        for _ in 0..count {
+            self.entries.pop_first();
+        }
+        count
+    }
+}
+
+impl Backend {
+    /// Append an entry, returns its ID. With `nomkstream` nothing happens (None) if the
+    /// stream does not exist. The stream is trimmed after the entry is added.
+    pub fn xadd(
+        &self,
+        key: Vec<u8>,
+        id: XAddId,
+        fields: StreamFields,
+        nomkstream: bool,
+        trim: Option<StreamTrim>,
+    ) -> Result<Option<StreamId>, BackendError> {
+        let id = {
+            let _guard = self.locks.write([&key]);
+            self.expire_if_needed(&key);
+            let mut entry = match self.keyspace.entry(key.clone()) {
+                Entry::Occupied(entry) => entry.into_ref(),
+                Entry::Vacant(_) if nomkstream => return Ok(None),
+                Entry::Vacant(entry) => {
+                    // a rejected ID does not leave an empty stream behind
+                    Stream::default().next_id(id, 0)?;
+                    entry.insert(Value::Stream(Stream::default()))
+                }
+            };
+            let stream = entry.as_stream_mut()?;
+            let id = stream.add(id, fields)?;
+            if let Some(trim) = trim {
+                stream.trim(trim);
+            }
+            id
+        };
+        // blocked readers read with the lock of key, it must be released by now
+        self.wake(&key);
+        Ok(Some(id))
+    }
+
+    pub fn xlen(&self, key: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_stream()?.len() as i64),
+            None => Ok(0),
+        }
+    }
+
+    /// XRANGE and XREVRANGE: at most `count` entries from start to end, both included.
+    pub fn xrange(
+        &self,
+        key: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        rev: bool,
+    ) -> Result<Vec<StreamEntry>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_stream()?.range(start, end, count, rev)),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// XREAD: at most `count` entries with an ID greater than `after`.
+    pub fn xread(
+        &self,
+        key: &[u8],
+        after: StreamId,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, BackendError> {
+        match after.next() {
+            Some(start) => self.xrange(key, start, StreamId::MAX, count, false),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The ID of the last entry added to the stream (`$`), 0-0 if it does not exist.
+    pub fn xlast_id(&self, key: &[u8]) -> Result<StreamId, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_stream()?.last_id),
+            None => Ok(StreamId::MIN),
+        }
+    }
+
+    /// Delete the entries with the given IDs, returns how many existed.
+    pub fn xdel(&self, key: &[u8], ids: &[StreamId]) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let stream = v.as_stream_mut()?;
+                let deleted = ids
+                    .iter()
+                    .filter(|id| stream.entries.remove(id).is_some())
+                    .count();
+                Ok(deleted as i64)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Returns how many entries were trimmed.
+    pub fn xtrim(&self, key: &[u8], trim: StreamTrim) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        match self.keyspace.get_mut(key) {
+            Some(mut v) => Ok(v.as_stream_mut()?.trim(trim) as i64),
+            None => Ok(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> StreamFields {
+        pairs
+            .iter()
+            .map(|(field, value)| (field.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_xadd_ids() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let entry = fields(&[("f", "v")]);
+        let add = |id| backend.xadd(b"s".to_vec(), id, entry.clone(), false, None);
+
+        assert_eq!(
+            add(XAddId::Explicit(StreamId::MIN)),
+            Err(BackendError::StreamIdZero)
+        );
+        assert!(!backend.exists(b"s"));
+        assert_eq!(add(XAddId::AutoSeq(0))?, Some(StreamId::new(0, 1)));
+        assert_eq!(add(XAddId::AutoSeq(5))?, Some(StreamId::new(5, 0)));
+        assert_eq!(add(XAddId::AutoSeq(5))?, Some(StreamId::new(5, 1)));
+        assert_eq!(
+            add(XAddId::Explicit(StreamId::new(5, 1))),
+            Err(BackendError::StreamIdTooSmall)
+        );
+        assert_eq!(add(XAddId::AutoSeq(4)), Err(BackendError::StreamIdTooSmall));
+        let id = add(XAddId::Auto)?.expect("stream exists");
+        assert!(id.ms >= now_ms() as u64 - 1000);
+
+        assert_eq!(
+            backend.xadd(b"missing".to_vec(), XAddId::Auto, entry.clone(), true, None),
+            Ok(None)
+        );
+        assert_eq!(backend.xlen(b"s"), Ok(4));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_xdel_and_xtrim() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for ms in 1..=250 {
+            let id = XAddId::Explicit(StreamId::new(ms, 0));
+            backend.xadd(b"s".to_vec(), id, fields(&[("n", "v")]), false, None)?;
+        }
+
+        let entries = backend.xrange(b"s", StreamId::new(3, 0), StreamId::MAX, 2, false)?;
+        let ids = entries.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![StreamId::new(3, 0), StreamId::new(4, 0)]);
+        let entries = backend.xrange(b"s", StreamId::MIN, StreamId::new(3, 0), 10, true)?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].0, StreamId::new(3, 0));
+
+        let ids = [
+            StreamId::new(1, 0),
+            StreamId::new(1, 0),
+            StreamId::new(999, 0),
+        ];
+        assert_eq!(backend.xdel(b"s", &ids), Ok(1));
+
+        // approximate trimming only removes whole nodes
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MaxLen(100),
+            approximate: true,
+            limit: None,
+        };
+        assert_eq!(backend.xtrim(b"s", trim), Ok(100));
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MinId(StreamId::new(200, 0)),
+            approximate: false,
+            limit: None,
+        };
+        assert_eq!(backend.xtrim(b"s", trim), Ok(98));
+        assert_eq!(backend.xlen(b"s"), Ok(51));
+
+        // deleting every entry keeps the stream, and its last ID
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MaxLen(0),
+            approximate: false,
+            limit: None,
+        };
+        assert_eq!(backend.xtrim(b"s", trim), Ok(51));
+        assert_eq!(backend.xlast_id(b"s"), Ok(StreamId::new(250, 0)));
+        assert_eq!(backend.xread(b"s", StreamId::MIN, 10), Ok(vec![]));
+        Ok(())
+    }
+}
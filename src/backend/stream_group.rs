@@ -0,0 +1,985 @@
+use super::scan::Entry;
+use super::{now_ms, Backend, BackendError, Stream, StreamEntry, StreamFields, StreamId, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+// every entry delivered to a consumer stays in the pending entries list (PEL) of its group
+// until it is acknowledged, so it can be claimed by another consumer if the first one dies
+
+/// A consumer group of a stream: the last entry delivered to the group, and the entries
+/// delivered to its consumers but not acknowledged yet.
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroup {
+    last_delivered: StreamId,
+    // entries read by the group, None when it cannot be told (e.g. SETID to an arbitrary ID)
+    entries_read: Option<u64>,
+    pending: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+#[derive(Debug, Clone)]
+struct PendingEntry {
+    consumer: Vec<u8>,
+    // unix milliseconds of the last delivery
+    delivered_at: i64,
+    deliveries: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Consumer {
+    // last interaction, and last time it read or claimed entries
+    seen_at: i64,
+    active_at: Option<i64>,
+    pending: BTreeSet<StreamId>,
+}
+
+/// An entry read by XREADGROUP, None for an entry of the history deleted since.
+pub type GroupEntry = (StreamId, Option<StreamFields>);
+
+/// A pending entry as XPENDING reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: Vec<u8>,
+    // milliseconds since the last delivery
+    pub idle: i64,
+    pub deliveries: u64,
+}
+
+/// The summary form of XPENDING.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    // the lowest and highest pending IDs
+    pub range: Option<(StreamId, StreamId)>,
+    pub consumers: Vec<(Vec<u8>, usize)>,
+}
+
+/// The options of XCLAIM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    // IDLE / TIME: the delivery time to record, now by default
+    pub delivered_at: Option<i64>,
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub just_id: bool,
+    pub last_id: Option<StreamId>,
+}
+
+/// The reply of XAUTOCLAIM: where the next call starts (0-0 once the whole PEL was scanned),
+/// the claimed entries, and the pending IDs whose entry was deleted, dropped from the PEL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoClaim {
+    pub next: StreamId,
+    pub claimed: Vec<StreamEntry>,
+    pub deleted: Vec<StreamId>,
+}
+
+/// XINFO STREAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_generated_id: StreamId,
+    pub entries_added: u64,
+    pub groups: usize,
+    pub first_entry: Option<StreamEntry>,
+    pub last_entry: Option<StreamEntry>,
+}
+
+/// A group as XINFO GROUPS reports it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub name: Vec<u8>,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_delivered_id: StreamId,
+    pub entries_read: Option<u64>,
+    // entries not delivered to the group yet
+    pub lag: Option<u64>,
+}
+
+/// A consumer as XINFO CONSUMERS reports it, times in milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerInfo {
+    pub name: Vec<u8>,
+    pub pending: usize,
+    pub idle: i64,
+    pub inactive: Option<i64>,
+}
+
+impl ConsumerGroup {
+    // the group reads what follows last_delivered, $ (None) is the last ID of the stream
+    fn new(stream: &Stream, last_delivered: Option<StreamId>, entries_read: Option<u64>) -> Self {
+        let mut group = Self::default();
+        group.set_id(stream, last_delivered, entries_read);
+        group
+    }
+
+    fn set_id(&mut self, stream: &Stream, id: Option<StreamId>, entries_read: Option<u64>) {
+        self.last_delivered = id.unwrap_or(stream.last_id);
+        self.entries_read = match (entries_read, id) {
+            (Some(n), _) => Some(n),
+            (None, None) => Some(stream.entries_added),
+            (None, Some(StreamId::MIN)) => Some(0),
+            (None, Some(_)) => None,
+        };
+    }
+
+    // the consumer, created on first use, seen now
+    fn consumer(&mut self, name: &[u8], now: i64) -> &mut Consumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_vec())
+            .or_insert_with(|| Consumer {
+                seen_at: now,
+                active_at: None,
+                pending: BTreeSet::new(),
+            });
+        consumer.seen_at = now;
+        consumer
+    }
+
+    // give the pending entry to the consumer, whoever had it before
+    fn assign(&mut self, id: StreamId, consumer: &[u8], delivered_at: i64, deliveries: u64) {
+        self.unassign(id);
+        self.consumer(consumer, delivered_at.max(0))
+            .pending
+            .insert(id);
+        self.pending.insert(
+            id,
+            PendingEntry {
+                consumer: consumer.to_vec(),
+                delivered_at,
+                deliveries,
+            },
+        );
+    }
+
+    fn unassign(&mut self, id: StreamId) -> bool {
+        match self.pending.remove(&id) {
+            Some(entry) => {
+                if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+                    consumer.pending.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn activate(&mut self, consumer: &[u8], now: i64) {
+        let consumer = self.consumer(consumer, now);
+        consumer.active_at = Some(now);
+    }
+}
+
+impl Backend {
+    /// XGROUP CREATE: the group reads what follows `id`, None for `$`. Without `mkstream`
+    /// the stream must exist.
+    pub fn xgroup_create(
+        &self,
+        key: Vec<u8>,
+        group: Vec<u8>,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        let _guard = self.locks.write([&key]);
+        self.expire_if_needed(&key);
+        let mut entry = match self.keyspace.entry(key) {
+            Entry::Occupied(entry) => entry.into_ref(),
+            Entry::Vacant(_) if !mkstream => return Err(BackendError::XGroupNoKey),
+            Entry::Vacant(entry) => entry.insert(Value::Stream(Stream::default())),
+        };
+        let stream = entry.as_stream_mut()?;
+        if stream.groups.contains_key(&group) {
+            return Err(BackendError::BusyGroup);
+        }
+        let new = ConsumerGroup::new(stream, id, entries_read);
+        stream.groups.insert(group, new);
+        Ok(())
+    }
+
+    /// XGROUP SETID: where the group reads from next, None for `$`.
+    pub fn xgroup_setid(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        // moving the group back gives entries to the clients blocked on it
+        self.with_stream(key, |stream| {
+            let mut consumer_group = stream
+                .groups
+                .remove(group)
+                .ok_or_else(|| no_group(key, group))?;
+            consumer_group.set_id(stream, id, entries_read);
+            stream.groups.insert(group.to_vec(), consumer_group);
+            Ok(())
+        })?;
+        self.wake(key);
+        Ok(())
+    }
+
+    /// XGROUP DESTROY, returns whether the group existed.
+    pub fn xgroup_destroy(&self, key: &[u8], group: &[u8]) -> Result<bool, BackendError> {
+        let destroyed =
+            self.with_stream(key, |stream| Ok(stream.groups.remove(group).is_some()))?;
+        // the clients blocked on the group are told it is gone
+        self.wake(key);
+        Ok(destroyed)
+    }
+
+    /// XGROUP CREATECONSUMER, returns whether the consumer was created.
+    pub fn xgroup_createconsumer(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+    ) -> Result<bool, BackendError> {
+        self.with_group(key, group, |group, _| {
+            if group.consumers.contains_key(consumer) {
+                return Ok(false);
+            }
+            group.consumer(consumer, now_ms());
+            Ok(true)
+        })
+    }
+
+    /// XGROUP DELCONSUMER, returns how many entries the consumer had pending, which are
+    /// dropped with it.
+    pub fn xgroup_delconsumer(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+    ) -> Result<i64, BackendError> {
+        self.with_group(key, group, |group, _| {
+            match group.consumers.remove(consumer) {
+                Some(consumer) => {
+                    for id in &consumer.pending {
+                        group.pending.remove(id);
+                    }
+                    Ok(consumer.pending.len() as i64)
+                }
+                None => Ok(0),
+            }
+        })
+    }
+
+    /// XREADGROUP on one stream. With `after` None (`>`), at most `count` entries never
+    /// delivered to the group, which become pending for the consumer unless `noack`. With an
+    /// ID, the history: the entries pending for the consumer after that ID, delivered again.
+    pub fn xreadgroup(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        after: Option<StreamId>,
+        count: usize,
+        noack: bool,
+    ) -> Result<Vec<GroupEntry>, BackendError> {
+        let now = now_ms();
+        self.with_group(key, group, |group, stream| {
+            group.consumer(consumer, now);
+            let after = match after {
+                Some(after) => return Ok(read_history(group, stream, consumer, after, count, now)),
+                None => group.last_delivered,
+            };
+
+            let entries = match after.next() {
+                Some(start) => stream.range(start, StreamId::MAX, count, false),
+                None => vec![],
+            };
+            let last = match entries.last() {
+                Some((last, _)) => *last,
+                None => return Ok(vec![]),
+            };
+            if !noack {
+                for (id, _) in &entries {
+                    group.assign(*id, consumer, now, 1);
+                }
+            }
+            group.activate(consumer, now);
+            group.entries_read = if last == stream.last_id {
+                Some(stream.entries_added)
+            } else {
+                group.entries_read.map(|n| n + entries.len() as u64)
+            };
+            group.last_delivered = last;
+            Ok(entries
+                .into_iter()
+                .map(|(id, fields)| (id, Some(fields)))
+                .collect())
+        })
+    }
+
+    /// Acknowledge pending entries, returns how many were pending.
+    pub fn xack(&self, key: &[u8], group: &[u8], ids: &[StreamId]) -> Result<i64, BackendError> {
+        match self.with_group(key, group, |group, _| {
+            Ok(ids.iter().filter(|id| group.unassign(**id)).count() as i64)
+        }) {
+            // nothing can be pending in a group that does not exist
+            Err(BackendError::NoGroup { .. }) => Ok(0),
+            result => result,
+        }
+    }
+
+    /// The summary form of XPENDING.
+    pub fn xpending_summary(
+        &self,
+        key: &[u8],
+        group: &[u8],
+    ) -> Result<PendingSummary, BackendError> {
+        self.read_group(key, group, |group| {
+            let range = group
+                .pending
+                .first_key_value()
+                .zip(group.pending.last_key_value())
+                .map(|((first, _), (last, _))| (*first, *last));
+            let consumers = group
+                .consumers
+                .iter()
+                .filter(|(_, consumer)| !consumer.pending.is_empty())
+                .map(|(name, consumer)| (name.clone(), consumer.pending.len()))
+                .collect();
+            PendingSummary {
+                count: group.pending.len(),
+                range,
+                consumers,
+            }
+        })
+    }
+
+    /// The extended form of XPENDING: at most `count` pending entries from start to end,
+    /// idle for at least `min_idle` milliseconds, of one consumer if given.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xpending(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        min_idle: i64,
+        consumer: Option<&[u8]>,
+    ) -> Result<Vec<PendingInfo>, BackendError> {
+        if start > end {
+            return self.read_group(key, group, |_| vec![]);
+        }
+        let now = now_ms();
+        self.read_group(key, group, |group| {
+            group
+                .pending
+                .range(start..=end)
+                .filter(|(_, entry)| consumer.is_none_or(|c| entry.consumer == c))
+                .filter(|(_, entry)| now - entry.delivered_at >= min_idle)
+                .take(count)
+                .map(|(id, entry)| PendingInfo {
+                    id: *id,
+                    consumer: entry.consumer.clone(),
+                    idle: (now - entry.delivered_at).max(0),
+                    deliveries: entry.deliveries,
+                })
+                .collect()
+        })
+    }
+
+    /// XCLAIM: give the pending entries idle for at least `min_idle` milliseconds to the
+    /// consumer, returns them. Entries deleted from the stream are dropped from the PEL.
+    pub fn xclaim(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: i64,
+        ids: &[StreamId],
+        options: ClaimOptions,
+    ) -> Result<Vec<StreamEntry>, BackendError> {
+        let now = now_ms();
+        self.with_group(key, group, |group, stream| {
+            group.consumer(consumer, now);
+            let mut claimed = vec![];
+            for id in ids {
+                let fields = stream.entries.get(id).cloned();
+                let deliveries = match (group.pending.get(id), fields) {
+                    (Some(_), None) => {
+                        group.unassign(*id);
+                        continue;
+                    }
+                    (Some(entry), Some(_)) if now - entry.delivered_at < min_idle => continue,
+                    (Some(entry), Some(fields)) => {
+                        claimed.push((*id, fields));
+                        entry.deliveries
+                    }
+                    (None, Some(fields)) if options.force => {
+                        claimed.push((*id, fields));
+                        0
+                    }
+                    (None, _) => continue,
+                };
+                let deliveries = match options.retry_count {
+                    Some(retry_count) => retry_count,
+                    // JUSTID claims do not count as deliveries
+                    None if options.just_id => deliveries,
+                    None => deliveries + 1,
+                };
+                let delivered_at = options.delivered_at.unwrap_or(now);
+                group.assign(*id, consumer, delivered_at, deliveries);
+            }
+
+            if let Some(last_id) = options.last_id {
+                group.last_delivered = group.last_delivered.max(last_id);
+            }
+            if !claimed.is_empty() {
+                group.activate(consumer, now);
+            }
+            Ok(claimed)
+        })
+    }
+
+    /// XAUTOCLAIM: XCLAIM the pending entries idle for at least `min_idle` milliseconds,
+    /// scanning the PEL from start, until `count` entries are claimed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        consumer: &[u8],
+        min_idle: i64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> Result<AutoClaim, BackendError> {
+        let now = now_ms();
+        self.with_group(key, group, |group, stream| {
+            group.consumer(consumer, now);
+            let ids = group
+                .pending
+                .range(start..)
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+            let (mut claimed, mut deleted) = (vec![], vec![]);
+            // like redis, a scan looks at most at 10 times as many entries as it may claim
+            let mut attempts = count.saturating_mul(10);
+            let mut scanned = 0;
+            for id in &ids {
+                if claimed.len() == count || attempts == 0 {
+                    break;
+                }
+                attempts -= 1;
+                scanned += 1;
+
+                let entry = &group.pending[id];
+                if now - entry.delivered_at < min_idle {
+                    continue;
+                }
+                let deliveries = entry.deliveries + if just_id { 0 } else { 1 };
+                match stream.entries.get(id) {
+                    Some(fields) => {
+                        claimed.push((*id, fields.clone()));
+                        group.assign(*id, consumer, now, deliveries);
+                    }
+                    None => {
+                        group.unassign(*id);
+                        deleted.push(*id);
+                    }
+                }
+            }
+            if !claimed.is_empty() {
+                group.activate(consumer, now);
+            }
+            Ok(AutoClaim {
+                next: ids.get(scanned).copied().unwrap_or(StreamId::MIN),
+                claimed,
+                deleted,
+            })
+        })
+    }
+
+    pub fn xinfo_stream(&self, key: &[u8]) -> Result<StreamInfo, BackendError> {
+        self.expire_if_needed(key);
+        let entry = self.keyspace.get(key).ok_or(BackendError::NoSuchKey)?;
+        let stream = entry.as_stream()?;
+        Ok(StreamInfo {
+            length: stream.len(),
+            last_generated_id: stream.last_id,
+            entries_added: stream.entries_added,
+            groups: stream.groups.len(),
+            first_entry: stream.first_entry(),
+            last_entry: stream.last_entry(),
+        })
+    }
+
+    pub fn xinfo_groups(&self, key: &[u8]) -> Result<Vec<GroupInfo>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = self.keyspace.get(key).ok_or(BackendError::NoSuchKey)?;
+        let stream = entry.as_stream()?;
+        Ok(stream
+            .groups
+            .iter()
+            .map(|(name, group)| GroupInfo {
+                name: name.clone(),
+                consumers: group.consumers.len(),
+                pending: group.pending.len(),
+                last_delivered_id: group.last_delivered,
+                entries_read: group.entries_read,
+                lag: group
+                    .entries_read
+                    .map(|n| stream.entries_added.saturating_sub(n)),
+            })
+            .collect())
+    }
+
+    pub fn xinfo_consumers(
+        &self,
+        key: &[u8],
+        group: &[u8],
+    ) -> Result<Vec<ConsumerInfo>, BackendError> {
+        let now = now_ms();
+        self.read_group(key, group, |group| {
+            group
+                .consumers
+                .iter()
+                .map(|(name, consumer)| ConsumerInfo {
+                    name: name.clone(),
+                    pending: consumer.pending.len(),
+                    idle: (now - consumer.seen_at).max(0),
+                    inactive: consumer.active_at.map(|at| (now - at).max(0)),
+                })
+                .collect()
+        })
+    }
+
+    // run f on the stream at key with its lock held, NOGROUP if there is no stream
+    fn with_stream<T>(
+        &self,
+        key: &[u8],
+        f: impl FnOnce(&mut Stream) -> Result<T, BackendError>,
+    ) -> Result<T, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        match self.keyspace.get_mut(key) {
+            Some(mut v) => f(v.as_stream_mut()?),
+            None => Err(BackendError::XGroupNoKey),
+        }
+    }
+
+    // run f on a group and the entries of its stream with the lock of key held
+    fn with_group<T>(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        f: impl FnOnce(&mut ConsumerGroup, &Stream) -> Result<T, BackendError>,
+    ) -> Result<T, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let mut entry = self
+            .keyspace
+            .get_mut(key)
+            .ok_or_else(|| no_group(key, group))?;
+        let stream = entry.as_stream_mut()?;
+        // the group is taken out for f to see the stream, the lock keeps others from noticing
+        let mut consumer_group = stream
+            .groups
+            .remove(group)
+            .ok_or_else(|| no_group(key, group))?;
+        let result = f(&mut consumer_group, stream);
+        stream.groups.insert(group.to_vec(), consumer_group);
+        result
+    }
+
+    fn read_group<T>(
+        &self,
+        key: &[u8],
+        group: &[u8],
+        f: impl FnOnce(&ConsumerGroup) -> T,
+    ) -> Result<T, BackendError> {
+        self.expire_if_needed(key);
+        let entry = self.keyspace.get(key).ok_or_else(|| no_group(key, group))?;
+        let stream = entry.as_stream()?;
+        let group = stream
+            .groups
+            .get(group)
+            .ok_or_else(|| no_group(key, group))?;
+        Ok(f(group))
+    }
+}
+
+// the entries pending for the consumer after `after`, which count as delivered again
+fn read_history(
+    group: &mut ConsumerGroup,
+    stream: &Stream,
+    consumer: &[u8],
+    after: StreamId,
+    count: usize,
+    now: i64,
+) -> Vec<GroupEntry> {
+    let ids = match (group.consumers.get(consumer), after.next()) {
+        (Some(consumer), Some(start)) => consumer
+            .pending
+            .range(start..)
+            .take(count)
+            .copied()
+            .collect::<Vec<_>>(),
+        _ => return vec![],
+    };
+    ids.into_iter()
+        .map(|id| {
+            let fields = stream.entries.get(&id).cloned();
+            if let (Some(_), Some(entry)) = (&fields, group.pending.get_mut(&id)) {
+                entry.delivered_at = now;
+                entry.deliveries += 1;
+            }
+            (id, fields)
+        })
+        .collect()
+}
+
+fn no_group(key: &[u8], group: &[u8]) -> BackendError {
+    BackendError::NoGroup {
+        key: String::from_utf8_lossy(key).to_string(),
+        group: String::from_utf8_lossy(group).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XAddId;
+
+    fn add(backend: &Backend, ms: u64) {
+        let id = XAddId::Explicit(StreamId::new(ms, 0));
+        let fields = vec![(b"n".to_vec(), ms.to_string().into_bytes())];
+        backend
+            .xadd(b"s".to_vec(), id, fields, false, None)
+            .unwrap();
+    }
+
+    fn ids(entries: &[GroupEntry]) -> Vec<StreamId> {
+        entries.iter().map(|(id, _)| *id).collect()
+    }
+
+    #[test]
+    fn test_xreadgroup_and_xack() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.xgroup_create(b"s".to_vec(), b"g".to_vec(), None, false, None),
+            Err(BackendError::XGroupNoKey)
+        );
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            true,
+            None,
+        )?;
+        assert_eq!(
+            backend.xgroup_create(b"s".to_vec(), b"g".to_vec(), None, false, None),
+            Err(BackendError::BusyGroup)
+        );
+        for ms in 1..=3 {
+            add(&backend, ms);
+        }
+
+        let read = backend.xreadgroup(b"s", b"g", b"alice", None, 2, false)?;
+        assert_eq!(ids(&read), vec![StreamId::new(1, 0), StreamId::new(2, 0)]);
+        let read = backend.xreadgroup(b"s", b"g", b"bob", None, 10, false)?;
+        assert_eq!(ids(&read), vec![StreamId::new(3, 0)]);
+        assert!(backend
+            .xreadgroup(b"s", b"g", b"bob", None, 10, false)?
+            .is_empty());
+
+        // the history of a consumer is what it has pending, deleted entries without fields
+        backend.xdel(b"s", &[StreamId::new(2, 0)])?;
+        let history = backend.xreadgroup(b"s", b"g", b"alice", Some(StreamId::MIN), 10, false)?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1], (StreamId::new(2, 0), None));
+
+        let summary = backend.xpending_summary(b"s", b"g")?;
+        assert_eq!(summary.count, 3);
+        assert_eq!(
+            summary.range,
+            Some((StreamId::new(1, 0), StreamId::new(3, 0)))
+        );
+        assert_eq!(
+            summary.consumers,
+            vec![(b"alice".to_vec(), 2), (b"bob".to_vec(), 1)]
+        );
+        let pending = backend.xpending(b"s", b"g", StreamId::MIN, StreamId::MAX, 10, 0, None)?;
+        // read again from the history
+        assert_eq!(pending[0].deliveries, 2);
+
+        let acked = [
+            StreamId::new(1, 0),
+            StreamId::new(3, 0),
+            StreamId::new(9, 0),
+        ];
+        assert_eq!(backend.xack(b"s", b"g", &acked), Ok(2));
+        assert_eq!(backend.xack(b"s", b"missing", &acked), Ok(0));
+        assert_eq!(backend.xgroup_delconsumer(b"s", b"g", b"alice"), Ok(1));
+        assert_eq!(backend.xpending_summary(b"s", b"g")?.count, 0);
+
+        let groups = backend.xinfo_groups(b"s")?;
+        assert_eq!(groups[0].last_delivered_id, StreamId::new(3, 0));
+        assert_eq!(groups[0].lag, Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xclaim_and_xautoclaim() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for ms in 1..=4 {
+            add(&backend, ms);
+        }
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            false,
+            None,
+        )?;
+        backend.xreadgroup(b"s", b"g", b"dead", None, 10, false)?;
+
+        let ids = [StreamId::new(1, 0), StreamId::new(2, 0)];
+        // not idle for long enough
+        let claimed = backend.xclaim(b"s", b"g", b"alive", 60_000, &ids, Default::default())?;
+        assert!(claimed.is_empty());
+        let options = ClaimOptions {
+            delivered_at: Some(now_ms() - 120_000),
+            ..Default::default()
+        };
+        let claimed = backend.xclaim(b"s", b"g", b"alive", 0, &ids, options)?;
+        assert_eq!(claimed.len(), 2);
+        let pending =
+            backend.xpending(b"s", b"g", StreamId::MIN, StreamId::MAX, 10, 60_000, None)?;
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].consumer, b"alive");
+        assert_eq!(pending[0].deliveries, 2);
+
+        backend.xdel(b"s", &[StreamId::new(1, 0)])?;
+        let result = backend.xautoclaim(b"s", b"g", b"other", 60_000, StreamId::MIN, 1, true)?;
+        assert_eq!(result.claimed.len(), 1);
+        assert_eq!(result.claimed[0].0, StreamId::new(2, 0));
+        assert_eq!(result.deleted, vec![StreamId::new(1, 0)]);
+        assert_eq!(result.next, StreamId::new(3, 0));
+
+        let consumers = backend.xinfo_consumers(b"s", b"g")?;
+        let names = consumers.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![b"alive".to_vec(), b"dead".to_vec(), b"other".to_vec()]
+        );
+        assert_eq!(consumers[2].pending, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xclaim_options() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for ms in 1..=4 {
+            add(&backend, ms);
+        }
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            false,
+            None,
+        )?;
+        backend.xreadgroup(b"s", b"g", b"alice", None, 3, false)?;
+        let pending = |id: StreamId| {
+            backend
+                .xpending(b"s", b"g", id, id, 1, 0, None)
+                .map(|mut pending| pending.pop())
+        };
+
+        // IDLE and TIME set the delivery time, RETRYCOUNT the number of deliveries
+        let options = ClaimOptions {
+            delivered_at: Some(now_ms() - 60_000),
+            retry_count: Some(5),
+            ..Default::default()
+        };
+        let claimed = backend.xclaim(b"s", b"g", b"bob", 0, &[StreamId::new(1, 0)], options)?;
+        assert_eq!(claimed.len(), 1);
+        let info = pending(StreamId::new(1, 0))?.unwrap();
+        assert_eq!(
+            (info.consumer.as_slice(), info.deliveries),
+            (&b"bob"[..], 5)
+        );
+        assert!(info.idle >= 60_000);
+
+        // a JUSTID claim is no delivery, it resets the idle time only
+        let options = ClaimOptions {
+            just_id: true,
+            ..Default::default()
+        };
+        let claimed = backend.xclaim(b"s", b"g", b"bob", 0, &[StreamId::new(2, 0)], options)?;
+        assert_eq!(claimed.len(), 1);
+        let info = pending(StreamId::new(2, 0))?.unwrap();
+        assert_eq!(
+            (info.consumer.as_slice(), info.deliveries),
+            (&b"bob"[..], 1)
+        );
+        assert!(info.idle < 60_000);
+
+        // an entry no longer pending is only claimed with FORCE, if it is still in the stream
+        backend.xack(b"s", b"g", &[StreamId::new(3, 0)])?;
+        let ids = [StreamId::new(3, 0), StreamId::new(9, 0)];
+        assert!(backend
+            .xclaim(b"s", b"g", b"bob", 0, &ids, Default::default())?
+            .is_empty());
+        let options = ClaimOptions {
+            force: true,
+            last_id: Some(StreamId::new(4, 0)),
+            ..Default::default()
+        };
+        let claimed = backend.xclaim(b"s", b"g", b"bob", 0, &ids, options)?;
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].0, StreamId::new(3, 0));
+        assert_eq!(pending(StreamId::new(3, 0))?.unwrap().consumer, b"bob");
+        assert_eq!(pending(StreamId::new(9, 0))?, None);
+        // LASTID moves the group past the entries it has not delivered
+        assert_eq!(
+            backend.xinfo_groups(b"s")?[0].last_delivered_id,
+            StreamId::new(4, 0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_xautoclaim_reports_deleted_entries() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for ms in 1..=5 {
+            add(&backend, ms);
+        }
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            false,
+            None,
+        )?;
+        backend.xreadgroup(b"s", b"g", b"alice", None, 10, false)?;
+        backend.xdel(b"s", &[StreamId::new(1, 0), StreamId::new(2, 0)])?;
+
+        // deleted entries are dropped from the PEL without counting as claimed
+        let result = backend.xautoclaim(b"s", b"g", b"bob", 0, StreamId::MIN, 1, false)?;
+        assert_eq!(result.claimed.len(), 1);
+        assert_eq!(result.claimed[0].0, StreamId::new(3, 0));
+        assert_eq!(
+            result.deleted,
+            vec![StreamId::new(1, 0), StreamId::new(2, 0)]
+        );
+        assert_eq!(result.next, StreamId::new(4, 0));
+        assert_eq!(backend.xpending_summary(b"s", b"g")?.count, 3);
+
+        backend.xdel(b"s", &[StreamId::new(5, 0)])?;
+        let result = backend.xautoclaim(b"s", b"g", b"bob", 0, result.next, 10, true)?;
+        assert_eq!(result.claimed.len(), 1);
+        assert_eq!(result.claimed[0].0, StreamId::new(4, 0));
+        assert_eq!(result.deleted, vec![StreamId::new(5, 0)]);
+        assert_eq!(result.next, StreamId::MIN);
+
+        let summary = backend.xpending_summary(b"s", b"g")?;
+        assert_eq!(summary.consumers, vec![(b"bob".to_vec(), 2)]);
+        // JUSTID claimed entry 4 without counting a delivery
+        let pending = backend.xpending(b"s", b"g", StreamId::MIN, StreamId::MAX, 10, 0, None)?;
+        let deliveries = pending.iter().map(|p| p.deliveries).collect::<Vec<_>>();
+        assert_eq!(deliveries, vec![2, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xpending_idle_and_consumer() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for ms in 1..=4 {
+            add(&backend, ms);
+        }
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            false,
+            None,
+        )?;
+        backend.xreadgroup(b"s", b"g", b"alice", None, 2, false)?;
+        backend.xreadgroup(b"s", b"g", b"bob", None, 2, false)?;
+        // entries 1 of alice and 3 of bob were delivered a minute ago
+        let options = ClaimOptions {
+            delivered_at: Some(now_ms() - 60_000),
+            retry_count: Some(1),
+            ..Default::default()
+        };
+        backend.xclaim(b"s", b"g", b"alice", 0, &[StreamId::new(1, 0)], options)?;
+        backend.xclaim(b"s", b"g", b"bob", 0, &[StreamId::new(3, 0)], options)?;
+
+        let pending_ids = |min_idle, consumer: Option<&[u8]>| {
+            backend
+                .xpending(
+                    b"s",
+                    b"g",
+                    StreamId::MIN,
+                    StreamId::MAX,
+                    10,
+                    min_idle,
+                    consumer,
+                )
+                .map(|pending| pending.into_iter().map(|p| p.id.ms).collect::<Vec<_>>())
+        };
+        assert_eq!(pending_ids(0, None)?, vec![1, 2, 3, 4]);
+        assert_eq!(pending_ids(30_000, None)?, vec![1, 3]);
+        assert_eq!(pending_ids(0, Some(b"bob"))?, vec![3, 4]);
+        assert_eq!(pending_ids(30_000, Some(b"alice"))?, vec![1]);
+        assert_eq!(pending_ids(120_000, Some(b"alice"))?, vec![]);
+        assert_eq!(pending_ids(0, Some(b"nobody"))?, vec![]);
+
+        // COUNT applies to the entries left once filtered
+        let pending =
+            backend.xpending(b"s", b"g", StreamId::MIN, StreamId::MAX, 1, 0, Some(b"bob"))?;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, StreamId::new(3, 0));
+        assert!(pending[0].idle >= 60_000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_xgroup_setid_entries_read() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for ms in 1..=3 {
+            add(&backend, ms);
+        }
+        backend.xgroup_create(b"s".to_vec(), b"g".to_vec(), None, false, None)?;
+        let read_and_lag = |backend: &Backend| {
+            backend
+                .xinfo_groups(b"s")
+                .map(|groups| (groups[0].entries_read, groups[0].lag))
+        };
+        // $ has read the whole stream
+        assert_eq!(read_and_lag(&backend)?, (Some(3), Some(0)));
+
+        backend.xgroup_setid(b"s", b"g", Some(StreamId::MIN), None)?;
+        assert_eq!(read_and_lag(&backend)?, (Some(0), Some(3)));
+
+        // the count of an arbitrary ID is unknown unless ENTRIESREAD tells it
+        backend.xgroup_setid(b"s", b"g", Some(StreamId::new(1, 0)), None)?;
+        assert_eq!(read_and_lag(&backend)?, (None, None));
+        backend.xgroup_setid(b"s", b"g", Some(StreamId::new(1, 0)), Some(1))?;
+        assert_eq!(read_and_lag(&backend)?, (Some(1), Some(2)));
+
+        // reads count from there, up to the whole stream once its last entry is read
+        backend.xreadgroup(b"s", b"g", b"c", None, 1, false)?;
+        assert_eq!(read_and_lag(&backend)?, (Some(2), Some(1)));
+        backend.xreadgroup(b"s", b"g", b"c", None, 1, false)?;
+        assert_eq!(read_and_lag(&backend)?, (Some(3), Some(0)));
+
+        // ENTRIESREAD wins over $ too
+        backend.xgroup_setid(b"s", b"g", None, Some(1))?;
+        assert_eq!(read_and_lag(&backend)?, (Some(1), Some(2)));
+        assert_eq!(
+            backend.xgroup_setid(b"s", b"missing", None, None),
+            Err(no_group(b"s", b"missing"))
+        );
+        Ok(())
+    }
+}
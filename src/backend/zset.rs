@@ -0,0 +1,926 @@
+use super::scan::Entry;
+use super::{rank_tree::RankTree, Backend, BackendError, SetCondition, SetOperation, Value};
+use std::{cmp::Ordering, collections::HashMap};
+
+// like the other collections, a sorted set is deleted as soon as its last member is removed
+
+/// Whether ZADD may move an existing member, from the GT / LT options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreUpdate {
+    Always,
+    Greater,
+    Less,
+}
+
+/// The NX / XX, GT / LT and CH options of ZADD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZAddOptions {
+    pub condition: SetCondition,
+    pub update: ScoreUpdate,
+    // CH: count the members whose score changed along with the new ones
+    pub changed: bool,
+}
+
+impl Default for ZAddOptions {
+    fn default() -> Self {
+        Self {
+            condition: SetCondition::Always,
+            update: ScoreUpdate::Always,
+            changed: false,
+        }
+    }
+}
+
+/// A score bound of ZRANGE BYSCORE or ZCOUNT, `(` makes it exclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+/// A member bound of ZRANGE BYLEX: `-`, `+`, `[member` or `(member`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Min,
+    Max,
+    Inclusive(Vec<u8>),
+    Exclusive(Vec<u8>),
+}
+
+/// What the start and stop arguments of ZRANGE are.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// How ZUNIONSTORE and ZINTERSTORE combine the scores of a member found in several sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+/// The end of a sorted set members are popped from, the lowest or the highest scores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreEnd {
+    Min,
+    Max,
+}
+
+// members with their scores, in order
+type ScoredMembers = Vec<(Vec<u8>, f64)>;
+
+// the key a multi-key pop was served from, with the popped members
+type KeyMembers = (Vec<u8>, ScoredMembers);
+
+// members are ordered by score, then byte-wise by member for equal scores
+#[derive(Debug, Clone, PartialEq)]
+struct ScoredMember {
+    score: f64,
+    member: Vec<u8>,
+}
+
+// scores are never NaN, ZADD and ZINCRBY reject them
+impl Eq for ScoredMember {}
+
+impl PartialOrd for ScoredMember {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoredMember {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .partial_cmp(&other.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.member.cmp(&other.member))
+    }
+}
+
+/// The value of a sorted set: the score of every member, and the members in order.
+#[derive(Debug, Clone, Default)]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    tree: RankTree<ScoredMember>,
+}
+
+impl SortedSet {
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Add the member or move it to a new score, returns its previous score.
+    pub(crate) fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        // -0 and 0 are the same score
+        let score = score + 0.0;
+        let old = self.remove(&member);
+        self.scores.insert(member.clone(), score);
+        self.tree.insert(ScoredMember { score, member });
+        old
+    }
+
+    pub(crate) fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.tree.remove(&ScoredMember {
+            score,
+            member: member.to_vec(),
+        });
+        Some(score)
+    }
+
+    /// Rank of the member, 0 for the lowest score.
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.tree.rank(&ScoredMember {
+            score,
+            member: member.to_vec(),
+        }))
+    }
+
+    /// Members of rank start (included) to end (excluded) with their scores, in order.
+    pub fn range(&self, start: usize, end: usize) -> Vec<(Vec<u8>, f64)> {
+        self.tree
+            .range(start, end)
+            .into_iter()
+            .map(|v| (v.member.clone(), v.score))
+            .collect()
+    }
+
+    /// Members scored from min (included) to max (excluded) with their scores, in order.
+    pub(crate) fn range_by_score(&self, min: f64, max: f64) -> Vec<(Vec<u8>, f64)> {
+        let by = ZRangeBy::Score(ScoreBound::Inclusive(min), ScoreBound::Exclusive(max));
+        let (start, end) = self.span(&by, false);
+        self.range(start, end)
+    }
+
+    /// Remove and return up to `count` members, starting from the given end.
+    pub(crate) fn pop(&mut self, end: ScoreEnd, count: usize) -> Vec<(Vec<u8>, f64)> {
+        let len = self.len();
+        let count = count.min(len);
+        let members = match end {
+            ScoreEnd::Min => self.range(0, count),
+            ScoreEnd::Max => self.range(len - count, len).into_iter().rev().collect(),
+        };
+        for (member, _) in &members {
+            self.remove(member);
+        }
+        members
+    }
+
+    // ranks (start included, end excluded) of the members within the range, in ascending
+    // order. With `rev` rank indices count from the highest score.
+    fn span(&self, by: &ZRangeBy, rev: bool) -> (usize, usize) {
+        let len = self.len();
+        let (start, end) = match by {
+            ZRangeBy::Rank(start, stop) => match rank_range(len, *start, *stop) {
+                Some((start, stop)) if rev => (len - 1 - stop, len - start),
+                Some((start, stop)) => (start, stop + 1),
+                None => (0, 0),
+            },
+            ZRangeBy::Score(min, max) => (
+                self.tree.count_while(|v| below_min(min, v.score)),
+                self.tree.count_while(|v| !above_max(max, v.score)),
+            ),
+            ZRangeBy::Lex(min, max) => (
+                self.tree.count_while(|v| below_min_lex(min, &v.member)),
+                self.tree.count_while(|v| !above_max_lex(max, &v.member)),
+            ),
+        };
+        (start, end.max(start))
+    }
+}
+
+impl Backend {
+    /// Add or update members, returns how many were added, plus how many changed with CH.
+    pub fn zadd(
+        &self,
+        key: Vec<u8>,
+        pairs: Vec<(f64, Vec<u8>)>,
+        options: ZAddOptions,
+    ) -> Result<i64, BackendError> {
+        let (added, changed) = {
+            let _guard = self.locks.write([&key]);
+            self.expire_if_needed(&key);
+            let mut entry = match self.keyspace.entry(key.clone()) {
+                Entry::Occupied(entry) => entry.into_ref(),
+                // XX never adds members, the key is not created for nothing
+                Entry::Vacant(_) if options.condition == SetCondition::Exists => return Ok(0),
+                Entry::Vacant(entry) => entry.insert(Value::ZSet(SortedSet::default())),
+            };
+            let zset = entry.as_zset_mut()?;
+
+            let (mut added, mut changed) = (0, 0);
+            for (score, member) in pairs {
+                match update_score(zset, member, score, options) {
+                    Some(None) => added += 1,
+                    Some(Some(old)) if old != score => changed += 1,
+                    _ => {}
+                }
+            }
+            (added, changed)
+        };
+        // blocked clients pop with the lock of key, it must be released by now
+        self.wake(&key);
+        Ok(if options.changed {
+            added + changed
+        } else {
+            added
+        })
+    }
+
+    /// ZADD INCR and ZINCRBY: add the increment to the score of the member (0 if it is new),
+    /// returns the new score, None if the options prevented the update.
+    pub fn zadd_incr(
+        &self,
+        key: Vec<u8>,
+        increment: f64,
+        member: Vec<u8>,
+        options: ZAddOptions,
+    ) -> Result<Option<f64>, BackendError> {
+        let score = {
+            let _guard = self.locks.write([&key]);
+            self.expire_if_needed(&key);
+            let mut entry = match self.keyspace.entry(key.clone()) {
+                Entry::Occupied(entry) => entry.into_ref(),
+                Entry::Vacant(_) if options.condition == SetCondition::Exists => return Ok(None),
+                Entry::Vacant(entry) => entry.insert(Value::ZSet(SortedSet::default())),
+            };
+            let zset = entry.as_zset_mut()?;
+
+            let score = zset.score(&member).unwrap_or(0.0) + increment;
+            if score.is_nan() {
+                return Err(BackendError::ScoreNaN);
+            }
+            update_score(zset, member, score, options).map(|_| score)
+        };
+        self.wake(&key);
+        Ok(score)
+    }
+
+    pub fn zscore(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_zset()?.score(member)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn zmscore(
+        &self,
+        key: &[u8],
+        members: &[Vec<u8>],
+    ) -> Result<Vec<Option<f64>>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let zset = v.as_zset()?;
+                Ok(members.iter().map(|member| zset.score(member)).collect())
+            }
+            None => Ok(vec![None; members.len()]),
+        }
+    }
+
+    /// Remove the given members, returns how many of them existed.
+    pub fn zrem(&self, key: &[u8], members: &[Vec<u8>]) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let (removed, empty) = match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let zset = v.as_zset_mut()?;
+                let removed = members
+                    .iter()
+                    .filter(|member| zset.remove(member).is_some())
+                    .count();
+                (removed as i64, zset.is_empty())
+            }
+            None => return Ok(0),
+        };
+        // the ref is released by now, removing the key while holding it would deadlock
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(removed)
+    }
+
+    pub fn zcard(&self, key: &[u8]) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => Ok(v.as_zset()?.len() as i64),
+            None => Ok(0),
+        }
+    }
+
+    /// Number of members with a score between min and max, in O(log n).
+    pub fn zcount(
+        &self,
+        key: &[u8],
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> Result<i64, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key) {
+            Some(v) => {
+                let (start, end) = v.as_zset()?.span(&ZRangeBy::Score(min, max), false);
+                Ok((end - start) as i64)
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Rank of the member and its score, with `rev` the rank counts from the highest score.
+    pub fn zrank(
+        &self,
+        key: &[u8],
+        member: &[u8],
+        rev: bool,
+    ) -> Result<Option<(i64, f64)>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let zset = entry.as_zset()?;
+        Ok(zset
+            .rank(member)
+            .zip(zset.score(member))
+            .map(|(rank, score)| {
+                let rank = if rev { zset.len() - 1 - rank } else { rank };
+                (rank as i64, score)
+            }))
+    }
+
+    /// Members within the range with their scores, from the highest score with `rev`.
+    /// The first `offset` members are skipped and at most `count` returned (LIMIT).
+    pub fn zrange(
+        &self,
+        key: &[u8],
+        by: &ZRangeBy,
+        rev: bool,
+        offset: usize,
+        count: usize,
+    ) -> Result<Vec<(Vec<u8>, f64)>, BackendError> {
+        self.expire_if_needed(key);
+        let entry = match self.keyspace.get(key) {
+            Some(v) => v,
+            None => return Ok(vec![]),
+        };
+        let zset = entry.as_zset()?;
+
+        let (start, end) = zset.span(by, rev);
+        if rev {
+            let end = end.saturating_sub(offset).max(start);
+            let start = end.saturating_sub(count).max(start);
+            let mut members = zset.range(start, end);
+            members.reverse();
+            Ok(members)
+        } else {
+            let start = start.saturating_add(offset).min(end);
+            let end = start.saturating_add(count).min(end);
+            Ok(zset.range(start, end))
+        }
+    }
+
+    /// Combine the sorted sets at keys, plain sets count as members with a score of 1.
+    /// The scores of each source are multiplied by its weight (1 by default), the result
+    /// is ordered like a sorted set. ZDIFF ignores the weights and the aggregate.
+    pub fn zcombine(
+        &self,
+        op: SetOperation,
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<Vec<(Vec<u8>, f64)>, BackendError> {
+        let _guard = self.locks.read(keys);
+        let zset = self.zcombine_locked(op, keys, weights, aggregate)?;
+        Ok(zset.range(0, zset.len()))
+    }
+
+    /// Store the combination of the sets at keys in destination, returns its size.
+    /// An empty result deletes destination.
+    pub fn zcombine_store(
+        &self,
+        op: SetOperation,
+        destination: Vec<u8>,
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<i64, BackendError> {
+        let count = {
+            // the sources and destination stay locked, so no client sees a partial result
+            let _guard = self.locks.write(keys.iter().chain([&destination]));
+            let zset = self.zcombine_locked(op, keys, weights, aggregate)?;
+            self.store_zset(destination.clone(), zset)
+        };
+        self.wake(&destination);
+        Ok(count)
+    }
+
+    /// ZRANGESTORE: store the members ZRANGE would return in destination, returns their
+    /// number. An empty range deletes destination.
+    pub fn zrangestore(
+        &self,
+        destination: Vec<u8>,
+        source: &[u8],
+        by: &ZRangeBy,
+        rev: bool,
+        offset: usize,
+        count: usize,
+    ) -> Result<i64, BackendError> {
+        let count = {
+            let _guard = self.locks.write([source, destination.as_slice()]);
+            let mut zset = SortedSet::default();
+            for (member, score) in self.zrange(source, by, rev, offset, count)? {
+                zset.insert(member, score);
+            }
+            self.store_zset(destination.clone(), zset)
+        };
+        self.wake(&destination);
+        Ok(count)
+    }
+
+    /// Remove the members within the range (ranks count from the lowest score), returns
+    /// how many were removed.
+    pub fn zremrange(&self, key: &[u8], by: &ZRangeBy) -> Result<i64, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.expire_if_needed(key);
+        let (removed, empty) = match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let zset = v.as_zset_mut()?;
+                let (start, end) = zset.span(by, false);
+                for (member, _) in zset.range(start, end) {
+                    zset.remove(&member);
+                }
+                ((end - start) as i64, zset.is_empty())
+            }
+            None => return Ok(0),
+        };
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(removed)
+    }
+
+    /// Remove and return up to `count` members from the given end, None if the sorted set
+    /// does not exist.
+    pub fn zpop(
+        &self,
+        key: &[u8],
+        end: ScoreEnd,
+        count: usize,
+    ) -> Result<Option<ScoredMembers>, BackendError> {
+        let _guard = self.locks.write([key]);
+        self.zpop_locked(key, end, count)
+    }
+
+    /// Pop up to `count` members from the first non-empty sorted set among keys,
+    /// returns its key together with the members.
+    pub fn zmpop(
+        &self,
+        keys: &[Vec<u8>],
+        end: ScoreEnd,
+        count: usize,
+    ) -> Result<Option<KeyMembers>, BackendError> {
+        let _guard = self.locks.write(keys);
+        for key in keys {
+            if let Some(popped) = self.zpop_locked(key, end, count)? {
+                return Ok(Some((key.clone(), popped)));
+            }
+        }
+        Ok(None)
+    }
+
+    // pop for callers already holding the lock of key
+    fn zpop_locked(
+        &self,
+        key: &[u8],
+        end: ScoreEnd,
+        count: usize,
+    ) -> Result<Option<ScoredMembers>, BackendError> {
+        self.expire_if_needed(key);
+        let (popped, empty) = match self.keyspace.get_mut(key) {
+            Some(mut v) => {
+                let zset = v.as_zset_mut()?;
+                let popped = zset.pop(end, count);
+                (popped, zset.is_empty())
+            }
+            None => return Ok(None),
+        };
+        // the ref is released by now, removing the key while holding it would deadlock
+        if empty {
+            self.remove_key(key);
+        }
+        Ok(Some(popped))
+    }
+
+    // for callers holding the locks of keys
+    fn zcombine_locked(
+        &self,
+        op: SetOperation,
+        keys: &[Vec<u8>],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Result<SortedSet, BackendError> {
+        // every type is checked, even once a missing key makes the result empty
+        let mut sources = Vec::with_capacity(keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            let weight = match op {
+                SetOperation::Diff => 1.0,
+                _ => weights.get(i).copied().unwrap_or(1.0),
+            };
+            sources.push((self.scored_members(key)?, weight));
+        }
+
+        let mut members: HashMap<Vec<u8>, f64> = HashMap::new();
+        match op {
+            SetOperation::Union => {
+                for (source, weight) in sources {
+                    for (member, score) in source {
+                        let score = weighted(score, weight);
+                        members
+                            .entry(member)
+                            .and_modify(|v| *v = aggregate.apply(*v, score))
+                            .or_insert(score);
+                    }
+                }
+            }
+            SetOperation::Inter => {
+                // the smallest source bounds the result
+                sources.sort_by_key(|(source, _)| source.len());
+                let mut sources = sources.into_iter();
+                if let Some((first, weight)) = sources.next() {
+                    members = first
+                        .into_iter()
+                        .map(|(member, score)| (member, weighted(score, weight)))
+                        .collect();
+                }
+                for (source, weight) in sources {
+                    members.retain(|member, v| match source.get(member) {
+                        Some(score) => {
+                            *v = aggregate.apply(*v, weighted(*score, weight));
+                            true
+                        }
+                        None => false,
+                    });
+                }
+            }
+            SetOperation::Diff => {
+                let mut sources = sources.into_iter();
+                if let Some((first, _)) = sources.next() {
+                    members = first;
+                }
+                for (source, _) in sources {
+                    members.retain(|member, _| !source.contains_key(member));
+                }
+            }
+        }
+
+        let mut zset = SortedSet::default();
+        for (member, score) in members {
+            zset.insert(member, score);
+        }
+        Ok(zset)
+    }
+
+    fn scored_members(&self, key: &[u8]) -> Result<HashMap<Vec<u8>, f64>, BackendError> {
+        self.expire_if_needed(key);
+        match self.keyspace.get(key).as_deref() {
+            Some(Value::ZSet(zset)) => Ok(zset.scores.clone()),
+            Some(Value::Set(set)) => Ok(set.iter().map(|v| (v.key().clone(), 1.0)).collect()),
+            Some(_) => Err(BackendError::WrongType),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    // replace destination, whose lock the caller holds, returns the size of the sorted set
+    pub(crate) fn store_zset(&self, destination: Vec<u8>, zset: SortedSet) -> i64 {
+        let count = zset.len() as i64;
+        self.remove_key(&destination);
+        if !zset.is_empty() {
+            self.keyspace.insert(destination, Value::ZSet(zset));
+        }
+        count
+    }
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            // inf + -inf is NaN, which is no score
+            Aggregate::Sum => zero_if_nan(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+// inf * 0 is NaN, which is no score
+fn weighted(score: f64, weight: f64) -> f64 {
+    zero_if_nan(score * weight)
+}
+
+fn zero_if_nan(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+// add or move the member according to the options, None if they prevented it,
+// otherwise the previous score
+fn update_score(
+    zset: &mut SortedSet,
+    member: Vec<u8>,
+    score: f64,
+    options: ZAddOptions,
+) -> Option<Option<f64>> {
+    let old = zset.score(&member);
+    match (old, options.condition) {
+        (Some(_), SetCondition::NotExists) | (None, SetCondition::Exists) => return None,
+        _ => {}
+    }
+    if let Some(old) = old {
+        match options.update {
+            ScoreUpdate::Greater if score <= old => return None,
+            ScoreUpdate::Less if score >= old => return None,
+            _ => {}
+        }
+    }
+    zset.insert(member, score);
+    Some(old)
+}
+
+// resolve an inclusive start / stop rank range, negative ranks count from the end
+fn rank_range(len: usize, start: i64, stop: i64) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        return None;
+    }
+    Some((start as usize, stop as usize))
+}
+
+fn below_min(min: &ScoreBound, score: f64) -> bool {
+    match min {
+        ScoreBound::Inclusive(min) => score < *min,
+        ScoreBound::Exclusive(min) => score <= *min,
+    }
+}
+
+fn above_max(max: &ScoreBound, score: f64) -> bool {
+    match max {
+        ScoreBound::Inclusive(max) => score > *max,
+        ScoreBound::Exclusive(max) => score >= *max,
+    }
+}
+
+fn below_min_lex(min: &LexBound, member: &[u8]) -> bool {
+    match min {
+        LexBound::Min => false,
+        LexBound::Max => true,
+        LexBound::Inclusive(min) => member < min.as_slice(),
+        LexBound::Exclusive(min) => member <= min.as_slice(),
+    }
+}
+
+fn above_max_lex(max: &LexBound, member: &[u8]) -> bool {
+    match max {
+        LexBound::Min => true,
+        LexBound::Max => false,
+        LexBound::Inclusive(max) => member > max.as_slice(),
+        LexBound::Exclusive(max) => member >= max.as_slice(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BulkString;
+
+    fn pairs(pairs: &[(f64, &str)]) -> Vec<(f64, Vec<u8>)> {
+        pairs
+            .iter()
+            .map(|(score, member)| (*score, member.as_bytes().to_vec()))
+            .collect()
+    }
+
+    fn members(members: &[(&str, f64)]) -> Vec<(Vec<u8>, f64)> {
+        members
+            .iter()
+            .map(|(member, score)| (member.as_bytes().to_vec(), *score))
+            .collect()
+    }
+
+    #[test]
+    fn test_zadd_options() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let options = ZAddOptions::default();
+        let added = backend.zadd(b"z".to_vec(), pairs(&[(1.0, "a"), (2.0, "b")]), options)?;
+        assert_eq!(added, 2);
+
+        let nx = ZAddOptions {
+            condition: SetCondition::NotExists,
+            ..options
+        };
+        assert_eq!(
+            backend.zadd(b"z".to_vec(), pairs(&[(5.0, "a"), (3.0, "c")]), nx),
+            Ok(1)
+        );
+        assert_eq!(backend.zscore(b"z", b"a"), Ok(Some(1.0)));
+
+        let gt_ch = ZAddOptions {
+            update: ScoreUpdate::Greater,
+            changed: true,
+            ..options
+        };
+        let result = backend.zadd(b"z".to_vec(), pairs(&[(0.0, "a"), (4.0, "b")]), gt_ch);
+        assert_eq!(result, Ok(1));
+        assert_eq!(
+            backend.zmscore(b"z", &[b"a".to_vec(), b"b".to_vec()]),
+            Ok(vec![Some(1.0), Some(4.0)])
+        );
+
+        let xx = ZAddOptions {
+            condition: SetCondition::Exists,
+            ..options
+        };
+        assert_eq!(
+            backend.zadd_incr(b"missing".to_vec(), 1.0, b"a".to_vec(), xx),
+            Ok(None)
+        );
+        assert!(!backend.exists(b"missing"));
+        assert_eq!(
+            backend.zadd_incr(b"z".to_vec(), 1.5, b"a".to_vec(), xx),
+            Ok(Some(2.5))
+        );
+        assert_eq!(
+            backend.zadd_incr(b"z".to_vec(), f64::NEG_INFINITY, b"inf".to_vec(), options),
+            Ok(Some(f64::NEG_INFINITY))
+        );
+        assert_eq!(
+            backend.zadd_incr(b"z".to_vec(), f64::INFINITY, b"inf".to_vec(), options),
+            Err(BackendError::ScoreNaN)
+        );
+
+        backend.set(b"str".to_vec(), BulkString::from("value").into());
+        assert_eq!(backend.zcard(b"str"), Err(BackendError::WrongType));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrank_and_ranges() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let list = pairs(&[(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d"), (5.0, "e")]);
+        backend.zadd(b"z".to_vec(), list, ZAddOptions::default())?;
+
+        assert_eq!(backend.zrank(b"z", b"c", false), Ok(Some((2, 2.0))));
+        assert_eq!(backend.zrank(b"z", b"c", true), Ok(Some((2, 2.0))));
+        assert_eq!(backend.zrank(b"z", b"e", true), Ok(Some((0, 5.0))));
+        assert_eq!(backend.zrank(b"z", b"missing", false), Ok(None));
+
+        let by = ZRangeBy::Rank(0, -4);
+        let expected = members(&[("a", 1.0), ("b", 2.0)]);
+        assert_eq!(backend.zrange(b"z", &by, false, 0, usize::MAX)?, expected);
+        let expected = members(&[("e", 5.0), ("d", 3.0)]);
+        assert_eq!(backend.zrange(b"z", &by, true, 0, usize::MAX)?, expected);
+
+        let by = ZRangeBy::Score(ScoreBound::Exclusive(1.0), ScoreBound::Inclusive(3.0));
+        let expected = members(&[("b", 2.0), ("c", 2.0), ("d", 3.0)]);
+        assert_eq!(backend.zrange(b"z", &by, false, 0, usize::MAX)?, expected);
+        let expected = members(&[("c", 2.0), ("b", 2.0)]);
+        assert_eq!(backend.zrange(b"z", &by, true, 1, 2)?, expected);
+        assert_eq!(
+            backend.zcount(
+                b"z",
+                ScoreBound::Inclusive(f64::NEG_INFINITY),
+                ScoreBound::Exclusive(5.0)
+            ),
+            Ok(4)
+        );
+        let by = ZRangeBy::Score(ScoreBound::Inclusive(4.0), ScoreBound::Inclusive(2.0));
+        assert!(backend.zrange(b"z", &by, false, 0, usize::MAX)?.is_empty());
+
+        backend.zadd(
+            b"lex".to_vec(),
+            pairs(&[(0.0, "a"), (0.0, "b"), (0.0, "c")]),
+            ZAddOptions::default(),
+        )?;
+        let by = ZRangeBy::Lex(LexBound::Exclusive(b"a".to_vec()), LexBound::Max);
+        let expected = members(&[("b", 0.0), ("c", 0.0)]);
+        assert_eq!(backend.zrange(b"lex", &by, false, 0, usize::MAX)?, expected);
+        let by = ZRangeBy::Lex(LexBound::Min, LexBound::Inclusive(b"b".to_vec()));
+        let expected = members(&[("b", 0.0)]);
+        assert_eq!(backend.zrange(b"lex", &by, true, 0, 1)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrem_removes_empty_zset() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.zadd(
+            b"z".to_vec(),
+            pairs(&[(1.0, "a"), (2.0, "b")]),
+            ZAddOptions::default(),
+        )?;
+        // moving a member keeps a single entry for it
+        backend.zadd(b"z".to_vec(), pairs(&[(0.0, "b")]), ZAddOptions::default())?;
+        assert_eq!(backend.zcard(b"z"), Ok(2));
+        assert_eq!(backend.zrank(b"z", b"b", false), Ok(Some((0, 0.0))));
+
+        assert_eq!(
+            backend.zrem(b"z", &[b"a".to_vec(), b"missing".to_vec()]),
+            Ok(1)
+        );
+        assert_eq!(backend.zrem(b"z", &[b"b".to_vec()]), Ok(1));
+        assert!(!backend.exists(b"z"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_zcombine_and_pop() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let options = ZAddOptions::default();
+        backend.zadd(b"a".to_vec(), pairs(&[(1.0, "x"), (2.0, "y")]), options)?;
+        backend.zadd(
+            b"b".to_vec(),
+            pairs(&[(10.0, "y"), (f64::INFINITY, "z")]),
+            options,
+        )?;
+        // members of plain sets have a score of 1
+        backend.sadd(b"s".to_vec(), vec![b"y".to_vec()])?;
+
+        let keys = [b"a".to_vec(), b"b".to_vec(), b"s".to_vec()];
+        let union =
+            backend.zcombine(SetOperation::Union, &keys, &[2.0, 1.0, 0.0], Aggregate::Sum)?;
+        let expected = members(&[("x", 2.0), ("y", 14.0), ("z", f64::INFINITY)]);
+        assert_eq!(union, expected);
+        let inter = backend.zcombine(SetOperation::Inter, &keys, &[], Aggregate::Min)?;
+        assert_eq!(inter, members(&[("y", 1.0)]));
+        let diff = backend.zcombine(SetOperation::Diff, &keys[..2], &[], Aggregate::Sum)?;
+        assert_eq!(diff, members(&[("x", 1.0)]));
+
+        let stored = backend.zcombine_store(
+            SetOperation::Inter,
+            b"a".to_vec(),
+            &[b"a".to_vec(), b"missing".to_vec()],
+            &[],
+            Aggregate::Sum,
+        )?;
+        assert_eq!(stored, 0);
+        assert!(!backend.exists(b"a"));
+
+        assert_eq!(
+            backend.zpop(b"b", ScoreEnd::Max, 5)?,
+            Some(members(&[("z", f64::INFINITY), ("y", 10.0)]))
+        );
+        assert!(!backend.exists(b"b"));
+        assert_eq!(backend.zpop(b"b", ScoreEnd::Min, 1)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zremrange_and_zrangestore() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let list = pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c"), (4.0, "d")]);
+        backend.zadd(b"z".to_vec(), list, ZAddOptions::default())?;
+
+        let by = ZRangeBy::Score(ScoreBound::Exclusive(1.0), ScoreBound::Inclusive(4.0));
+        assert_eq!(
+            backend.zrangestore(b"dst".to_vec(), b"z", &by, true, 0, 2),
+            Ok(2)
+        );
+        assert_eq!(
+            backend.zrange(b"dst", &ZRangeBy::Rank(0, -1), false, 0, usize::MAX)?,
+            members(&[("c", 3.0), ("d", 4.0)])
+        );
+
+        assert_eq!(backend.zremrange(b"z", &ZRangeBy::Rank(-2, -1)), Ok(2));
+        let by = ZRangeBy::Lex(LexBound::Min, LexBound::Exclusive(b"b".to_vec()));
+        assert_eq!(backend.zremrange(b"z", &by), Ok(1));
+        assert_eq!(backend.zcard(b"z"), Ok(1));
+        assert_eq!(backend.zremrange(b"z", &ZRangeBy::Rank(0, 0)), Ok(1));
+        assert!(!backend.exists(b"z"));
+        Ok(())
+    }
+}
@@ -0,0 +1,558 @@
+use super::{
+    extract_args, extract_integer, syntax_error, validate_command, BitCount, BitField, BitOp,
+    BitPos, CommandExecutor, GetBit, SetBit,
+};
+use crate::{
+    cmd::CommandError, Backend, BitFieldOp, BitFieldOverflow, BitFieldType, BitOperation, BitUnit,
+    RespArray, RespFrame, RespNull,
+};
+
+// bit offsets are limited to 2^32 like redis, that is a 512MB string
+const MAX_BIT_OFFSET: i64 = 1 << 32;
+
+impl CommandExecutor for SetBit {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.setbit(self.key, self.offset, self.value) {
+            Ok(bit) => RespFrame::Integer(bit as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GetBit {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.getbit(&self.key, self.offset) {
+            Ok(bit) => RespFrame::Integer(bit as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bitcount(&self.key, self.range) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bitpos(&self.key, self.bit, self.start, self.end, self.unit) {
+            Ok(pos) => RespFrame::Integer(pos),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitOp {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bitop(self.op, self.dest, &self.keys) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BitField {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bitfield(self.key, &self.ops) {
+            Ok(results) => {
+                let ret = results
+                    .into_iter()
+                    .map(|v| match v {
+                        Some(n) => RespFrame::Integer(n),
+                        None => RespFrame::Null(RespNull),
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SetBit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["setbit"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(offset), Some(value)) => Ok(SetBit {
+                key: key.0.expect("Invalid key"),
+                offset: extract_bit_offset(offset)?,
+                value: match extract_integer(value) {
+                    Ok(0) => false,
+                    Ok(1) => true,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "bit is not an integer or out of range".to_string(),
+                        ))
+                    }
+                },
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, offset or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GetBit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["getbit"], Some(2))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(offset)) => Ok(GetBit {
+                key: key.0.expect("Invalid key"),
+                offset: extract_bit_offset(offset)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or offset".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BitCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitcount"], None)?;
+
+        // BITCOUNT key [start end [BYTE | BIT]]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let range = match (args.next(), args.next()) {
+            (Some(start), Some(end)) => Some((
+                extract_integer(start)?,
+                extract_integer(end)?,
+                extract_bit_unit(args.next())?,
+            )),
+            (None, None) => None,
+            _ => return Err(syntax_error()),
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(BitCount { key, range })
+    }
+}
+
+impl TryFrom<RespArray> for BitPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitpos"], None)?;
+
+        // BITPOS key bit [start [end [BYTE | BIT]]]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, bit) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(bit)) => (
+                key.0.expect("Invalid key"),
+                match extract_integer(bit)? {
+                    0 => false,
+                    1 => true,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "The bit argument must be 1 or 0.".to_string(),
+                        ))
+                    }
+                },
+            ),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or bit".to_string(),
+                ))
+            }
+        };
+        let start = args.next().map(extract_integer).transpose()?;
+        let end = args.next().map(extract_integer).transpose()?;
+        let unit = extract_bit_unit(args.next())?;
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(BitPos {
+            key,
+            bit,
+            start,
+            end,
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BitOp {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitop"], None)?;
+
+        // BITOP <AND | OR | XOR | NOT> destkey key [key ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let op = match args.next() {
+            Some(RespFrame::BulkString(op)) => match op.as_ref().to_ascii_lowercase().as_slice() {
+                b"and" => BitOperation::And,
+                b"or" => BitOperation::Or,
+                b"xor" => BitOperation::Xor,
+                b"not" => BitOperation::Not,
+                _ => return Err(syntax_error()),
+            },
+            _ => return Err(syntax_error()),
+        };
+
+        let mut keys = vec![];
+        for v in args {
+            match v {
+                RespFrame::BulkString(key) => {
+                    keys.push(key.0.expect("Invalid key"));
+                }
+                _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+            }
+        }
+        if keys.len() < 2 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'bitop' command".to_string(),
+            ));
+        }
+        let dest = keys.remove(0);
+        if op == BitOperation::Not && keys.len() != 1 {
+            return Err(CommandError::InvalidArgument(
+                "BITOP NOT must be called with a single source key.".to_string(),
+            ));
+        }
+
+        Ok(BitOp { op, dest, keys })
+    }
+}
+
+impl TryFrom<RespArray> for BitField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitfield"], None)?;
+
+        // BITFIELD key [GET encoding offset | [OVERFLOW <WRAP | SAT | FAIL>]
+        //   <SET encoding offset value | INCRBY encoding offset increment> ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        let mut ops = vec![];
+        let mut overflow = BitFieldOverflow::Wrap;
+        while let Some(sub) = args.next() {
+            let sub = match sub {
+                RespFrame::BulkString(sub) => sub.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            if sub == b"overflow" {
+                overflow = match args.next() {
+                    Some(RespFrame::BulkString(v)) => {
+                        match v.as_ref().to_ascii_lowercase().as_slice() {
+                            b"wrap" => BitFieldOverflow::Wrap,
+                            b"sat" => BitFieldOverflow::Sat,
+                            b"fail" => BitFieldOverflow::Fail,
+                            _ => {
+                                return Err(CommandError::InvalidArgument(
+                                    "Invalid OVERFLOW type specified".to_string(),
+                                ))
+                            }
+                        }
+                    }
+                    _ => return Err(syntax_error()),
+                };
+                continue;
+            }
+
+            let (ty, offset) = match (args.next(), args.next()) {
+                (Some(ty), Some(offset)) => {
+                    let ty = extract_bitfield_type(ty)?;
+                    (ty, extract_bitfield_offset(offset, ty)?)
+                }
+                _ => return Err(syntax_error()),
+            };
+            let op = match sub.as_slice() {
+                b"get" => BitFieldOp::Get { ty, offset },
+                b"set" => BitFieldOp::Set {
+                    ty,
+                    offset,
+                    value: extract_integer(args.next().ok_or_else(syntax_error)?)?,
+                    overflow,
+                },
+                b"incrby" => BitFieldOp::IncrBy {
+                    ty,
+                    offset,
+                    increment: extract_integer(args.next().ok_or_else(syntax_error)?)?,
+                    overflow,
+                },
+                _ => return Err(syntax_error()),
+            };
+            ops.push(op);
+        }
+
+        Ok(BitField { key, ops })
+    }
+}
+
+fn bit_offset_error() -> CommandError {
+    CommandError::OutOfRange("bit offset is not an integer or out of range".to_string())
+}
+
+fn extract_bit_offset(frame: RespFrame) -> Result<usize, CommandError> {
+    match extract_integer(frame) {
+        Ok(offset) if (0..MAX_BIT_OFFSET).contains(&offset) => Ok(offset as usize),
+        _ => Err(bit_offset_error()),
+    }
+}
+
+fn extract_bit_unit(frame: Option<RespFrame>) -> Result<BitUnit, CommandError> {
+    match frame {
+        None => Ok(BitUnit::Byte),
+        Some(RespFrame::BulkString(unit)) => match unit.as_ref().to_ascii_lowercase().as_slice() {
+            b"byte" => Ok(BitUnit::Byte),
+            b"bit" => Ok(BitUnit::Bit),
+            _ => Err(syntax_error()),
+        },
+        Some(_) => Err(syntax_error()),
+    }
+}
+
+// i1 to i64 and u1 to u63, u64 is not supported since replies are signed integers
+fn extract_bitfield_type(frame: RespFrame) -> Result<BitFieldType, CommandError> {
+    let ty = match frame {
+        RespFrame::BulkString(ty) => ty.as_ref().to_ascii_lowercase(),
+        _ => vec![],
+    };
+    let bits = std::str::from_utf8(ty.get(1..).unwrap_or_default())
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok());
+    match (ty.first(), bits) {
+        (Some(b'i'), Some(bits)) if (1..=64).contains(&bits) => {
+            Ok(BitFieldType { signed: true, bits })
+        }
+        (Some(b'u'), Some(bits)) if (1..=63).contains(&bits) => Ok(BitFieldType {
+            signed: false,
+            bits,
+        }),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string(),
+        )),
+    }
+}
+
+// an offset prefixed with '#' is multiplied by the width of the type
+fn extract_bitfield_offset(frame: RespFrame, ty: BitFieldType) -> Result<usize, CommandError> {
+    let offset = match frame {
+        RespFrame::BulkString(s) if s.as_ref().starts_with(b"#") => {
+            let n = String::from_utf8_lossy(&s.as_ref()[1..])
+                .parse::<i64>()
+                .map_err(|_| bit_offset_error())?;
+            n.checked_mul(ty.bits as i64).ok_or_else(bit_offset_error)?
+        }
+        frame => extract_integer(frame).map_err(|_| bit_offset_error())?,
+    };
+    // the field ends past the offset, which may be as large as i64::MAX
+    match offset.checked_add(ty.bits as i64) {
+        Some(end) if offset >= 0 && end <= MAX_BIT_OFFSET => Ok(offset as usize),
+        _ => Err(bit_offset_error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_setbit_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$2\r\n10\r\n$1\r\n1\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: SetBit = frame.try_into()?;
+        assert_eq!(result.key, b"key");
+        assert_eq!(result.offset, 10);
+        assert!(result.value);
+
+        buf.extend_from_slice(b"*4\r\n$6\r\nsetbit\r\n$3\r\nkey\r\n$2\r\n10\r\n$1\r\n2\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<SetBit, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitcount_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nbitcount\r\n$3\r\nkey\r\n$1\r\n5\r\n$2\r\n30\r\n$3\r\nBIT\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BitCount = frame.try_into()?;
+        assert_eq!(result.range, Some((5, 30, BitUnit::Bit)));
+
+        buf.extend_from_slice(b"*3\r\n$8\r\nbitcount\r\n$3\r\nkey\r\n$1\r\n5\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<BitCount, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*11\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nGET\r\n$2\r\nu4\r\n$1\r\n0\r\n$8\r\nOVERFLOW\r\n$3\r\nSAT\r\n$6\r\nINCRBY\r\n$2\r\ni8\r\n$2\r\n#2\r\n$3\r\n100\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BitField = frame.try_into()?;
+        assert_eq!(result.key, b"key");
+        assert_eq!(
+            result.ops,
+            vec![
+                BitFieldOp::Get {
+                    ty: BitFieldType {
+                        signed: false,
+                        bits: 4
+                    },
+                    offset: 0
+                },
+                BitFieldOp::IncrBy {
+                    ty: BitFieldType {
+                        signed: true,
+                        bits: 8
+                    },
+                    offset: 16,
+                    increment: 100,
+                    overflow: BitFieldOverflow::Sat
+                },
+            ]
+        );
+
+        buf.extend_from_slice(
+            b"*5\r\n$8\r\nbitfield\r\n$3\r\nkey\r\n$3\r\nGET\r\n$3\r\nu64\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<BitField, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        // offsets whose field would end past the limit, even by overflowing an i64
+        for offset in [
+            "9223372036854775807",
+            "4294967289",
+            "#536870912",
+            "#1152921504606846976",
+        ] {
+            let frame = RespArray::new(vec![
+                BulkString::from("bitfield").into(),
+                BulkString::from("key").into(),
+                BulkString::from("SET").into(),
+                BulkString::from("i8").into(),
+                BulkString::from(offset).into(),
+                BulkString::from("1").into(),
+            ]);
+            let result: Result<BitField, CommandError> = frame.try_into();
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "bit offset is not an integer or out of range"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nbitop\r\n$3\r\nNOT\r\n$4\r\ndest\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<BitOp, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nbitop\r\n$3\r\nxor\r\n$4\r\ndest\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: BitOp = frame.try_into()?;
+        assert_eq!(result.op, BitOperation::Xor);
+        assert_eq!(result.dest, b"dest");
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitmap_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = SetBit {
+            key: b"visits".to_vec(),
+            offset: 3,
+            value: true,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = GetBit {
+            key: b"visits".to_vec(),
+            offset: 3,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = BitCount {
+            key: b"visits".to_vec(),
+            range: None,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = BitPos {
+            key: b"visits".to_vec(),
+            bit: true,
+            start: None,
+            end: None,
+            unit: BitUnit::Byte,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd = BitField {
+            key: b"visits".to_vec(),
+            ops: vec![BitFieldOp::IncrBy {
+                ty: BitFieldType {
+                    signed: false,
+                    bits: 4,
+                },
+                offset: 0,
+                increment: 15,
+                overflow: BitFieldOverflow::Fail,
+            }],
+        };
+        let expected = RespArray::new(vec![RespFrame::Null(RespNull)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        backend.set(b"hash".to_vec(), RespFrame::Integer(1));
+        let cmd = GetBit {
+            key: b"hash".to_vec(),
+            offset: 0,
+        };
+        assert_eq!(cmd.execute(&backend), crate::BackendError::WrongType.into());
+
+        assert_eq!(
+            backend.get(b"visits"),
+            Ok(Some(BulkString::from(b"\x10").into()))
+        );
+        Ok(())
+    }
+}
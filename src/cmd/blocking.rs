@@ -0,0 +1,522 @@
+use super::{
+    extract_args, extract_float, extract_num_keys, list::extract_list_end,
+    list::extract_mpop_count, syntax_error, validate_command, zset::extract_score_end,
+    zset::key_members_reply, BLMPop, BLMove, BLPop, BRPop, BZMPop, BZPopMax, BZPopMin, Command,
+    CommandError, CommandExecutor,
+};
+use crate::{Backend, BulkString, ListEnd, RespArray, RespFrame, RespNull, ScoreEnd, ServeFn};
+use std::time::Duration;
+
+/// What a blocking command waits for when none of its keys can serve it right away.
+///
+/// The connection awaits `wait` instead of executing the command, so only that connection
+/// is parked. Executed like any other command, e.g. without a connection to park, a blocking
+/// command does not wait and replies as if its timeout expired.
+pub struct Block {
+    keys: Vec<Vec<u8>>,
+    // None blocks forever
+    timeout: Option<Duration>,
+    serve: ServeFn,
+    // the reply once the timeout expires, a null array but for BLMOVE
+    expired: RespFrame,
+}
+
+impl Block {
+    pub(crate) fn new(keys: Vec<Vec<u8>>, timeout: Option<Duration>, serve: ServeFn) -> Self {
+        Self {
+            keys,
+            timeout,
+            serve,
+            expired: RespArray::new(None).into(),
+        }
+    }
+
+    pub async fn wait(self, backend: &Backend) -> RespFrame {
+        backend
+            .block_on(self.keys, self.timeout, self.serve)
+            .await
+            .unwrap_or(self.expired)
+    }
+
+    fn try_serve(self, backend: &Backend) -> RespFrame {
+        self.keys
+            .iter()
+            .find_map(|key| (self.serve)(backend, key))
+            .unwrap_or(self.expired)
+    }
+}
+
+impl Command {
+    /// Blocking commands as what they wait for, the other commands are given back as they are.
+    /// XREAD only blocks with BLOCK, and looks up the last IDs of its streams for `$`.
+    /// XREADGROUP only blocks with BLOCK when it reads new entries of all its streams.
+    // the error is the command itself, moved back once per request
+    #[allow(clippy::result_large_err)]
+    pub fn into_block(self, backend: &Backend) -> Result<Block, Command> {
+        match self {
+            Command::BLPop(cmd) => Ok(cmd.into()),
+            Command::BRPop(cmd) => Ok(cmd.into()),
+            Command::BLMove(cmd) => Ok(cmd.into()),
+            Command::BLMPop(cmd) => Ok(cmd.into()),
+            Command::BZPopMin(cmd) => Ok(cmd.into()),
+            Command::BZPopMax(cmd) => Ok(cmd.into()),
+            Command::BZMPop(cmd) => Ok(cmd.into()),
+            Command::XRead(cmd) if cmd.block => Ok(cmd.into_block(backend)),
+            Command::XReadGroup(cmd) if cmd.blocks() => Ok(cmd.into_block()),
+            cmd => Err(cmd),
+        }
+    }
+}
+
+impl From<BLPop> for Block {
+    fn from(cmd: BLPop) -> Self {
+        Block {
+            keys: cmd.keys,
+            timeout: cmd.timeout,
+            serve: pop_element(ListEnd::Left),
+            expired: RespArray::new(None).into(),
+        }
+    }
+}
+
+impl From<BRPop> for Block {
+    fn from(cmd: BRPop) -> Self {
+        Block {
+            keys: cmd.keys,
+            timeout: cmd.timeout,
+            serve: pop_element(ListEnd::Right),
+            expired: RespArray::new(None).into(),
+        }
+    }
+}
+
+impl From<BLMove> for Block {
+    fn from(cmd: BLMove) -> Self {
+        let (destination, from, to) = (cmd.destination, cmd.from, cmd.to);
+        Block {
+            keys: vec![cmd.source],
+            timeout: cmd.timeout,
+            serve: Box::new(
+                move |backend, key| match backend.lmove(key, &destination, from, to) {
+                    Ok(Some(element)) => Some(BulkString::from(element).into()),
+                    Ok(None) => None,
+                    Err(e) => Some(e.into()),
+                },
+            ),
+            // like redis, the element BLMOVE waits for is a bulk string
+            expired: RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl From<BLMPop> for Block {
+    fn from(cmd: BLMPop) -> Self {
+        let (end, count) = (cmd.end, cmd.count);
+        Block {
+            keys: cmd.keys,
+            timeout: cmd.timeout,
+            serve: Box::new(move |backend, key| match backend.pop(key, end, count) {
+                Ok(Some(elements)) => {
+                    let elements = elements
+                        .into_iter()
+                        .map(|element| BulkString::from(element).into())
+                        .collect::<Vec<RespFrame>>();
+                    Some(
+                        RespArray::new(vec![
+                            BulkString::from(key.to_vec()).into(),
+                            RespArray::new(elements).into(),
+                        ])
+                        .into(),
+                    )
+                }
+                Ok(None) => None,
+                Err(e) => Some(e.into()),
+            }),
+            expired: RespArray::new(None).into(),
+        }
+    }
+}
+
+impl From<BZPopMin> for Block {
+    fn from(cmd: BZPopMin) -> Self {
+        Block {
+            keys: cmd.keys,
+            timeout: cmd.timeout,
+            serve: pop_member(ScoreEnd::Min),
+            expired: RespArray::new(None).into(),
+        }
+    }
+}
+
+impl From<BZPopMax> for Block {
+    fn from(cmd: BZPopMax) -> Self {
+        Block {
+            keys: cmd.keys,
+            timeout: cmd.timeout,
+            serve: pop_member(ScoreEnd::Max),
+            expired: RespArray::new(None).into(),
+        }
+    }
+}
+
+impl From<BZMPop> for Block {
+    fn from(cmd: BZMPop) -> Self {
+        let (end, count) = (cmd.end, cmd.count);
+        Block {
+            keys: cmd.keys,
+            timeout: cmd.timeout,
+            serve: Box::new(move |backend, key| match backend.zpop(key, end, count) {
+                Ok(Some(members)) => Some(key_members_reply(key.to_vec(), members)),
+                Ok(None) => None,
+                Err(e) => Some(e.into()),
+            }),
+            expired: RespArray::new(None).into(),
+        }
+    }
+}
+
+// BLPOP and BRPOP reply with the key and the element
+fn pop_element(end: ListEnd) -> ServeFn {
+    Box::new(move |backend, key| match backend.pop(key, end, 1) {
+        Ok(Some(mut elements)) => elements.pop().map(|element| {
+            RespArray::new(vec![
+                BulkString::from(key.to_vec()).into(),
+                BulkString::from(element).into(),
+            ])
+            .into()
+        }),
+        Ok(None) => None,
+        Err(e) => Some(e.into()),
+    })
+}
+
+// BZPOPMIN and BZPOPMAX reply with the key, the member and its score
+fn pop_member(end: ScoreEnd) -> ServeFn {
+    Box::new(move |backend, key| match backend.zpop(key, end, 1) {
+        Ok(Some(mut members)) => members.pop().map(|(member, score)| {
+            RespArray::new(vec![
+                BulkString::from(key.to_vec()).into(),
+                BulkString::from(member).into(),
+                RespFrame::Double(score),
+            ])
+            .into()
+        }),
+        Ok(None) => None,
+        Err(e) => Some(e.into()),
+    })
+}
+
+impl CommandExecutor for BLPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl CommandExecutor for BRPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl CommandExecutor for BLMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl CommandExecutor for BLMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl CommandExecutor for BZPopMin {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl CommandExecutor for BZPopMax {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl CommandExecutor for BZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        Block::from(self).try_serve(backend)
+    }
+}
+
+impl TryFrom<RespArray> for BLPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = extract_keys_and_timeout(value, "blpop")?;
+        Ok(BLPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BRPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = extract_keys_and_timeout(value, "brpop")?;
+        Ok(BRPop { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blmove"], Some(5))?;
+
+        // BLMOVE source destination <LEFT | RIGHT> <LEFT | RIGHT> timeout
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+            args.next(),
+        ) {
+            (
+                Some(RespFrame::BulkString(source)),
+                Some(RespFrame::BulkString(destination)),
+                Some(from),
+                Some(to),
+                Some(timeout),
+            ) => Ok(BLMove {
+                source: source.0.expect("Invalid key"),
+                destination: destination.0.expect("Invalid key"),
+                from: extract_list_end(from)?,
+                to: extract_list_end(to)?,
+                timeout: extract_timeout(timeout)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blmpop"], None)?;
+
+        // BLMPOP timeout numkeys key [key ...] <LEFT | RIGHT> [COUNT count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = match args.next() {
+            Some(timeout) => extract_timeout(timeout)?,
+            None => return Err(syntax_error()),
+        };
+        let keys = extract_num_keys(&mut args)?;
+        let end = match args.next() {
+            Some(end) => extract_list_end(end)?,
+            None => return Err(syntax_error()),
+        };
+        let count = extract_mpop_count(&mut args)?;
+        Ok(BLMPop {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMin {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = extract_keys_and_timeout(value, "bzpopmin")?;
+        Ok(BZPopMin { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZPopMax {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (keys, timeout) = extract_keys_and_timeout(value, "bzpopmax")?;
+        Ok(BZPopMax { keys, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bzmpop"], None)?;
+
+        // BZMPOP timeout numkeys key [key ...] <MIN | MAX> [COUNT count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = match args.next() {
+            Some(timeout) => extract_timeout(timeout)?,
+            None => return Err(syntax_error()),
+        };
+        let keys = extract_num_keys(&mut args)?;
+        let end = match args.next() {
+            Some(end) => extract_score_end(end)?,
+            None => return Err(syntax_error()),
+        };
+        let count = extract_mpop_count(&mut args)?;
+        Ok(BZMPop {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+// seconds as a float, 0 blocks forever
+pub(crate) fn extract_timeout(frame: RespFrame) -> Result<Option<Duration>, CommandError> {
+    let timeout = extract_float(frame).map_err(|_| {
+        CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+    })?;
+    if timeout < 0.0 {
+        return Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        ));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(timeout)
+        .map(Some)
+        .map_err(|_| CommandError::InvalidArgument("timeout is out of range".to_string()))
+}
+
+// key [key ...] timeout
+fn extract_keys_and_timeout(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<Vec<u8>>, Option<Duration>), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?;
+    let timeout = match args.pop() {
+        Some(timeout) if !args.is_empty() => extract_timeout(timeout)?,
+        _ => {
+            return Err(CommandError::InvalidArgument(format!(
+                "{} command must have at least 2 arguments",
+                name
+            )))
+        }
+    };
+
+    let mut keys = vec![];
+    for v in args {
+        match v {
+            RespFrame::BulkString(key) => keys.push(key.0.expect("Invalid key")),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+    Ok((keys, timeout))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespDecode, RespEncode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_blpop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nblpop\r\n$1\r\na\r\n$1\r\nb\r\n$3\r\n1.5\r\n");
+        let result: BLPop = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(result.timeout, Some(Duration::from_millis(1500)));
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nbrpop\r\n$1\r\na\r\n$1\r\n0\r\n");
+        let result: BRPop = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.timeout, None);
+
+        buf.extend_from_slice(b"*3\r\n$5\r\nblpop\r\n$1\r\na\r\n$2\r\n-1\r\n");
+        let result: Result<BLPop, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_brpop_waits_for_push() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = Command::from(BRPop {
+            keys: vec![b"a".to_vec(), b"b".to_vec()],
+            timeout: None,
+        });
+        let block = match cmd.into_block(&backend) {
+            Ok(block) => block,
+            Err(cmd) => panic!("{:?} does not block", cmd),
+        };
+
+        let blocked = tokio::spawn({
+            let backend = backend.clone();
+            async move { block.wait(&backend).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        backend.push(b"b".to_vec(), ListEnd::Left, vec![b"x".to_vec()], false)?;
+
+        let expected = RespArray::new(vec![
+            BulkString::from("b").into(),
+            BulkString::from("x").into(),
+        ]);
+        assert_eq!(blocked.await?, expected.into());
+        assert!(!backend.exists(b"b"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocking_command_timeout() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = BLMove {
+            source: b"src".to_vec(),
+            destination: b"dst".to_vec(),
+            from: ListEnd::Left,
+            to: ListEnd::Right,
+            timeout: Some(Duration::from_millis(10)),
+        };
+        // like redis, a null bulk string for BLMOVE and a null array for the others
+        assert_eq!(
+            Block::from(cmd).wait(&backend).await,
+            RespFrame::Null(RespNull)
+        );
+        let cmd = BLPop {
+            keys: vec![b"src".to_vec()],
+            timeout: Some(Duration::from_millis(10)),
+        };
+        let expired = Block::from(cmd).wait(&backend).await;
+        assert_eq!(expired.into_resp2().encode(), b"*-1\r\n");
+
+        // without a connection to park, it does not wait
+        let cmd = BLPop {
+            keys: vec![b"src".to_vec()],
+            timeout: None,
+        };
+        assert_eq!(cmd.execute(&backend), RespArray::new(None).into());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bzpopmin_waits_for_zadd() -> Result<()> {
+        let backend = Backend::new();
+        let block = Block::from(BZPopMin {
+            keys: vec![b"z".to_vec()],
+            timeout: None,
+        });
+
+        let blocked = tokio::spawn({
+            let backend = backend.clone();
+            async move { block.wait(&backend).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let pairs = vec![(2.0, b"b".to_vec()), (1.0, b"a".to_vec())];
+        backend.zadd(b"z".to_vec(), pairs, Default::default())?;
+
+        let expected = RespArray::new(vec![
+            BulkString::from("z").into(),
+            BulkString::from("a").into(),
+            RespFrame::Double(1.0),
+        ]);
+        assert_eq!(blocked.await?, expected.into());
+        assert_eq!(backend.zcard(b"z")?, 1);
+        Ok(())
+    }
+}
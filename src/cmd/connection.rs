@@ -0,0 +1,258 @@
+use super::{
+    extract_args, extract_integer, pubsub::message_reply, syntax_error, validate_command,
+    CommandError, CommandExecutor, Hello, Ping,
+};
+use crate::{Backend, BulkString, RespArray, RespFrame, RespMap, SimpleError, Subscriber};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// RESP version spoken on a connection, clients start with RESP2 and switch with HELLO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Resp2,
+    Resp3,
+}
+
+/// State of one client connection.
+#[derive(Debug)]
+pub struct Session {
+    id: u64,
+    protocol: Protocol,
+    name: Option<Vec<u8>>,
+    // created by the first (P)SUBSCRIBE, its subscriptions end with the connection
+    subscriber: Option<Subscriber>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+            protocol: Protocol::Resp2,
+            name: None,
+            subscriber: None,
+        }
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// Commands reply with RESP3 frames, RESP2 clients get their RESP2 form.
+    pub fn reply(&self, frame: RespFrame) -> RespFrame {
+        match self.protocol {
+            Protocol::Resp2 => frame.into_resp2(),
+            Protocol::Resp3 => frame,
+        }
+    }
+
+    /// Number of channels and patterns the connection is subscribed to.
+    pub fn subscriptions(&self) -> usize {
+        self.subscriber.as_ref().map_or(0, Subscriber::count)
+    }
+
+    pub(crate) fn subscriber(&mut self, backend: &Backend) -> &mut Subscriber {
+        self.subscriber
+            .get_or_insert_with(|| Subscriber::new(backend))
+    }
+
+    /// The next message published to a channel or pattern of the connection, as it is sent to
+    /// the client. Never resolves while it is not subscribed to any, None once the client
+    /// fell too far behind and must be disconnected.
+    pub async fn message(&mut self) -> Option<RespFrame> {
+        let message = match self.subscriber.as_mut() {
+            Some(subscriber) => subscriber.recv().await?,
+            None => std::future::pending().await,
+        };
+        Some(self.reply(message_reply(message)))
+    }
+
+    /// A RESP2 connection subscribed to a channel or pattern only reads messages, it accepts
+    /// nothing but the subscribe family and PING. RESP3 tells replies and messages apart.
+    pub fn refuse_when_subscribed(&self, frame: &RespFrame) -> Option<RespFrame> {
+        if self.protocol == Protocol::Resp3 || self.subscriptions() == 0 {
+            return None;
+        }
+        let name = match frame {
+            RespFrame::Array(RespArray(Some(frames))) => match frames.first() {
+                Some(RespFrame::BulkString(name)) => name.as_ref().to_ascii_lowercase(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        match name.as_slice() {
+            b"subscribe" | b"unsubscribe" | b"psubscribe" | b"punsubscribe" | b"ping" => None,
+            _ => Some(
+                SimpleError::new(format!(
+                    "ERR Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context",
+                    String::from_utf8_lossy(&name)
+                ))
+                .into(),
+            ),
+        }
+    }
+}
+
+impl CommandExecutor for Ping {
+    fn execute(self, _: &Backend) -> RespFrame {
+        match self.message {
+            Some(message) => BulkString::from(message).into(),
+            None => crate::SimpleString::new("PONG").into(),
+        }
+    }
+
+    // a subscribed RESP2 connection only reads arrays, the reply is one like a message
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        if session.protocol == Protocol::Resp3 || session.subscriptions() == 0 {
+            return self.execute(backend);
+        }
+        RespArray::new(vec![
+            BulkString::from("pong").into(),
+            BulkString::from(self.message.unwrap_or_default()).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for Hello {
+    // without a connection at hand, answer as a new one would be answered
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, _: &Backend, session: &mut Session) -> RespFrame {
+        session.protocol = match self.protocol {
+            Some(2) => Protocol::Resp2,
+            Some(3) => Protocol::Resp3,
+            Some(_) => return SimpleError::new("NOPROTO unsupported protocol version").into(),
+            None => session.protocol,
+        };
+        if self.client_name.is_some() {
+            session.name = self.client_name;
+        }
+
+        let proto = match session.protocol {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        };
+        let mut map = RespMap::new();
+        map.insert(
+            "server".to_string(),
+            BulkString::from("simple-redis").into(),
+        );
+        map.insert(
+            "version".to_string(),
+            BulkString::from(env!("CARGO_PKG_VERSION")).into(),
+        );
+        map.insert("proto".to_string(), RespFrame::Integer(proto));
+        map.insert("id".to_string(), RespFrame::Integer(session.id as i64));
+        map.insert("mode".to_string(), BulkString::from("standalone").into());
+        map.insert("role".to_string(), BulkString::from("master").into());
+        map.insert("modules".to_string(), RespArray::new(vec![]).into());
+        map.into()
+    }
+}
+
+impl TryFrom<RespArray> for Hello {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hello"], None)?;
+
+        // HELLO [protover [AUTH username password] [SETNAME clientname]]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut hello = Hello {
+            protocol: args.next().map(extract_integer).transpose()?,
+            client_name: None,
+        };
+        while let Some(option) = args.next() {
+            let option = match option {
+                RespFrame::BulkString(opt) => opt.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match (option.as_slice(), args.next()) {
+                // there are no users, any credentials are accepted
+                (b"auth", Some(_)) if args.next().is_some() => {}
+                (b"setname", Some(RespFrame::BulkString(name))) => {
+                    hello.client_name = Some(name.0.unwrap_or_default());
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(hello)
+    }
+}
+
+impl TryFrom<RespArray> for Ping {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ping"], None)?;
+
+        // PING [message]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let message = match (args.next(), args.next()) {
+            (None, _) => None,
+            (Some(RespFrame::BulkString(message)), None) => Some(message.0.unwrap_or_default()),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "ping command must have at most 1 argument".to_string(),
+                ))
+            }
+        };
+        Ok(Ping { message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Command, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_hello_switches_protocol() -> Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::new();
+        assert_eq!(session.protocol(), Protocol::Resp2);
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nSETNAME\r\n$3\r\napp\r\n");
+        let cmd: Command = RespArray::decode(&mut buf)?.try_into()?;
+        match cmd.execute_with(&backend, &mut session) {
+            RespFrame::Map(map) => assert_eq!(map.get("proto"), Some(&RespFrame::Integer(3))),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+        assert_eq!(session.protocol(), Protocol::Resp3);
+        assert_eq!(session.name.as_deref(), Some(b"app".as_slice()));
+
+        // an unsupported version leaves the protocol as it was
+        let cmd = Hello {
+            protocol: Some(4),
+            client_name: None,
+        };
+        assert_eq!(
+            cmd.execute_with(&backend, &mut session),
+            SimpleError::new("NOPROTO unsupported protocol version").into()
+        );
+        assert_eq!(session.protocol(), Protocol::Resp3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_reply() {
+        let mut session = Session::new();
+        let frame: RespFrame = crate::RespSet::new(vec![RespFrame::Integer(1)]).into();
+        assert_eq!(
+            session.reply(frame.clone()),
+            RespArray::new(vec![RespFrame::Integer(1)]).into()
+        );
+        session.protocol = Protocol::Resp3;
+        assert_eq!(session.reply(frame.clone()), frame);
+    }
+}
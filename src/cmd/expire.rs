@@ -0,0 +1,231 @@
+use super::{
+    extract_key, extract_key_and_integer, invalid_expire_time, CommandExecutor, Expire, ExpireAt,
+    PExpire, PExpireAt, PTtl, Persist, Ttl,
+};
+use crate::{backend::now_ms, cmd::CommandError, Backend, RespArray, RespFrame};
+
+impl CommandExecutor for Expire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self
+            .seconds
+            .checked_mul(1000)
+            .and_then(|ms| ms.checked_add(now_ms()))
+        {
+            Some(at) => expire_at(backend, &self.key, at),
+            None => invalid_expire_time("expire"),
+        }
+    }
+}
+
+impl CommandExecutor for PExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.milliseconds.checked_add(now_ms()) {
+            Some(at) => expire_at(backend, &self.key, at),
+            None => invalid_expire_time("pexpire"),
+        }
+    }
+}
+
+impl CommandExecutor for ExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.timestamp.checked_mul(1000) {
+            Some(at) => expire_at(backend, &self.key, at),
+            None => invalid_expire_time("expireat"),
+        }
+    }
+}
+
+impl CommandExecutor for PExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        expire_at(backend, &self.key, self.timestamp)
+    }
+}
+
+impl CommandExecutor for Ttl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pttl(&self.key) {
+            ttl if ttl < 0 => RespFrame::Integer(ttl),
+            // round to the closest second like redis does
+            ttl => RespFrame::Integer((ttl + 500) / 1000),
+        }
+    }
+}
+
+impl CommandExecutor for PTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.pttl(&self.key))
+    }
+}
+
+impl CommandExecutor for Persist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.persist(&self.key) as i64)
+    }
+}
+
+fn expire_at(backend: &Backend, key: &[u8], at: i64) -> RespFrame {
+    RespFrame::Integer(backend.expire_at(key, at) as i64)
+}
+
+impl TryFrom<RespArray> for Expire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, seconds) = extract_key_and_integer(value, "expire")?;
+        Ok(Expire { key, seconds })
+    }
+}
+
+impl TryFrom<RespArray> for PExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, milliseconds) = extract_key_and_integer(value, "pexpire")?;
+        Ok(PExpire { key, milliseconds })
+    }
+}
+
+impl TryFrom<RespArray> for ExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp) = extract_key_and_integer(value, "expireat")?;
+        Ok(ExpireAt { key, timestamp })
+    }
+}
+
+impl TryFrom<RespArray> for PExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp) = extract_key_and_integer(value, "pexpireat")?;
+        Ok(PExpireAt { key, timestamp })
+    }
+}
+
+impl TryFrom<RespArray> for Ttl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Ttl {
+            key: extract_key(value, "ttl")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PTtl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(PTtl {
+            key: extract_key(value, "pttl")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Persist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Persist {
+            key: extract_key(value, "persist")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Get, BulkString, RespDecode, RespNull};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_expire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nexpire\r\n$5\r\nhello\r\n$2\r\n10\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Expire = frame.try_into()?;
+        assert_eq!(result.key, b"hello");
+        assert_eq!(result.seconds, 10);
+
+        buf.extend_from_slice(b"*3\r\n$6\r\nexpire\r\n$5\r\nhello\r\n$3\r\nabc\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Expire, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ttl_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$3\r\nttl\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Ttl = frame.try_into()?;
+        assert_eq!(result.key, b"hello");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_ttl_persist_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"hello".to_vec(), BulkString::from("world").into());
+
+        let cmd = Ttl {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-1));
+
+        let cmd = Expire {
+            key: b"hello".to_vec(),
+            seconds: 100,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = Ttl {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(100));
+
+        let cmd = Persist {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = PTtl {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-1));
+
+        let cmd = Expire {
+            key: b"missing".to_vec(),
+            seconds: 100,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expire_in_the_past_deletes_key() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"hello".to_vec(), BulkString::from("world").into());
+
+        let cmd = PExpireAt {
+            key: b"hello".to_vec(),
+            timestamp: now_ms() - 1,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = Get {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd = Ttl {
+            key: b"hello".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-2));
+
+        Ok(())
+    }
+}
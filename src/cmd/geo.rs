@@ -0,0 +1,519 @@
+use super::{
+    extract_args, extract_float, extract_integer, syntax_error, validate_command, CommandError,
+    CommandExecutor, GeoAdd, GeoDist, GeoHash, GeoPos, GeoSearch, GeoSearchStore,
+};
+use crate::{
+    valid_position, Backend, BulkString, GeoMatch, GeoOrigin, GeoQuery, GeoShape, GeoSort,
+    RespArray, RespFrame, RespNull, SetCondition, ZAddOptions,
+};
+
+impl CommandExecutor for GeoAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.geoadd(self.key, self.positions, self.options) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoDist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.geodist(&self.key, &self.member1, &self.member2) {
+            Ok(Some(dist)) => dist_reply(dist, self.unit),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.geopos(&self.key, &self.members) {
+            Ok(positions) => {
+                let frames = positions
+                    .into_iter()
+                    .map(|position| match position {
+                        Some((lon, lat)) => coord_reply(lon, lat),
+                        // RESP2 clients get a null array, like redis sends
+                        None => RespArray::new(None).into(),
+                    })
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoHash {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.geohash(&self.key, &self.members) {
+            Ok(hashes) => {
+                let frames = hashes
+                    .into_iter()
+                    .map(|hash| match hash {
+                        Some(hash) => BulkString::from(hash).into(),
+                        None => RespFrame::Null(RespNull),
+                    })
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoSearch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let found = match backend.geosearch(&self.key, &self.query) {
+            Ok(found) => found,
+            Err(e) => return e.into(),
+        };
+        let frames = found
+            .into_iter()
+            .map(|found| self.match_reply(found))
+            .collect::<Vec<_>>();
+        RespArray::new(frames).into()
+    }
+}
+
+impl GeoSearch {
+    // the member alone, or with its distance, hash and coordinates, in that order
+    fn match_reply(&self, found: GeoMatch) -> RespFrame {
+        if !(self.with_dist || self.with_hash || self.with_coord) {
+            return BulkString::from(found.member).into();
+        }
+        let mut frames = vec![BulkString::from(found.member).into()];
+        if self.with_dist {
+            frames.push(dist_reply(found.dist, self.unit));
+        }
+        if self.with_hash {
+            frames.push(RespFrame::Integer(found.hash as i64));
+        }
+        if self.with_coord {
+            frames.push(coord_reply(found.lon, found.lat));
+        }
+        RespArray::new(frames).into()
+    }
+}
+
+impl CommandExecutor for GeoSearchStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let dist_unit = self.store_dist.then_some(self.unit);
+        match backend.geosearchstore(self.destination, &self.key, &self.query, dist_unit) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// distances are sent as strings with 4 decimals, in the unit of the command
+fn dist_reply(meters: f64, unit: f64) -> RespFrame {
+    BulkString::from(format!("{:.4}", meters / unit)).into()
+}
+
+fn coord_reply(lon: f64, lat: f64) -> RespFrame {
+    RespArray::new(vec![RespFrame::Double(lon), RespFrame::Double(lat)]).into()
+}
+
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geoadd"], None)?;
+
+        // GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        let mut options = ZAddOptions::default();
+        let (mut nx, mut xx) = (false, false);
+        while let Some(RespFrame::BulkString(option)) = args.peek() {
+            match option.as_ref().to_ascii_lowercase().as_slice() {
+                b"nx" => (nx, options.condition) = (true, SetCondition::NotExists),
+                b"xx" => (xx, options.condition) = (true, SetCondition::Exists),
+                b"ch" => options.changed = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(3) {
+            return Err(syntax_error());
+        }
+        let mut positions = vec![];
+        let mut args = args.into_iter();
+        while let (Some(lon), Some(lat), Some(member)) = (args.next(), args.next(), args.next()) {
+            let (lon, lat) = extract_position(lon, lat)?;
+            let member = match member {
+                RespFrame::BulkString(member) => member.0.expect("Invalid member"),
+                _ => return Err(CommandError::InvalidArgument("Invalid member".to_string())),
+            };
+            positions.push((lon, lat, member));
+        }
+        Ok(GeoAdd {
+            key,
+            positions,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geodist"], None)?;
+
+        // GEODIST key member1 member2 [M | KM | FT | MI]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, member1, member2) = match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(member1)),
+                Some(RespFrame::BulkString(member2)),
+            ) => (
+                key.0.expect("Invalid key"),
+                member1.0.expect("Invalid member"),
+                member2.0.expect("Invalid member"),
+            ),
+            _ => return Err(syntax_error()),
+        };
+        let unit = match (args.next(), args.next()) {
+            (None, _) => 1.0,
+            (Some(unit), None) => extract_unit(unit)?,
+            _ => return Err(syntax_error()),
+        };
+        Ok(GeoDist {
+            key,
+            member1,
+            member2,
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // GEOPOS key [member [member ...]]
+        let (key, members) = extract_key_and_members(value, "geopos")?;
+        Ok(GeoPos { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoHash {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // GEOHASH key [member [member ...]]
+        let (key, members) = extract_key_and_members(value, "geohash")?;
+        Ok(GeoHash { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geosearch"], None)?;
+
+        // GEOSEARCH key <FROMMEMBER member | FROMLONLAT longitude latitude>
+        //           <BYRADIUS radius <M | KM | FT | MI> | BYBOX width height <M | KM | FT | MI>>
+        //           [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let search = extract_search(args, false)?;
+        Ok(GeoSearch {
+            key,
+            query: search.query,
+            unit: search.unit,
+            with_coord: search.with_coord,
+            with_dist: search.with_dist,
+            with_hash: search.with_hash,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geosearchstore"], None)?;
+
+        // GEOSEARCHSTORE destination source <FROMMEMBER ... | FROMLONLAT ...>
+        //                <BYRADIUS ... | BYBOX ...> [ASC | DESC] [COUNT count [ANY]] [STOREDIST]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (destination, key) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(destination)), Some(RespFrame::BulkString(key))) => (
+                destination.0.expect("Invalid key"),
+                key.0.expect("Invalid key"),
+            ),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let search = extract_search(args, true)?;
+        Ok(GeoSearchStore {
+            destination,
+            key,
+            query: search.query,
+            unit: search.unit,
+            store_dist: search.store_dist,
+        })
+    }
+}
+
+// the options of GEOSEARCH and GEOSEARCHSTORE, each only accepts its own reply options
+struct SearchArgs {
+    query: GeoQuery,
+    unit: f64,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+    store_dist: bool,
+}
+
+fn extract_search(
+    mut args: impl Iterator<Item = RespFrame>,
+    store: bool,
+) -> Result<SearchArgs, CommandError> {
+    let (mut origin, mut shape, mut unit) = (None, None, 1.0);
+    let (mut sort, mut count, mut any) = (GeoSort::Unsorted, None, false);
+    let (mut with_coord, mut with_dist, mut with_hash, mut store_dist) =
+        (false, false, false, false);
+
+    let mut next = || args.next().ok_or_else(syntax_error);
+    while let Ok(option) = next() {
+        let option = match option {
+            RespFrame::BulkString(option) => option.as_ref().to_ascii_lowercase(),
+            _ => return Err(syntax_error()),
+        };
+        match option.as_slice() {
+            b"frommember" if origin.is_none() => match next()? {
+                RespFrame::BulkString(member) => {
+                    origin = Some(GeoOrigin::Member(member.0.expect("Invalid member")))
+                }
+                _ => return Err(syntax_error()),
+            },
+            b"fromlonlat" if origin.is_none() => {
+                let (lon, lat) = extract_position(next()?, next()?)?;
+                origin = Some(GeoOrigin::LonLat(lon, lat));
+            }
+            b"frommember" | b"fromlonlat" => {
+                return Err(CommandError::InvalidArgument(
+                    "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH"
+                        .to_string(),
+                ))
+            }
+            b"byradius" if shape.is_none() => {
+                let radius = extract_float(next()?)?;
+                if radius < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "radius cannot be negative".to_string(),
+                    ));
+                }
+                unit = extract_unit(next()?)?;
+                shape = Some(GeoShape::Radius(radius * unit));
+            }
+            b"bybox" if shape.is_none() => {
+                let (width, height) = (extract_float(next()?)?, extract_float(next()?)?);
+                if width < 0.0 || height < 0.0 {
+                    return Err(CommandError::InvalidArgument(
+                        "height or width cannot be negative".to_string(),
+                    ));
+                }
+                unit = extract_unit(next()?)?;
+                shape = Some(GeoShape::Box {
+                    width: width * unit,
+                    height: height * unit,
+                });
+            }
+            b"byradius" | b"bybox" => {
+                return Err(CommandError::InvalidArgument(
+                    "exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH".to_string(),
+                ))
+            }
+            b"asc" => sort = GeoSort::Asc,
+            b"desc" => sort = GeoSort::Desc,
+            b"count" => {
+                count = match usize::try_from(extract_integer(next()?)?) {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be > 0".to_string(),
+                        ))
+                    }
+                };
+            }
+            b"any" => any = true,
+            b"withcoord" if !store => with_coord = true,
+            b"withdist" if !store => with_dist = true,
+            b"withhash" if !store => with_hash = true,
+            b"storedist" if store => store_dist = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let origin = origin.ok_or_else(|| {
+        CommandError::InvalidArgument(
+            "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH".to_string(),
+        )
+    })?;
+    let shape = shape.ok_or_else(|| {
+        CommandError::InvalidArgument(
+            "exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH".to_string(),
+        )
+    })?;
+    if any && count.is_none() {
+        return Err(CommandError::InvalidArgument(
+            "the ANY argument requires COUNT argument".to_string(),
+        ));
+    }
+    Ok(SearchArgs {
+        query: GeoQuery {
+            origin,
+            shape,
+            sort,
+            count,
+            any,
+        },
+        unit,
+        with_coord,
+        with_dist,
+        with_hash,
+        store_dist,
+    })
+}
+
+// GEOPOS and GEOHASH take no member at all too
+fn extract_key_and_members(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = match args.next() {
+        Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+        _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    };
+    let members = args
+        .map(|member| match member {
+            RespFrame::BulkString(member) => Ok(member.0.expect("Invalid member")),
+            _ => Err(CommandError::InvalidArgument("Invalid member".to_string())),
+        })
+        .collect::<Result<Vec<_>, CommandError>>()?;
+    Ok((key, members))
+}
+
+fn extract_position(lon: RespFrame, lat: RespFrame) -> Result<(f64, f64), CommandError> {
+    let (lon, lat) = (extract_float(lon)?, extract_float(lat)?);
+    if !valid_position(lon, lat) {
+        return Err(CommandError::InvalidArgument(format!(
+            "invalid longitude,latitude pair {:.6},{:.6}",
+            lon, lat
+        )));
+    }
+    Ok((lon, lat))
+}
+
+// meters per unit
+fn extract_unit(frame: RespFrame) -> Result<f64, CommandError> {
+    let unit = match &frame {
+        RespFrame::BulkString(unit) => unit.as_ref().to_ascii_lowercase(),
+        _ => vec![],
+    };
+    match unit.as_slice() {
+        b"m" => Ok(1.0),
+        b"km" => Ok(1000.0),
+        b"ft" => Ok(0.3048),
+        b"mi" => Ok(1609.34),
+        _ => Err(CommandError::InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_geosearch_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*13\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nFROMLONLAT\r\n$2\r\n15\r\n$2\r\n37\r\n$8\r\nBYRADIUS\r\n$3\r\n200\r\n$2\r\nkm\r\n$4\r\nDESC\r\n$5\r\nCOUNT\r\n$1\r\n1\r\n$3\r\nANY\r\n$8\r\nWITHDIST\r\n");
+        let result: GeoSearch = RespArray::decode(&mut buf)?.try_into()?;
+        let query = GeoQuery {
+            origin: GeoOrigin::LonLat(15.0, 37.0),
+            shape: GeoShape::Radius(200_000.0),
+            sort: GeoSort::Desc,
+            count: Some(1),
+            any: true,
+        };
+        assert_eq!(result.query, query);
+        assert_eq!(result.unit, 1000.0);
+        assert!(result.with_dist && !result.with_coord);
+
+        // STOREDIST is for GEOSEARCHSTORE only
+        buf.extend_from_slice(b"*9\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nFROMMEMBER\r\n$1\r\na\r\n$5\r\nBYBOX\r\n$1\r\n1\r\n$1\r\n1\r\n$1\r\nm\r\n$9\r\nSTOREDIST\r\n");
+        let result: Result<GeoSearch, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*5\r\n$9\r\ngeosearch\r\n$6\r\nSicily\r\n$10\r\nFROMMEMBER\r\n$1\r\na\r\n$3\r\nANY\r\n");
+        let result: Result<GeoSearch, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geoadd_rejects_invalid_positions() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\ngeoadd\r\n$6\r\nSicily\r\n$2\r\n13\r\n$2\r\n86\r\n$1\r\na\r\n",
+        );
+        let result: Result<GeoAdd, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geosearch_reply() -> Result<()> {
+        let backend = Backend::new();
+        let positions = vec![
+            (13.361389, 38.115556, b"Palermo".to_vec()),
+            (15.087269, 37.502669, b"Catania".to_vec()),
+        ];
+        backend.geoadd(b"Sicily".to_vec(), positions, ZAddOptions::default())?;
+        let cmd = GeoSearch {
+            key: b"Sicily".to_vec(),
+            query: GeoQuery {
+                origin: GeoOrigin::LonLat(15.0, 37.0),
+                shape: GeoShape::Radius(100_000.0),
+                sort: GeoSort::Unsorted,
+                count: None,
+                any: false,
+            },
+            unit: 1000.0,
+            with_coord: false,
+            with_dist: true,
+            with_hash: true,
+        };
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("Catania").into(),
+            BulkString::from("56.4413").into(),
+            RespFrame::Integer(3479447370796909),
+        ])
+        .into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+}
@@ -0,0 +1,561 @@
+use super::{
+    extract_args, extract_expire, extract_integer, extract_pairs, invalid_expire_time,
+    syntax_error, validate_command, CommandExecutor, ExpireOption, HExpire, HExpireAt, HExpireTime,
+    HGetEx, HPExpire, HPExpireAt, HPExpireTime, HPTtl, HPersist, HSetEx, HTtl,
+};
+use crate::{
+    backend::now_ms, cmd::CommandError, Backend, BackendError, BulkString, ExpireCondition,
+    KeyExpire, RespArray, RespFrame, SetCondition,
+};
+
+impl CommandExecutor for HExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self
+            .seconds
+            .checked_mul(1000)
+            .and_then(|ms| ms.checked_add(now_ms()))
+        {
+            Some(at) => integers(backend.hexpire_at(&self.key, at, self.condition, &self.fields)),
+            None => invalid_expire_time("hexpire"),
+        }
+    }
+}
+
+impl CommandExecutor for HPExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.milliseconds.checked_add(now_ms()) {
+            Some(at) => integers(backend.hexpire_at(&self.key, at, self.condition, &self.fields)),
+            None => invalid_expire_time("hpexpire"),
+        }
+    }
+}
+
+impl CommandExecutor for HExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self.timestamp.checked_mul(1000) {
+            Some(at) => integers(backend.hexpire_at(&self.key, at, self.condition, &self.fields)),
+            None => invalid_expire_time("hexpireat"),
+        }
+    }
+}
+
+impl CommandExecutor for HPExpireAt {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integers(backend.hexpire_at(&self.key, self.timestamp, self.condition, &self.fields))
+    }
+}
+
+// negative replies (no such field, no deadline) are passed through as they are
+impl CommandExecutor for HTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let now = now_ms();
+        integers(
+            backend
+                .hpexpire_time(&self.key, &self.fields)
+                .map(|deadlines| {
+                    deadlines
+                        .into_iter()
+                        // round to the closest second like TTL does
+                        .map(|at| {
+                            if at < 0 {
+                                at
+                            } else {
+                                ((at - now).max(0) + 500) / 1000
+                            }
+                        })
+                        .collect()
+                }),
+        )
+    }
+}
+
+impl CommandExecutor for HPTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let now = now_ms();
+        integers(
+            backend
+                .hpexpire_time(&self.key, &self.fields)
+                .map(|deadlines| {
+                    deadlines
+                        .into_iter()
+                        .map(|at| if at < 0 { at } else { (at - now).max(0) })
+                        .collect()
+                }),
+        )
+    }
+}
+
+impl CommandExecutor for HExpireTime {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integers(
+            backend
+                .hpexpire_time(&self.key, &self.fields)
+                .map(|deadlines| {
+                    deadlines
+                        .into_iter()
+                        .map(|at| if at < 0 { at } else { (at + 500) / 1000 })
+                        .collect()
+                }),
+        )
+    }
+}
+
+impl CommandExecutor for HPExpireTime {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integers(backend.hpexpire_time(&self.key, &self.fields))
+    }
+}
+
+impl CommandExecutor for HPersist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        integers(backend.hpersist(&self.key, &self.fields))
+    }
+}
+
+impl CommandExecutor for HGetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let expire = match self.expire.map(|v| v.to_key_expire()) {
+            Some(Some(expire)) => expire,
+            Some(None) => return invalid_expire_time("hgetex"),
+            None => KeyExpire::Keep,
+        };
+        match backend.hgetex(&self.key, &self.fields, expire) {
+            Ok(values) => {
+                let ret = values
+                    .into_iter()
+                    .map(|v| v.unwrap_or_else(|| BulkString::new(None).into()))
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HSetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // like SET, fields written without an expire option lose their deadline
+        let expire = match self.expire.map(|v| v.to_key_expire()) {
+            Some(Some(expire)) => expire,
+            Some(None) => return invalid_expire_time("hsetex"),
+            None => KeyExpire::Persist,
+        };
+        match backend.hsetex(self.key, self.pairs, self.condition, expire) {
+            Ok(set) => RespFrame::Integer(set as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// one integer per field
+fn integers(result: Result<Vec<i64>, BackendError>) -> RespFrame {
+    match result {
+        Ok(codes) => {
+            let ret = codes
+                .into_iter()
+                .map(RespFrame::Integer)
+                .collect::<Vec<RespFrame>>();
+            RespArray::new(ret).into()
+        }
+        Err(e) => e.into(),
+    }
+}
+
+impl TryFrom<RespArray> for HExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, seconds, condition, fields) = extract_hexpire(value, "hexpire")?;
+        Ok(HExpire {
+            key,
+            seconds,
+            condition,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, milliseconds, condition, fields) = extract_hexpire(value, "hpexpire")?;
+        Ok(HPExpire {
+            key,
+            milliseconds,
+            condition,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp, condition, fields) = extract_hexpire(value, "hexpireat")?;
+        Ok(HExpireAt {
+            key,
+            timestamp,
+            condition,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HPExpireAt {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, timestamp, condition, fields) = extract_hexpire(value, "hpexpireat")?;
+        Ok(HPExpireAt {
+            key,
+            timestamp,
+            condition,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HTtl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = extract_key_and_fields(value, "httl")?;
+        Ok(HTtl { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPTtl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = extract_key_and_fields(value, "hpttl")?;
+        Ok(HPTtl { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HExpireTime {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = extract_key_and_fields(value, "hexpiretime")?;
+        Ok(HExpireTime { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPExpireTime {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = extract_key_and_fields(value, "hpexpiretime")?;
+        Ok(HPExpireTime { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPersist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, fields) = extract_key_and_fields(value, "hpersist")?;
+        Ok(HPersist { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HGetEx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hgetex"], None)?;
+
+        // HGETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+        //   PXAT unix-time-milliseconds | PERSIST] FIELDS numfields field [field ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_hash_key(args.next())?;
+        let mut expire = None;
+        loop {
+            let option = match args.next() {
+                Some(RespFrame::BulkString(opt)) => opt.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                b"fields" => break,
+                _ if expire.is_some() => return Err(syntax_error()),
+                b"persist" => expire = Some(ExpireOption::Persist),
+                b"ex" | b"px" | b"exat" | b"pxat" => {
+                    expire = Some(extract_expire(&option, &mut args, "hgetex")?);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        let fields = extract_fields(args)?;
+
+        Ok(HGetEx {
+            key,
+            expire,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HSetEx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetex"], None)?;
+
+        // HSETEX key [FNX | FXX] [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+        //   PXAT unix-time-milliseconds | KEEPTTL] FIELDS numfields field value [field value ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_hash_key(args.next())?;
+        let mut condition = SetCondition::Always;
+        let mut expire = None;
+        loop {
+            let option = match args.next() {
+                Some(RespFrame::BulkString(opt)) => opt.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                b"fields" => break,
+                b"fnx" | b"fxx" => {
+                    if condition != SetCondition::Always {
+                        return Err(syntax_error());
+                    }
+                    condition = if option == b"fnx" {
+                        SetCondition::NotExists
+                    } else {
+                        SetCondition::Exists
+                    };
+                }
+                _ if expire.is_some() => return Err(syntax_error()),
+                b"keepttl" => expire = Some(ExpireOption::KeepTtl),
+                b"ex" | b"px" | b"exat" | b"pxat" => {
+                    expire = Some(extract_expire(&option, &mut args, "hsetex")?);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        let count = extract_num_fields(args.next())?;
+        let pairs = extract_pairs(args.collect(), "hsetex")?;
+        if pairs.len() != count {
+            return Err(num_fields_mismatch());
+        }
+
+        Ok(HSetEx {
+            key,
+            condition,
+            expire,
+            pairs,
+        })
+    }
+}
+
+type HExpireArgs = (Vec<u8>, i64, ExpireCondition, Vec<Vec<u8>>);
+
+// key time [NX | XX | GT | LT] FIELDS numfields field [field ...]
+fn extract_hexpire(value: RespArray, name: &'static str) -> Result<HExpireArgs, CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_hash_key(args.next())?;
+    let time = match args.next() {
+        Some(frame) => extract_integer(frame)?,
+        None => return Err(syntax_error()),
+    };
+
+    let mut option = match args.next() {
+        Some(RespFrame::BulkString(opt)) => opt.as_ref().to_ascii_lowercase(),
+        _ => return Err(syntax_error()),
+    };
+    let condition = match option.as_slice() {
+        b"nx" => ExpireCondition::NoDeadline,
+        b"xx" => ExpireCondition::HasDeadline,
+        b"gt" => ExpireCondition::Greater,
+        b"lt" => ExpireCondition::Less,
+        _ => ExpireCondition::Always,
+    };
+    if condition != ExpireCondition::Always {
+        option = match args.next() {
+            Some(RespFrame::BulkString(opt)) => opt.as_ref().to_ascii_lowercase(),
+            _ => return Err(syntax_error()),
+        };
+    }
+    if option != b"fields" {
+        return Err(syntax_error());
+    }
+
+    Ok((key, time, condition, extract_fields(args)?))
+}
+
+// key FIELDS numfields field [field ...]
+fn extract_key_and_fields(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_hash_key(args.next())?;
+    match args.next() {
+        Some(RespFrame::BulkString(opt)) if opt.as_ref().eq_ignore_ascii_case(b"fields") => {}
+        _ => return Err(syntax_error()),
+    }
+    Ok((key, extract_fields(args)?))
+}
+
+fn extract_hash_key(frame: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(key)) => Ok(key.0.expect("Invalid key")),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+// numfields field [field ...], what follows the FIELDS keyword
+fn extract_fields(mut args: impl Iterator<Item = RespFrame>) -> Result<Vec<Vec<u8>>, CommandError> {
+    let count = extract_num_fields(args.next())?;
+    let mut fields = vec![];
+    for v in args {
+        match v {
+            RespFrame::BulkString(field) => fields.push(field.0.expect("Invalid field")),
+            _ => return Err(CommandError::InvalidArgument("Invalid field".to_string())),
+        }
+    }
+    if fields.len() != count {
+        return Err(num_fields_mismatch());
+    }
+    Ok(fields)
+}
+
+fn extract_num_fields(frame: Option<RespFrame>) -> Result<usize, CommandError> {
+    let count = match frame {
+        Some(frame) => extract_integer(frame)?,
+        None => return Err(syntax_error()),
+    };
+    if count <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "numfields should be greater than 0".to_string(),
+        ));
+    }
+    Ok(count as usize)
+}
+
+fn num_fields_mismatch() -> CommandError {
+    CommandError::InvalidArgument(
+        "The `numfields` parameter must match the number of arguments".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::HGet, RespDecode, RespNull};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn fields(names: &[&str]) -> Vec<Vec<u8>> {
+        names.iter().map(|name| name.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_hexpire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$7\r\nhexpire\r\n$4\r\nhash\r\n$2\r\n60\r\n$2\r\nNX\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n");
+        let result: HExpire = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.key, b"hash");
+        assert_eq!(result.seconds, 60);
+        assert_eq!(result.condition, ExpireCondition::NoDeadline);
+        assert_eq!(result.fields, fields(&["a"]));
+
+        buf.extend_from_slice(b"*6\r\n$7\r\nhexpire\r\n$4\r\nhash\r\n$2\r\n60\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n");
+        let result: HExpire = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.condition, ExpireCondition::Always);
+
+        // numfields must match the fields given
+        buf.extend_from_slice(b"*6\r\n$7\r\nhexpire\r\n$4\r\nhash\r\n$2\r\n60\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n");
+        let result: Result<HExpire, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hgetex_hsetex_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$6\r\nhgetex\r\n$4\r\nhash\r\n$7\r\nPERSIST\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n");
+        let result: HGetEx = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.expire, Some(ExpireOption::Persist));
+        assert_eq!(result.fields, fields(&["a"]));
+
+        buf.extend_from_slice(b"*9\r\n$6\r\nhsetex\r\n$4\r\nhash\r\n$3\r\nFNX\r\n$2\r\nPX\r\n$3\r\n100\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n$1\r\n1\r\n");
+        let result: HSetEx = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.condition, SetCondition::NotExists);
+        assert_eq!(result.expire, Some(ExpireOption::Ttl(100)));
+        assert_eq!(result.pairs.len(), 1);
+
+        // KEEPTTL is not an option of HGETEX
+        buf.extend_from_slice(b"*6\r\n$6\r\nhgetex\r\n$4\r\nhash\r\n$7\r\nKEEPTTL\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n");
+        let result: Result<HGetEx, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_field_ttl_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = HSetEx {
+            key: b"flags".to_vec(),
+            condition: SetCondition::Always,
+            expire: Some(ExpireOption::Ttl(10_000)),
+            pairs: vec![
+                (b"beta".to_vec(), BulkString::from("on").into()),
+                (b"dark".to_vec(), BulkString::from("off").into()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = HTtl {
+            key: b"flags".to_vec(),
+            fields: fields(&["beta", "missing"]),
+        };
+        let expected = RespArray::new(vec![RespFrame::Integer(10), RespFrame::Integer(-2)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = HPersist {
+            key: b"flags".to_vec(),
+            fields: fields(&["beta"]),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(1)]).into()
+        );
+
+        // a deadline already passed deletes the field
+        let cmd = HPExpire {
+            key: b"flags".to_vec(),
+            milliseconds: -1,
+            condition: ExpireCondition::Always,
+            fields: fields(&["beta"]),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![RespFrame::Integer(2)]).into()
+        );
+        let cmd = HGet {
+            key: b"flags".to_vec(),
+            field: b"beta".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd = HGetEx {
+            key: b"flags".to_vec(),
+            expire: Some(ExpireOption::Ttl(5_000)),
+            fields: fields(&["dark", "beta"]),
+        };
+        let expected = RespArray::new(vec![
+            BulkString::from("off").into(),
+            BulkString::new(None).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = HPTtl {
+            key: b"flags".to_vec(),
+            fields: fields(&["dark"]),
+        };
+        match cmd.execute(&backend) {
+            RespFrame::Array(array) => match array.as_ref().map(|v| v.as_slice()) {
+                Some([RespFrame::Integer(ttl)]) => assert!(*ttl > 4_000 && *ttl <= 5_000),
+                frame => panic!("unexpected reply {:?}", frame),
+            },
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+        Ok(())
+    }
+}
@@ -1,18 +1,27 @@
-use super::{extract_args, validate_command, CommandExecutor, HGet, HGetAll, HMGet, HSet};
-use crate::{cmd::CommandError, BulkString, RespArray, RespFrame};
+use super::{
+    extract_args, extract_cursor, extract_float, extract_integer, extract_key, extract_pairs,
+    extract_scan_options, scan_reply, syntax_error, validate_command, CommandExecutor, HDel,
+    HExists, HGet, HGetAll, HIncrBy, HIncrByFloat, HKeys, HLen, HMGet, HMSet, HRandField, HScan,
+    HSet, HSetNx, HStrlen, HVals, RESP_OK,
+};
+use crate::{cmd::CommandError, format_incr_float, BulkString, RespArray, RespFrame, RespNull};
 
 impl CommandExecutor for HGet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.hget(&self.key, &self.field) {
-            Some(value) => value,
-            None => RespFrame::Null(crate::RespNull),
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for HMGet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        let data = backend.hmget(&self.key, &self.fields);
+        let data = match backend.hmget(&self.key, &self.fields) {
+            Ok(data) => data,
+            Err(e) => return e.into(),
+        };
 
         let ret = data
             .into_iter()
@@ -28,10 +37,10 @@ impl CommandExecutor for HMGet {
 
 impl CommandExecutor for HGetAll {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        let hmap = backend.hmap.get(&self.key);
+        let hmap = backend.hgetall(&self.key);
 
         match hmap {
-            Some(hmap) => {
+            Ok(Some(hmap)) => {
                 let mut data = Vec::with_capacity(hmap.len());
                 for v in hmap.iter() {
                     let key = v.key().to_owned();
@@ -47,14 +56,165 @@ impl CommandExecutor for HGetAll {
 
                 RespArray::new(Some(ret)).into()
             }
-            None => RespArray(None).into(),
+            Ok(None) => RespArray(None).into(),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for HSet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        RespFrame::Integer(backend.hset(self.key, self.field, self.value))
+        match backend.hset(self.key, self.pairs) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// HMSET is the deprecated form of HSET, it only differs by its reply
+impl CommandExecutor for HMSet {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hset(self.key, self.pairs) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hscan(
+            &self.key,
+            self.cursor,
+            self.options.count,
+            self.options.pattern.as_deref(),
+        ) {
+            Ok(page) => {
+                let items = page
+                    .items
+                    .into_iter()
+                    .flat_map(|(k, v)| vec![BulkString::from(k).into(), v])
+                    .collect();
+                scan_reply(page.cursor, items)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HDel {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hdel(&self.key, &self.fields) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HExists {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hexists(&self.key, &self.field) {
+            Ok(found) => RespFrame::Integer(found as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HLen {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HKeys {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hkeys(&self.key) {
+            Ok(fields) => {
+                let ret = fields
+                    .into_iter()
+                    .map(|field| BulkString::from(field).into())
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HVals {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hvals(&self.key) {
+            Ok(values) => RespArray::new(values).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HSetNx {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hsetnx(self.key, self.field, self.value) {
+            Ok(set) => RespFrame::Integer(set as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HStrlen {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hstrlen(&self.key, &self.field) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HRandField {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let entries = match backend.hrandfield(&self.key, self.count.unwrap_or(1)) {
+            Ok(entries) => entries,
+            Err(e) => return e.into(),
+        };
+
+        // without a count the reply is a single field, nil if the hash does not exist
+        if self.count.is_none() {
+            return match entries.into_iter().next() {
+                Some((field, _)) => BulkString::from(field).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        }
+        let ret = entries
+            .into_iter()
+            .flat_map(|(field, value)| {
+                let field = BulkString::from(field).into();
+                if self.with_values {
+                    vec![field, value]
+                } else {
+                    vec![field]
+                }
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for HIncrBy {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hincr_by(self.key, self.field, self.increment) {
+            Ok(value) => RespFrame::Integer(value),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HIncrByFloat {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.hincr_by_float(self.key, self.field, self.increment) {
+            Ok(value) => BulkString::from(format_incr_float(value)).into(),
+            Err(e) => e.into(),
+        }
     }
 }
 
@@ -66,8 +226,8 @@ impl TryFrom<RespArray> for HGet {
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(HGet {
-                key: String::from_utf8(key.0.expect("Invalid key"))?,
-                field: String::from_utf8(field.0.expect("Invalid field"))?,
+                key: key.0.expect("Invalid key"),
+                field: field.0.expect("Invalid field"),
             }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or field".to_string(),
@@ -84,7 +244,7 @@ impl TryFrom<RespArray> for HMGet {
         let mut args = extract_args(value, 1)?.into_iter();
 
         let key = match args.next() {
-            Some(RespFrame::BulkString(key)) => String::from_utf8(key.0.expect("Invalid key"))?,
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
             _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
         };
 
@@ -92,7 +252,7 @@ impl TryFrom<RespArray> for HMGet {
         for v in args {
             match v {
                 RespFrame::BulkString(field) => {
-                    fields.push(String::from_utf8(field.0.expect("Invalid field"))?);
+                    fields.push(field.0.expect("Invalid field"));
                 }
                 _ => return Err(CommandError::InvalidArgument("Invalid field".to_string())),
             }
@@ -110,7 +270,7 @@ impl TryFrom<RespArray> for HGetAll {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(HGetAll {
-                key: String::from_utf8(key.0.expect("Invalid key"))?,
+                key: key.0.expect("Invalid key"),
                 sort: false,
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
@@ -121,14 +281,116 @@ impl TryFrom<RespArray> for HGetAll {
 impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hset"], Some(3))?;
+        let (key, pairs) = extract_key_and_pairs(value, "hset")?;
+        Ok(HSet { key, pairs })
+    }
+}
+
+impl TryFrom<RespArray> for HMSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, pairs) = extract_key_and_pairs(value, "hmset")?;
+        Ok(HMSet { key, pairs })
+    }
+}
+
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hscan"], None)?;
+
+        // HSCAN key cursor [MATCH pattern] [COUNT count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let cursor = extract_cursor(args.next())?;
+        let options = extract_scan_options(args, false)?;
+        Ok(HScan {
+            key,
+            cursor,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hdel"], None)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        let mut fields = vec![];
+        for v in args {
+            match v {
+                RespFrame::BulkString(field) => {
+                    fields.push(field.0.expect("Invalid field"));
+                }
+                _ => return Err(CommandError::InvalidArgument("Invalid field".to_string())),
+            }
+        }
+        if fields.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "hdel command must have at least 2 arguments".to_string(),
+            ));
+        }
+
+        Ok(HDel { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HExists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, field) = extract_key_and_field(value, "hexists")?;
+        Ok(HExists { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(HLen {
+            key: extract_key(value, "hlen")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HKeys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(HKeys {
+            key: extract_key(value, "hkeys")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HVals {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(HVals {
+            key: extract_key(value, "hvals")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetnx"], Some(3))?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSet {
-                    key: String::from_utf8(key.0.expect("Invalid key"))?,
-                    field: String::from_utf8(field.0.expect("Invalid field"))?,
+                Ok(HSetNx {
+                    key: key.0.expect("Invalid key"),
+                    field: field.0.expect("Invalid field"),
                     value,
                 })
             }
@@ -139,11 +401,133 @@ impl TryFrom<RespArray> for HSet {
     }
 }
 
+impl TryFrom<RespArray> for HStrlen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, field) = extract_key_and_field(value, "hstrlen")?;
+        Ok(HStrlen { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hrandfield"], None)?;
+
+        // HRANDFIELD key [count [WITHVALUES]]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let count = args.next().map(extract_integer).transpose()?;
+        let with_values = match args.next() {
+            Some(RespFrame::BulkString(opt))
+                if opt.as_ref().eq_ignore_ascii_case(b"withvalues") =>
+            {
+                true
+            }
+            Some(_) => return Err(syntax_error()),
+            None => false,
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hincrby"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(field)),
+                Some(increment),
+            ) => Ok(HIncrBy {
+                key: key.0.expect("Invalid key"),
+                field: field.0.expect("Invalid field"),
+                increment: extract_integer(increment)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, field or increment".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for HIncrByFloat {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hincrbyfloat"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(field)),
+                Some(increment),
+            ) => Ok(HIncrByFloat {
+                key: key.0.expect("Invalid key"),
+                field: field.0.expect("Invalid field"),
+                increment: extract_float(increment)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, field or increment".to_string(),
+            )),
+        }
+    }
+}
+
+type FieldValues = Vec<(Vec<u8>, RespFrame)>;
+
+// key field value [field value ...]
+fn extract_key_and_pairs(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, FieldValues), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = match args.next() {
+        Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+        _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    };
+    Ok((key, extract_pairs(args.collect(), name)?))
+}
+
+fn extract_key_and_field(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<u8>), CommandError> {
+    validate_command(&value, &[name], Some(2))?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => {
+            Ok((key.0.expect("Invalid key"), field.0.expect("Invalid field")))
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key or field".to_string(),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::RespDecode;
 
     use super::*;
+    use crate::SimpleError;
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -155,8 +539,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HGet = frame.try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.field, b"hello");
 
         Ok(())
     }
@@ -171,10 +555,10 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HMGet = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
         assert_eq!(result.fields.len(), 2);
-        assert_eq!(result.fields[0], "field1");
-        assert_eq!(result.fields[1], "field2");
+        assert_eq!(result.fields[0], b"field1");
+        assert_eq!(result.fields[1], b"field2");
 
         Ok(())
     }
@@ -187,7 +571,7 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HGetAll = frame.try_into()?;
-        assert_eq!(result.key, "map");
+        assert_eq!(result.key, b"map");
 
         Ok(())
     }
@@ -200,10 +584,66 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HSet = frame.try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
-        assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
+        assert_eq!(result.key, b"map");
+        assert_eq!(
+            result.pairs,
+            vec![(b"hello".to_vec(), RespFrame::BulkString(b"world".into()))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_variadic_hset_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$5\r\nhmset\r\n$3\r\nmap\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n",
+        );
+        let result: HMSet = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.pairs.len(), 2);
+        assert_eq!(result.pairs[1].0, b"b");
+
+        // a field without its value is rejected
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nhset\r\n$3\r\nmap\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n",
+        );
+        let result: Result<HSet, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
 
+    #[test]
+    fn test_variadic_hset_hmset_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        let cmd = HSet {
+            key: b"map".to_vec(),
+            pairs: vec![
+                (b"a".to_vec(), BulkString::from("1").into()),
+                (b"b".to_vec(), BulkString::from("2").into()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = HMSet {
+            key: b"map".to_vec(),
+            pairs: vec![
+                (b"b".to_vec(), BulkString::from("3").into()),
+                (b"c".to_vec(), BulkString::from("4").into()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd = HMGet {
+            key: b"map".to_vec(),
+            fields: vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+        };
+        let expected = RespArray::new(vec![
+            BulkString::from("1").into(),
+            BulkString::from("3").into(),
+            BulkString::from("4").into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
         Ok(())
     }
 
@@ -211,29 +651,27 @@ mod tests {
     fn test_hset_hget_hgetall_commands() -> Result<()> {
         let backend = crate::Backend::new();
         let cmd = HSet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
-            value: RespFrame::BulkString(b"world".into()),
+            key: b"map".to_vec(),
+            pairs: vec![(b"hello".to_vec(), RespFrame::BulkString(b"world".into()))],
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::Integer(1));
 
         let cmd = HSet {
-            key: "map".to_string(),
-            field: "hello1".to_string(),
-            value: RespFrame::BulkString(b"world1".into()),
+            key: b"map".to_vec(),
+            pairs: vec![(b"hello1".to_vec(), RespFrame::BulkString(b"world1".into()))],
         };
         cmd.execute(&backend);
 
         let cmd = HGet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: b"map".to_vec(),
+            field: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         let cmd = HGetAll {
-            key: "map".to_string(),
+            key: b"map".to_vec(),
             sort: true,
         };
         let result = cmd.execute(&backend);
@@ -252,23 +690,21 @@ mod tests {
     fn test_hset_hmget_commands() -> Result<()> {
         let backend = crate::Backend::new();
         let cmd = HSet {
-            key: "hello".to_string(),
-            field: "field1".to_string(),
-            value: RespFrame::BulkString(b"world1".into()),
+            key: b"hello".to_vec(),
+            pairs: vec![(b"field1".to_vec(), RespFrame::BulkString(b"world1".into()))],
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::Integer(1));
 
         let cmd = HSet {
-            key: "hello".to_string(),
-            field: "field2".to_string(),
-            value: RespFrame::BulkString(b"world2".into()),
+            key: b"hello".to_vec(),
+            pairs: vec![(b"field2".to_vec(), RespFrame::BulkString(b"world2".into()))],
         };
         cmd.execute(&backend);
 
         let cmd = HMGet {
-            key: "hello".to_string(),
-            fields: vec!["field1".to_string(), "field2".to_string()],
+            key: b"hello".to_vec(),
+            fields: vec![b"field1".to_vec(), b"field2".to_vec()],
         };
         let result = cmd.execute(&backend);
 
@@ -279,4 +715,172 @@ mod tests {
         assert_eq!(result, expected.into());
         Ok(())
     }
+
+    #[test]
+    fn test_binary_safe_key_and_field() -> Result<()> {
+        let backend = crate::Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$4\r\nhset\r\n$2\r\n\xff\x00\r\n$2\r\n\xfe\x01\r\n$5\r\nworld\r\n",
+        );
+        let cmd: HSet = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = HGetAll {
+            key: b"\xff\x00".to_vec(),
+            sort: false,
+        };
+        let expected = RespArray::new(Some(vec![
+            BulkString::new(b"\xfe\x01".to_vec()).into(),
+            BulkString::from("world").into(),
+        ]));
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hrandfield_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$10\r\nhrandfield\r\n$3\r\nmap\r\n$2\r\n-5\r\n$10\r\nWITHVALUES\r\n",
+        );
+        let result: HRandField = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.count, Some(-5));
+        assert!(result.with_values);
+
+        buf.extend_from_slice(b"*2\r\n$10\r\nhrandfield\r\n$3\r\nmap\r\n");
+        let result: HRandField = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.count, None);
+        assert!(!result.with_values);
+
+        buf.extend_from_slice(b"*4\r\n$10\r\nhrandfield\r\n$3\r\nmap\r\n$1\r\n1\r\n$3\r\nbad\r\n");
+        let result: Result<HRandField, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_field_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        for (field, value) in [("a", "1"), ("b", "22")] {
+            let cmd = HSetNx {
+                key: b"map".to_vec(),
+                field: field.as_bytes().to_vec(),
+                value: BulkString::from(value).into(),
+            };
+            assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        }
+        let cmd = HSetNx {
+            key: b"map".to_vec(),
+            field: b"a".to_vec(),
+            value: BulkString::from("other").into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = HStrlen {
+            key: b"map".to_vec(),
+            field: b"b".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = HExists {
+            key: b"map".to_vec(),
+            field: b"a".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = HRandField {
+            key: b"map".to_vec(),
+            count: Some(-3),
+            with_values: true,
+        };
+        match cmd.execute(&backend) {
+            RespFrame::Array(array) => assert_eq!(array.as_ref().map(|v| v.len()), Some(6)),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+
+        let cmd = HDel {
+            key: b"map".to_vec(),
+            fields: vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd = HLen {
+            key: b"map".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd = HKeys {
+            key: b"map".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespArray::new(vec![]).into());
+        let cmd = HRandField {
+            key: b"map".to_vec(),
+            count: None,
+            with_values: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincrby_commands() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$7\r\nhincrby\r\n$7\r\nuser:42\r\n$6\r\nlogins\r\n$1\r\n1\r\n",
+        );
+        let cmd: HIncrBy = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(cmd.increment, 1);
+
+        let backend = crate::Backend::new();
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = HIncrByFloat {
+            key: b"user:42".to_vec(),
+            field: b"logins".to_vec(),
+            increment: 0.25,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("1.25").into());
+
+        // stored and replied with the same text, no increment is rounded away
+        backend.hset(
+            b"user:42".to_vec(),
+            vec![(
+                b"score".to_vec(),
+                BulkString::from("100000000000000").into(),
+            )],
+        )?;
+        let increments = [
+            (0.01, "100000000000000.02"),
+            (0.01, "100000000000000.03"),
+            (-0.03, "100000000000000"),
+        ];
+        for (increment, expected) in increments {
+            let cmd = HIncrByFloat {
+                key: b"user:42".to_vec(),
+                field: b"score".to_vec(),
+                increment,
+            };
+            assert_eq!(cmd.execute(&backend), BulkString::from(expected).into());
+            assert_eq!(
+                backend.hget(b"user:42", b"score"),
+                Ok(Some(BulkString::from(expected).into()))
+            );
+        }
+
+        let cmd = HIncrBy {
+            key: b"user:42".to_vec(),
+            field: b"logins".to_vec(),
+            increment: 1,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR hash value is not an integer").into()
+        );
+
+        buf.extend_from_slice(
+            b"*4\r\n$7\r\nhincrby\r\n$7\r\nuser:42\r\n$6\r\nlogins\r\n$3\r\none\r\n",
+        );
+        let result: Result<HIncrBy, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
 }
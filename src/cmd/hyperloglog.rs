@@ -0,0 +1,98 @@
+use super::{extract_keys, CommandError, CommandExecutor, PfAdd, PfCount, PfMerge, RESP_OK};
+use crate::{Backend, RespArray, RespFrame};
+
+impl CommandExecutor for PfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfadd(self.key, &self.elements) {
+            Ok(changed) => RespFrame::Integer(changed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfcount(&self.keys) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfMerge {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfmerge(self.dest, &self.keys) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // PFADD key [element [element ...]], without elements the key is only created
+        let mut args = extract_keys(value, "pfadd")?;
+        let key = args.remove(0);
+        Ok(PfAdd {
+            key,
+            elements: args,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(PfCount {
+            keys: extract_keys(value, "pfcount")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // PFMERGE destkey [sourcekey [sourcekey ...]]
+        let mut keys = extract_keys(value, "pfmerge")?;
+        let dest = keys.remove(0);
+        Ok(PfMerge { dest, keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_pfadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*2\r\n$5\r\npfadd\r\n$3\r\nhll\r\n");
+        let result: PfAdd = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.key, b"hll");
+        assert!(result.elements.is_empty());
+
+        buf.extend_from_slice(b"*1\r\n$7\r\npfmerge\r\n");
+        let result: Result<PfMerge, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pfcount_of_a_plain_string() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"s".to_vec(), BulkString::from("hello").into());
+        let cmd = PfCount {
+            keys: vec![b"s".to_vec()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            crate::SimpleError::new("WRONGTYPE Key is not a valid HyperLogLog string value.")
+                .into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+use super::{
+    extract_args, extract_cursor, extract_integer, extract_key, extract_keys, extract_scan_options,
+    scan_reply, syntax_error, validate_command, CommandExecutor, CopyKey, Del, Exists, Keys,
+    RandomKey, Rename, RenameNx, Scan, Touch, Type, Unlink, RESP_OK,
+};
+use crate::{cmd::CommandError, Backend, BulkString, RespArray, RespFrame, SimpleString};
+
+impl CommandExecutor for Del {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.del(&self.keys))
+    }
+}
+
+// values are dropped right away, there is no background reclaim to hand them to
+impl CommandExecutor for Unlink {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.del(&self.keys))
+    }
+}
+
+impl CommandExecutor for Exists {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.exists_count(&self.keys))
+    }
+}
+
+impl CommandExecutor for Type {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        SimpleString::new(backend.key_type(&self.key)).into()
+    }
+}
+
+impl CommandExecutor for Rename {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.rename(&self.key, &self.newkey, false) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for RenameNx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.rename(&self.key, &self.newkey, true) {
+            Ok(renamed) => RespFrame::Integer(renamed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CopyKey {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.copy(&self.source, &self.destination, self.replace) {
+            Ok(copied) => RespFrame::Integer(copied as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// keys carry no access time, so touching them only reports which ones exist
+impl CommandExecutor for Touch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.exists_count(&self.keys))
+    }
+}
+
+impl CommandExecutor for RandomKey {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.random_key() {
+            Some(key) => BulkString::new(key).into(),
+            None => BulkString::new(None).into(),
+        }
+    }
+}
+
+impl CommandExecutor for Keys {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let keys = backend
+            .keys(&self.pattern)
+            .into_iter()
+            .map(|key| BulkString::from(key).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(keys).into()
+    }
+}
+
+impl CommandExecutor for Scan {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let page = backend.scan(
+            self.cursor,
+            self.options.count,
+            self.options.pattern.as_deref(),
+            self.options.key_type.as_deref(),
+        );
+        let keys = page
+            .items
+            .into_iter()
+            .map(|key| BulkString::from(key).into())
+            .collect();
+        scan_reply(page.cursor, keys)
+    }
+}
+
+impl TryFrom<RespArray> for Del {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Del {
+            keys: extract_keys(value, "del")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unlink {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Unlink {
+            keys: extract_keys(value, "unlink")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Exists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Exists {
+            keys: extract_keys(value, "exists")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Type {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Type {
+            key: extract_key(value, "type")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Rename {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, newkey) = extract_two_keys(value, "rename")?;
+        Ok(Rename { key, newkey })
+    }
+}
+
+impl TryFrom<RespArray> for RenameNx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, newkey) = extract_two_keys(value, "renamenx")?;
+        Ok(RenameNx { key, newkey })
+    }
+}
+
+impl TryFrom<RespArray> for CopyKey {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["copy"], None)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (source, destination) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(source)), Some(RespFrame::BulkString(destination))) => (
+                source.0.expect("Invalid key"),
+                destination.0.expect("Invalid key"),
+            ),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        // COPY source destination [DB destination-db] [REPLACE]
+        let mut replace = false;
+        while let Some(option) = args.next() {
+            let option = match option {
+                RespFrame::BulkString(opt) => opt.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                b"replace" => replace = true,
+                // there is a single database, so only db 0 is a valid destination
+                b"db" => match args.next().map(extract_integer).transpose()? {
+                    Some(0) => {}
+                    Some(_) => {
+                        return Err(CommandError::InvalidArgument(
+                            "DB index is out of range".to_string(),
+                        ))
+                    }
+                    None => return Err(syntax_error()),
+                },
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(CopyKey {
+            source,
+            destination,
+            replace,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Touch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Touch {
+            keys: extract_keys(value, "touch")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for RandomKey {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["randomkey"], Some(0))?;
+        Ok(RandomKey)
+    }
+}
+
+impl TryFrom<RespArray> for Keys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Keys {
+            pattern: extract_key(value, "keys")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Scan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["scan"], None)?;
+
+        // SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let cursor = extract_cursor(args.next())?;
+        let options = extract_scan_options(args, true)?;
+        Ok(Scan { cursor, options })
+    }
+}
+
+fn extract_two_keys(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<u8>), CommandError> {
+    validate_command(&value, &[name], Some(2))?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(newkey))) => {
+            Ok((key.0.expect("Invalid key"), newkey.0.expect("Invalid key")))
+        }
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Get, cmd::ScanOptions, RespDecode, RespNull};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_del_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$3\r\ndel\r\n$5\r\nhello\r\n$5\r\nworld\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Del = frame.try_into()?;
+        assert_eq!(result.keys, vec![b"hello".to_vec(), b"world".to_vec()]);
+
+        buf.extend_from_slice(b"*1\r\n$3\r\ndel\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Del, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\ncopy\r\n$1\r\na\r\n$1\r\nb\r\n$2\r\nDB\r\n$1\r\n0\r\n$7\r\nREPLACE\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: CopyKey = frame.try_into()?;
+        assert_eq!(result.source, b"a");
+        assert_eq!(result.destination, b"b");
+        assert!(result.replace);
+
+        buf.extend_from_slice(b"*5\r\n$4\r\ncopy\r\n$1\r\na\r\n$1\r\nb\r\n$2\r\ndb\r\n$1\r\n1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<CopyKey, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyspace_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"hello".to_vec(), BulkString::from("world").into());
+        backend.sadd(b"myset".to_vec(), vec![b"member".to_vec()])?;
+
+        let cmd = Exists {
+            keys: vec![b"hello".to_vec(), b"myset".to_vec(), b"missing".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = Type {
+            key: b"myset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), SimpleString::new("set").into());
+
+        let cmd = RenameNx {
+            key: b"hello".to_vec(),
+            newkey: b"myset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = Rename {
+            key: b"hello".to_vec(),
+            newkey: b"greeting".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd = Get {
+            key: b"greeting".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("world").into());
+
+        let cmd = Rename {
+            key: b"hello".to_vec(),
+            newkey: b"greeting".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::Error(crate::SimpleError::new("ERR no such key"))
+        );
+
+        let cmd = Del {
+            keys: vec![b"greeting".to_vec(), b"myset".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = RandomKey;
+        assert_eq!(cmd.execute(&backend), BulkString::new(None).into());
+
+        let cmd = Type {
+            key: b"myset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), SimpleString::new("none").into());
+
+        let cmd = Get {
+            key: b"myset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*8\r\n$4\r\nscan\r\n$2\r\n42\r\n$5\r\nMATCH\r\n$6\r\nuser:*\r\n$5\r\nCOUNT\r\n$3\r\n100\r\n$4\r\nTYPE\r\n$4\r\nHASH\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Scan = frame.try_into()?;
+        assert_eq!(result.cursor, 42);
+        assert_eq!(
+            result.options,
+            ScanOptions {
+                pattern: Some(b"user:*".to_vec()),
+                count: 100,
+                key_type: Some("hash".to_string()),
+            }
+        );
+
+        buf.extend_from_slice(b"*2\r\n$4\r\nscan\r\n$2\r\n-1\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Scan, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*4\r\n$4\r\nscan\r\n$1\r\n0\r\n$5\r\ncount\r\n$1\r\n0\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Scan, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keys_and_scan_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"user:1".to_vec(), BulkString::from("alice").into());
+        backend.sadd(b"tags".to_vec(), vec![b"a".to_vec()])?;
+
+        let cmd = Keys {
+            pattern: b"user:?".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![BulkString::from("user:1").into()]).into()
+        );
+
+        let cmd = Scan {
+            cursor: 0,
+            options: ScanOptions {
+                pattern: None,
+                count: 10,
+                key_type: Some("set".to_string()),
+            },
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                BulkString::from("0").into(),
+                RespArray::new(vec![BulkString::from("tags").into()]).into(),
+            ])
+            .into()
+        );
+
+        Ok(())
+    }
+}
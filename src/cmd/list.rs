@@ -0,0 +1,663 @@
+use super::{
+    extract_args, extract_integer, extract_key, extract_key_and_count, extract_key_and_integer,
+    extract_key_and_members, extract_num_keys, syntax_error, validate_command, CommandError,
+    CommandExecutor, LIndex, LInsert, LLen, LMPop, LMove, LPop, LPos, LPush, LPushX, LRange, LRem,
+    LSet, LTrim, RPop, RPopLPush, RPush, RPushX, RESP_OK,
+};
+use crate::{Backend, BulkString, InsertPosition, ListEnd, RespArray, RespFrame, RespNull};
+
+impl CommandExecutor for LPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, self.key, ListEnd::Left, self.elements, false)
+    }
+}
+
+impl CommandExecutor for RPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, self.key, ListEnd::Right, self.elements, false)
+    }
+}
+
+impl CommandExecutor for LPushX {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, self.key, ListEnd::Left, self.elements, true)
+    }
+}
+
+impl CommandExecutor for RPushX {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        push(backend, self.key, ListEnd::Right, self.elements, true)
+    }
+}
+
+impl CommandExecutor for LPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        pop(backend, &self.key, ListEnd::Left, self.count)
+    }
+}
+
+impl CommandExecutor for RPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        pop(backend, &self.key, ListEnd::Right, self.count)
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.llen(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lrange(&self.key, self.start, self.stop) {
+            Ok(elements) => RespArray::new(bulk_strings(elements)).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lindex(&self.key, self.index) {
+            Ok(Some(element)) => BulkString::from(element).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lset(&self.key, self.index, self.element) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.linsert(&self.key, self.position, &self.pivot, self.element) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lrem(&self.key, self.count, &self.element) {
+            Ok(removed) => RespFrame::Integer(removed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ltrim(&self.key, self.start, self.stop) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let count = self.count.unwrap_or(1);
+        let indices = match backend.lpos(&self.key, &self.element, self.rank, count, self.maxlen) {
+            Ok(indices) => indices,
+            Err(e) => return e.into(),
+        };
+        match self.count {
+            Some(_) => {
+                let ret = indices
+                    .into_iter()
+                    .map(RespFrame::Integer)
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            None => match indices.first() {
+                Some(index) => RespFrame::Integer(*index),
+                None => RespFrame::Null(RespNull),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        lmove(backend, &self.source, &self.destination, self.from, self.to)
+    }
+}
+
+impl CommandExecutor for RPopLPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        lmove(
+            backend,
+            &self.source,
+            &self.destination,
+            ListEnd::Right,
+            ListEnd::Left,
+        )
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lmpop(&self.keys, self.end, self.count) {
+            Ok(Some((key, elements))) => RespArray::new(vec![
+                BulkString::from(key).into(),
+                RespArray::new(bulk_strings(elements)).into(),
+            ])
+            .into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn push(
+    backend: &Backend,
+    key: Vec<u8>,
+    end: ListEnd,
+    elements: Vec<Vec<u8>>,
+    only_existing: bool,
+) -> RespFrame {
+    match backend.push(key, end, elements, only_existing) {
+        Ok(len) => RespFrame::Integer(len),
+        Err(e) => e.into(),
+    }
+}
+
+fn pop(backend: &Backend, key: &[u8], end: ListEnd, count: Option<usize>) -> RespFrame {
+    let elements = match backend.pop(key, end, count.unwrap_or(1)) {
+        Ok(Some(elements)) => elements,
+        Ok(None) => return RespFrame::Null(RespNull),
+        Err(e) => return e.into(),
+    };
+    match count {
+        Some(_) => RespArray::new(bulk_strings(elements)).into(),
+        None => match elements.into_iter().next() {
+            Some(element) => BulkString::from(element).into(),
+            None => RespFrame::Null(RespNull),
+        },
+    }
+}
+
+fn lmove(
+    backend: &Backend,
+    source: &[u8],
+    destination: &[u8],
+    from: ListEnd,
+    to: ListEnd,
+) -> RespFrame {
+    match backend.lmove(source, destination, from, to) {
+        Ok(Some(element)) => BulkString::from(element).into(),
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+fn bulk_strings(elements: Vec<Vec<u8>>) -> Vec<RespFrame> {
+    elements
+        .into_iter()
+        .map(|element| BulkString::from(element).into())
+        .collect()
+}
+
+impl TryFrom<RespArray> for LPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, elements) = extract_key_and_members(value, "lpush")?;
+        Ok(LPush { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for RPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, elements) = extract_key_and_members(value, "rpush")?;
+        Ok(RPush { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for LPushX {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, elements) = extract_key_and_members(value, "lpushx")?;
+        Ok(LPushX { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for RPushX {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, elements) = extract_key_and_members(value, "rpushx")?;
+        Ok(RPushX { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for LPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_and_count(value, "lpop")?;
+        Ok(LPop {
+            key,
+            count: positive_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for RPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_and_count(value, "rpop")?;
+        Ok(RPop {
+            key,
+            count: positive_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(LLen {
+            key: extract_key(value, "llen")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = extract_key_and_range(value, "lrange")?;
+        Ok(LRange { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, index) = extract_key_and_integer(value, "lindex")?;
+        Ok(LIndex { key, index })
+    }
+}
+
+impl TryFrom<RespArray> for LSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lset"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(index),
+                Some(RespFrame::BulkString(element)),
+            ) => Ok(LSet {
+                key: key.0.expect("Invalid key"),
+                index: extract_integer(index)?,
+                element: element.0.expect("Invalid element"),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or element".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["linsert"], Some(4))?;
+
+        // LINSERT key <BEFORE | AFTER> pivot element
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(RespFrame::BulkString(position)),
+                Some(RespFrame::BulkString(pivot)),
+                Some(RespFrame::BulkString(element)),
+            ) => {
+                let position = match position.as_ref().to_ascii_lowercase().as_slice() {
+                    b"before" => InsertPosition::Before,
+                    b"after" => InsertPosition::After,
+                    _ => return Err(syntax_error()),
+                };
+                Ok(LInsert {
+                    key: key.0.expect("Invalid key"),
+                    position,
+                    pivot: pivot.0.expect("Invalid pivot"),
+                    element: element.0.expect("Invalid element"),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or element".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrem"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(count),
+                Some(RespFrame::BulkString(element)),
+            ) => Ok(LRem {
+                key: key.0.expect("Invalid key"),
+                count: extract_integer(count)?,
+                element: element.0.expect("Invalid element"),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or element".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LTrim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, start, stop) = extract_key_and_range(value, "ltrim")?;
+        Ok(LTrim { key, start, stop })
+    }
+}
+
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lpos"], None)?;
+
+        // LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, element) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(element))) => (
+                key.0.expect("Invalid key"),
+                element.0.expect("Invalid element"),
+            ),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or element".to_string(),
+                ))
+            }
+        };
+
+        let (mut rank, mut count, mut maxlen) = (1, None, 0);
+        while let Some(option) = args.next() {
+            let option = match option {
+                RespFrame::BulkString(option) => option.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            let n = match args.next() {
+                Some(n) => extract_integer(n)?,
+                None => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                b"rank" => {
+                    if n == 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                        ));
+                    }
+                    rank = n;
+                }
+                b"count" => {
+                    if n < 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT can't be negative".to_string(),
+                        ));
+                    }
+                    count = Some(n as usize);
+                }
+                b"maxlen" => {
+                    if n < 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "MAXLEN can't be negative".to_string(),
+                        ));
+                    }
+                    maxlen = n as usize;
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(LPos {
+            key,
+            element,
+            rank,
+            count,
+            maxlen,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmove"], Some(4))?;
+
+        // LMOVE source destination <LEFT | RIGHT> <LEFT | RIGHT>
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(source)),
+                Some(RespFrame::BulkString(destination)),
+                Some(from),
+                Some(to),
+            ) => Ok(LMove {
+                source: source.0.expect("Invalid key"),
+                destination: destination.0.expect("Invalid key"),
+                from: extract_list_end(from)?,
+                to: extract_list_end(to)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for RPopLPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["rpoplpush"], Some(2))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(source)), Some(RespFrame::BulkString(destination))) => {
+                Ok(RPopLPush {
+                    source: source.0.expect("Invalid key"),
+                    destination: destination.0.expect("Invalid key"),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmpop"], None)?;
+
+        // LMPOP numkeys key [key ...] <LEFT | RIGHT> [COUNT count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let keys = extract_num_keys(&mut args)?;
+        let end = match args.next() {
+            Some(end) => extract_list_end(end)?,
+            None => return Err(syntax_error()),
+        };
+        let count = extract_mpop_count(&mut args)?;
+        Ok(LMPop { keys, end, count })
+    }
+}
+
+// LEFT or RIGHT
+pub(crate) fn extract_list_end(frame: RespFrame) -> Result<ListEnd, CommandError> {
+    match frame {
+        RespFrame::BulkString(end) => match end.as_ref().to_ascii_lowercase().as_slice() {
+            b"left" => Ok(ListEnd::Left),
+            b"right" => Ok(ListEnd::Right),
+            _ => Err(syntax_error()),
+        },
+        _ => Err(syntax_error()),
+    }
+}
+
+// the optional COUNT count closing LMPOP and its blocking variant, 1 by default
+pub(crate) fn extract_mpop_count(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<usize, CommandError> {
+    match (args.next(), args.next(), args.next()) {
+        (None, _, _) => Ok(1),
+        (Some(RespFrame::BulkString(option)), Some(count), None)
+            if option.as_ref().eq_ignore_ascii_case(b"count") =>
+        {
+            let count = extract_integer(count)?;
+            if count <= 0 {
+                return Err(CommandError::InvalidArgument(
+                    "count should be greater than 0".to_string(),
+                ));
+            }
+            Ok(count as usize)
+        }
+        _ => Err(syntax_error()),
+    }
+}
+
+// LPOP, RPOP, ZPOPMIN and ZPOPMAX take an optional count that must not be negative
+pub(crate) fn positive_count(count: Option<i64>) -> Result<Option<usize>, CommandError> {
+    match count {
+        Some(count) if count < 0 => Err(CommandError::InvalidArgument(
+            "value is out of range, must be positive".to_string(),
+        )),
+        count => Ok(count.map(|count| count as usize)),
+    }
+}
+
+// key start stop
+fn extract_key_and_range(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, i64, i64), CommandError> {
+    validate_command(&value, &[name], Some(3))?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(start), Some(stop)) => Ok((
+            key.0.expect("Invalid key"),
+            extract_integer(start)?,
+            extract_integer(stop)?,
+        )),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_lpos_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$4\r\nlpos\r\n$4\r\nlist\r\n$1\r\na\r\n$4\r\nRANK\r\n$2\r\n-1\r\n$5\r\ncount\r\n$1\r\n0\r\n");
+        let result: LPos = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.rank, -1);
+        assert_eq!(result.count, Some(0));
+        assert_eq!(result.maxlen, 0);
+
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nlpos\r\n$4\r\nlist\r\n$1\r\na\r\n$4\r\nrank\r\n$1\r\n0\r\n",
+        );
+        let result: Result<LPos, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lmpop_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$5\r\nlmpop\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nRIGHT\r\n$5\r\nCOUNT\r\n$1\r\n3\r\n");
+        let result: LMPop = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(result.end, ListEnd::Right);
+        assert_eq!(result.count, 3);
+
+        buf.extend_from_slice(b"*4\r\n$5\r\nlmpop\r\n$1\r\n1\r\n$1\r\na\r\n$2\r\nup\r\n");
+        let result: Result<LMPop, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = RPush {
+            key: b"list".to_vec(),
+            elements: vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd = LRange {
+            key: b"list".to_vec(),
+            start: 0,
+            stop: -2,
+        };
+        let expected = RespArray::new(vec![
+            BulkString::from("a").into(),
+            BulkString::from("b").into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = LPos {
+            key: b"list".to_vec(),
+            element: b"c".to_vec(),
+            rank: 1,
+            count: None,
+            maxlen: 0,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = LPop {
+            key: b"list".to_vec(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("a").into());
+
+        let cmd = LMPop {
+            keys: vec![b"missing".to_vec(), b"list".to_vec()],
+            end: ListEnd::Right,
+            count: 5,
+        };
+        let expected = RespArray::new(vec![
+            BulkString::from("list").into(),
+            RespArray::new(vec![
+                BulkString::from("c").into(),
+                BulkString::from("b").into(),
+            ])
+            .into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = RPop {
+            key: b"list".to_vec(),
+            count: Some(1),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        Ok(())
+    }
+}
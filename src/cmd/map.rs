@@ -1,25 +1,223 @@
-use super::{extract_args, validate_command, CommandExecutor, Set, RESP_OK};
+use super::{
+    extract_args, extract_expire, extract_float, extract_integer, extract_key,
+    extract_key_and_integer, extract_pairs, invalid_expire_time, syntax_error, validate_command,
+    Append, CommandExecutor, Decr, DecrBy, ExpireOption, GetDel, GetEx, GetRange, Incr, IncrBy,
+    IncrByFloat, Lcs, MGet, MSet, MSetNx, Set, SetRange, Strlen, RESP_OK,
+};
 use crate::{
     cmd::{CommandError, Get},
-    RespArray, RespFrame, RespNull,
+    format_incr_float, BackendError, BulkString, KeyExpire, RespArray, RespFrame, RespMap,
+    RespNull, SetCondition,
 };
 
 impl CommandExecutor for Get {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         match backend.get(&self.key) {
-            Some(value) => value,
-            None => RespFrame::Null(RespNull),
+            Ok(Some(value)) => value,
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
         }
     }
 }
 
 impl CommandExecutor for Set {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.set(self.key, self.value);
+        let expire = match self.expire.map(|v| v.to_key_expire()) {
+            Some(Some(expire)) => expire,
+            Some(None) => return invalid_expire_time("set"),
+            None => KeyExpire::Persist,
+        };
+
+        let (applied, old) = match backend.set_with_options(
+            self.key,
+            self.value,
+            self.condition,
+            expire,
+            self.get,
+        ) {
+            Ok(v) => v,
+            Err(e) => return e.into(),
+        };
+
+        match (self.get, applied) {
+            (true, _) => old.unwrap_or(RespFrame::Null(RespNull)),
+            (false, true) => RESP_OK.clone(),
+            (false, false) => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for Incr {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        incr_by(backend, self.key, 1)
+    }
+}
+
+impl CommandExecutor for Decr {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        incr_by(backend, self.key, -1)
+    }
+}
+
+impl CommandExecutor for IncrBy {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        incr_by(backend, self.key, self.increment)
+    }
+}
+
+impl CommandExecutor for DecrBy {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.decrement.checked_neg() {
+            Some(delta) => incr_by(backend, self.key, delta),
+            None => BackendError::Overflow.into(),
+        }
+    }
+}
+
+impl CommandExecutor for IncrByFloat {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.incr_by_float(self.key, self.increment) {
+            Ok(value) => BulkString::from(format_incr_float(value)).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for MGet {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let ret = backend
+            .mget(&self.keys)
+            .into_iter()
+            .map(|v| match v {
+                Some(value) => value,
+                None => BulkString::new(None).into(),
+            })
+            .collect::<Vec<RespFrame>>();
+
+        RespArray::new(Some(ret)).into()
+    }
+}
+
+impl CommandExecutor for MSet {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        backend.mset(self.pairs);
         RESP_OK.clone()
     }
 }
 
+impl CommandExecutor for MSetNx {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespFrame::Integer(backend.msetnx(self.pairs) as i64)
+    }
+}
+
+impl CommandExecutor for Append {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.append(self.key, &self.value) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Strlen {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.strlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GetRange {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.getrange(&self.key, self.start, self.end) {
+            Ok(value) => BulkString::new(value).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SetRange {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.setrange(self.key, self.offset, &self.value) {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GetDel {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.getdel(&self.key) {
+            Ok(value) => value.unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GetEx {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let expire = match self.expire.map(|v| v.to_key_expire()) {
+            Some(Some(expire)) => expire,
+            Some(None) => return invalid_expire_time("getex"),
+            None => KeyExpire::Keep,
+        };
+        match backend.getex(&self.key, expire) {
+            Ok(value) => value.unwrap_or(RespFrame::Null(RespNull)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for Lcs {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let lcs = match backend.lcs(&self.key1, &self.key2) {
+            Ok(lcs) => lcs,
+            Err(e) => return e.into(),
+        };
+
+        if self.len {
+            return RespFrame::Integer(lcs.len() as i64);
+        }
+        let (result, matches) = lcs.matches();
+        if !self.idx {
+            return BulkString::new(result).into();
+        }
+
+        let range = |(start, end): (usize, usize)| -> RespFrame {
+            RespArray::new(vec![
+                RespFrame::Integer(start as i64),
+                RespFrame::Integer(end as i64),
+            ])
+            .into()
+        };
+        let matches = matches
+            .into_iter()
+            .filter(|m| m.a.1 - m.a.0 + 1 >= self.min_match_len)
+            .map(|m| {
+                let mut entry = vec![range(m.a), range(m.b)];
+                if self.with_match_len {
+                    entry.push(RespFrame::Integer((m.a.1 - m.a.0 + 1) as i64));
+                }
+                RespArray::new(entry).into()
+            })
+            .collect::<Vec<RespFrame>>();
+
+        let mut map = RespMap::new();
+        map.insert("matches".to_string(), RespArray::new(matches).into());
+        map.insert("len".to_string(), RespFrame::Integer(lcs.len() as i64));
+        map.into()
+    }
+}
+
+fn incr_by(backend: &crate::Backend, key: Vec<u8>, delta: i64) -> RespFrame {
+    match backend.incr_by(key, delta) {
+        Ok(value) => RespFrame::Integer(value),
+        Err(e) => e.into(),
+    }
+}
+
 impl TryFrom<RespArray> for Get {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
@@ -28,7 +226,7 @@ impl TryFrom<RespArray> for Get {
         let mut args = extract_args(value, 1)?.into_iter();
         match args.next() {
             Some(RespFrame::BulkString(key)) => Ok(Get {
-                key: String::from_utf8(key.0.expect("Invalid key"))?,
+                key: key.0.expect("Invalid key"),
             }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
@@ -38,13 +236,177 @@ impl TryFrom<RespArray> for Get {
 impl TryFrom<RespArray> for Set {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["set"], Some(2))?;
+        validate_command(&value, &["set"], None)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, value) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(value)) => (key.0.expect("Invalid key"), value),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "Invalid key or value".to_string(),
+                ))
+            }
+        };
+
+        // SET key value [NX | XX] [GET] [EX seconds | PX milliseconds |
+        //   EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
+        let mut condition = SetCondition::Always;
+        let mut expire = None;
+        let mut get = false;
+        while let Some(option) = args.next() {
+            let option = match option {
+                RespFrame::BulkString(opt) => opt.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                b"nx" | b"xx" => {
+                    if condition != SetCondition::Always {
+                        return Err(syntax_error());
+                    }
+                    condition = if option == b"nx" {
+                        SetCondition::NotExists
+                    } else {
+                        SetCondition::Exists
+                    };
+                }
+                b"get" => {
+                    if get {
+                        return Err(syntax_error());
+                    }
+                    get = true;
+                }
+                b"keepttl" => {
+                    if expire.is_some() {
+                        return Err(syntax_error());
+                    }
+                    expire = Some(ExpireOption::KeepTtl);
+                }
+                b"ex" | b"px" | b"exat" | b"pxat" => {
+                    if expire.is_some() {
+                        return Err(syntax_error());
+                    }
+                    expire = Some(extract_expire(&option, &mut args, "set")?);
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        Ok(Set {
+            key,
+            value,
+            condition,
+            expire,
+            get,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Incr {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Incr {
+            key: extract_key(value, "incr")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Decr {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Decr {
+            key: extract_key(value, "decr")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for IncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, increment) = extract_key_and_integer(value, "incrby")?;
+        Ok(IncrBy { key, increment })
+    }
+}
+
+impl TryFrom<RespArray> for DecrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, decrement) = extract_key_and_integer(value, "decrby")?;
+        Ok(DecrBy { key, decrement })
+    }
+}
+
+impl TryFrom<RespArray> for IncrByFloat {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["incrbyfloat"], Some(2))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(increment)) => Ok(IncrByFloat {
+                key: key.0.expect("Invalid key"),
+                increment: extract_float(increment)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or increment".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for MGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["mget"], None)?;
+
+        let mut keys = vec![];
+        for v in extract_args(value, 1)? {
+            match v {
+                RespFrame::BulkString(key) => {
+                    keys.push(key.0.expect("Invalid key"));
+                }
+                _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+            }
+        }
+        if keys.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "mget command must have at least 1 argument".to_string(),
+            ));
+        }
+
+        Ok(MGet { keys })
+    }
+}
+
+impl TryFrom<RespArray> for MSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["mset"], None)?;
+        Ok(MSet {
+            pairs: extract_pairs(extract_args(value, 1)?, "mset")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for MSetNx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["msetnx"], None)?;
+        Ok(MSetNx {
+            pairs: extract_pairs(extract_args(value, 1)?, "msetnx")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Append {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["append"], Some(2))?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Set {
-                key: String::from_utf8(key.0.expect("Invalid key"))?,
-                value,
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(value))) => Ok(Append {
+                key: key.0.expect("Invalid key"),
+                value: value.0.unwrap_or_default(),
             }),
             _ => Err(CommandError::InvalidArgument(
                 "Invalid key or value".to_string(),
@@ -53,10 +415,158 @@ impl TryFrom<RespArray> for Set {
     }
 }
 
+impl TryFrom<RespArray> for Strlen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(Strlen {
+            key: extract_key(value, "strlen")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GetRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["getrange"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(start), Some(end)) => Ok(GetRange {
+                key: key.0.expect("Invalid key"),
+                start: extract_integer(start)?,
+                end: extract_integer(end)?,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, start or end".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SetRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["setrange"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(offset),
+                Some(RespFrame::BulkString(value)),
+            ) => Ok(SetRange {
+                key: key.0.expect("Invalid key"),
+                offset: usize::try_from(extract_integer(offset)?).map_err(|_| {
+                    CommandError::InvalidArgument("offset is out of range".to_string())
+                })?,
+                value: value.0.unwrap_or_default(),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key, offset or value".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for GetDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(GetDel {
+            key: extract_key(value, "getdel")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GetEx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["getex"], None)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        // GETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+        //   PXAT unix-time-milliseconds | PERSIST]
+        let expire = match args.next() {
+            Some(RespFrame::BulkString(option)) => {
+                let option = option.as_ref().to_ascii_lowercase();
+                match option.as_slice() {
+                    b"persist" => Some(ExpireOption::Persist),
+                    b"ex" | b"px" | b"exat" | b"pxat" => {
+                        Some(extract_expire(&option, &mut args, "getex")?)
+                    }
+                    _ => return Err(syntax_error()),
+                }
+            }
+            Some(_) => return Err(syntax_error()),
+            None => None,
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+
+        Ok(GetEx { key, expire })
+    }
+}
+
+impl TryFrom<RespArray> for Lcs {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lcs"], None)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key1, key2) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key1)), Some(RespFrame::BulkString(key2))) => {
+                (key1.0.expect("Invalid key"), key2.0.expect("Invalid key"))
+            }
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        // LCS key1 key2 [LEN] [IDX] [MINMATCHLEN min-match-len] [WITHMATCHLEN]
+        let mut lcs = Lcs {
+            key1,
+            key2,
+            len: false,
+            idx: false,
+            min_match_len: 0,
+            with_match_len: false,
+        };
+        while let Some(option) = args.next() {
+            let option = match option {
+                RespFrame::BulkString(opt) => opt.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                b"len" => lcs.len = true,
+                b"idx" => lcs.idx = true,
+                b"withmatchlen" => lcs.with_match_len = true,
+                b"minmatchlen" => {
+                    let n = match args.next() {
+                        Some(frame) => extract_integer(frame)?,
+                        None => return Err(syntax_error()),
+                    };
+                    lcs.min_match_len = n.max(0) as usize;
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        if lcs.len && lcs.idx {
+            return Err(CommandError::InvalidArgument(
+                "If you want both the length and indexes, please just use IDX.".to_string(),
+            ));
+        }
+
+        Ok(lcs)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Backend, RespDecode};
+    use crate::{Backend, RespDecode, SimpleError};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -68,7 +578,7 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: Get = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
 
         Ok(())
     }
@@ -81,28 +591,503 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: Set = frame.try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
+        assert_eq!(result.expire, None);
 
         Ok(())
     }
 
+    #[test]
+    fn test_set_with_expire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nEX\r\n$2\r\n10\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.expire, Some(ExpireOption::Ttl(10_000)));
+
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\npx\r\n$3\r\n100\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.expire, Some(ExpireOption::Ttl(100)));
+
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n$2\r\nex\r\n$1\r\n0\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Set, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_expire_out_of_range() -> Result<()> {
+        let backend = Backend::new();
+        let max = i64::MAX.to_string();
+        // EX and EXAT overflow once in milliseconds, PX once added to the current time, and
+        // PXAT is not after the epoch
+        let times = [
+            ("EX", max.as_str()),
+            ("PX", &max),
+            ("EXAT", &max),
+            ("PXAT", "0"),
+        ];
+        for (option, time) in times {
+            let frame = RespArray::new(
+                ["set", "key", "value", option, time]
+                    .into_iter()
+                    .map(|arg| BulkString::from(arg).into())
+                    .collect::<Vec<RespFrame>>(),
+            );
+            // replied like the network layer replies a command that fails to parse
+            let reply = match Set::try_from(frame) {
+                Ok(cmd) => cmd.execute(&backend),
+                Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+            };
+            assert_eq!(
+                reply,
+                SimpleError::new("ERR invalid expire time in 'set' command").into(),
+                "{} {}",
+                option,
+                time
+            );
+        }
+        assert_eq!(backend.get(b"key"), Ok(None));
+        Ok(())
+    }
+
     #[test]
     fn test_set_get_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Set {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
             value: RespFrame::BulkString(b"world".into()),
+            condition: SetCondition::Always,
+            expire: None,
+            get: false,
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = Get {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_options_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$3\r\nset\r\n$4\r\nlock\r\n$1\r\n1\r\n$2\r\nNX\r\n$3\r\nGET\r\n$4\r\nEXAT\r\n$10\r\n1700000000\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Set = frame.try_into()?;
+        assert_eq!(result.condition, SetCondition::NotExists);
+        assert!(result.get);
+        assert_eq!(result.expire, Some(ExpireOption::At(1_700_000_000_000)));
+
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nset\r\n$4\r\nlock\r\n$1\r\n1\r\n$2\r\nNX\r\n$2\r\nXX\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Set, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(
+            b"*6\r\n$3\r\nset\r\n$4\r\nlock\r\n$1\r\n1\r\n$7\r\nKEEPTTL\r\n$2\r\nPX\r\n$2\r\n10\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Set, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_nx_xx_get_command() -> Result<()> {
+        let backend = Backend::new();
+        let set = |condition, expire, get| Set {
+            key: b"lock".to_vec(),
+            value: RespFrame::BulkString(b"owner1".into()),
+            condition,
+            expire,
+            get,
+        };
+
+        let result = set(SetCondition::Exists, None, false).execute(&backend);
+        assert_eq!(result, RespFrame::Null(RespNull));
+
+        let result = set(
+            SetCondition::NotExists,
+            Some(ExpireOption::Ttl(10_000)),
+            false,
+        )
+        .execute(&backend);
+        assert_eq!(result, RESP_OK.clone());
+
+        let result = set(SetCondition::NotExists, None, true).execute(&backend);
+        assert_eq!(result, RespFrame::BulkString(b"owner1".into()));
+
+        let cmd = Set {
+            key: b"lock".to_vec(),
+            value: RespFrame::BulkString(b"owner2".into()),
+            condition: SetCondition::Exists,
+            expire: Some(ExpireOption::KeepTtl),
+            get: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::BulkString(b"owner1".into())
+        );
+        assert!(backend.pttl(b"lock") > 0);
+
+        let cmd = Get {
+            key: b"lock".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::BulkString(b"owner2".into())
+        );
+
+        set(SetCondition::Always, None, false).execute(&backend);
+        assert_eq!(backend.pttl(b"lock"), -1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_commands_on_wrong_type() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd(b"myset".to_vec(), vec![b"member".to_vec()])?;
+        let wrong_type: RespFrame = BackendError::WrongType.into();
+
+        let cmd = Get {
+            key: b"myset".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), wrong_type);
+
+        let cmd = Set {
+            key: b"myset".to_vec(),
+            value: RespFrame::BulkString(b"value".into()),
+            condition: SetCondition::Always,
+            expire: None,
+            get: true,
+        };
+        assert_eq!(cmd.execute(&backend), wrong_type);
+        assert_eq!(backend.key_type(b"myset"), "set");
+
+        let cmd = Set {
+            key: b"myset".to_vec(),
+            value: RespFrame::BulkString(b"value".into()),
+            condition: SetCondition::Always,
+            expire: None,
+            get: false,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.key_type(b"myset"), "string");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incrbyfloat_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$11\r\nincrbyfloat\r\n$5\r\nprice\r\n$3\r\n0.1\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: IncrByFloat = frame.try_into()?;
+        assert_eq!(result.key, b"price");
+        assert_eq!(result.increment, 0.1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incrbyfloat_keeps_every_increment() {
+        let backend = Backend::new();
+        backend.set(b"c".to_vec(), BulkString::from("100000000000000").into());
+        let mut previous = 100000000000000.0;
+        for _ in 0..3 {
+            let cmd = IncrByFloat {
+                key: b"c".to_vec(),
+                increment: 0.01,
+            };
+            let reply = cmd.execute(&backend);
+            let stored = backend.get(b"c").unwrap().unwrap();
+            // the reply is the stored text, which reads back a larger value each time
+            assert_eq!(reply, stored);
+            let RespFrame::BulkString(stored) = stored else {
+                panic!("expected a bulk string, got {:?}", stored);
+            };
+            let value: f64 = String::from_utf8_lossy(stored.as_ref()).parse().unwrap();
+            assert!(value > previous);
+            previous = value;
+        }
+
+        // large values print in plain decimal, like the %.17Lf of redis
+        let cmd = IncrByFloat {
+            key: b"big".to_vec(),
+            increment: 1e300,
+        };
+        let expected = BulkString::from(format!("1{}", "0".repeat(300)));
+        assert_eq!(cmd.execute(&backend), expected.clone().into());
+        assert_eq!(backend.get(b"big"), Ok(Some(expected.into())));
+    }
+
+    #[test]
+    fn test_incr_decr_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = Incr {
+            key: b"counter".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = IncrBy {
+            key: b"counter".to_vec(),
+            increment: 10,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(11));
+
+        let cmd = DecrBy {
+            key: b"counter".to_vec(),
+            decrement: 20,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-9));
+
+        let cmd = Decr {
+            key: b"counter".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(-10));
+
+        let cmd = DecrBy {
+            key: b"counter".to_vec(),
+            decrement: i64::MIN,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR increment or decrement would overflow").into()
+        );
+
+        let cmd = IncrByFloat {
+            key: b"counter".to_vec(),
+            increment: 0.5,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("-9.5").into());
+
+        let cmd = Incr {
+            key: b"counter".to_vec(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR value is not an integer or out of range").into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mset_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*5\r\n$4\r\nmset\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n$1\r\n2\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: MSet = frame.try_into()?;
+        assert_eq!(
+            result.pairs,
+            vec![
+                (b"a".to_vec(), RespFrame::BulkString(b"1".into())),
+                (b"b".to_vec(), RespFrame::BulkString(b"2".into())),
+            ]
+        );
+
+        buf.extend_from_slice(b"*4\r\n$4\r\nmset\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<MSet, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mset_msetnx_mget_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = MSet {
+            pairs: vec![
+                (b"a".to_vec(), RespFrame::BulkString(b"1".into())),
+                (b"b".to_vec(), RespFrame::BulkString(b"2".into())),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd = MSetNx {
+            pairs: vec![
+                (b"b".to_vec(), RespFrame::BulkString(b"3".into())),
+                (b"c".to_vec(), RespFrame::BulkString(b"3".into())),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        let cmd = MGet {
+            keys: vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+        };
+        let expected = RespArray::new(Some(vec![
+            BulkString::from("1").into(),
+            BulkString::from("2").into(),
+            BulkString::new(None).into(),
+        ]));
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = MSetNx {
+            pairs: vec![(b"c".to_vec(), RespFrame::BulkString(b"3".into()))],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mset_is_atomic_for_mget() {
+        let backend = Backend::new();
+        let keys = (0..16)
+            .map(|i| format!("key{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        backend.mset(
+            keys.iter()
+                .map(|k| (k.clone(), RespFrame::Integer(0)))
+                .collect(),
+        );
+
+        let writer = {
+            let backend = backend.clone();
+            let keys = keys.clone();
+            std::thread::spawn(move || {
+                for n in 1..500 {
+                    backend.mset(
+                        keys.iter()
+                            .map(|k| (k.clone(), RespFrame::Integer(n)))
+                            .collect(),
+                    );
+                }
+            })
+        };
+
+        for _ in 0..500 {
+            let values = backend.mget(&keys);
+            assert!(values.windows(2).all(|w| w[0] == w[1]));
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_getex_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$5\r\ngetex\r\n$3\r\nkey\r\n$2\r\nPX\r\n$3\r\n100\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: GetEx = frame.try_into()?;
+        assert_eq!(result.key, b"key");
+        assert_eq!(result.expire, Some(ExpireOption::Ttl(100)));
+
+        buf.extend_from_slice(b"*3\r\n$5\r\ngetex\r\n$3\r\nkey\r\n$7\r\npersist\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: GetEx = frame.try_into()?;
+        assert_eq!(result.expire, Some(ExpireOption::Persist));
+
+        buf.extend_from_slice(b"*3\r\n$5\r\ngetex\r\n$3\r\nkey\r\n$7\r\nkeepttl\r\n");
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<GetEx, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcs_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$3\r\nlcs\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n$3\r\nIDX\r\n$11\r\nMINMATCHLEN\r\n$1\r\n4\r\n$12\r\nWITHMATCHLEN\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Lcs = frame.try_into()?;
+        assert_eq!(result.key1, b"key1");
+        assert_eq!(result.key2, b"key2");
+        assert!(result.idx && result.with_match_len && !result.len);
+        assert_eq!(result.min_match_len, 4);
+
+        buf.extend_from_slice(
+            b"*5\r\n$3\r\nlcs\r\n$4\r\nkey1\r\n$4\r\nkey2\r\n$3\r\nIDX\r\n$3\r\nLEN\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<Lcs, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcs_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key1".to_vec(), BulkString::from("ohmytext").into());
+        backend.set(b"key2".to_vec(), BulkString::from("mynewtext").into());
+        let lcs = |len, idx, min_match_len, with_match_len| Lcs {
+            key1: b"key1".to_vec(),
+            key2: b"key2".to_vec(),
+            len,
+            idx,
+            min_match_len,
+            with_match_len,
+        };
+
+        let result = lcs(false, false, 0, false).execute(&backend);
+        assert_eq!(result, BulkString::from("mytext").into());
+
+        let result = lcs(true, false, 0, false).execute(&backend);
+        assert_eq!(result, RespFrame::Integer(6));
+
+        let result = lcs(false, true, 4, true).execute(&backend);
+        let mut expected = RespMap::new();
+        expected.insert(
+            "matches".to_string(),
+            RespArray::new(vec![RespArray::new(vec![
+                RespArray::new(vec![4.into(), 7.into()]).into(),
+                RespArray::new(vec![5.into(), 8.into()]).into(),
+                4.into(),
+            ])
+            .into()])
+            .into(),
+        );
+        expected.insert("len".to_string(), 6.into());
+        assert_eq!(result, expected.into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_commands_wrong_type() -> Result<()> {
+        let backend = Backend::new();
+        backend.set(b"key".to_vec(), RespFrame::Integer(1));
+
+        let cmd = Append {
+            key: b"key".to_vec(),
+            value: b"1".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), BackendError::WrongType.into());
+
+        let cmd = GetDel {
+            key: b"key".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), BackendError::WrongType.into());
+
+        Ok(())
+    }
 }
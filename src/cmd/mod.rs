@@ -1,99 +1,1114 @@
+mod bitmap;
+mod blocking;
+mod connection;
 mod echo;
+mod expire;
+mod geo;
+mod hexpire;
 mod hmap;
+mod hyperloglog;
+mod keyspace;
+mod list;
 mod map;
+mod pubsub;
 mod set;
+mod stream;
+mod stream_group;
+mod zset;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+use crate::{
+    backend::now_ms, Aggregate, Backend, BitFieldOp, BitOperation, BitUnit, BulkString,
+    ClaimOptions, ExpireCondition, GeoQuery, InsertPosition, KeyExpire, LexBound, ListEnd,
+    RespArray, RespError, RespFrame, ScoreBound, ScoreEnd, SetCondition, SimpleError, SimpleString,
+    StreamFields, StreamId, StreamTrim, XAddId, ZAddOptions, ZRangeBy,
+};
 use enum_dispatch::enum_dispatch;
+use std::time::Duration;
+
+pub use blocking::Block;
+pub use connection::{Protocol, Session};
 use lazy_static::lazy_static;
+pub use stream::XReadId;
 use thiserror::Error;
 
-// you could also use once_cell instead of lazy_static
-lazy_static! {
-    static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
+// you could also use once_cell instead of lazy_static
+lazy_static! {
+    static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("Invalid command: {0}")]
+    InvalidCommand(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    // an argument redis rejects with its own message, replied as it is
+    #[error("{0}")]
+    OutOfRange(String),
+
+    #[error("{0}")]
+    RespError(#[from] RespError),
+    #[error("Utf8 error: {0}")]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+}
+
+#[enum_dispatch]
+pub trait CommandExecutor: Sized {
+    fn execute(self, backend: &Backend) -> RespFrame;
+
+    // the few commands reading or changing the state of the connection override this one
+    fn execute_with(self, backend: &Backend, _session: &mut Session) -> RespFrame {
+        self.execute(backend)
+    }
+}
+
+#[enum_dispatch(CommandExecutor)]
+#[derive(Debug)]
+pub enum Command {
+    Get(Get),
+    Set(Set),
+    HGet(HGet),
+    HSet(HSet),
+    HMSet(HMSet),
+    HMGet(HMGet),
+    HGetAll(HGetAll),
+    HDel(HDel),
+    HExists(HExists),
+    HLen(HLen),
+    HKeys(HKeys),
+    HVals(HVals),
+    HSetNx(HSetNx),
+    HStrlen(HStrlen),
+    HRandField(HRandField),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HExpire(HExpire),
+    HPExpire(HPExpire),
+    HExpireAt(HExpireAt),
+    HPExpireAt(HPExpireAt),
+    HTtl(HTtl),
+    HPTtl(HPTtl),
+    HExpireTime(HExpireTime),
+    HPExpireTime(HPExpireTime),
+    HPersist(HPersist),
+    HGetEx(HGetEx),
+    HSetEx(HSetEx),
+    Echo(Echo),
+    Hello(Hello),
+    Sadd(Sadd),
+    Sismember(Sismember),
+    SRem(SRem),
+    SMembers(SMembers),
+    SCard(SCard),
+    SMIsMember(SMIsMember),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SMove(SMove),
+    SInter(SInter),
+    SUnion(SUnion),
+    SDiff(SDiff),
+    SInterStore(SInterStore),
+    SUnionStore(SUnionStore),
+    SDiffStore(SDiffStore),
+    SInterCard(SInterCard),
+    LPush(LPush),
+    RPush(RPush),
+    LPushX(LPushX),
+    RPushX(RPushX),
+    LPop(LPop),
+    RPop(RPop),
+    LLen(LLen),
+    LRange(LRange),
+    LIndex(LIndex),
+    LSet(LSet),
+    LInsert(LInsert),
+    LRem(LRem),
+    LTrim(LTrim),
+    LPos(LPos),
+    LMove(LMove),
+    RPopLPush(RPopLPush),
+    LMPop(LMPop),
+    BLPop(BLPop),
+    BRPop(BRPop),
+    BLMove(BLMove),
+    BLMPop(BLMPop),
+    ZAdd(ZAdd),
+    ZRange(ZRange),
+    ZRank(ZRank),
+    ZRevRank(ZRevRank),
+    ZScore(ZScore),
+    ZMScore(ZMScore),
+    ZIncrBy(ZIncrBy),
+    ZRem(ZRem),
+    ZCard(ZCard),
+    ZCount(ZCount),
+    ZUnionStore(ZUnionStore),
+    ZInterStore(ZInterStore),
+    ZDiff(ZDiff),
+    ZDiffStore(ZDiffStore),
+    ZRangeStore(ZRangeStore),
+    ZRemRangeByRank(ZRemRangeByRank),
+    ZRemRangeByScore(ZRemRangeByScore),
+    ZRemRangeByLex(ZRemRangeByLex),
+    ZPopMin(ZPopMin),
+    ZPopMax(ZPopMax),
+    ZMPop(ZMPop),
+    BZPopMin(BZPopMin),
+    BZPopMax(BZPopMax),
+    BZMPop(BZMPop),
+    XAdd(XAdd),
+    XRange(XRange),
+    XRevRange(XRevRange),
+    XLen(XLen),
+    XDel(XDel),
+    XTrim(XTrim),
+    XRead(XRead),
+    XGroupCreate(XGroupCreate),
+    XGroupSetId(XGroupSetId),
+    XGroupDestroy(XGroupDestroy),
+    XGroupCreateConsumer(XGroupCreateConsumer),
+    XGroupDelConsumer(XGroupDelConsumer),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    XInfoStream(XInfoStream),
+    XInfoGroups(XInfoGroups),
+    XInfoConsumers(XInfoConsumers),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoDist(GeoDist),
+    GeoPos(GeoPos),
+    GeoHash(GeoHash),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+    Ping(Ping),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    PubSubChannels(PubSubChannels),
+    PubSubNumSub(PubSubNumSub),
+    PubSubNumPat(PubSubNumPat),
+    Expire(Expire),
+    PExpire(PExpire),
+    ExpireAt(ExpireAt),
+    PExpireAt(PExpireAt),
+    Ttl(Ttl),
+    PTtl(PTtl),
+    Persist(Persist),
+    Incr(Incr),
+    Decr(Decr),
+    IncrBy(IncrBy),
+    DecrBy(DecrBy),
+    IncrByFloat(IncrByFloat),
+    MGet(MGet),
+    MSet(MSet),
+    MSetNx(MSetNx),
+    Append(Append),
+    Strlen(Strlen),
+    GetRange(GetRange),
+    SetRange(SetRange),
+    GetDel(GetDel),
+    GetEx(GetEx),
+    Lcs(Lcs),
+    SetBit(SetBit),
+    GetBit(GetBit),
+    BitCount(BitCount),
+    BitPos(BitPos),
+    BitOp(BitOp),
+    BitField(BitField),
+    Del(Del),
+    Unlink(Unlink),
+    Exists(Exists),
+    Type(Type),
+    Rename(Rename),
+    RenameNx(RenameNx),
+    CopyKey(CopyKey),
+    Touch(Touch),
+    RandomKey(RandomKey),
+    Keys(Keys),
+    Scan(Scan),
+    HScan(HScan),
+    SScan(SScan),
+
+    // unrecognized command
+    Unrecognized(Unrecognized),
+}
+
+#[derive(Debug)]
+pub struct Sadd {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Sismember {
+    key: Vec<u8>,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SRem {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SMembers {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SCard {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SPop {
+    key: Vec<u8>,
+    // None replies with a single member instead of a set
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SRandMember {
+    key: Vec<u8>,
+    // None replies with a single member instead of an array
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SMove {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct SInter {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SUnion {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SDiff {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SInterStore {
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SUnionStore {
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SDiffStore {
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<Vec<u8>>,
+    // 0 means no limit
+    limit: usize,
+}
+
+#[derive(Debug)]
+pub struct LPush {
+    key: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct RPush {
+    key: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct LPushX {
+    key: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct RPushX {
+    key: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct LPop {
+    key: Vec<u8>,
+    // None replies with a single element instead of an array
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct RPop {
+    key: Vec<u8>,
+    // None replies with a single element instead of an array
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: Vec<u8>,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: Vec<u8>,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSet {
+    key: Vec<u8>,
+    index: i64,
+    element: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: Vec<u8>,
+    position: InsertPosition,
+    pivot: Vec<u8>,
+    element: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: Vec<u8>,
+    count: i64,
+    element: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LTrim {
+    key: Vec<u8>,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LPos {
+    key: Vec<u8>,
+    element: Vec<u8>,
+    rank: i64,
+    // None replies with a single index instead of an array, Some(0) returns every match
+    count: Option<usize>,
+    // 0 means the whole list
+    maxlen: usize,
+}
+
+#[derive(Debug)]
+pub struct LMove {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+#[derive(Debug)]
+pub struct RPopLPush {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct LMPop {
+    keys: Vec<Vec<u8>>,
+    end: ListEnd,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct BLPop {
+    keys: Vec<Vec<u8>>,
+    // None blocks forever
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BRPop {
+    keys: Vec<Vec<u8>>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMove {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    from: ListEnd,
+    to: ListEnd,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMPop {
+    keys: Vec<Vec<u8>>,
+    end: ListEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: Vec<u8>,
+    options: ZAddOptions,
+    // INCR: the single pair is an increment, the reply is the new score
+    incr: bool,
+    pairs: Vec<(f64, Vec<u8>)>,
+}
+
+#[derive(Debug)]
+pub struct ZRange {
+    key: Vec<u8>,
+    by: ZRangeBy,
+    rev: bool,
+    // LIMIT offset count, count is usize::MAX without a limit
+    offset: usize,
+    count: usize,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRank {
+    key: Vec<u8>,
+    member: Vec<u8>,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRevRank {
+    key: Vec<u8>,
+    member: Vec<u8>,
+    with_score: bool,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: Vec<u8>,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZMScore {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: Vec<u8>,
+    increment: f64,
+    member: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: Vec<u8>,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZUnionStore {
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug)]
+pub struct ZInterStore {
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug)]
+pub struct ZDiff {
+    keys: Vec<Vec<u8>>,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZDiffStore {
+    destination: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    destination: Vec<u8>,
+    source: Vec<u8>,
+    by: ZRangeBy,
+    rev: bool,
+    offset: usize,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByRank {
+    key: Vec<u8>,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByScore {
+    key: Vec<u8>,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+#[derive(Debug)]
+pub struct ZRemRangeByLex {
+    key: Vec<u8>,
+    min: LexBound,
+    max: LexBound,
+}
+
+#[derive(Debug)]
+pub struct ZPopMin {
+    key: Vec<u8>,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZPopMax {
+    key: Vec<u8>,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<Vec<u8>>,
+    end: ScoreEnd,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct BZPopMin {
+    keys: Vec<Vec<u8>>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZPopMax {
+    keys: Vec<Vec<u8>>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZMPop {
+    keys: Vec<Vec<u8>>,
+    end: ScoreEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: Vec<u8>,
+    id: XAddId,
+    fields: StreamFields,
+    nomkstream: bool,
+    trim: Option<StreamTrim>,
+}
+
+#[derive(Debug)]
+pub struct XRange {
+    key: Vec<u8>,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct XRevRange {
+    key: Vec<u8>,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: Vec<u8>,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: Vec<u8>,
+    trim: StreamTrim,
+}
+
+#[derive(Debug)]
+pub struct XRead {
+    streams: Vec<(Vec<u8>, XReadId)>,
+    count: usize,
+    // BLOCK was given, with a timeout of None to block forever
+    block: bool,
+    timeout: Option<Duration>,
+}
+
+// XGROUP subcommands, a missing ID is `$`
+#[derive(Debug)]
+pub struct XGroupCreate {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    id: Option<StreamId>,
+    mkstream: bool,
+    entries_read: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct XGroupSetId {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    id: Option<StreamId>,
+    entries_read: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct XGroupDestroy {
+    key: Vec<u8>,
+    group: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XGroupCreateConsumer {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XGroupDelConsumer {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    // a missing ID is `>`, the entries never delivered to the group
+    streams: Vec<(Vec<u8>, Option<StreamId>)>,
+    count: usize,
+    block: bool,
+    timeout: Option<Duration>,
+    noack: bool,
+}
+
+#[derive(Debug)]
+pub struct XAck {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    // start, end and count of the extended form, the summary without them
+    range: Option<(StreamId, StreamId, usize)>,
+    min_idle: i64,
+    consumer: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    min_idle: i64,
+    ids: Vec<StreamId>,
+    options: ClaimOptions,
+}
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: Vec<u8>,
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    min_idle: i64,
+    start: StreamId,
+    count: usize,
+    just_id: bool,
+}
+
+#[derive(Debug)]
+pub struct XInfoStream {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XInfoGroups {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct XInfoConsumers {
+    key: Vec<u8>,
+    group: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: Vec<u8>,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PfMerge {
+    dest: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: Vec<u8>,
+    // longitude, latitude, member
+    positions: Vec<(f64, f64, Vec<u8>)>,
+    options: ZAddOptions,
+}
+
+// distances are replied in the unit of the command, in meters per unit
+#[derive(Debug)]
+pub struct GeoDist {
+    key: Vec<u8>,
+    member1: Vec<u8>,
+    member2: Vec<u8>,
+    unit: f64,
+}
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct GeoHash {
+    key: Vec<u8>,
+    members: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: Vec<u8>,
+    query: GeoQuery,
+    unit: f64,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+}
+
+#[derive(Debug)]
+pub struct GeoSearchStore {
+    destination: Vec<u8>,
+    key: Vec<u8>,
+    query: GeoQuery,
+    unit: f64,
+    // STOREDIST scores the members by distance instead of geohash
+    store_dist: bool,
+}
+
+#[derive(Debug)]
+pub struct Ping {
+    message: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Subscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+// no channels unsubscribes from all of them
+#[derive(Debug)]
+pub struct Unsubscribe {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    patterns: Vec<Vec<u8>>,
+}
+
+// no patterns unsubscribes from all of them
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    patterns: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: Vec<u8>,
+    message: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PubSubChannels {
+    pattern: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PubSubNumSub {
+    channels: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PubSubNumPat;
+
+#[derive(Debug)]
+pub struct Get {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Set {
+    key: Vec<u8>,
+    value: RespFrame,
+    condition: SetCondition,
+    expire: Option<ExpireOption>,
+    get: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExpireOption {
+    // relative time to live in milliseconds, from EX or PX
+    Ttl(i64),
+    // absolute deadline in unix milliseconds, from EXAT or PXAT
+    At(i64),
+    KeepTtl,
+    Persist,
+}
+
+#[derive(Debug)]
+pub struct HGet {
+    key: Vec<u8>,
+    field: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HSet {
+    key: Vec<u8>,
+    pairs: Vec<(Vec<u8>, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct HMSet {
+    key: Vec<u8>,
+    pairs: Vec<(Vec<u8>, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct HMGet {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct HGetAll {
+    key: Vec<u8>,
+    sort: bool,
+}
+
+#[derive(Debug)]
+pub struct HDel {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct HExists {
+    key: Vec<u8>,
+    field: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HLen {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HKeys {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HVals {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct HSetNx {
+    key: Vec<u8>,
+    field: Vec<u8>,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HStrlen {
+    key: Vec<u8>,
+    field: Vec<u8>,
 }
 
-#[derive(Debug, Error)]
-pub enum CommandError {
-    #[error("Invalid command: {0}")]
-    InvalidCommand(String),
-    #[error("Invalid argument: {0}")]
-    InvalidArgument(String),
+#[derive(Debug)]
+pub struct HRandField {
+    key: Vec<u8>,
+    // None replies with a single field instead of an array
+    count: Option<i64>,
+    with_values: bool,
+}
 
-    #[error("{0}")]
-    RespError(#[from] RespError),
-    #[error("Utf8 error: {0}")]
-    Utf8Error(#[from] std::string::FromUtf8Error),
+#[derive(Debug)]
+pub struct HIncrBy {
+    key: Vec<u8>,
+    field: Vec<u8>,
+    increment: i64,
 }
 
-#[enum_dispatch]
-pub trait CommandExecutor {
-    fn execute(self, backend: &Backend) -> RespFrame;
+#[derive(Debug)]
+pub struct HIncrByFloat {
+    key: Vec<u8>,
+    field: Vec<u8>,
+    increment: f64,
 }
 
-#[enum_dispatch(CommandExecutor)]
 #[derive(Debug)]
-pub enum Command {
-    Get(Get),
-    Set(Set),
-    HGet(HGet),
-    HSet(HSet),
-    HMGet(HMGet),
-    HGetAll(HGetAll),
-    Echo(Echo),
-    Sadd(Sadd),
-    Sismember(Sismember),
+pub struct HExpire {
+    key: Vec<u8>,
+    seconds: i64,
+    condition: ExpireCondition,
+    fields: Vec<Vec<u8>>,
+}
 
-    // unrecognized command
-    Unrecognized(Unrecognized),
+#[derive(Debug)]
+pub struct HPExpire {
+    key: Vec<u8>,
+    milliseconds: i64,
+    condition: ExpireCondition,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct Sadd {
-    key: String,
-    members: Vec<String>,
+pub struct HExpireAt {
+    key: Vec<u8>,
+    timestamp: i64,
+    condition: ExpireCondition,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct Sismember {
-    key: String,
-    member: String,
+pub struct HPExpireAt {
+    key: Vec<u8>,
+    timestamp: i64,
+    condition: ExpireCondition,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct Get {
-    key: String,
+pub struct HTtl {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct Set {
-    key: String,
-    value: RespFrame,
+pub struct HPTtl {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct HGet {
-    key: String,
-    field: String,
+pub struct HExpireTime {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct HSet {
-    key: String,
-    field: String,
-    value: RespFrame,
+pub struct HPExpireTime {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct HMGet {
-    key: String,
-    fields: Vec<String>,
+pub struct HPersist {
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
 }
 
 #[derive(Debug)]
-pub struct HGetAll {
-    key: String,
-    sort: bool,
+pub struct HGetEx {
+    key: Vec<u8>,
+    expire: Option<ExpireOption>,
+    fields: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct HSetEx {
+    key: Vec<u8>,
+    condition: SetCondition,
+    expire: Option<ExpireOption>,
+    pairs: Vec<(Vec<u8>, RespFrame)>,
 }
 
 #[derive(Debug)]
@@ -101,6 +1116,262 @@ pub struct Echo {
     message: String,
 }
 
+#[derive(Debug)]
+pub struct Hello {
+    protocol: Option<i64>,
+    client_name: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Expire {
+    key: Vec<u8>,
+    seconds: i64,
+}
+
+#[derive(Debug)]
+pub struct PExpire {
+    key: Vec<u8>,
+    milliseconds: i64,
+}
+
+#[derive(Debug)]
+pub struct ExpireAt {
+    key: Vec<u8>,
+    timestamp: i64,
+}
+
+#[derive(Debug)]
+pub struct PExpireAt {
+    key: Vec<u8>,
+    timestamp: i64,
+}
+
+#[derive(Debug)]
+pub struct Ttl {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PTtl {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Persist {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Incr {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Decr {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct IncrBy {
+    key: Vec<u8>,
+    increment: i64,
+}
+
+#[derive(Debug)]
+pub struct DecrBy {
+    key: Vec<u8>,
+    decrement: i64,
+}
+
+#[derive(Debug)]
+pub struct IncrByFloat {
+    key: Vec<u8>,
+    increment: f64,
+}
+
+#[derive(Debug)]
+pub struct MGet {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct MSet {
+    pairs: Vec<(Vec<u8>, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct MSetNx {
+    pairs: Vec<(Vec<u8>, RespFrame)>,
+}
+
+#[derive(Debug)]
+pub struct Append {
+    key: Vec<u8>,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Strlen {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetRange {
+    key: Vec<u8>,
+    start: i64,
+    end: i64,
+}
+
+#[derive(Debug)]
+pub struct SetRange {
+    key: Vec<u8>,
+    offset: usize,
+    value: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetDel {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct GetEx {
+    key: Vec<u8>,
+    expire: Option<ExpireOption>,
+}
+
+#[derive(Debug)]
+pub struct Lcs {
+    key1: Vec<u8>,
+    key2: Vec<u8>,
+    len: bool,
+    idx: bool,
+    min_match_len: usize,
+    with_match_len: bool,
+}
+
+#[derive(Debug)]
+pub struct SetBit {
+    key: Vec<u8>,
+    offset: usize,
+    value: bool,
+}
+
+#[derive(Debug)]
+pub struct GetBit {
+    key: Vec<u8>,
+    offset: usize,
+}
+
+#[derive(Debug)]
+pub struct BitCount {
+    key: Vec<u8>,
+    range: Option<(i64, i64, BitUnit)>,
+}
+
+#[derive(Debug)]
+pub struct BitPos {
+    key: Vec<u8>,
+    bit: bool,
+    start: Option<i64>,
+    end: Option<i64>,
+    unit: BitUnit,
+}
+
+#[derive(Debug)]
+pub struct BitOp {
+    op: BitOperation,
+    dest: Vec<u8>,
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct BitField {
+    key: Vec<u8>,
+    ops: Vec<BitFieldOp>,
+}
+
+#[derive(Debug)]
+pub struct Del {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Unlink {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Exists {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Type {
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Rename {
+    key: Vec<u8>,
+    newkey: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct RenameNx {
+    key: Vec<u8>,
+    newkey: Vec<u8>,
+}
+
+// COPY, named so it does not shadow the Copy marker trait
+#[derive(Debug)]
+pub struct CopyKey {
+    source: Vec<u8>,
+    destination: Vec<u8>,
+    replace: bool,
+}
+
+#[derive(Debug)]
+pub struct Touch {
+    keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct RandomKey;
+
+#[derive(Debug)]
+pub struct Keys {
+    pattern: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Scan {
+    cursor: u64,
+    options: ScanOptions,
+}
+
+#[derive(Debug)]
+pub struct HScan {
+    key: Vec<u8>,
+    cursor: u64,
+    options: ScanOptions,
+}
+
+#[derive(Debug)]
+pub struct SScan {
+    key: Vec<u8>,
+    cursor: u64,
+    options: ScanOptions,
+}
+
+// [MATCH pattern] [COUNT count] [TYPE type], TYPE is only accepted by SCAN
+#[derive(Debug, PartialEq)]
+pub struct ScanOptions {
+    pattern: Option<Vec<u8>>,
+    count: usize,
+    key_type: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Unrecognized;
 
@@ -127,11 +1398,198 @@ impl TryFrom<RespArray> for Command {
                         b"set" => Ok(Set::try_from(v)?.into()),
                         b"hget" => Ok(HGet::try_from(v)?.into()),
                         b"hset" => Ok(HSet::try_from(v)?.into()),
+                        b"hmset" => Ok(HMSet::try_from(v)?.into()),
                         b"hmget" => Ok(HMGet::try_from(v)?.into()),
                         b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
+                        b"hdel" => Ok(HDel::try_from(v)?.into()),
+                        b"hexists" => Ok(HExists::try_from(v)?.into()),
+                        b"hlen" => Ok(HLen::try_from(v)?.into()),
+                        b"hkeys" => Ok(HKeys::try_from(v)?.into()),
+                        b"hvals" => Ok(HVals::try_from(v)?.into()),
+                        b"hsetnx" => Ok(HSetNx::try_from(v)?.into()),
+                        b"hstrlen" => Ok(HStrlen::try_from(v)?.into()),
+                        b"hrandfield" => Ok(HRandField::try_from(v)?.into()),
+                        b"hincrby" => Ok(HIncrBy::try_from(v)?.into()),
+                        b"hincrbyfloat" => Ok(HIncrByFloat::try_from(v)?.into()),
+                        b"hexpire" => Ok(HExpire::try_from(v)?.into()),
+                        b"hpexpire" => Ok(HPExpire::try_from(v)?.into()),
+                        b"hexpireat" => Ok(HExpireAt::try_from(v)?.into()),
+                        b"hpexpireat" => Ok(HPExpireAt::try_from(v)?.into()),
+                        b"httl" => Ok(HTtl::try_from(v)?.into()),
+                        b"hpttl" => Ok(HPTtl::try_from(v)?.into()),
+                        b"hexpiretime" => Ok(HExpireTime::try_from(v)?.into()),
+                        b"hpexpiretime" => Ok(HPExpireTime::try_from(v)?.into()),
+                        b"hpersist" => Ok(HPersist::try_from(v)?.into()),
+                        b"hgetex" => Ok(HGetEx::try_from(v)?.into()),
+                        b"hsetex" => Ok(HSetEx::try_from(v)?.into()),
                         b"echo" => Ok(Echo::try_from(v)?.into()),
+                        b"hello" => Ok(Hello::try_from(v)?.into()),
                         b"sadd" => Ok(Sadd::try_from(v)?.into()),
                         b"sismember" => Ok(Sismember::try_from(v)?.into()),
+                        b"srem" => Ok(SRem::try_from(v)?.into()),
+                        b"smembers" => Ok(SMembers::try_from(v)?.into()),
+                        b"scard" => Ok(SCard::try_from(v)?.into()),
+                        b"smismember" => Ok(SMIsMember::try_from(v)?.into()),
+                        b"spop" => Ok(SPop::try_from(v)?.into()),
+                        b"srandmember" => Ok(SRandMember::try_from(v)?.into()),
+                        b"smove" => Ok(SMove::try_from(v)?.into()),
+                        b"sinter" => Ok(SInter::try_from(v)?.into()),
+                        b"sunion" => Ok(SUnion::try_from(v)?.into()),
+                        b"sdiff" => Ok(SDiff::try_from(v)?.into()),
+                        b"sinterstore" => Ok(SInterStore::try_from(v)?.into()),
+                        b"sunionstore" => Ok(SUnionStore::try_from(v)?.into()),
+                        b"sdiffstore" => Ok(SDiffStore::try_from(v)?.into()),
+                        b"sintercard" => Ok(SInterCard::try_from(v)?.into()),
+                        b"lpush" => Ok(LPush::try_from(v)?.into()),
+                        b"rpush" => Ok(RPush::try_from(v)?.into()),
+                        b"lpushx" => Ok(LPushX::try_from(v)?.into()),
+                        b"rpushx" => Ok(RPushX::try_from(v)?.into()),
+                        b"lpop" => Ok(LPop::try_from(v)?.into()),
+                        b"rpop" => Ok(RPop::try_from(v)?.into()),
+                        b"llen" => Ok(LLen::try_from(v)?.into()),
+                        b"lrange" => Ok(LRange::try_from(v)?.into()),
+                        b"lindex" => Ok(LIndex::try_from(v)?.into()),
+                        b"lset" => Ok(LSet::try_from(v)?.into()),
+                        b"linsert" => Ok(LInsert::try_from(v)?.into()),
+                        b"lrem" => Ok(LRem::try_from(v)?.into()),
+                        b"ltrim" => Ok(LTrim::try_from(v)?.into()),
+                        b"lpos" => Ok(LPos::try_from(v)?.into()),
+                        b"lmove" => Ok(LMove::try_from(v)?.into()),
+                        b"rpoplpush" => Ok(RPopLPush::try_from(v)?.into()),
+                        b"lmpop" => Ok(LMPop::try_from(v)?.into()),
+                        b"blpop" => Ok(BLPop::try_from(v)?.into()),
+                        b"brpop" => Ok(BRPop::try_from(v)?.into()),
+                        b"blmove" => Ok(BLMove::try_from(v)?.into()),
+                        b"blmpop" => Ok(BLMPop::try_from(v)?.into()),
+                        b"zadd" => Ok(ZAdd::try_from(v)?.into()),
+                        b"zrange" => Ok(ZRange::try_from(v)?.into()),
+                        b"zrank" => Ok(ZRank::try_from(v)?.into()),
+                        b"zrevrank" => Ok(ZRevRank::try_from(v)?.into()),
+                        b"zscore" => Ok(ZScore::try_from(v)?.into()),
+                        b"zmscore" => Ok(ZMScore::try_from(v)?.into()),
+                        b"zincrby" => Ok(ZIncrBy::try_from(v)?.into()),
+                        b"zrem" => Ok(ZRem::try_from(v)?.into()),
+                        b"zcard" => Ok(ZCard::try_from(v)?.into()),
+                        b"zcount" => Ok(ZCount::try_from(v)?.into()),
+                        b"zunionstore" => Ok(ZUnionStore::try_from(v)?.into()),
+                        b"zinterstore" => Ok(ZInterStore::try_from(v)?.into()),
+                        b"zdiff" => Ok(ZDiff::try_from(v)?.into()),
+                        b"zdiffstore" => Ok(ZDiffStore::try_from(v)?.into()),
+                        b"zrangestore" => Ok(ZRangeStore::try_from(v)?.into()),
+                        b"zremrangebyrank" => Ok(ZRemRangeByRank::try_from(v)?.into()),
+                        b"zremrangebyscore" => Ok(ZRemRangeByScore::try_from(v)?.into()),
+                        b"zremrangebylex" => Ok(ZRemRangeByLex::try_from(v)?.into()),
+                        b"zpopmin" => Ok(ZPopMin::try_from(v)?.into()),
+                        b"zpopmax" => Ok(ZPopMax::try_from(v)?.into()),
+                        b"zmpop" => Ok(ZMPop::try_from(v)?.into()),
+                        b"bzpopmin" => Ok(BZPopMin::try_from(v)?.into()),
+                        b"bzpopmax" => Ok(BZPopMax::try_from(v)?.into()),
+                        b"bzmpop" => Ok(BZMPop::try_from(v)?.into()),
+                        b"xadd" => Ok(XAdd::try_from(v)?.into()),
+                        b"xrange" => Ok(XRange::try_from(v)?.into()),
+                        b"xrevrange" => Ok(XRevRange::try_from(v)?.into()),
+                        b"xlen" => Ok(XLen::try_from(v)?.into()),
+                        b"xdel" => Ok(XDel::try_from(v)?.into()),
+                        b"xtrim" => Ok(XTrim::try_from(v)?.into()),
+                        b"xread" => Ok(XRead::try_from(v)?.into()),
+                        b"xgroup" => match frames.get(1) {
+                            Some(RespFrame::BulkString(sub)) => {
+                                match sub.as_ref().to_ascii_lowercase().as_slice() {
+                                    b"create" => Ok(XGroupCreate::try_from(v)?.into()),
+                                    b"setid" => Ok(XGroupSetId::try_from(v)?.into()),
+                                    b"destroy" => Ok(XGroupDestroy::try_from(v)?.into()),
+                                    b"createconsumer" => {
+                                        Ok(XGroupCreateConsumer::try_from(v)?.into())
+                                    }
+                                    b"delconsumer" => Ok(XGroupDelConsumer::try_from(v)?.into()),
+                                    _ => Ok(Unrecognized.into()),
+                                }
+                            }
+                            _ => Ok(Unrecognized.into()),
+                        },
+                        b"xreadgroup" => Ok(XReadGroup::try_from(v)?.into()),
+                        b"xack" => Ok(XAck::try_from(v)?.into()),
+                        b"xpending" => Ok(XPending::try_from(v)?.into()),
+                        b"xclaim" => Ok(XClaim::try_from(v)?.into()),
+                        b"xautoclaim" => Ok(XAutoClaim::try_from(v)?.into()),
+                        b"xinfo" => match frames.get(1) {
+                            Some(RespFrame::BulkString(sub)) => {
+                                match sub.as_ref().to_ascii_lowercase().as_slice() {
+                                    b"stream" => Ok(XInfoStream::try_from(v)?.into()),
+                                    b"groups" => Ok(XInfoGroups::try_from(v)?.into()),
+                                    b"consumers" => Ok(XInfoConsumers::try_from(v)?.into()),
+                                    _ => Ok(Unrecognized.into()),
+                                }
+                            }
+                            _ => Ok(Unrecognized.into()),
+                        },
+                        b"pfadd" => Ok(PfAdd::try_from(v)?.into()),
+                        b"pfcount" => Ok(PfCount::try_from(v)?.into()),
+                        b"pfmerge" => Ok(PfMerge::try_from(v)?.into()),
+                        b"geoadd" => Ok(GeoAdd::try_from(v)?.into()),
+                        b"geodist" => Ok(GeoDist::try_from(v)?.into()),
+                        b"geopos" => Ok(GeoPos::try_from(v)?.into()),
+                        b"geohash" => Ok(GeoHash::try_from(v)?.into()),
+                        b"geosearch" => Ok(GeoSearch::try_from(v)?.into()),
+                        b"geosearchstore" => Ok(GeoSearchStore::try_from(v)?.into()),
+                        b"ping" => Ok(Ping::try_from(v)?.into()),
+                        b"subscribe" => Ok(Subscribe::try_from(v)?.into()),
+                        b"unsubscribe" => Ok(Unsubscribe::try_from(v)?.into()),
+                        b"psubscribe" => Ok(PSubscribe::try_from(v)?.into()),
+                        b"punsubscribe" => Ok(PUnsubscribe::try_from(v)?.into()),
+                        b"publish" => Ok(Publish::try_from(v)?.into()),
+                        b"pubsub" => match frames.get(1) {
+                            Some(RespFrame::BulkString(sub)) => {
+                                match sub.as_ref().to_ascii_lowercase().as_slice() {
+                                    b"channels" => Ok(PubSubChannels::try_from(v)?.into()),
+                                    b"numsub" => Ok(PubSubNumSub::try_from(v)?.into()),
+                                    b"numpat" => Ok(PubSubNumPat::try_from(v)?.into()),
+                                    _ => Ok(Unrecognized.into()),
+                                }
+                            }
+                            _ => Ok(Unrecognized.into()),
+                        },
+                        b"expire" => Ok(Expire::try_from(v)?.into()),
+                        b"pexpire" => Ok(PExpire::try_from(v)?.into()),
+                        b"expireat" => Ok(ExpireAt::try_from(v)?.into()),
+                        b"pexpireat" => Ok(PExpireAt::try_from(v)?.into()),
+                        b"ttl" => Ok(Ttl::try_from(v)?.into()),
+                        b"pttl" => Ok(PTtl::try_from(v)?.into()),
+                        b"persist" => Ok(Persist::try_from(v)?.into()),
+                        b"incr" => Ok(Incr::try_from(v)?.into()),
+                        b"decr" => Ok(Decr::try_from(v)?.into()),
+                        b"incrby" => Ok(IncrBy::try_from(v)?.into()),
+                        b"decrby" => Ok(DecrBy::try_from(v)?.into()),
+                        b"incrbyfloat" => Ok(IncrByFloat::try_from(v)?.into()),
+                        b"mget" => Ok(MGet::try_from(v)?.into()),
+                        b"mset" => Ok(MSet::try_from(v)?.into()),
+                        b"msetnx" => Ok(MSetNx::try_from(v)?.into()),
+                        b"append" => Ok(Append::try_from(v)?.into()),
+                        b"strlen" => Ok(Strlen::try_from(v)?.into()),
+                        b"getrange" => Ok(GetRange::try_from(v)?.into()),
+                        b"setrange" => Ok(SetRange::try_from(v)?.into()),
+                        b"getdel" => Ok(GetDel::try_from(v)?.into()),
+                        b"getex" => Ok(GetEx::try_from(v)?.into()),
+                        b"lcs" => Ok(Lcs::try_from(v)?.into()),
+                        b"setbit" => Ok(SetBit::try_from(v)?.into()),
+                        b"getbit" => Ok(GetBit::try_from(v)?.into()),
+                        b"bitcount" => Ok(BitCount::try_from(v)?.into()),
+                        b"bitpos" => Ok(BitPos::try_from(v)?.into()),
+                        b"bitop" => Ok(BitOp::try_from(v)?.into()),
+                        b"bitfield" => Ok(BitField::try_from(v)?.into()),
+                        b"del" => Ok(Del::try_from(v)?.into()),
+                        b"unlink" => Ok(Unlink::try_from(v)?.into()),
+                        b"exists" => Ok(Exists::try_from(v)?.into()),
+                        b"type" => Ok(Type::try_from(v)?.into()),
+                        b"rename" => Ok(Rename::try_from(v)?.into()),
+                        b"renamenx" => Ok(RenameNx::try_from(v)?.into()),
+                        b"copy" => Ok(CopyKey::try_from(v)?.into()),
+                        b"touch" => Ok(Touch::try_from(v)?.into()),
+                        b"randomkey" => Ok(RandomKey::try_from(v)?.into()),
+                        b"keys" => Ok(Keys::try_from(v)?.into()),
+                        b"scan" => Ok(Scan::try_from(v)?.into()),
+                        b"hscan" => Ok(HScan::try_from(v)?.into()),
+                        b"sscan" => Ok(SScan::try_from(v)?.into()),
                         _ => Ok(Unrecognized.into()),
                     }
                 }
@@ -221,6 +1679,289 @@ fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, Comman
     // Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
 
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+// integer arguments (e.g. seconds of EXPIRE) are sent as BulkString
+fn extract_integer(frame: RespFrame) -> Result<i64, CommandError> {
+    match frame {
+        RespFrame::Integer(n) => Ok(n),
+        RespFrame::BulkString(s) => String::from_utf8_lossy(s.as_ref()).parse().map_err(|_| {
+            CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+        }),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not an integer or out of range".to_string(),
+        )),
+    }
+}
+
+// float arguments (e.g. increment of INCRBYFLOAT) are sent as BulkString
+fn extract_float(frame: RespFrame) -> Result<f64, CommandError> {
+    let n = match frame {
+        RespFrame::Integer(n) => Ok(n as f64),
+        RespFrame::Double(n) => Ok(n),
+        RespFrame::BulkString(s) => String::from_utf8_lossy(s.as_ref()).parse::<f64>(),
+        _ => "".parse::<f64>(),
+    };
+    match n {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        )),
+    }
+}
+
+fn extract_key(value: RespArray, name: &'static str) -> Result<Vec<u8>, CommandError> {
+    validate_command(&value, &[name], Some(1))?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    match args.next() {
+        Some(RespFrame::BulkString(key)) => Ok(key.0.expect("Invalid key")),
+        _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    }
+}
+
+// commands taking one or more keys, e.g. DEL key [key ...]
+fn extract_keys(value: RespArray, name: &'static str) -> Result<Vec<Vec<u8>>, CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut keys = vec![];
+    for v in extract_args(value, 1)? {
+        match v {
+            RespFrame::BulkString(key) => {
+                keys.push(key.0.expect("Invalid key"));
+            }
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+    if keys.is_empty() {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at least 1 argument",
+            name
+        )));
+    }
+    Ok(keys)
+}
+
+// numkeys key [key ...], e.g. the keys of SINTERCARD, the arguments after them are left in args
+fn extract_num_keys(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<Vec<Vec<u8>>, CommandError> {
+    let num_keys = match args.next() {
+        Some(frame) => extract_integer(frame)?,
+        None => return Err(syntax_error()),
+    };
+    if num_keys <= 0 {
+        return Err(CommandError::InvalidArgument(
+            "numkeys should be greater than 0".to_string(),
+        ));
+    }
+
+    let mut keys = vec![];
+    for _ in 0..num_keys {
+        match args.next() {
+            Some(RespFrame::BulkString(key)) => keys.push(key.0.expect("Invalid key")),
+            Some(_) => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+            None => {
+                return Err(CommandError::InvalidArgument(
+                    "Number of keys can't be greater than number of args".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(keys)
+}
+
+// name value [name value ...], e.g. the keys of MSET or the fields of HSET
+fn extract_pairs(
+    args: Vec<RespFrame>,
+    name: &str,
+) -> Result<Vec<(Vec<u8>, RespFrame)>, CommandError> {
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(CommandError::InvalidArgument(format!(
+            "wrong number of arguments for '{}' command",
+            name
+        )));
+    }
+
+    let mut pairs = Vec::with_capacity(args.len() / 2);
+    let mut args = args.into_iter();
+    while let (Some(name), Some(value)) = (args.next(), args.next()) {
+        match name {
+            RespFrame::BulkString(name) => pairs.push((name.0.expect("Invalid name"), value)),
+            _ => return Err(CommandError::InvalidArgument("Invalid name".to_string())),
+        }
+    }
+    Ok(pairs)
+}
+
+// cursors are unsigned 64 bit integers, sent as BulkString
+fn extract_cursor(frame: Option<RespFrame>) -> Result<u64, CommandError> {
+    match frame {
+        Some(RespFrame::BulkString(s)) => String::from_utf8_lossy(s.as_ref())
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string())),
+        Some(RespFrame::Integer(n)) if n >= 0 => Ok(n as u64),
+        _ => Err(CommandError::InvalidArgument("invalid cursor".to_string())),
+    }
+}
+
+fn extract_scan_options(
+    mut args: impl Iterator<Item = RespFrame>,
+    with_type: bool,
+) -> Result<ScanOptions, CommandError> {
+    let mut options = ScanOptions {
+        pattern: None,
+        count: 10,
+        key_type: None,
+    };
+    while let Some(option) = args.next() {
+        let option = match option {
+            RespFrame::BulkString(opt) => opt.as_ref().to_ascii_lowercase(),
+            _ => return Err(syntax_error()),
+        };
+        match (option.as_slice(), args.next()) {
+            (b"match", Some(RespFrame::BulkString(pattern))) => {
+                options.pattern = Some(pattern.0.unwrap_or_default());
+            }
+            (b"count", Some(count)) => {
+                options.count = match extract_integer(count)? {
+                    count if count >= 1 => count as usize,
+                    _ => return Err(syntax_error()),
+                };
+            }
+            (b"type", Some(RespFrame::BulkString(ty))) if with_type => {
+                let ty = String::from_utf8(ty.0.unwrap_or_default())?;
+                options.key_type = Some(ty.to_ascii_lowercase());
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok(options)
+}
+
+// SCAN-like replies are the next cursor followed by the page
+fn scan_reply(cursor: u64, items: Vec<RespFrame>) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::from(cursor.to_string()).into(),
+        RespArray::new(items).into(),
+    ])
+    .into()
+}
+
+// EX seconds | PX milliseconds | EXAT unix-time-seconds | PXAT unix-time-milliseconds
+fn extract_expire(
+    option: &[u8],
+    args: &mut impl Iterator<Item = RespFrame>,
+    name: &str,
+) -> Result<ExpireOption, CommandError> {
+    let n = match args.next() {
+        Some(frame) => extract_integer(frame)?,
+        None => return Err(syntax_error()),
+    };
+    let unit = if option.starts_with(b"e") { 1000 } else { 1 };
+    let ms = match n.checked_mul(unit) {
+        Some(ms) if ms > 0 => ms,
+        _ => return Err(expire_time_error(name)),
+    };
+    Ok(if option.ends_with(b"at") {
+        ExpireOption::At(ms)
+    } else {
+        ExpireOption::Ttl(ms)
+    })
+}
+
+impl ExpireOption {
+    // resolve a relative time to live against the current time, None if the deadline overflows
+    fn to_key_expire(&self) -> Option<KeyExpire> {
+        match self {
+            ExpireOption::Ttl(ttl) => now_ms().checked_add(*ttl).map(KeyExpire::At),
+            ExpireOption::At(at) => Some(KeyExpire::At(*at)),
+            ExpireOption::KeepTtl => Some(KeyExpire::Keep),
+            ExpireOption::Persist => Some(KeyExpire::Persist),
+        }
+    }
+}
+
+// a time out of range is rejected alike whether it overflows while parsed or once resolved
+// against the current time
+fn expire_time_error(name: &str) -> CommandError {
+    CommandError::OutOfRange(format!("invalid expire time in '{}' command", name))
+}
+
+fn invalid_expire_time(name: &str) -> RespFrame {
+    SimpleError::new(format!("ERR {}", expire_time_error(name))).into()
+}
+
+fn extract_key_and_integer(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, i64), CommandError> {
+    validate_command(&value, &[name], Some(2))?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(n)) => {
+            Ok((key.0.expect("Invalid key"), extract_integer(n)?))
+        }
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid key or value".to_string(),
+        )),
+    }
+}
+
+// key member [member ...], e.g. SREM or LPUSH
+fn extract_key_and_members(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = match args.next() {
+        Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+        _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    };
+
+    let mut members = vec![];
+    for v in args {
+        match v {
+            RespFrame::BulkString(member) => members.push(member.0.expect("Invalid member")),
+            _ => return Err(CommandError::InvalidArgument("Invalid member".to_string())),
+        }
+    }
+    if members.is_empty() {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at least 2 arguments",
+            name
+        )));
+    }
+    Ok((key, members))
+}
+
+// key [count], e.g. SPOP or LPOP
+fn extract_key_and_count(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Option<i64>), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = match args.next() {
+        Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+        _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+    };
+    let count = args.next().map(extract_integer).transpose()?;
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at most 2 arguments",
+            name
+        )));
+    }
+    Ok((key, count))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,398 @@
+use super::{
+    extract_args, extract_keys, validate_command, Command, CommandError, CommandExecutor,
+    PSubscribe, PUnsubscribe, PubSubChannels, PubSubNumPat, PubSubNumSub, Publish, Session,
+    Subscribe, Unsubscribe,
+};
+use crate::{
+    Backend, BulkString, PubSubMessage, RespArray, RespFrame, RespNull, RespPush, Subscriber,
+};
+
+impl Command {
+    /// The subscribe family replies once per channel or pattern, the other commands are given
+    /// back as they are.
+    // the error is the command itself, moved back once per request
+    #[allow(clippy::result_large_err)]
+    pub fn subscribe_with(
+        self,
+        backend: &Backend,
+        session: &mut Session,
+    ) -> Result<Vec<RespFrame>, Command> {
+        match self {
+            Command::Subscribe(cmd) => Ok(cmd.confirm(backend, session)),
+            Command::Unsubscribe(cmd) => Ok(cmd.confirm(backend, session)),
+            Command::PSubscribe(cmd) => Ok(cmd.confirm(backend, session)),
+            Command::PUnsubscribe(cmd) => Ok(cmd.confirm(backend, session)),
+            cmd => Err(cmd),
+        }
+    }
+}
+
+impl CommandExecutor for Subscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    // executed as a single command, e.g. without a connection to keep the subscriptions of,
+    // the confirmations come as one array
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        RespArray::new(self.confirm(backend, session)).into()
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        RespArray::new(self.confirm(backend, session)).into()
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        RespArray::new(self.confirm(backend, session)).into()
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        RespArray::new(self.confirm(backend, session)).into()
+    }
+}
+
+impl Subscribe {
+    fn confirm(self, backend: &Backend, session: &mut Session) -> Vec<RespFrame> {
+        let subscriber = session.subscriber(backend);
+        self.channels
+            .into_iter()
+            .map(|channel| {
+                subscriber.subscribe(channel.clone());
+                confirmation("subscribe", Some(channel), subscriber)
+            })
+            .collect()
+    }
+}
+
+impl Unsubscribe {
+    fn confirm(self, backend: &Backend, session: &mut Session) -> Vec<RespFrame> {
+        let subscriber = session.subscriber(backend);
+        let channels = match self.channels.is_empty() {
+            true => subscriber.channels(),
+            false => self.channels,
+        };
+        if channels.is_empty() {
+            return vec![confirmation("unsubscribe", None, subscriber)];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                subscriber.unsubscribe(&channel);
+                confirmation("unsubscribe", Some(channel), subscriber)
+            })
+            .collect()
+    }
+}
+
+impl PSubscribe {
+    fn confirm(self, backend: &Backend, session: &mut Session) -> Vec<RespFrame> {
+        let subscriber = session.subscriber(backend);
+        self.patterns
+            .into_iter()
+            .map(|pattern| {
+                subscriber.psubscribe(pattern.clone());
+                confirmation("psubscribe", Some(pattern), subscriber)
+            })
+            .collect()
+    }
+}
+
+impl PUnsubscribe {
+    fn confirm(self, backend: &Backend, session: &mut Session) -> Vec<RespFrame> {
+        let subscriber = session.subscriber(backend);
+        let patterns = match self.patterns.is_empty() {
+            true => subscriber.patterns(),
+            false => self.patterns,
+        };
+        if patterns.is_empty() {
+            return vec![confirmation("punsubscribe", None, subscriber)];
+        }
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                subscriber.punsubscribe(&pattern);
+                confirmation("punsubscribe", Some(pattern), subscriber)
+            })
+            .collect()
+    }
+}
+
+// [kind, channel or pattern, subscriptions left], null when unsubscribing from nothing
+fn confirmation(kind: &str, name: Option<Vec<u8>>, subscriber: &Subscriber) -> RespFrame {
+    let name = match name {
+        Some(name) => BulkString::from(name).into(),
+        None => RespFrame::Null(RespNull),
+    };
+    RespPush::new(vec![
+        BulkString::from(kind).into(),
+        name,
+        RespFrame::Integer(subscriber.count() as i64),
+    ])
+    .into()
+}
+
+/// A published message as sent to the subscriber, a push frame RESP2 clients get as an array.
+pub(crate) fn message_reply(message: PubSubMessage) -> RespFrame {
+    let frames = match message {
+        PubSubMessage::Message { channel, payload } => vec![
+            BulkString::from("message").into(),
+            BulkString::from(channel).into(),
+            BulkString::from(payload).into(),
+        ],
+        PubSubMessage::PMessage {
+            pattern,
+            channel,
+            payload,
+        } => vec![
+            BulkString::from("pmessage").into(),
+            BulkString::from(pattern).into(),
+            BulkString::from(channel).into(),
+            BulkString::from(payload).into(),
+        ],
+    };
+    RespPush::new(frames).into()
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.publish(&self.channel, &self.message))
+    }
+}
+
+impl CommandExecutor for PubSubChannels {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let channels = backend
+            .pubsub_channels(self.pattern.as_deref())
+            .into_iter()
+            .map(|channel| BulkString::from(channel).into())
+            .collect::<Vec<_>>();
+        RespArray::new(channels).into()
+    }
+}
+
+impl CommandExecutor for PubSubNumSub {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // a flat array even in RESP3, in the order of the arguments, repeated ones included
+        let counts = backend.pubsub_numsub(&self.channels);
+        let frames = self
+            .channels
+            .into_iter()
+            .zip(counts)
+            .flat_map(|(channel, count)| {
+                [BulkString::new(channel).into(), RespFrame::Integer(count)]
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(frames).into()
+    }
+}
+
+impl CommandExecutor for PubSubNumPat {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.pubsub_numpat())
+    }
+}
+
+impl TryFrom<RespArray> for Subscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // SUBSCRIBE channel [channel ...]
+        Ok(Subscribe {
+            channels: extract_keys(value, "subscribe")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Unsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // UNSUBSCRIBE [channel [channel ...]], without channels from all of them
+        Ok(Unsubscribe {
+            channels: extract_names(value, "unsubscribe")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PSubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // PSUBSCRIBE pattern [pattern ...]
+        Ok(PSubscribe {
+            patterns: extract_keys(value, "psubscribe")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PUnsubscribe {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // PUNSUBSCRIBE [pattern [pattern ...]], without patterns from all of them
+        Ok(PUnsubscribe {
+            patterns: extract_names(value, "punsubscribe")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for Publish {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"], Some(2))?;
+
+        // PUBLISH channel message
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(channel)), Some(RespFrame::BulkString(message))) => {
+                Ok(Publish {
+                    channel: channel.0.expect("Invalid channel"),
+                    message: message.0.unwrap_or_default(),
+                })
+            }
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid channel or message".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PubSubChannels {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // PUBSUB CHANNELS [pattern]
+        let mut args = extract_names(value, "pubsub channels")?.into_iter();
+        let pattern = args.next();
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument(
+                "pubsub channels command must have at most 1 argument".to_string(),
+            ));
+        }
+        Ok(PubSubChannels { pattern })
+    }
+}
+
+impl TryFrom<RespArray> for PubSubNumSub {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // PUBSUB NUMSUB [channel [channel ...]]
+        Ok(PubSubNumSub {
+            channels: extract_names(value, "pubsub numsub")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for PubSubNumPat {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pubsub", "numpat"], Some(0))?;
+        Ok(PubSubNumPat)
+    }
+}
+
+// channels or patterns after the command name, there may be none
+fn extract_names(value: RespArray, name: &'static str) -> Result<Vec<Vec<u8>>, CommandError> {
+    let words = name.split(' ').collect::<Vec<_>>();
+    validate_command(&value, &words, None)?;
+
+    extract_args(value, words.len())?
+        .into_iter()
+        .map(|frame| match frame {
+            RespFrame::BulkString(name) => Ok(name.0.expect("Invalid channel")),
+            _ => Err(CommandError::InvalidArgument("Invalid channel".to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::Protocol, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_unsubscribe_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*1\r\n$11\r\nunsubscribe\r\n");
+        let result: Unsubscribe = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(result.channels.is_empty());
+
+        buf.extend_from_slice(b"*1\r\n$9\r\nsubscribe\r\n");
+        let result: Result<Subscribe, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*3\r\n$6\r\npubsub\r\n$8\r\nchannels\r\n$6\r\nnews.*\r\n");
+        let result: Command = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(matches!(
+            result,
+            Command::PubSubChannels(PubSubChannels { pattern: Some(p) }) if p == b"news.*"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_confirmations() -> Result<()> {
+        let backend = Backend::new();
+        let mut session = Session::new();
+
+        let cmd = Command::Subscribe(Subscribe {
+            channels: vec![b"a".to_vec(), b"b".to_vec()],
+        });
+        let frames = cmd.subscribe_with(&backend, &mut session).unwrap();
+        let expected: RespFrame = RespPush::new(vec![
+            BulkString::from("subscribe").into(),
+            BulkString::from("b").into(),
+            RespFrame::Integer(2),
+        ])
+        .into();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1], expected);
+        assert_eq!(session.protocol(), Protocol::Resp2);
+        assert_eq!(session.subscriptions(), 2);
+
+        let cmd = PubSubNumSub {
+            channels: vec![b"c".to_vec(), b"a".to_vec(), b"c".to_vec()],
+        };
+        let expected: RespFrame = RespArray::new(vec![
+            BulkString::from("c").into(),
+            RespFrame::Integer(0),
+            BulkString::from("a").into(),
+            RespFrame::Integer(1),
+            BulkString::from("c").into(),
+            RespFrame::Integer(0),
+        ])
+        .into();
+        assert_eq!(cmd.execute(&backend), expected);
+
+        // without channels from all of them, then from nothing
+        let cmd = Command::Unsubscribe(Unsubscribe { channels: vec![] });
+        let frames = cmd.subscribe_with(&backend, &mut session).unwrap();
+        assert_eq!(frames.len(), 2);
+        let cmd = Command::Unsubscribe(Unsubscribe { channels: vec![] });
+        let frames = cmd.subscribe_with(&backend, &mut session).unwrap();
+        let expected: RespFrame = RespPush::new(vec![
+            BulkString::from("unsubscribe").into(),
+            RespFrame::Null(RespNull),
+            RespFrame::Integer(0),
+        ])
+        .into();
+        assert_eq!(frames, vec![expected]);
+        assert_eq!(backend.pubsub_channels(None), Vec::<Vec<u8>>::new());
+        Ok(())
+    }
+}
@@ -1,15 +1,205 @@
-use super::{extract_args, validate_command, CommandExecutor, Sadd, Sismember};
-use crate::{cmd::CommandError, RespArray, RespFrame};
+use super::{
+    extract_args, extract_cursor, extract_integer, extract_key, extract_key_and_count,
+    extract_key_and_members, extract_keys, extract_num_keys, extract_scan_options, scan_reply,
+    syntax_error, validate_command, CommandExecutor, SCard, SDiff, SDiffStore, SInter, SInterCard,
+    SInterStore, SMIsMember, SMembers, SMove, SPop, SRandMember, SRem, SScan, SUnion, SUnionStore,
+    Sadd, Sismember,
+};
+use crate::{
+    cmd::CommandError, Backend, BulkString, RespArray, RespFrame, RespNull, RespSet, SetOperation,
+};
 
 impl CommandExecutor for Sadd {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        RespFrame::Integer(backend.sadd(self.key, self.members))
+        match backend.sadd(self.key, self.members) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
     }
 }
 
 impl CommandExecutor for Sismember {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        RespFrame::Integer(backend.sismember(&self.key, &self.member))
+        match backend.sismember(&self.key, &self.member) {
+            Ok(found) => RespFrame::Integer(found),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SScan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.sscan(
+            &self.key,
+            self.cursor,
+            self.options.count,
+            self.options.pattern.as_deref(),
+        ) {
+            Ok(page) => {
+                let members = page
+                    .items
+                    .into_iter()
+                    .map(|member| BulkString::from(member).into())
+                    .collect();
+                scan_reply(page.cursor, members)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SRem {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.srem(&self.key, &self.members) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// RESP2 connections receive the set as an array
+impl CommandExecutor for SMembers {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.smembers(&self.key) {
+            Ok(members) => RespSet::new(bulk_strings(members)).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.scard(&self.key) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.smismember(&self.key, &self.members) {
+            Ok(found) => {
+                let ret = found
+                    .into_iter()
+                    .map(RespFrame::Integer)
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let members = match backend.spop(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        match self.count {
+            Some(_) => RespSet::new(bulk_strings(members)).into(),
+            None => single_member(members),
+        }
+    }
+}
+
+// with a negative count members may repeat, so the reply is an array even for RESP3
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let members = match backend.srandmember(&self.key, self.count.unwrap_or(1)) {
+            Ok(members) => members,
+            Err(e) => return e.into(),
+        };
+        match self.count {
+            Some(_) => RespArray::new(bulk_strings(members)).into(),
+            None => single_member(members),
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.smove(&self.source, &self.destination, &self.member) {
+            Ok(moved) => RespFrame::Integer(moved as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SInter {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        combine(backend, SetOperation::Inter, &self.keys)
+    }
+}
+
+impl CommandExecutor for SUnion {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        combine(backend, SetOperation::Union, &self.keys)
+    }
+}
+
+impl CommandExecutor for SDiff {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        combine(backend, SetOperation::Diff, &self.keys)
+    }
+}
+
+impl CommandExecutor for SInterStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.scombine_store(SetOperation::Inter, self.destination, &self.keys) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SUnionStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.scombine_store(SetOperation::Union, self.destination, &self.keys) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SDiffStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.scombine_store(SetOperation::Diff, self.destination, &self.keys) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match backend.sintercard(&self.keys, self.limit) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn combine(backend: &Backend, op: SetOperation, keys: &[Vec<u8>]) -> RespFrame {
+    match backend.scombine(op, keys) {
+        Ok(members) => RespSet::new(bulk_strings(members)).into(),
+        Err(e) => e.into(),
+    }
+}
+
+fn bulk_strings(members: Vec<Vec<u8>>) -> Vec<RespFrame> {
+    members
+        .into_iter()
+        .map(|member| BulkString::from(member).into())
+        .collect()
+}
+
+// the reply without a count: the member, or nil if the set does not exist
+fn single_member(members: Vec<Vec<u8>>) -> RespFrame {
+    match members.into_iter().next() {
+        Some(member) => BulkString::from(member).into(),
+        None => RespFrame::Null(RespNull),
     }
 }
 
@@ -21,7 +211,7 @@ impl TryFrom<RespArray> for Sadd {
         let mut args = extract_args(value, 1)?.into_iter();
 
         let key = match args.next() {
-            Some(RespFrame::BulkString(key)) => String::from_utf8(key.0.expect("Invalid key"))?,
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
             _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
         };
 
@@ -29,7 +219,7 @@ impl TryFrom<RespArray> for Sadd {
         for v in args {
             match v {
                 RespFrame::BulkString(member) => {
-                    members.push(String::from_utf8(member.0.expect("Invalid member"))?);
+                    members.push(member.0.expect("Invalid member"));
                 }
                 _ => return Err(CommandError::InvalidArgument("Invalid member".to_string())),
             }
@@ -48,8 +238,8 @@ impl TryFrom<RespArray> for Sismember {
         match (args.next(), args.next()) {
             (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(member))) => {
                 Ok(Sismember {
-                    key: String::from_utf8(key.0.expect("Invalid key"))?,
-                    member: String::from_utf8(member.0.expect("Invalid member"))?,
+                    key: key.0.expect("Invalid key"),
+                    member: member.0.expect("Invalid member"),
                 })
             }
             _ => Err(CommandError::InvalidArgument(
@@ -59,6 +249,202 @@ impl TryFrom<RespArray> for Sismember {
     }
 }
 
+impl TryFrom<RespArray> for SScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sscan"], None)?;
+
+        // SSCAN key cursor [MATCH pattern] [COUNT count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let cursor = extract_cursor(args.next())?;
+        let options = extract_scan_options(args, false)?;
+        Ok(SScan {
+            key,
+            cursor,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = extract_key_and_members(value, "srem")?;
+        Ok(SRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SMembers {
+            key: extract_key(value, "smembers")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SCard {
+            key: extract_key(value, "scard")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = extract_key_and_members(value, "smismember")?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_and_count(value, "spop")?;
+        let count = match count {
+            Some(count) if count < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "value is out of range, must be positive".to_string(),
+                ))
+            }
+            count => count.map(|count| count as usize),
+        };
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_and_count(value, "srandmember")?;
+        Ok(SRandMember { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smove"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(source)),
+                Some(RespFrame::BulkString(destination)),
+                Some(RespFrame::BulkString(member)),
+            ) => Ok(SMove {
+                source: source.0.expect("Invalid key"),
+                destination: destination.0.expect("Invalid key"),
+                member: member.0.expect("Invalid member"),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or member".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for SInter {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SInter {
+            keys: extract_keys(value, "sinter")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SUnion {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SUnion {
+            keys: extract_keys(value, "sunion")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SDiff {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(SDiff {
+            keys: extract_keys(value, "sdiff")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = extract_destination_and_keys(value, "sinterstore")?;
+        Ok(SInterStore { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SUnionStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = extract_destination_and_keys(value, "sunionstore")?;
+        Ok(SUnionStore { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SDiffStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys) = extract_destination_and_keys(value, "sdiffstore")?;
+        Ok(SDiffStore { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sintercard"], None)?;
+
+        // SINTERCARD numkeys key [key ...] [LIMIT limit]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let keys = extract_num_keys(&mut args)?;
+        let limit = match (args.next(), args.next(), args.next()) {
+            (None, _, _) => 0,
+            (Some(RespFrame::BulkString(option)), Some(limit), None)
+                if option.as_ref().eq_ignore_ascii_case(b"limit") =>
+            {
+                let limit = extract_integer(limit)?;
+                if limit < 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "LIMIT can't be negative".to_string(),
+                    ));
+                }
+                limit as usize
+            }
+            _ => return Err(syntax_error()),
+        };
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+// destination key [key ...]
+fn extract_destination_and_keys(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<Vec<u8>>), CommandError> {
+    let mut keys = extract_keys(value, name)?;
+    if keys.len() < 2 {
+        return Err(CommandError::InvalidArgument(format!(
+            "{} command must have at least 2 arguments",
+            name
+        )));
+    }
+    let destination = keys.remove(0);
+    Ok((destination, keys))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,8 +460,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: Sadd = frame.try_into()?;
-        assert_eq!(result.key, "myset");
-        assert_eq!(result.members, vec!["hello".to_owned(), "world".to_owned()]);
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.members, vec![b"hello".to_vec(), b"world".to_vec()]);
 
         Ok(())
     }
@@ -88,8 +474,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: Sismember = frame.try_into()?;
-        assert_eq!(result.key, "myset");
-        assert_eq!(result.member, "hello");
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.member, b"hello");
 
         Ok(())
     }
@@ -98,19 +484,180 @@ mod tests {
     fn test_sadd_sismember_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Sadd {
-            key: "myset".to_string(),
-            members: vec!["hello".to_owned(), "world".to_owned()],
+            key: b"myset".to_vec(),
+            members: vec![b"hello".to_vec(), b"world".to_vec()],
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::Integer(2));
 
         let cmd = Sismember {
-            key: "myset".to_string(),
-            member: "hello".to_string(),
+            key: b"myset".to_vec(),
+            member: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::Integer(1));
 
         Ok(())
     }
+
+    #[test]
+    fn test_sscan_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nsscan\r\n$5\r\nmyset\r\n$1\r\n0\r\n$5\r\nmatch\r\n$2\r\nh*\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: SScan = frame.try_into()?;
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.cursor, 0);
+        assert_eq!(result.options.pattern, Some(b"h*".to_vec()));
+        assert_eq!(result.options.count, 10);
+
+        // TYPE only makes sense for keys
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nsscan\r\n$5\r\nmyset\r\n$1\r\n0\r\n$4\r\ntype\r\n$6\r\nstring\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<SScan, CommandError> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sscan_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd(
+            b"myset".to_vec(),
+            vec![b"hello".to_vec(), b"world".to_vec()],
+        )?;
+
+        let cmd: SScan = RespArray::decode(&mut BytesMut::from(
+            &b"*5\r\n$5\r\nsscan\r\n$5\r\nmyset\r\n$1\r\n0\r\n$5\r\nmatch\r\n$2\r\nh*\r\n"[..],
+        ))?
+        .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(vec![
+                BulkString::from("0").into(),
+                RespArray::new(vec![BulkString::from("hello").into()]).into(),
+            ])
+            .into()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_srandmember_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$4\r\nspop\r\n$3\r\nset\r\n$1\r\n2\r\n");
+        let result: SPop = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.count, Some(2));
+
+        buf.extend_from_slice(b"*3\r\n$4\r\nspop\r\n$3\r\nset\r\n$2\r\n-2\r\n");
+        let result: Result<SPop, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*3\r\n$11\r\nsrandmember\r\n$3\r\nset\r\n$2\r\n-2\r\n");
+        let result: SRandMember = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.count, Some(-2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = Sadd {
+            key: b"set".to_vec(),
+            members: vec![b"a".to_vec(), b"b".to_vec()],
+        };
+        cmd.execute(&backend);
+
+        let cmd = SMembers {
+            key: b"set".to_vec(),
+        };
+        match cmd.execute(&backend) {
+            RespFrame::Set(set) => assert_eq!(set.len(), 2),
+            frame => panic!("unexpected reply {:?}", frame),
+        }
+
+        let cmd = SMIsMember {
+            key: b"set".to_vec(),
+            members: vec![b"a".to_vec(), b"c".to_vec()],
+        };
+        let expected = RespArray::new(vec![RespFrame::Integer(1), RespFrame::Integer(0)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = SMove {
+            source: b"set".to_vec(),
+            destination: b"other".to_vec(),
+            member: b"a".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = SPop {
+            key: b"set".to_vec(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("b").into());
+        let cmd = SCard {
+            key: b"set".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd = SRandMember {
+            key: b"set".to_vec(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd = SRem {
+            key: b"other".to_vec(),
+            members: vec![b"a".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nLIMIT\r\n$1\r\n5\r\n");
+        let result: SInterCard = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(result.limit, 5);
+
+        buf.extend_from_slice(b"*4\r\n$10\r\nsintercard\r\n$1\r\n3\r\n$1\r\na\r\n$1\r\nb\r\n");
+        let result: Result<SInterCard, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_algebra_commands() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd(b"a".to_vec(), vec![b"1".to_vec(), b"2".to_vec()])?;
+        backend.sadd(b"b".to_vec(), vec![b"2".to_vec(), b"3".to_vec()])?;
+
+        let cmd = SInter {
+            keys: vec![b"a".to_vec(), b"b".to_vec()],
+        };
+        let expected = RespSet::new(vec![BulkString::from("2").into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = SUnionStore {
+            destination: b"dst".to_vec(),
+            keys: vec![b"a".to_vec(), b"b".to_vec()],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd = SDiff {
+            keys: vec![b"dst".to_vec(), b"a".to_vec()],
+        };
+        let expected = RespSet::new(vec![BulkString::from("3").into()]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
 }
@@ -0,0 +1,667 @@
+use super::{
+    extract_args, extract_integer, extract_key, extract_pairs, syntax_error, validate_command,
+    Block, CommandError, CommandExecutor, XAdd, XDel, XLen, XRange, XRead, XRevRange, XTrim,
+};
+use crate::{
+    Backend, BulkString, RespArray, RespFrame, RespNull, StreamEntry, StreamFields, StreamId,
+    StreamTrim, TrimStrategy, XAddId,
+};
+use std::{iter::Peekable, time::Duration};
+
+/// Where XREAD starts reading a stream from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XReadId {
+    // entries added after this ID
+    After(StreamId),
+    // $: entries added after the command arrives
+    Last,
+}
+
+impl CommandExecutor for XAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xadd(self.key, self.id, self.fields, self.nomkstream, self.trim) {
+            Ok(Some(id)) => BulkString::from(id.to_string()).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xrange(&self.key, self.start, self.end, self.count, false) {
+            Ok(entries) => entries_reply(entries),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRevRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xrange(&self.key, self.start, self.end, self.count, true) {
+            Ok(entries) => entries_reply(entries),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xlen(&self.key) {
+            Ok(len) => RespFrame::Integer(len),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xdel(&self.key, &self.ids) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xtrim(&self.key, self.trim) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRead {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let streams = resolve_ids(backend, self.streams);
+        read_streams(backend, &streams, self.count).unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl XRead {
+    /// XREAD BLOCK waits for any of its streams to receive entries. `$` is resolved now,
+    /// so it only reads what is added after the command arrived. Reading consumes nothing,
+    /// so every reader blocked on a stream is tried on each XADD.
+    pub(crate) fn into_block(self, backend: &Backend) -> Block {
+        let keys = self.streams.iter().map(|(key, _)| key.clone()).collect();
+        let (streams, count) = (resolve_ids(backend, self.streams), self.count);
+        // like a non-blocking read, the reply holds every stream with new entries
+        let serve = move |backend: &Backend, _: &[u8]| read_streams(backend, &streams, count);
+        Block::new(keys, self.timeout, Box::new(serve))
+    }
+}
+
+fn resolve_ids(backend: &Backend, streams: Vec<(Vec<u8>, XReadId)>) -> Vec<(Vec<u8>, StreamId)> {
+    streams
+        .into_iter()
+        .map(|(key, id)| {
+            let id = match id {
+                XReadId::After(id) => id,
+                // a key of another type replies WRONGTYPE once read
+                XReadId::Last => backend.xlast_id(&key).unwrap_or(StreamId::MIN),
+            };
+            (key, id)
+        })
+        .collect()
+}
+
+// the streams with entries after their ID with those entries, None if there are none
+fn read_streams(
+    backend: &Backend,
+    streams: &[(Vec<u8>, StreamId)],
+    count: usize,
+) -> Option<RespFrame> {
+    let mut frames = vec![];
+    for (key, after) in streams {
+        match backend.xread(key, *after, count) {
+            Ok(entries) if entries.is_empty() => {}
+            Ok(entries) => frames.push(
+                RespArray::new(vec![
+                    BulkString::from(key.clone()).into(),
+                    entries_reply(entries),
+                ])
+                .into(),
+            ),
+            Err(e) => return Some(e.into()),
+        }
+    }
+    if frames.is_empty() {
+        return None;
+    }
+    Some(RespArray::new(frames).into())
+}
+
+// an entry is its ID and a flat array of its fields and values
+pub(crate) fn entry_reply((id, fields): StreamEntry) -> RespFrame {
+    let fields = fields
+        .into_iter()
+        .flat_map(|(field, value)| {
+            [
+                BulkString::from(field).into(),
+                BulkString::from(value).into(),
+            ]
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(vec![
+        BulkString::from(id.to_string()).into(),
+        RespArray::new(fields).into(),
+    ])
+    .into()
+}
+
+pub(crate) fn entries_reply(entries: Vec<StreamEntry>) -> RespFrame {
+    let entries = entries.into_iter().map(entry_reply).collect::<Vec<_>>();
+    RespArray::new(entries).into()
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xadd"], None)?;
+
+        // XADD key [NOMKSTREAM] [<MAXLEN | MINID> [= | ~] threshold [LIMIT count]]
+        //      <* | id> field value [field value ...]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        let (mut nomkstream, mut trim) = (false, None);
+        let id = loop {
+            let arg = match args.next() {
+                Some(RespFrame::BulkString(arg)) => arg.0.expect("Invalid argument"),
+                _ => return Err(syntax_error()),
+            };
+            match arg.to_ascii_lowercase().as_slice() {
+                b"nomkstream" => nomkstream = true,
+                b"maxlen" => trim = Some(extract_trim(false, &mut args)?),
+                b"minid" => trim = Some(extract_trim(true, &mut args)?),
+                b"*" => break XAddId::Auto,
+                _ => break parse_xadd_id(&arg)?,
+            }
+        };
+
+        let fields = extract_pairs(args.collect(), "xadd")?
+            .into_iter()
+            .map(|(field, value)| match value {
+                RespFrame::BulkString(value) => Ok((field, value.0.expect("Invalid value"))),
+                _ => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+            })
+            .collect::<Result<StreamFields, CommandError>>()?;
+        Ok(XAdd {
+            key,
+            id,
+            fields,
+            nomkstream,
+            trim,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // XRANGE key start end [COUNT count]
+        let (key, start, end, count) = extract_range_args(value, "xrange")?;
+        Ok(XRange {
+            key,
+            start: extract_range_start(start)?,
+            end: extract_range_end(end)?,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRevRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // XREVRANGE key end start [COUNT count]
+        let (key, end, start, count) = extract_range_args(value, "xrevrange")?;
+        Ok(XRevRange {
+            key,
+            start: extract_range_start(start)?,
+            end: extract_range_end(end)?,
+            count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(XLen {
+            key: extract_key(value, "xlen")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xdel"], None)?;
+
+        // XDEL key id [id ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let ids = args
+            .map(extract_stream_id)
+            .collect::<Result<Vec<StreamId>, CommandError>>()?;
+        if ids.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'xdel' command".to_string(),
+            ));
+        }
+        Ok(XDel { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xtrim"], None)?;
+
+        // XTRIM key <MAXLEN | MINID> [= | ~] threshold [LIMIT count]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+        let trim = match args.next() {
+            Some(RespFrame::BulkString(s)) => match s.as_ref().to_ascii_lowercase().as_slice() {
+                b"maxlen" => extract_trim(false, &mut args)?,
+                b"minid" => extract_trim(true, &mut args)?,
+                _ => return Err(syntax_error()),
+            },
+            _ => return Err(syntax_error()),
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XTrim { key, trim })
+    }
+}
+
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xread"], None)?;
+
+        // XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (mut count, mut block, mut timeout) = (usize::MAX, false, None);
+        loop {
+            let option = match args.next() {
+                Some(RespFrame::BulkString(option)) => option.as_ref().to_ascii_lowercase(),
+                _ => return Err(syntax_error()),
+            };
+            match (option.as_slice(), args.next()) {
+                // a count of 0 or less reads everything
+                (b"count", Some(n)) => count = usize::try_from(extract_integer(n)?).unwrap_or(0),
+                (b"block", Some(ms)) => (block, timeout) = (true, extract_block_timeout(ms)?),
+                (b"streams", Some(first)) => {
+                    let streams = extract_streams(first, args)?;
+                    let count = if count == 0 { usize::MAX } else { count };
+                    return Ok(XRead {
+                        streams,
+                        count,
+                        block,
+                        timeout,
+                    });
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+    }
+}
+
+// key [key ...] id [id ...], the first key already taken
+fn extract_streams(
+    first: RespFrame,
+    args: impl Iterator<Item = RespFrame>,
+) -> Result<Vec<(Vec<u8>, XReadId)>, CommandError> {
+    let mut args = std::iter::once(first).chain(args).collect::<Vec<_>>();
+    if !args.len().is_multiple_of(2) {
+        return Err(CommandError::InvalidArgument(
+            "Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be \
+             specified."
+                .to_string(),
+        ));
+    }
+    let ids = args.split_off(args.len() / 2);
+    args.into_iter()
+        .zip(ids)
+        .map(|(key, id)| {
+            let key = match key {
+                RespFrame::BulkString(key) => key.0.expect("Invalid key"),
+                _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+            };
+            let id = match id {
+                RespFrame::BulkString(s) if s.as_ref() == b"$" => XReadId::Last,
+                id => XReadId::After(extract_stream_id(id)?),
+            };
+            Ok((key, id))
+        })
+        .collect()
+}
+
+// milliseconds, 0 blocks forever
+pub(crate) fn extract_block_timeout(frame: RespFrame) -> Result<Option<Duration>, CommandError> {
+    let ms = extract_integer(frame).map_err(|_| {
+        CommandError::InvalidArgument("timeout is not an integer or out of range".to_string())
+    })?;
+    match ms {
+        ms if ms < 0 => Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        )),
+        0 => Ok(None),
+        ms => Ok(Some(Duration::from_millis(ms as u64))),
+    }
+}
+
+// key start end [COUNT count], start and end as given
+fn extract_range_args(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, RespFrame, RespFrame, usize), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let (key, start, end) = match (args.next(), args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(start), Some(end)) => {
+            (key.0.expect("Invalid key"), start, end)
+        }
+        _ => return Err(syntax_error()),
+    };
+    let count = match (args.next(), args.next(), args.next()) {
+        (None, _, _) => usize::MAX,
+        (Some(RespFrame::BulkString(option)), Some(count), None)
+            if option.as_ref().eq_ignore_ascii_case(b"count") =>
+        {
+            // a negative count returns nothing
+            usize::try_from(extract_integer(count)?).unwrap_or(0)
+        }
+        _ => return Err(syntax_error()),
+    };
+    Ok((key, start, end, count))
+}
+
+// [= | ~] threshold [LIMIT count], after MAXLEN or MINID
+fn extract_trim(
+    min_id: bool,
+    args: &mut Peekable<impl Iterator<Item = RespFrame>>,
+) -> Result<StreamTrim, CommandError> {
+    let approximate = match args.peek() {
+        Some(RespFrame::BulkString(s)) if s.as_ref() == b"~" => true,
+        Some(RespFrame::BulkString(s)) if s.as_ref() == b"=" => false,
+        _ => {
+            let threshold = args.next().ok_or_else(syntax_error)?;
+            return trim_with(min_id, threshold, false, None);
+        }
+    };
+    args.next();
+    let threshold = args.next().ok_or_else(syntax_error)?;
+
+    let limit = match args.peek() {
+        Some(RespFrame::BulkString(s)) if s.as_ref().eq_ignore_ascii_case(b"limit") => {
+            args.next();
+            let limit = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+            if limit < 0 {
+                return Err(CommandError::InvalidArgument(
+                    "The LIMIT argument must be >= 0.".to_string(),
+                ));
+            }
+            Some(limit as usize)
+        }
+        _ => None,
+    };
+    trim_with(min_id, threshold, approximate, limit)
+}
+
+fn trim_with(
+    min_id: bool,
+    threshold: RespFrame,
+    approximate: bool,
+    limit: Option<usize>,
+) -> Result<StreamTrim, CommandError> {
+    if limit.is_some() && !approximate {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+        ));
+    }
+    let strategy = if min_id {
+        TrimStrategy::MinId(extract_stream_id(threshold)?)
+    } else {
+        match extract_integer(threshold)? {
+            len if len < 0 => {
+                return Err(CommandError::InvalidArgument(
+                    "The MAXLEN argument must be >= 0.".to_string(),
+                ))
+            }
+            len => TrimStrategy::MaxLen(len as usize),
+        }
+    };
+    Ok(StreamTrim {
+        strategy,
+        approximate,
+        // LIMIT 0 lifts the limit
+        limit: limit.filter(|limit| *limit > 0),
+    })
+}
+
+// ms-seq, or ms alone for ms-0
+pub(crate) fn extract_stream_id(frame: RespFrame) -> Result<StreamId, CommandError> {
+    match frame {
+        RespFrame::BulkString(s) => parse_stream_id(s.as_ref(), 0),
+        _ => Err(invalid_stream_id()),
+    }
+}
+
+// - and + are the first and last possible IDs, ( excludes the bound
+pub(crate) fn extract_range_start(frame: RespFrame) -> Result<StreamId, CommandError> {
+    let s = match &frame {
+        RespFrame::BulkString(s) => s.as_ref(),
+        _ => return Err(invalid_stream_id()),
+    };
+    match s {
+        b"-" => Ok(StreamId::MIN),
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_stream_id(id, 0)?.next().ok_or_else(|| {
+            CommandError::InvalidArgument("invalid start ID for the interval".to_string())
+        }),
+        id => parse_stream_id(id, 0),
+    }
+}
+
+pub(crate) fn extract_range_end(frame: RespFrame) -> Result<StreamId, CommandError> {
+    let s = match &frame {
+        RespFrame::BulkString(s) => s.as_ref(),
+        _ => return Err(invalid_stream_id()),
+    };
+    match s {
+        b"-" => Ok(StreamId::MIN),
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_stream_id(id, u64::MAX)?.prev().ok_or_else(|| {
+            CommandError::InvalidArgument("invalid end ID for the interval".to_string())
+        }),
+        id => parse_stream_id(id, u64::MAX),
+    }
+}
+
+// XADD also takes ms-* for the next sequence number within ms
+fn parse_xadd_id(s: &[u8]) -> Result<XAddId, CommandError> {
+    match s.strip_suffix(b"-*") {
+        Some(ms) => parse_u64(ms).map(XAddId::AutoSeq),
+        None => parse_stream_id(s, 0).map(XAddId::Explicit),
+    }
+}
+
+// a missing sequence number is `seq`
+fn parse_stream_id(s: &[u8], seq: u64) -> Result<StreamId, CommandError> {
+    match s.iter().position(|c| *c == b'-') {
+        Some(i) => Ok(StreamId::new(parse_u64(&s[..i])?, parse_u64(&s[i + 1..])?)),
+        None => Ok(StreamId::new(parse_u64(s)?, seq)),
+    }
+}
+
+fn parse_u64(s: &[u8]) -> Result<u64, CommandError> {
+    std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid_stream_id)
+}
+
+fn invalid_stream_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_xadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*10\r\n$4\r\nxadd\r\n$1\r\ns\r\n$10\r\nNOMKSTREAM\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$4\r\n1000\r\n$3\r\n5-*\r\n$1\r\nf\r\n$1\r\nv\r\n$1\r\ng\r\n");
+        let result: Result<XAdd, _> = RespArray::decode(&mut buf)?.try_into();
+        // g has no value
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*10\r\n$4\r\nxadd\r\n$1\r\ns\r\n$5\r\nMINID\r\n$1\r\n~\r\n$3\r\n7-1\r\n$5\r\nLIMIT\r\n$2\r\n10\r\n$1\r\n*\r\n$1\r\nf\r\n$1\r\nv\r\n");
+        let result: XAdd = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.id, XAddId::Auto);
+        assert_eq!(result.fields, vec![(b"f".to_vec(), b"v".to_vec())]);
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MinId(StreamId::new(7, 1)),
+            approximate: true,
+            limit: Some(10),
+        };
+        assert_eq!(result.trim, Some(trim));
+
+        buf.extend_from_slice(b"*9\r\n$4\r\nxadd\r\n$1\r\ns\r\n$6\r\nMAXLEN\r\n$1\r\n5\r\n$5\r\nLIMIT\r\n$2\r\n10\r\n$1\r\n*\r\n$1\r\nf\r\n$1\r\nv\r\n");
+        let result: Result<XAdd, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xrange_bounds() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$9\r\nxrevrange\r\n$1\r\ns\r\n$3\r\n(10\r\n$1\r\n-\r\n$5\r\nCOUNT\r\n$1\r\n2\r\n",
+        );
+        let result: XRevRange = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.start, StreamId::MIN);
+        // a missing sequence number of an end is the last one, excluded
+        assert_eq!(result.end, StreamId::new(10, u64::MAX - 1));
+        assert_eq!(result.count, 2);
+
+        buf.extend_from_slice(b"*4\r\n$6\r\nxrange\r\n$1\r\ns\r\n$5\r\n(5-1a\r\n$1\r\n+\r\n");
+        let result: Result<XRange, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xread_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$5\r\nxread\r\n$5\r\nBLOCK\r\n$1\r\n0\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n$\r\n$3\r\n1-1\r\n");
+        let result: XRead = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(result.block);
+        assert_eq!(result.timeout, None);
+        assert_eq!(
+            result.streams,
+            vec![
+                (b"a".to_vec(), XReadId::Last),
+                (b"b".to_vec(), XReadId::After(StreamId::new(1, 1)))
+            ]
+        );
+
+        buf.extend_from_slice(
+            b"*5\r\n$5\r\nxread\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n$\r\n",
+        );
+        let result: Result<XRead, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xread_block_waits_for_xadd() -> Result<()> {
+        let backend = Backend::new();
+        let entry = vec![(b"f".to_vec(), b"v".to_vec())];
+        let old = XAddId::Explicit(StreamId::new(1, 0));
+        backend.xadd(b"s".to_vec(), old, entry.clone(), false, None)?;
+
+        let cmd = XRead {
+            streams: vec![(b"s".to_vec(), XReadId::Last)],
+            count: usize::MAX,
+            block: true,
+            timeout: None,
+        };
+        let block = cmd.into_block(&backend);
+        let blocked = tokio::spawn({
+            let backend = backend.clone();
+            async move { block.wait(&backend).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let new = XAddId::Explicit(StreamId::new(2, 0));
+        backend.xadd(b"s".to_vec(), new, entry.clone(), false, None)?;
+
+        // only the entry added after the command arrived
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("s").into(),
+            entries_reply(vec![(StreamId::new(2, 0), entry)]),
+        ])
+        .into()]);
+        assert_eq!(blocked.await?, expected.into());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xread_block_wakes_readers_behind_an_unserved_one() -> Result<()> {
+        let backend = Backend::new();
+        let mut clients = vec![];
+        let ids = [
+            XReadId::After(StreamId::new(99999999999999, 0)),
+            XReadId::Last,
+        ];
+        for id in ids {
+            let cmd = XRead {
+                streams: vec![(b"s".to_vec(), id)],
+                count: usize::MAX,
+                block: true,
+                timeout: Some(Duration::from_millis(200)),
+            };
+            let block = cmd.into_block(&backend);
+            clients.push(tokio::spawn({
+                let backend = backend.clone();
+                async move { block.wait(&backend).await }
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let entry = vec![(b"f".to_vec(), b"v".to_vec())];
+        let id = XAddId::Explicit(StreamId::new(1, 0));
+        backend.xadd(b"s".to_vec(), id, entry.clone(), false, None)?;
+
+        // the first reader waits for a later ID, the second one still reads the new entry
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("s").into(),
+            entries_reply(vec![(StreamId::new(1, 0), entry)]),
+        ])
+        .into()]);
+        let mut clients = clients.into_iter();
+        let (first, second) = (clients.next().unwrap(), clients.next().unwrap());
+        assert_eq!(second.await?, expected.into());
+        assert_eq!(first.await?, RespArray::new(None).into());
+        Ok(())
+    }
+}
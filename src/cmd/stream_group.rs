@@ -0,0 +1,1181 @@
+use super::{
+    extract_args, extract_integer, stream::entries_reply, stream::entry_reply,
+    stream::extract_block_timeout, stream::extract_range_end, stream::extract_range_start,
+    stream::extract_stream_id, syntax_error, validate_command, Block, CommandError,
+    CommandExecutor, XAck, XAutoClaim, XClaim, XGroupCreate, XGroupCreateConsumer,
+    XGroupDelConsumer, XGroupDestroy, XGroupSetId, XInfoConsumers, XInfoGroups, XInfoStream,
+    XPending, XReadGroup, RESP_OK,
+};
+use crate::{
+    backend::now_ms, Backend, BulkString, ClaimOptions, GroupEntry, RespArray, RespFrame, RespMap,
+    RespNull, StreamEntry, StreamId,
+};
+
+impl CommandExecutor for XGroupCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_create(
+            self.key,
+            self.group,
+            self.id,
+            self.mkstream,
+            self.entries_read,
+        ) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupSetId {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_setid(&self.key, &self.group, self.id, self.entries_read) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupDestroy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_destroy(&self.key, &self.group) {
+            Ok(destroyed) => RespFrame::Integer(destroyed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupCreateConsumer {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_createconsumer(&self.key, &self.group, &self.consumer) {
+            Ok(created) => RespFrame::Integer(created as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupDelConsumer {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_delconsumer(&self.key, &self.group, &self.consumer) {
+            Ok(pending) => RespFrame::Integer(pending),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XReadGroup {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        read_group(backend, &self.reader()).unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl XReadGroup {
+    /// Only reads of new entries (`>`) wait, reading the history of a consumer never blocks.
+    pub(crate) fn blocks(&self) -> bool {
+        self.block && self.streams.iter().all(|(_, id)| id.is_none())
+    }
+
+    /// XREADGROUP BLOCK waits for any of its streams to receive entries never delivered to
+    /// the group.
+    pub(crate) fn into_block(self) -> Block {
+        let keys = self.streams.iter().map(|(key, _)| key.clone()).collect();
+        let timeout = self.timeout;
+        let reader = self.reader();
+        let serve = move |backend: &Backend, _: &[u8]| read_group(backend, &reader);
+        Block::new(keys, timeout, Box::new(serve))
+    }
+
+    fn reader(self) -> GroupReader {
+        GroupReader {
+            group: self.group,
+            consumer: self.consumer,
+            streams: self.streams,
+            count: self.count,
+            noack: self.noack,
+        }
+    }
+}
+
+// key, group and ID / consumer / min-idle-time of the XGROUP and XCLAIM arguments
+type GroupIdArgs = (Vec<u8>, Vec<u8>, Option<StreamId>);
+type ConsumerArgs = (Vec<u8>, Vec<u8>, Vec<u8>);
+type ClaimArgs = (Vec<u8>, Vec<u8>, Vec<u8>, i64);
+
+// what XREADGROUP reads, kept by a blocked client until it is served
+struct GroupReader {
+    group: Vec<u8>,
+    consumer: Vec<u8>,
+    streams: Vec<(Vec<u8>, Option<StreamId>)>,
+    count: usize,
+    noack: bool,
+}
+
+// like XREAD the streams with new entries, but the history of a stream is always part of the
+// reply, None if there is nothing to reply
+fn read_group(backend: &Backend, reader: &GroupReader) -> Option<RespFrame> {
+    let mut frames = vec![];
+    for (key, after) in &reader.streams {
+        let entries = backend.xreadgroup(
+            key,
+            &reader.group,
+            &reader.consumer,
+            *after,
+            reader.count,
+            reader.noack,
+        );
+        match entries {
+            Ok(entries) if entries.is_empty() && after.is_none() => {}
+            Ok(entries) => frames.push(
+                RespArray::new(vec![
+                    BulkString::from(key.clone()).into(),
+                    group_entries_reply(entries),
+                ])
+                .into(),
+            ),
+            Err(e) => return Some(e.into()),
+        }
+    }
+    if frames.is_empty() {
+        return None;
+    }
+    Some(RespArray::new(frames).into())
+}
+
+// an entry deleted since it was delivered has no fields
+fn group_entries_reply(entries: Vec<GroupEntry>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| match fields {
+            Some(fields) => entry_reply((id, fields)),
+            None => RespArray::new(vec![
+                BulkString::from(id.to_string()).into(),
+                RespFrame::Null(RespNull),
+            ])
+            .into(),
+        })
+        .collect::<Vec<_>>();
+    RespArray::new(entries).into()
+}
+
+impl CommandExecutor for XAck {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xack(&self.key, &self.group, &self.ids) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XPending {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (start, end, count) = match self.range {
+            Some(range) => range,
+            None => return pending_summary(backend, &self.key, &self.group),
+        };
+        let pending = backend.xpending(
+            &self.key,
+            &self.group,
+            start,
+            end,
+            count,
+            self.min_idle,
+            self.consumer.as_deref(),
+        );
+        match pending {
+            Ok(pending) => {
+                let frames = pending
+                    .into_iter()
+                    .map(|info| {
+                        RespArray::new(vec![
+                            BulkString::from(info.id.to_string()).into(),
+                            BulkString::from(info.consumer).into(),
+                            RespFrame::Integer(info.idle),
+                            RespFrame::Integer(info.deliveries as i64),
+                        ])
+                        .into()
+                    })
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// [count, lowest ID, highest ID, [[consumer, count] ...]], the counts of consumers as strings
+fn pending_summary(backend: &Backend, key: &[u8], group: &[u8]) -> RespFrame {
+    let summary = match backend.xpending_summary(key, group) {
+        Ok(summary) => summary,
+        Err(e) => return e.into(),
+    };
+    let (min, max, consumers) = match summary.range {
+        Some((min, max)) => {
+            let consumers = summary
+                .consumers
+                .into_iter()
+                .map(|(name, count)| {
+                    RespArray::new(vec![
+                        BulkString::from(name).into(),
+                        BulkString::from(count.to_string()).into(),
+                    ])
+                    .into()
+                })
+                .collect::<Vec<_>>();
+            (
+                BulkString::from(min.to_string()).into(),
+                BulkString::from(max.to_string()).into(),
+                RespArray::new(consumers).into(),
+            )
+        }
+        None => (
+            RespFrame::Null(RespNull),
+            RespFrame::Null(RespNull),
+            RespFrame::Null(RespNull),
+        ),
+    };
+    RespArray::new(vec![
+        RespFrame::Integer(summary.count as i64),
+        min,
+        max,
+        consumers,
+    ])
+    .into()
+}
+
+impl CommandExecutor for XClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let just_id = self.options.just_id;
+        match backend.xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            self.options,
+        ) {
+            Ok(claimed) if just_id => ids_reply(claimed.into_iter().map(|(id, _)| id)),
+            Ok(claimed) => entries_reply(claimed),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XAutoClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let result = backend.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.just_id,
+        );
+        match result {
+            Ok(result) => {
+                let claimed = match self.just_id {
+                    true => ids_reply(result.claimed.into_iter().map(|(id, _)| id)),
+                    false => entries_reply(result.claimed),
+                };
+                RespArray::new(vec![
+                    BulkString::from(result.next.to_string()).into(),
+                    claimed,
+                    ids_reply(result.deleted.into_iter()),
+                ])
+                .into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn ids_reply(ids: impl Iterator<Item = StreamId>) -> RespFrame {
+    let ids = ids
+        .map(|id| BulkString::from(id.to_string()).into())
+        .collect::<Vec<_>>();
+    RespArray::new(ids).into()
+}
+
+// XINFO replies are maps, flattened to arrays for RESP2 clients
+impl CommandExecutor for XInfoStream {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.xinfo_stream(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let mut map = RespMap::new();
+        map.insert("length".to_string(), integer(info.length as u64));
+        map.insert(
+            "last-generated-id".to_string(),
+            BulkString::from(info.last_generated_id.to_string()).into(),
+        );
+        map.insert("entries-added".to_string(), integer(info.entries_added));
+        map.insert("groups".to_string(), integer(info.groups as u64));
+        map.insert("first-entry".to_string(), optional_entry(info.first_entry));
+        map.insert("last-entry".to_string(), optional_entry(info.last_entry));
+        map.into()
+    }
+}
+
+impl CommandExecutor for XInfoGroups {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let groups = match backend.xinfo_groups(&self.key) {
+            Ok(groups) => groups,
+            Err(e) => return e.into(),
+        };
+        let frames = groups
+            .into_iter()
+            .map(|group| {
+                let mut map = RespMap::new();
+                map.insert("name".to_string(), BulkString::from(group.name).into());
+                map.insert("consumers".to_string(), integer(group.consumers as u64));
+                map.insert("pending".to_string(), integer(group.pending as u64));
+                map.insert(
+                    "last-delivered-id".to_string(),
+                    BulkString::from(group.last_delivered_id.to_string()).into(),
+                );
+                map.insert("entries-read".to_string(), optional(group.entries_read));
+                map.insert("lag".to_string(), optional(group.lag));
+                map.into()
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(frames).into()
+    }
+}
+
+impl CommandExecutor for XInfoConsumers {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let consumers = match backend.xinfo_consumers(&self.key, &self.group) {
+            Ok(consumers) => consumers,
+            Err(e) => return e.into(),
+        };
+        let frames = consumers
+            .into_iter()
+            .map(|consumer| {
+                let mut map = RespMap::new();
+                map.insert("name".to_string(), BulkString::from(consumer.name).into());
+                map.insert("pending".to_string(), integer(consumer.pending as u64));
+                map.insert("idle".to_string(), RespFrame::Integer(consumer.idle));
+                // -1 for a consumer that never read nor claimed anything
+                map.insert(
+                    "inactive".to_string(),
+                    RespFrame::Integer(consumer.inactive.unwrap_or(-1)),
+                );
+                map.into()
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(frames).into()
+    }
+}
+
+fn integer(n: u64) -> RespFrame {
+    RespFrame::Integer(n as i64)
+}
+
+fn optional(n: Option<u64>) -> RespFrame {
+    n.map(integer).unwrap_or(RespFrame::Null(RespNull))
+}
+
+fn optional_entry(entry: Option<StreamEntry>) -> RespFrame {
+    entry.map(entry_reply).unwrap_or(RespFrame::Null(RespNull))
+}
+
+impl TryFrom<RespArray> for XGroupCreate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup", "create"], None)?;
+
+        // XGROUP CREATE key group <id | $> [MKSTREAM] [ENTRIESREAD entries-read]
+        let mut args = extract_args(value, 2)?.into_iter();
+        let (key, group, id) = extract_group_id(&mut args)?;
+        let (mut mkstream, mut entries_read) = (false, None);
+        while let Some(option) = args.next() {
+            match extract_bulk(option)?.to_ascii_lowercase().as_slice() {
+                b"mkstream" => mkstream = true,
+                b"entriesread" => entries_read = Some(extract_entries_read(args.next())?),
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(XGroupCreate {
+            key,
+            group,
+            id,
+            mkstream,
+            entries_read,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroupSetId {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup", "setid"], None)?;
+
+        // XGROUP SETID key group <id | $> [ENTRIESREAD entries-read]
+        let mut args = extract_args(value, 2)?.into_iter();
+        let (key, group, id) = extract_group_id(&mut args)?;
+        let entries_read = match (args.next(), args.next(), args.next()) {
+            (None, _, _) => None,
+            (Some(RespFrame::BulkString(option)), n, None)
+                if option.as_ref().eq_ignore_ascii_case(b"entriesread") =>
+            {
+                Some(extract_entries_read(n)?)
+            }
+            _ => return Err(syntax_error()),
+        };
+        Ok(XGroupSetId {
+            key,
+            group,
+            id,
+            entries_read,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroupDestroy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup", "destroy"], Some(2))?;
+
+        // XGROUP DESTROY key group
+        let mut args = extract_args(value, 2)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(group)) => Ok(XGroupDestroy {
+                key: extract_bulk(key)?,
+                group: extract_bulk(group)?,
+            }),
+            _ => Err(syntax_error()),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for XGroupCreateConsumer {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // XGROUP CREATECONSUMER key group consumer
+        let (key, group, consumer) = extract_consumer_args(value, "createconsumer")?;
+        Ok(XGroupCreateConsumer {
+            key,
+            group,
+            consumer,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroupDelConsumer {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        // XGROUP DELCONSUMER key group consumer
+        let (key, group, consumer) = extract_consumer_args(value, "delconsumer")?;
+        Ok(XGroupDelConsumer {
+            key,
+            group,
+            consumer,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xreadgroup"], None)?;
+
+        // XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK]
+        //            STREAMS key [key ...] id [id ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (group, consumer) = match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(option)), Some(group), Some(consumer))
+                if option.as_ref().eq_ignore_ascii_case(b"group") =>
+            {
+                (extract_bulk(group)?, extract_bulk(consumer)?)
+            }
+            _ => return Err(syntax_error()),
+        };
+
+        let (mut count, mut block, mut timeout, mut noack) = (usize::MAX, false, None, false);
+        loop {
+            let option = match args.next() {
+                Some(option) => extract_bulk(option)?.to_ascii_lowercase(),
+                None => return Err(syntax_error()),
+            };
+            match option.as_slice() {
+                // a count of 0 or less reads everything
+                b"count" => {
+                    let n = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+                    count = usize::try_from(n)
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .unwrap_or(usize::MAX);
+                }
+                b"block" => {
+                    timeout = extract_block_timeout(args.next().ok_or_else(syntax_error)?)?;
+                    block = true;
+                }
+                b"noack" => noack = true,
+                b"streams" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let mut args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must \
+                 be specified."
+                    .to_string(),
+            ));
+        }
+        let ids = args.split_off(args.len() / 2);
+        let streams = args
+            .into_iter()
+            .zip(ids)
+            .map(|(key, id)| {
+                let id = match id {
+                    RespFrame::BulkString(s) if s.as_ref() == b">" => None,
+                    id => Some(extract_stream_id(id)?),
+                };
+                Ok((extract_bulk(key)?, id))
+            })
+            .collect::<Result<Vec<_>, CommandError>>()?;
+        Ok(XReadGroup {
+            group,
+            consumer,
+            streams,
+            count,
+            block,
+            timeout,
+            noack,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xack"], None)?;
+
+        // XACK key group id [id ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (key, group) = match (args.next(), args.next()) {
+            (Some(key), Some(group)) => (extract_bulk(key)?, extract_bulk(group)?),
+            _ => return Err(wrong_arity("xack")),
+        };
+        let ids = args
+            .map(extract_stream_id)
+            .collect::<Result<Vec<StreamId>, CommandError>>()?;
+        if ids.is_empty() {
+            return Err(wrong_arity("xack"));
+        }
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xpending"], None)?;
+
+        // XPENDING key group [[IDLE min-idle-time] start end count [consumer]]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let (key, group) = match (args.next(), args.next()) {
+            (Some(key), Some(group)) => (extract_bulk(key)?, extract_bulk(group)?),
+            _ => return Err(wrong_arity("xpending")),
+        };
+        let mut min_idle = 0;
+        if let Some(RespFrame::BulkString(option)) = args.peek() {
+            if option.as_ref().eq_ignore_ascii_case(b"idle") {
+                args.next();
+                min_idle = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+            }
+        }
+
+        let range = match (args.next(), args.next(), args.next()) {
+            (None, None, None) if min_idle == 0 => None,
+            (Some(start), Some(end), Some(count)) => {
+                // a negative count returns nothing
+                let count = usize::try_from(extract_integer(count)?).unwrap_or(0);
+                Some((extract_range_start(start)?, extract_range_end(end)?, count))
+            }
+            _ => return Err(syntax_error()),
+        };
+        let consumer = args.next().map(extract_bulk).transpose()?;
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(XPending {
+            key,
+            group,
+            range,
+            min_idle,
+            consumer,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xclaim"], None)?;
+
+        // XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms]
+        //        [TIME unix-time-milliseconds] [RETRYCOUNT count] [FORCE] [JUSTID]
+        //        [LASTID lastid]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let (key, group, consumer, min_idle) = extract_claim_args(&mut args, "xclaim")?;
+
+        // IDs come first, the first argument that is not one starts the options
+        let mut ids = vec![];
+        while let Some(RespFrame::BulkString(s)) = args.peek() {
+            match extract_stream_id(RespFrame::BulkString(s.clone())) {
+                Ok(id) => ids.push(id),
+                Err(_) if !ids.is_empty() => break,
+                Err(e) => return Err(e),
+            }
+            args.next();
+        }
+        if ids.is_empty() {
+            return Err(wrong_arity("xclaim"));
+        }
+
+        let mut options = ClaimOptions::default();
+        while let Some(option) = args.next() {
+            let option = extract_bulk(option)?.to_ascii_lowercase();
+            match option.as_slice() {
+                b"force" => options.force = true,
+                b"justid" => options.just_id = true,
+                b"idle" | b"time" | b"retrycount" | b"lastid" => {
+                    let arg = args.next().ok_or_else(syntax_error)?;
+                    match option.as_slice() {
+                        b"idle" => {
+                            options.delivered_at = Some(now_ms() - extract_integer(arg)?.max(0))
+                        }
+                        b"time" => options.delivered_at = Some(extract_integer(arg)?),
+                        b"retrycount" => {
+                            let count = u64::try_from(extract_integer(arg)?).map_err(|_| {
+                                CommandError::InvalidArgument(
+                                    "Invalid RETRYCOUNT option argument for XCLAIM".to_string(),
+                                )
+                            })?;
+                            options.retry_count = Some(count);
+                        }
+                        _ => options.last_id = Some(extract_stream_id(arg)?),
+                    }
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Unrecognized XCLAIM option '{}'",
+                        String::from_utf8_lossy(&option)
+                    )))
+                }
+            }
+        }
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xautoclaim"], None)?;
+
+        // XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let (key, group, consumer, min_idle) = extract_claim_args(&mut args, "xautoclaim")?;
+        let start = extract_range_start(args.next().ok_or_else(|| wrong_arity("xautoclaim"))?)?;
+
+        let (mut count, mut just_id) = (100, false);
+        while let Some(option) = args.next() {
+            match extract_bulk(option)?.to_ascii_lowercase().as_slice() {
+                b"count" => {
+                    let n = extract_integer(args.next().ok_or_else(syntax_error)?)?;
+                    count = match usize::try_from(n) {
+                        Ok(n) if n > 0 && n <= i64::MAX as usize / 10 => n,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "COUNT must be > 0".to_string(),
+                            ))
+                        }
+                    };
+                }
+                b"justid" => just_id = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            just_id,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoStream {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xinfo", "stream"], Some(1))?;
+
+        // XINFO STREAM key
+        let mut args = extract_args(value, 2)?.into_iter();
+        let key = extract_bulk(args.next().ok_or_else(syntax_error)?)?;
+        Ok(XInfoStream { key })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoGroups {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xinfo", "groups"], Some(1))?;
+
+        // XINFO GROUPS key
+        let mut args = extract_args(value, 2)?.into_iter();
+        let key = extract_bulk(args.next().ok_or_else(syntax_error)?)?;
+        Ok(XInfoGroups { key })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoConsumers {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xinfo", "consumers"], Some(2))?;
+
+        // XINFO CONSUMERS key group
+        let mut args = extract_args(value, 2)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(key), Some(group)) => Ok(XInfoConsumers {
+                key: extract_bulk(key)?,
+                group: extract_bulk(group)?,
+            }),
+            _ => Err(syntax_error()),
+        }
+    }
+}
+
+// key group <id | $>, None for $
+fn extract_group_id(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<GroupIdArgs, CommandError> {
+    match (args.next(), args.next(), args.next()) {
+        (Some(key), Some(group), Some(id)) => {
+            let id = match id {
+                RespFrame::BulkString(s) if s.as_ref() == b"$" => None,
+                id => Some(extract_stream_id(id)?),
+            };
+            Ok((extract_bulk(key)?, extract_bulk(group)?, id))
+        }
+        _ => Err(syntax_error()),
+    }
+}
+
+fn extract_entries_read(frame: Option<RespFrame>) -> Result<u64, CommandError> {
+    let n = extract_integer(frame.ok_or_else(syntax_error)?)?;
+    u64::try_from(n).map_err(|_| {
+        CommandError::InvalidArgument("value for ENTRIESREAD must be positive or -1".to_string())
+    })
+}
+
+// XGROUP <sub> key group consumer
+fn extract_consumer_args(
+    value: RespArray,
+    sub: &'static str,
+) -> Result<ConsumerArgs, CommandError> {
+    validate_command(&value, &["xgroup", sub], Some(3))?;
+
+    let mut args = extract_args(value, 2)?.into_iter();
+    match (args.next(), args.next(), args.next()) {
+        (Some(key), Some(group), Some(consumer)) => Ok((
+            extract_bulk(key)?,
+            extract_bulk(group)?,
+            extract_bulk(consumer)?,
+        )),
+        _ => Err(syntax_error()),
+    }
+}
+
+// key group consumer min-idle-time, a negative idle time is 0
+fn extract_claim_args(
+    args: &mut impl Iterator<Item = RespFrame>,
+    name: &str,
+) -> Result<ClaimArgs, CommandError> {
+    match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(key), Some(group), Some(consumer), Some(min_idle)) => Ok((
+            extract_bulk(key)?,
+            extract_bulk(group)?,
+            extract_bulk(consumer)?,
+            extract_integer(min_idle)?.max(0),
+        )),
+        _ => Err(wrong_arity(name)),
+    }
+}
+
+fn extract_bulk(frame: RespFrame) -> Result<Vec<u8>, CommandError> {
+    match frame {
+        RespFrame::BulkString(s) => Ok(s.0.expect("Invalid argument")),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid argument".to_string(),
+        )),
+    }
+}
+
+fn wrong_arity(name: &str) -> CommandError {
+    CommandError::InvalidArgument(format!("wrong number of arguments for '{}' command", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespDecode, XAddId};
+    use anyhow::Result;
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    #[test]
+    fn test_xreadgroup_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*11\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\nBLOCK\r\n$3\r\n100\r\n$5\r\nNOACK\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\n>\r\n$1\r\n>\r\n");
+        let result: Result<XReadGroup, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*12\r\n$10\r\nxreadgroup\r\n$5\r\nGROUP\r\n$1\r\ng\r\n$1\r\nc\r\n$5\r\nBLOCK\r\n$3\r\n100\r\n$5\r\nNOACK\r\n$7\r\nSTREAMS\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\n>\r\n$1\r\n0\r\n");
+        let result: XReadGroup = RespArray::decode(&mut buf)?.try_into()?;
+        assert!(result.noack);
+        assert_eq!(result.timeout, Some(Duration::from_millis(100)));
+        assert_eq!(
+            result.streams,
+            vec![(b"a".to_vec(), None), (b"b".to_vec(), Some(StreamId::MIN))]
+        );
+        // reading the history of b does not wait
+        assert!(!result.blocks());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xclaim_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*12\r\n$6\r\nxclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$4\r\n1000\r\n$3\r\n1-0\r\n$1\r\n2\r\n$10\r\nRETRYCOUNT\r\n$1\r\n5\r\n$5\r\nFORCE\r\n$6\r\nLASTID\r\n$3\r\n9-9\r\n");
+        let result: XClaim = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.min_idle, 1000);
+        assert_eq!(result.ids, vec![StreamId::new(1, 0), StreamId::new(2, 0)]);
+        let options = ClaimOptions {
+            retry_count: Some(5),
+            force: true,
+            last_id: Some(StreamId::new(9, 9)),
+            ..Default::default()
+        };
+        assert_eq!(result.options, options);
+
+        buf.extend_from_slice(
+            b"*6\r\n$6\r\nxclaim\r\n$1\r\ns\r\n$1\r\ng\r\n$1\r\nc\r\n$1\r\n0\r\n$5\r\nFORCE\r\n",
+        );
+        let result: Result<XClaim, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    fn command(line: &str) -> RespArray {
+        RespArray::new(
+            line.split_whitespace()
+                .map(|arg| BulkString::from(arg).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+    }
+
+    // a stream s of 1-0, 2-0 and 3-0, all pending for alice in group g
+    fn read_by_alice() -> Result<Backend> {
+        let backend = Backend::new();
+        for ms in 1..=3 {
+            let entry = vec![(b"f".to_vec(), b"v".to_vec())];
+            let id = XAddId::Explicit(StreamId::new(ms, 0));
+            backend.xadd(b"s".to_vec(), id, entry, false, None)?;
+        }
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            false,
+            None,
+        )?;
+        backend.xreadgroup(b"s", b"g", b"alice", None, 10, false)?;
+        Ok(backend)
+    }
+
+    fn entry(ms: u64) -> StreamEntry {
+        (StreamId::new(ms, 0), vec![(b"f".to_vec(), b"v".to_vec())])
+    }
+
+    #[test]
+    fn test_xclaim_options_replies() -> Result<()> {
+        let backend = read_by_alice()?;
+        let execute = |line: &str| -> Result<RespFrame> {
+            Ok(XClaim::try_from(command(line))?.execute(&backend))
+        };
+
+        let reply = execute("xclaim s g bob 0 1-0 IDLE 60000 RETRYCOUNT 3 JUSTID")?;
+        assert_eq!(reply, ids_reply([StreamId::new(1, 0)].into_iter()));
+        let pending = backend.xpending(
+            b"s",
+            b"g",
+            StreamId::new(1, 0),
+            StreamId::new(1, 0),
+            1,
+            0,
+            None,
+        )?;
+        assert_eq!(
+            (pending[0].consumer.as_slice(), pending[0].deliveries),
+            (&b"bob"[..], 3)
+        );
+        assert!(pending[0].idle >= 60_000);
+
+        let time = (now_ms() - 60_000).to_string();
+        let reply = execute(&format!("xclaim s g bob 0 2-0 TIME {}", time))?;
+        assert_eq!(reply, entries_reply(vec![entry(2)]));
+        let pending = backend.xpending(
+            b"s",
+            b"g",
+            StreamId::new(2, 0),
+            StreamId::new(2, 0),
+            1,
+            30_000,
+            Some(b"bob"),
+        )?;
+        assert_eq!(pending[0].deliveries, 2);
+
+        // an acknowledged entry is claimed again with FORCE only
+        backend.xack(b"s", b"g", &[StreamId::new(3, 0)])?;
+        let reply = execute("xclaim s g bob 0 3-0")?;
+        assert_eq!(reply, RespArray::new(vec![]).into());
+        let reply = execute("xclaim s g bob 0 3-0 FORCE JUSTID")?;
+        assert_eq!(reply, ids_reply([StreamId::new(3, 0)].into_iter()));
+        assert_eq!(backend.xpending_summary(b"s", b"g")?.count, 3);
+
+        let result = XClaim::try_from(command("xclaim s g bob 0 1-0 RETRYCOUNT -1"));
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xautoclaim_reply_reports_deleted_entries() -> Result<()> {
+        let backend = read_by_alice()?;
+        backend.xdel(b"s", &[StreamId::new(2, 0)])?;
+
+        let cmd = XAutoClaim::try_from(command("xautoclaim s g bob 0 0-0 COUNT 10"))?;
+        let expected = RespArray::new(vec![
+            BulkString::from("0-0").into(),
+            entries_reply(vec![entry(1), entry(3)]),
+            ids_reply([StreamId::new(2, 0)].into_iter()),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        // the deleted entry left the PEL, it is reported once
+        let cmd = XAutoClaim::try_from(command("xautoclaim s g carol 0 0-0 JUSTID"))?;
+        let expected = RespArray::new(vec![
+            BulkString::from("0-0").into(),
+            ids_reply([StreamId::new(1, 0), StreamId::new(3, 0)].into_iter()),
+            RespArray::new(vec![]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xpending_idle_and_consumer() -> Result<()> {
+        let backend = read_by_alice()?;
+        let result = XPending::try_from(command("xpending s g IDLE 30000 - + 10 alice"))?;
+        assert_eq!(result.min_idle, 30_000);
+        assert_eq!(result.range, Some((StreamId::MIN, StreamId::MAX, 10)));
+        assert_eq!(result.consumer, Some(b"alice".to_vec()));
+        // delivered just now
+        assert_eq!(result.execute(&backend), RespArray::new(vec![]).into());
+
+        let time = (now_ms() - 60_000).to_string();
+        XClaim::try_from(command(&format!("xclaim s g bob 0 2-0 TIME {}", time)))?
+            .execute(&backend);
+        let execute = |line: &str| -> Result<Vec<RespFrame>> {
+            match XPending::try_from(command(line))?.execute(&backend) {
+                RespFrame::Array(RespArray(Some(pending))) => Ok(pending),
+                reply => panic!("expected an array, got {:?}", reply),
+            }
+        };
+        let pending = execute("xpending s g IDLE 30000 - + 10")?;
+        assert_eq!(pending.len(), 1);
+        let RespFrame::Array(RespArray(Some(info))) = &pending[0] else {
+            panic!("expected an array, got {:?}", pending[0]);
+        };
+        assert_eq!(info[0], BulkString::from("2-0").into());
+        assert_eq!(info[1], BulkString::from("bob").into());
+        assert!(matches!(info[2], RespFrame::Integer(idle) if idle >= 60_000));
+        assert_eq!(info[3], RespFrame::Integer(2));
+
+        assert_eq!(execute("xpending s g - + 10 alice")?.len(), 2);
+        assert!(execute("xpending s g IDLE 30000 - + 10 alice")?.is_empty());
+        // IDLE filters the extended form, which then needs its range
+        assert!(XPending::try_from(command("xpending s g IDLE 30000")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_xgroup_setid_entriesread() -> Result<()> {
+        let backend = read_by_alice()?;
+        let cmd = XGroupSetId::try_from(command("xgroup setid s g 1-0 ENTRIESREAD 1"))?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let groups = backend.xinfo_groups(b"s")?;
+        assert_eq!(groups[0].last_delivered_id, StreamId::new(1, 0));
+        assert_eq!((groups[0].entries_read, groups[0].lag), (Some(1), Some(2)));
+
+        let cmd = XGroupSetId::try_from(command("xgroup setid s g $"))?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let groups = backend.xinfo_groups(b"s")?;
+        assert_eq!((groups[0].entries_read, groups[0].lag), (Some(3), Some(0)));
+
+        for line in [
+            "xgroup setid s g 0 ENTRIESREAD -5",
+            "xgroup setid s g 0 ENTRIESREAD",
+            "xgroup setid s g 0 ENTRIESREAD 1 extra",
+            "xgroup setid s g 0 MKSTREAM",
+        ] {
+            assert!(XGroupSetId::try_from(command(line)).is_err(), "{}", line);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_xpending_and_xinfo_replies() -> Result<()> {
+        let backend = Backend::new();
+        let entry = vec![(b"f".to_vec(), b"v".to_vec())];
+        let id = XAddId::Explicit(StreamId::new(1, 0));
+        backend.xadd(b"s".to_vec(), id, entry, false, None)?;
+
+        let pending = XPending {
+            key: b"s".to_vec(),
+            group: b"g".to_vec(),
+            range: None,
+            min_idle: 0,
+            consumer: None,
+        };
+        assert!(matches!(pending.execute(&backend), RespFrame::Error(_)));
+
+        backend.xgroup_create(
+            b"s".to_vec(),
+            b"g".to_vec(),
+            Some(StreamId::MIN),
+            false,
+            None,
+        )?;
+        backend.xreadgroup(b"s", b"g", b"c", None, 10, false)?;
+        let pending = XPending {
+            key: b"s".to_vec(),
+            group: b"g".to_vec(),
+            range: None,
+            min_idle: 0,
+            consumer: None,
+        };
+        let expected = RespArray::new(vec![
+            RespFrame::Integer(1),
+            BulkString::from("1-0").into(),
+            BulkString::from("1-0").into(),
+            RespArray::new(vec![RespArray::new(vec![
+                BulkString::from("c").into(),
+                BulkString::from("1").into(),
+            ])
+            .into()])
+            .into(),
+        ]);
+        assert_eq!(pending.execute(&backend), expected.into());
+
+        let groups = XInfoGroups { key: b"s".to_vec() }.execute(&backend);
+        let RespFrame::Array(RespArray(Some(groups))) = groups else {
+            panic!("expected an array, got {:?}", groups);
+        };
+        let RespFrame::Map(group) = &groups[0] else {
+            panic!("expected a map, got {:?}", groups[0]);
+        };
+        assert_eq!(group.get("pending"), Some(&RespFrame::Integer(1)));
+        assert_eq!(group.get("lag"), Some(&RespFrame::Integer(0)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_block_waits_for_xadd() -> Result<()> {
+        let backend = Backend::new();
+        backend.xgroup_create(b"s".to_vec(), b"g".to_vec(), None, true, None)?;
+
+        let cmd = XReadGroup {
+            group: b"g".to_vec(),
+            consumer: b"c".to_vec(),
+            streams: vec![(b"s".to_vec(), None)],
+            count: usize::MAX,
+            block: true,
+            timeout: None,
+            noack: false,
+        };
+        let block = cmd.into_block();
+        let blocked = tokio::spawn({
+            let backend = backend.clone();
+            async move { block.wait(&backend).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let entry = vec![(b"f".to_vec(), b"v".to_vec())];
+        let id = XAddId::Explicit(StreamId::new(1, 0));
+        backend.xadd(b"s".to_vec(), id, entry.clone(), false, None)?;
+
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("s").into(),
+            entries_reply(vec![(StreamId::new(1, 0), entry)]),
+        ])
+        .into()]);
+        assert_eq!(blocked.await?, expected.into());
+        assert_eq!(backend.xpending_summary(b"s", b"g")?.count, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_xreadgroup_block_wakes_every_group() -> Result<()> {
+        let backend = Backend::new();
+        backend.xgroup_create(b"s".to_vec(), b"g".to_vec(), None, true, None)?;
+        backend.xgroup_create(b"s".to_vec(), b"h".to_vec(), None, false, None)?;
+
+        let mut clients = vec![];
+        for (group, consumer) in [("g", "c1"), ("g", "c2"), ("h", "d1")] {
+            let cmd = XReadGroup {
+                group: group.into(),
+                consumer: consumer.into(),
+                streams: vec![(b"s".to_vec(), None)],
+                count: usize::MAX,
+                block: true,
+                timeout: Some(Duration::from_millis(200)),
+                noack: false,
+            };
+            let block = cmd.into_block();
+            clients.push(tokio::spawn({
+                let backend = backend.clone();
+                async move { block.wait(&backend).await }
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let entry = vec![(b"f".to_vec(), b"v".to_vec())];
+        let id = XAddId::Explicit(StreamId::new(1, 0));
+        backend.xadd(b"s".to_vec(), id, entry.clone(), false, None)?;
+
+        let expected: RespFrame = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("s").into(),
+            entries_reply(vec![(StreamId::new(1, 0), entry)]),
+        ])
+        .into()])
+        .into();
+        let expected = [expected.clone(), RespArray::new(None).into(), expected];
+        // c2 finds nothing left in g, which does not keep d1 of h from its entry
+        for (client, expected) in clients.into_iter().zip(expected) {
+            assert_eq!(client.await?, expected);
+        }
+        Ok(())
+    }
+}
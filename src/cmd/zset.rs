@@ -0,0 +1,1105 @@
+use super::{
+    extract_args, extract_integer, extract_key, extract_key_and_count, extract_key_and_members,
+    extract_num_keys, list::extract_mpop_count, list::positive_count, syntax_error,
+    validate_command, CommandError, CommandExecutor, Protocol, Session, ZAdd, ZCard, ZCount, ZDiff,
+    ZDiffStore, ZIncrBy, ZInterStore, ZMPop, ZMScore, ZPopMax, ZPopMin, ZRange, ZRangeStore, ZRank,
+    ZRem, ZRemRangeByLex, ZRemRangeByRank, ZRemRangeByScore, ZRevRank, ZScore, ZUnionStore,
+};
+use crate::{
+    Aggregate, Backend, BulkString, LexBound, RespArray, RespFrame, RespNull, ScoreBound, ScoreEnd,
+    ScoreUpdate, SetCondition, SetOperation, ZAddOptions, ZRangeBy,
+};
+
+// Scores are RESP3 doubles, RESP2 clients get them as bulk strings through Session::reply.
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if self.incr {
+            let (increment, member) = self.pairs.into_iter().next().expect("one pair");
+            return match backend.zadd_incr(self.key, increment, member, self.options) {
+                Ok(score) => optional_score(score),
+                Err(e) => e.into(),
+            };
+        }
+        match backend.zadd(self.key, self.pairs, self.options) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    // WITHSCORES pairs are nested arrays for RESP3 and a flat array for RESP2
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        match backend.zrange(&self.key, &self.by, self.rev, self.offset, self.count) {
+            Ok(members) => members_reply(members, self.with_scores, session.protocol()),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        rank_reply(backend, &self.key, &self.member, false, self.with_score)
+    }
+}
+
+impl CommandExecutor for ZRevRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        rank_reply(backend, &self.key, &self.member, true, self.with_score)
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zscore(&self.key, &self.member) {
+            Ok(score) => optional_score(score),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZMScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zmscore(&self.key, &self.members) {
+            Ok(scores) => {
+                let ret = scores
+                    .into_iter()
+                    .map(optional_score)
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let options = ZAddOptions::default();
+        match backend.zadd_incr(self.key, self.increment, self.member, options) {
+            Ok(score) => optional_score(score),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrem(&self.key, &self.members) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcard(&self.key) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zcount(&self.key, self.min, self.max) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZUnionStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let op = SetOperation::Union;
+        let (keys, weights) = (self.keys, self.weights);
+        match backend.zcombine_store(op, self.destination, &keys, &weights, self.aggregate) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZInterStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let op = SetOperation::Inter;
+        let (keys, weights) = (self.keys, self.weights);
+        match backend.zcombine_store(op, self.destination, &keys, &weights, self.aggregate) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZDiff {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        match backend.zcombine(SetOperation::Diff, &self.keys, &[], Aggregate::Sum) {
+            Ok(members) => members_reply(members, self.with_scores, session.protocol()),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZDiffStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let op = SetOperation::Diff;
+        match backend.zcombine_store(op, self.destination, &self.keys, &[], Aggregate::Sum) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRangeStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrangestore(
+            self.destination,
+            &self.source,
+            &self.by,
+            self.rev,
+            self.offset,
+            self.count,
+        ) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRemRangeByRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let by = ZRangeBy::Rank(self.start, self.stop);
+        match backend.zremrange(&self.key, &by) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRemRangeByScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let by = ZRangeBy::Score(self.min, self.max);
+        match backend.zremrange(&self.key, &by) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZRemRangeByLex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let by = ZRangeBy::Lex(self.min, self.max);
+        match backend.zremrange(&self.key, &by) {
+            Ok(count) => RespFrame::Integer(count),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZPopMin {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        pop_reply(backend, &self.key, ScoreEnd::Min, self.count, session)
+    }
+}
+
+impl CommandExecutor for ZPopMax {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.execute_with(backend, &mut Session::new())
+    }
+
+    fn execute_with(self, backend: &Backend, session: &mut Session) -> RespFrame {
+        pop_reply(backend, &self.key, ScoreEnd::Max, self.count, session)
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zmpop(&self.keys, self.end, self.count) {
+            Ok(Some((key, members))) => key_members_reply(key, members),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn optional_score(score: Option<f64>) -> RespFrame {
+    match score {
+        Some(score) => RespFrame::Double(score),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+fn rank_reply(
+    backend: &Backend,
+    key: &[u8],
+    member: &[u8],
+    rev: bool,
+    with_score: bool,
+) -> RespFrame {
+    match backend.zrank(key, member, rev) {
+        Ok(Some((rank, score))) if with_score => {
+            RespArray::new(vec![RespFrame::Integer(rank), RespFrame::Double(score)]).into()
+        }
+        Ok(Some((rank, _))) => RespFrame::Integer(rank),
+        Ok(None) => RespFrame::Null(RespNull),
+        Err(e) => e.into(),
+    }
+}
+
+fn members_reply(members: Vec<(Vec<u8>, f64)>, with_scores: bool, protocol: Protocol) -> RespFrame {
+    let frames = match (with_scores, protocol) {
+        (false, _) => members
+            .into_iter()
+            .map(|(member, _)| BulkString::from(member).into())
+            .collect::<Vec<RespFrame>>(),
+        (true, Protocol::Resp2) => members
+            .into_iter()
+            .flat_map(|(member, score)| [BulkString::from(member).into(), RespFrame::Double(score)])
+            .collect(),
+        (true, Protocol::Resp3) => members
+            .into_iter()
+            .map(|(member, score)| {
+                RespArray::new(vec![
+                    BulkString::from(member).into(),
+                    RespFrame::Double(score),
+                ])
+                .into()
+            })
+            .collect(),
+    };
+    RespArray::new(frames).into()
+}
+
+// without a count ZPOPMIN and ZPOPMAX reply with a single flat member and score pair,
+// with one like ZRANGE WITHSCORES
+fn pop_reply(
+    backend: &Backend,
+    key: &[u8],
+    end: ScoreEnd,
+    count: Option<usize>,
+    session: &Session,
+) -> RespFrame {
+    let members = match backend.zpop(key, end, count.unwrap_or(1)) {
+        Ok(members) => members.unwrap_or_default(),
+        Err(e) => return e.into(),
+    };
+    match count {
+        Some(_) => members_reply(members, true, session.protocol()),
+        None => members_reply(members, true, Protocol::Resp2),
+    }
+}
+
+// ZMPOP and BZMPOP reply with the key and the members, each with its score
+pub(crate) fn key_members_reply(key: Vec<u8>, members: Vec<(Vec<u8>, f64)>) -> RespFrame {
+    RespArray::new(vec![
+        BulkString::from(key).into(),
+        members_reply(members, true, Protocol::Resp3),
+    ])
+    .into()
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zadd"], None)?;
+
+        // ZADD key [NX | XX] [GT | LT] [CH] [INCR] score member [score member ...]
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        };
+
+        let mut options = ZAddOptions::default();
+        let (mut nx, mut xx, mut gt, mut lt, mut incr) = (false, false, false, false, false);
+        while let Some(RespFrame::BulkString(option)) = args.peek() {
+            match option.as_ref().to_ascii_lowercase().as_slice() {
+                b"nx" => (nx, options.condition) = (true, SetCondition::NotExists),
+                b"xx" => (xx, options.condition) = (true, SetCondition::Exists),
+                b"gt" => (gt, options.update) = (true, ScoreUpdate::Greater),
+                b"lt" => (lt, options.update) = (true, ScoreUpdate::Less),
+                b"ch" => options.changed = true,
+                b"incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+
+        let mut pairs = vec![];
+        while let Some(score) = args.next() {
+            let score = extract_score(score)?;
+            match args.next() {
+                Some(RespFrame::BulkString(member)) => {
+                    pairs.push((score, member.0.expect("Invalid member")))
+                }
+                _ => return Err(syntax_error()),
+            }
+        }
+        if pairs.is_empty() {
+            return Err(syntax_error());
+        }
+
+        if nx && xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (gt && lt) || (nx && options.update != ScoreUpdate::Always) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if incr && pairs.len() > 1 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        Ok(ZAdd {
+            key,
+            options,
+            incr,
+            pairs,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrange"], None)?;
+
+        // ZRANGE key start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = match args.next() {
+            Some(RespFrame::BulkString(key)) => key.0.expect("Invalid key"),
+            _ => return Err(syntax_error()),
+        };
+        let range = extract_range(args)?;
+        Ok(ZRange {
+            key,
+            by: range.by,
+            rev: range.rev,
+            offset: range.offset,
+            count: range.count,
+            with_scores: range.with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrangestore"], None)?;
+
+        // ZRANGESTORE dst src min max [BYSCORE | BYLEX] [REV] [LIMIT offset count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (destination, source) = match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(destination)), Some(RespFrame::BulkString(source))) => (
+                destination.0.expect("Invalid key"),
+                source.0.expect("Invalid key"),
+            ),
+            _ => return Err(syntax_error()),
+        };
+        let range = extract_range(args)?;
+        if range.with_scores {
+            return Err(syntax_error());
+        }
+        Ok(ZRangeStore {
+            destination,
+            source,
+            by: range.by,
+            rev: range.rev,
+            offset: range.offset,
+            count: range.count,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = extract_rank_args(value, "zrank")?;
+        Ok(ZRank {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRevRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, member, with_score) = extract_rank_args(value, "zrevrank")?;
+        Ok(ZRevRank {
+            key,
+            member,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zscore"], Some(2))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(member))) => Ok(ZScore {
+                key: key.0.expect("Invalid key"),
+                member: member.0.expect("Invalid member"),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or member".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZMScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = extract_key_and_members(value, "zmscore")?;
+        Ok(ZMScore { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zincrby"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (
+                Some(RespFrame::BulkString(key)),
+                Some(increment),
+                Some(RespFrame::BulkString(member)),
+            ) => Ok(ZIncrBy {
+                key: key.0.expect("Invalid key"),
+                increment: extract_score(increment)?,
+                member: member.0.expect("Invalid member"),
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "Invalid key or member".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, members) = extract_key_and_members(value, "zrem")?;
+        Ok(ZRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        Ok(ZCard {
+            key: extract_key(value, "zcard")?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcount"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(min), Some(max)) => Ok(ZCount {
+                key: key.0.expect("Invalid key"),
+                min: extract_score_bound(min)?,
+                max: extract_score_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZUnionStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys, weights, aggregate) = extract_combine_args(value, "zunionstore")?;
+        Ok(ZUnionStore {
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (destination, keys, weights, aggregate) = extract_combine_args(value, "zinterstore")?;
+        Ok(ZInterStore {
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZDiff {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zdiff"], None)?;
+
+        // ZDIFF numkeys key [key ...] [WITHSCORES]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let keys = extract_num_keys(&mut args)?;
+        let with_scores = match (args.next(), args.next()) {
+            (None, _) => false,
+            (Some(RespFrame::BulkString(option)), None)
+                if option.as_ref().eq_ignore_ascii_case(b"withscores") =>
+            {
+                true
+            }
+            _ => return Err(syntax_error()),
+        };
+        Ok(ZDiff { keys, with_scores })
+    }
+}
+
+impl TryFrom<RespArray> for ZDiffStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zdiffstore"], None)?;
+
+        // ZDIFFSTORE destination numkeys key [key ...]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = match args.next() {
+            Some(RespFrame::BulkString(destination)) => destination.0.expect("Invalid key"),
+            _ => return Err(syntax_error()),
+        };
+        let keys = extract_num_keys(&mut args)?;
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(ZDiffStore { destination, keys })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebyrank"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(start), Some(stop)) => Ok(ZRemRangeByRank {
+                key: key.0.expect("Invalid key"),
+                start: extract_integer(start)?,
+                stop: extract_integer(stop)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebyscore"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(min), Some(max)) => Ok(ZRemRangeByScore {
+                key: key.0.expect("Invalid key"),
+                min: extract_score_bound(min)?,
+                max: extract_score_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRangeByLex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zremrangebylex"], Some(3))?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(RespFrame::BulkString(key)), Some(min), Some(max)) => Ok(ZRemRangeByLex {
+                key: key.0.expect("Invalid key"),
+                min: extract_lex_bound(min)?,
+                max: extract_lex_bound(max)?,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMin {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_and_count(value, "zpopmin")?;
+        Ok(ZPopMin {
+            key,
+            count: positive_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZPopMax {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, count) = extract_key_and_count(value, "zpopmax")?;
+        Ok(ZPopMax {
+            key,
+            count: positive_count(count)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zmpop"], None)?;
+
+        // ZMPOP numkeys key [key ...] <MIN | MAX> [COUNT count]
+        let mut args = extract_args(value, 1)?.into_iter();
+        let keys = extract_num_keys(&mut args)?;
+        let end = match args.next() {
+            Some(end) => extract_score_end(end)?,
+            None => return Err(syntax_error()),
+        };
+        let count = extract_mpop_count(&mut args)?;
+        Ok(ZMPop { keys, end, count })
+    }
+}
+
+// the destination, keys, weights and aggregate of ZUNIONSTORE and ZINTERSTORE
+type CombineArgs = (Vec<u8>, Vec<Vec<u8>>, Vec<f64>, Aggregate);
+
+// destination numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE <SUM | MIN | MAX>]
+fn extract_combine_args(value: RespArray, name: &'static str) -> Result<CombineArgs, CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let destination = match args.next() {
+        Some(RespFrame::BulkString(destination)) => destination.0.expect("Invalid key"),
+        _ => return Err(syntax_error()),
+    };
+    let keys = extract_num_keys(&mut args)?;
+
+    let (mut weights, mut aggregate) = (vec![1.0; keys.len()], Aggregate::Sum);
+    while let Some(option) = args.next() {
+        let option = match option {
+            RespFrame::BulkString(option) => option.as_ref().to_ascii_lowercase(),
+            _ => return Err(syntax_error()),
+        };
+        match option.as_slice() {
+            b"weights" => {
+                for weight in weights.iter_mut() {
+                    *weight = match args.next() {
+                        Some(frame) => extract_score(frame).map_err(|_| {
+                            CommandError::InvalidArgument("weight value is not a float".to_string())
+                        })?,
+                        None => return Err(syntax_error()),
+                    };
+                }
+            }
+            b"aggregate" => {
+                aggregate = match args.next() {
+                    Some(RespFrame::BulkString(v)) => {
+                        match v.as_ref().to_ascii_lowercase().as_slice() {
+                            b"sum" => Aggregate::Sum,
+                            b"min" => Aggregate::Min,
+                            b"max" => Aggregate::Max,
+                            _ => return Err(syntax_error()),
+                        }
+                    }
+                    _ => return Err(syntax_error()),
+                }
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((destination, keys, weights, aggregate))
+}
+
+// MIN or MAX, the end ZMPOP and BZMPOP pop from
+pub(crate) fn extract_score_end(frame: RespFrame) -> Result<ScoreEnd, CommandError> {
+    match frame {
+        RespFrame::BulkString(end) => match end.as_ref().to_ascii_lowercase().as_slice() {
+            b"min" => Ok(ScoreEnd::Min),
+            b"max" => Ok(ScoreEnd::Max),
+            _ => Err(syntax_error()),
+        },
+        _ => Err(syntax_error()),
+    }
+}
+
+// the arguments of ZRANGE and ZRANGESTORE following the keys
+struct RangeArgs {
+    by: ZRangeBy,
+    rev: bool,
+    offset: usize,
+    count: usize,
+    with_scores: bool,
+}
+
+// start stop [BYSCORE | BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
+fn extract_range(mut args: impl Iterator<Item = RespFrame>) -> Result<RangeArgs, CommandError> {
+    let (start, stop) = match (args.next(), args.next()) {
+        (Some(start), Some(stop)) => (start, stop),
+        _ => return Err(syntax_error()),
+    };
+
+    #[derive(PartialEq)]
+    enum By {
+        Rank,
+        Score,
+        Lex,
+    }
+    let (mut by, mut rev, mut limit, mut with_scores) = (By::Rank, false, None, false);
+    while let Some(option) = args.next() {
+        let option = match option {
+            RespFrame::BulkString(option) => option.as_ref().to_ascii_lowercase(),
+            _ => return Err(syntax_error()),
+        };
+        match option.as_slice() {
+            b"byscore" => by = By::Score,
+            b"bylex" => by = By::Lex,
+            b"rev" => rev = true,
+            b"withscores" => with_scores = true,
+            b"limit" => match (args.next(), args.next()) {
+                (Some(offset), Some(count)) => {
+                    limit = Some((extract_integer(offset)?, extract_integer(count)?))
+                }
+                _ => return Err(syntax_error()),
+            },
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    if limit.is_some() && by == By::Rank {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_string(),
+        ));
+    }
+    if with_scores && by == By::Lex {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+    // with REV, the score and lex ranges are given from max to min
+    let (min, max) = match by {
+        By::Score | By::Lex if rev => (stop, start),
+        _ => (start, stop),
+    };
+    let by = match by {
+        By::Rank => ZRangeBy::Rank(extract_integer(min)?, extract_integer(max)?),
+        By::Score => ZRangeBy::Score(extract_score_bound(min)?, extract_score_bound(max)?),
+        By::Lex => ZRangeBy::Lex(extract_lex_bound(min)?, extract_lex_bound(max)?),
+    };
+    // a negative offset returns nothing, a negative count everything from the offset on
+    let (offset, count) = match limit {
+        Some((offset, _)) if offset < 0 => (usize::MAX, 0),
+        Some((offset, count)) if count < 0 => (offset as usize, usize::MAX),
+        Some((offset, count)) => (offset as usize, count as usize),
+        None => (0, usize::MAX),
+    };
+    Ok(RangeArgs {
+        by,
+        rev,
+        offset,
+        count,
+        with_scores,
+    })
+}
+
+// key member [WITHSCORE]
+fn extract_rank_args(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(Vec<u8>, Vec<u8>, bool), CommandError> {
+    validate_command(&value, &[name], None)?;
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let (key, member) = match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(member))) => (
+            key.0.expect("Invalid key"),
+            member.0.expect("Invalid member"),
+        ),
+        _ => return Err(syntax_error()),
+    };
+    match (args.next(), args.next()) {
+        (None, _) => Ok((key, member, false)),
+        (Some(RespFrame::BulkString(option)), None)
+            if option.as_ref().eq_ignore_ascii_case(b"withscore") =>
+        {
+            Ok((key, member, true))
+        }
+        _ => Err(syntax_error()),
+    }
+}
+
+// scores may be infinite, unlike the floats of INCRBYFLOAT
+pub(crate) fn extract_score(frame: RespFrame) -> Result<f64, CommandError> {
+    let score = match frame {
+        RespFrame::Integer(n) => Some(n as f64),
+        RespFrame::Double(n) => Some(n),
+        RespFrame::BulkString(s) => parse_score(s.as_ref()),
+        _ => None,
+    };
+    match score {
+        Some(score) if !score.is_nan() => Ok(score),
+        _ => Err(CommandError::InvalidArgument(
+            "value is not a valid float".to_string(),
+        )),
+    }
+}
+
+// a score, `(` in front makes it exclusive
+pub(crate) fn extract_score_bound(frame: RespFrame) -> Result<ScoreBound, CommandError> {
+    let bound = match &frame {
+        RespFrame::BulkString(s) => match s.as_ref().strip_prefix(b"(") {
+            Some(score) => parse_score(score).map(ScoreBound::Exclusive),
+            None => parse_score(s.as_ref()).map(ScoreBound::Inclusive),
+        },
+        RespFrame::Integer(n) => Some(ScoreBound::Inclusive(*n as f64)),
+        _ => None,
+    };
+    match bound {
+        Some(ScoreBound::Inclusive(score) | ScoreBound::Exclusive(score)) if score.is_nan() => {
+            Err(invalid_score_bound())
+        }
+        Some(bound) => Ok(bound),
+        None => Err(invalid_score_bound()),
+    }
+}
+
+// `-`, `+`, `[member` or `(member`
+pub(crate) fn extract_lex_bound(frame: RespFrame) -> Result<LexBound, CommandError> {
+    let bound = match frame {
+        RespFrame::BulkString(s) => match s.as_ref() {
+            b"-" => Some(LexBound::Min),
+            b"+" => Some(LexBound::Max),
+            [b'[', member @ ..] => Some(LexBound::Inclusive(member.to_vec())),
+            [b'(', member @ ..] => Some(LexBound::Exclusive(member.to_vec())),
+            _ => None,
+        },
+        _ => None,
+    };
+    bound.ok_or_else(|| {
+        CommandError::InvalidArgument("min or max not valid string range item".to_string())
+    })
+}
+
+fn parse_score(s: &[u8]) -> Option<f64> {
+    std::str::from_utf8(s).ok()?.parse().ok()
+}
+
+fn invalid_score_bound() -> CommandError {
+    CommandError::InvalidArgument("min or max is not a float".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$4\r\nzadd\r\n$1\r\nz\r\n$2\r\nxx\r\n$2\r\nGT\r\n$2\r\nch\r\n$4\r\n+inf\r\n$1\r\na\r\n$1\r\n2\r\n");
+        let result: Result<ZAdd, _> = RespArray::decode(&mut buf)?.try_into();
+        // the last pair is missing its member
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*9\r\n$4\r\nzadd\r\n$1\r\nz\r\n$2\r\nxx\r\n$2\r\nGT\r\n$2\r\nch\r\n$4\r\n+inf\r\n$1\r\na\r\n$3\r\n1.5\r\n$2\r\nnx\r\n");
+        let result: ZAdd = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.options.condition, SetCondition::Exists);
+        assert_eq!(result.options.update, ScoreUpdate::Greater);
+        assert!(result.options.changed);
+        assert_eq!(
+            result.pairs,
+            vec![(f64::INFINITY, b"a".to_vec()), (1.5, b"nx".to_vec())]
+        );
+
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nzadd\r\n$1\r\nz\r\n$2\r\nnx\r\n$2\r\nlt\r\n$1\r\n1\r\n$1\r\na\r\n",
+        );
+        let result: Result<ZAdd, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*9\r\n$6\r\nzrange\r\n$1\r\nz\r\n$4\r\n(5.5\r\n$4\r\n-inf\r\n$7\r\nBYSCORE\r\n$3\r\nREV\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n$2\r\n-1\r\n");
+        let result: ZRange = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(
+            result.by,
+            ZRangeBy::Score(
+                ScoreBound::Inclusive(f64::NEG_INFINITY),
+                ScoreBound::Exclusive(5.5)
+            )
+        );
+        assert!(result.rev);
+        assert_eq!((result.offset, result.count), (1, usize::MAX));
+
+        buf.extend_from_slice(b"*7\r\n$6\r\nzrange\r\n$1\r\nz\r\n$1\r\n0\r\n$1\r\n1\r\n$5\r\nLIMIT\r\n$1\r\n0\r\n$1\r\n1\r\n");
+        let result: Result<ZRange, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+
+        buf.extend_from_slice(b"*6\r\n$6\r\nzrange\r\n$1\r\nz\r\n$1\r\n-\r\n$2\r\n[b\r\n$5\r\nBYLEX\r\n$10\r\nWITHSCORES\r\n");
+        let result: Result<ZRange, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zset_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = ZAdd {
+            key: b"z".to_vec(),
+            options: ZAddOptions::default(),
+            incr: false,
+            pairs: vec![(1.0, b"a".to_vec()), (2.5, b"b".to_vec())],
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        let cmd = ZIncrBy {
+            key: b"z".to_vec(),
+            increment: 2.0,
+            member: b"a".to_vec(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(3.0));
+
+        let cmd = ZRevRank {
+            key: b"z".to_vec(),
+            member: b"b".to_vec(),
+            with_score: true,
+        };
+        let expected = RespArray::new(vec![RespFrame::Integer(1), RespFrame::Double(2.5)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let range = || ZRange {
+            key: b"z".to_vec(),
+            by: ZRangeBy::Rank(0, -1),
+            rev: false,
+            offset: 0,
+            count: usize::MAX,
+            with_scores: true,
+        };
+        // RESP2 clients get a flat array, with the scores as bulk strings
+        let mut session = Session::new();
+        let expected = RespArray::new(vec![
+            BulkString::from("b").into(),
+            BulkString::from("2.5").into(),
+            BulkString::from("a").into(),
+            BulkString::from("3").into(),
+        ]);
+        let reply = range().execute_with(&backend, &mut session);
+        assert_eq!(session.reply(reply), expected.into());
+        // RESP3 clients get a pair per member
+        let members = vec![(b"b".to_vec(), 2.5)];
+        let expected = RespArray::new(vec![RespArray::new(vec![
+            BulkString::from("b").into(),
+            RespFrame::Double(2.5),
+        ])
+        .into()]);
+        assert_eq!(
+            members_reply(members, true, Protocol::Resp3),
+            expected.into()
+        );
+
+        let cmd = ZMScore {
+            key: b"z".to_vec(),
+            members: vec![b"a".to_vec(), b"missing".to_vec()],
+        };
+        let expected = RespArray::new(vec![RespFrame::Double(3.0), RespFrame::Null(RespNull)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zunionstore_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*10\r\n$11\r\nzunionstore\r\n$3\r\ndst\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n$3\r\n0.5\r\n$9\r\nAGGREGATE\r\n$3\r\nmax\r\n");
+        let result: ZUnionStore = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.destination, b"dst");
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(result.weights, vec![2.0, 0.5]);
+        assert_eq!(result.aggregate, Aggregate::Max);
+
+        // one weight per key
+        buf.extend_from_slice(b"*7\r\n$11\r\nzinterstore\r\n$3\r\ndst\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$7\r\nWEIGHTS\r\n$1\r\n2\r\n");
+        let result: Result<ZInterStore, _> = RespArray::decode(&mut buf)?.try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zpop_replies() -> Result<()> {
+        let backend = Backend::new();
+        let pairs = vec![
+            (1.0, b"a".to_vec()),
+            (2.0, b"b".to_vec()),
+            (3.0, b"c".to_vec()),
+        ];
+        backend.zadd(b"z".to_vec(), pairs, ZAddOptions::default())?;
+
+        let cmd = ZPopMin {
+            key: b"z".to_vec(),
+            count: None,
+        };
+        let expected = RespArray::new(vec![BulkString::from("a").into(), RespFrame::Double(1.0)]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = ZMPop {
+            keys: vec![b"missing".to_vec(), b"z".to_vec()],
+            end: ScoreEnd::Max,
+            count: 5,
+        };
+        let pair = |member: &str, score| {
+            RespArray::new(vec![
+                BulkString::from(member).into(),
+                RespFrame::Double(score),
+            ])
+            .into()
+        };
+        let expected = RespArray::new(vec![
+            BulkString::from("z").into(),
+            RespArray::new(vec![pair("c", 3.0), pair("b", 2.0)]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), expected.into());
+
+        let cmd = ZPopMax {
+            key: b"z".to_vec(),
+            count: Some(1),
+        };
+        assert_eq!(cmd.execute(&backend), RespArray::new(vec![]).into());
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use simple_redis::{network, Backend};
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tracing::{info, warn};
 
@@ -12,6 +13,11 @@ async fn main() -> Result<()> {
     let listener = TcpListener::bind(addr).await?;
 
     let backend = Backend::new();
+    tokio::spawn(
+        backend
+            .clone()
+            .run_active_expire(Duration::from_millis(100)),
+    );
     loop {
         let (stream, raddr) = listener.accept().await?;
         info!("Accepted connection from: {}", raddr);
@@ -1,6 +1,6 @@
 use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecode, RespEncode, RespError, RespFrame,
+    cmd::{Command, CommandExecutor, Session},
+    Backend, RespDecode, RespEncode, RespError, RespFrame, SimpleError,
 };
 use anyhow::Result;
 use futures::SinkExt;
@@ -18,25 +18,51 @@ struct RedisRequest {
     backend: Backend,
 }
 
+// most commands reply once, the subscribe family once per channel or pattern
 #[derive(Debug)]
 struct RedisResponse {
-    frame: RespFrame,
+    frames: Vec<RespFrame>,
 }
 
 pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
     // how to get a frame from the stream?
     let mut framed = Framed::new(stream, RespFrameCodec);
+    let mut session = Session::new();
     loop {
-        match framed.next().await {
+        let frame = tokio::select! {
+            biased;
+            // messages of the subscribed channels are sent as they arrive, between replies
+            message = session.message() => match message {
+                Some(message) => {
+                    info!("Sending message: {:?}", message);
+                    framed.send(message).await?;
+                    continue;
+                }
+                None => {
+                    info!("Disconnecting a subscriber too slow to read its messages");
+                    return Ok(());
+                }
+            },
+            frame = framed.next() => frame,
+        };
+        match frame {
             Some(Ok(frame)) => {
                 info!("Received frame: {:?}", frame);
                 let request = RedisRequest {
                     frame,
                     backend: backend.clone(),
                 };
-                let response = request_handler(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                framed.send(response.frame).await?;
+                let response = tokio::select! {
+                    biased;
+                    response = request_handler(request, &mut session) => response?,
+                    // a client gone while blocked drops the command, and with it its waiter
+                    _ = closed(&mut framed) => return Ok(()),
+                };
+                for frame in response.frames {
+                    info!("Sending response: {:?}", frame);
+                    framed.feed(frame).await?;
+                }
+                framed.flush().await?;
             }
             Some(Err(e)) => return Err(e),
             None => return Ok(()),
@@ -44,12 +70,56 @@ pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
     }
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
+async fn request_handler(request: RedisRequest, session: &mut Session) -> Result<RedisResponse> {
     let (frame, backend) = (request.frame, request.backend);
-    let cmd = Command::try_from(frame)?;
+    if let Some(frame) = session.refuse_when_subscribed(&frame) {
+        return Ok(RedisResponse {
+            frames: vec![frame],
+        });
+    }
+    // a malformed command is answered with an error instead of closing the connection
+    let cmd = match Command::try_from(frame) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            let frame = SimpleError::new(format!("ERR {}", e)).into();
+            return Ok(RedisResponse {
+                frames: vec![frame],
+            });
+        }
+    };
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
-    Ok(RedisResponse { frame })
+    let cmd = match cmd.subscribe_with(&backend, session) {
+        Ok(frames) => {
+            let frames = frames.into_iter().map(|f| session.reply(f)).collect();
+            return Ok(RedisResponse { frames });
+        }
+        Err(cmd) => cmd,
+    };
+    let frame = match cmd.into_block(&backend) {
+        Ok(block) => block.wait(&backend).await,
+        Err(cmd) => cmd.execute_with(&backend, session),
+    };
+    Ok(RedisResponse {
+        frames: vec![session.reply(frame)],
+    })
+}
+
+// resolves once the peer closes the connection. Commands pipelined meanwhile are read into
+// the frame buffer, so the end of the stream is still seen behind them, and they are decoded
+// once the current command is done.
+async fn closed(framed: &mut Framed<TcpStream, RespFrameCodec>) {
+    let mut buf = [0; 4096];
+    loop {
+        if framed.get_ref().readable().await.is_err() {
+            return;
+        }
+        match framed.get_ref().try_read(&mut buf) {
+            Ok(0) => return,
+            Ok(n) => framed.read_buffer_mut().extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(_) => return,
+        }
+    }
 }
 
 impl Encoder<RespFrame> for RespFrameCodec {
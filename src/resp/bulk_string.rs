@@ -131,6 +131,12 @@ impl From<String> for BulkString {
     }
 }
 
+impl From<Vec<u8>> for BulkString {
+    fn from(s: Vec<u8>) -> Self {
+        BulkString(Some(s))
+    }
+}
+
 impl From<Option<&[u8]>> for BulkString {
     fn from(s: Option<&[u8]>) -> Self {
         match s {
@@ -20,6 +20,31 @@ impl RespEncode for f64 {
     }
 }
 
+/// The text of a double as a RESP2 bulk string, like the %.17g of redis: the shortest text
+/// reading back the same number, with an exponent for large or small magnitudes.
+pub(crate) fn format_double(n: f64) -> String {
+    if n.is_nan() {
+        "nan".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else if n == 0.0 || (1e-4..1e17).contains(&n.abs()) {
+        n.to_string()
+    } else {
+        let s = format!("{:e}", n);
+        let (mantissa, exponent) = s.split_once('e').unwrap_or((&s, "0"));
+        let exponent: i32 = exponent.parse().unwrap_or_default();
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+    }
+}
+
+/// The text of an INCRBYFLOAT or HINCRBYFLOAT result, stored and replied. Like the %.17Lf of
+/// redis it is plain decimal without trailing zeros, with the fewest digits that read back
+/// the same f64: the stored value is the one computed, no increment is rounded away.
+pub(crate) fn format_incr_float(n: f64) -> String {
+    n.to_string()
+}
+
 // - double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
 impl RespDecode for f64 {
     const PREFIX: &'static str = ",";
@@ -58,6 +83,28 @@ mod tests {
         assert_eq!(&frame.encode(), b",-1.23456e-9\r\n");
     }
 
+    #[test]
+    fn test_format_double() {
+        assert_eq!(format_double(1.5), "1.5");
+        assert_eq!(format_double(-3.0), "-3");
+        assert_eq!(format_double(1e300), "1e+300");
+        assert_eq!(format_double(-1.25e-7), "-1.25e-07");
+        assert_eq!(format_double(f64::INFINITY), "inf");
+        assert_eq!(format_double(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_format_incr_float() {
+        assert_eq!(format_incr_float(10.5), "10.5");
+        assert_eq!(format_incr_float(-3.0), "-3");
+        assert_eq!(format_incr_float(100000000000000.01), "100000000000000.02");
+        assert_eq!(format_incr_float(1.5e-7), "0.00000015");
+        assert_eq!(format_incr_float(1e300), format!("1{}", "0".repeat(300)));
+        for n in [0.1 + 0.2, 1.0 / 3.0, 2f64.sqrt()] {
+            assert_eq!(format_incr_float(n).parse::<f64>(), Ok(n));
+        }
+    }
+
     #[test]
     fn test_double_decode() -> Result<()> {
         let mut buf = BytesMut::new();
@@ -1,6 +1,7 @@
+use super::double::format_double;
 use crate::{
-    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespSet, SimpleError,
-    SimpleString,
+    BulkString, RespArray, RespDecode, RespError, RespMap, RespNull, RespPush, RespSet,
+    SimpleError, SimpleString,
 };
 use bytes::BytesMut;
 use enum_dispatch::enum_dispatch;
@@ -20,6 +21,7 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    Push(RespPush),
 }
 
 impl RespDecode for RespFrame {
@@ -85,6 +87,10 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "expect_length: unknown frame type: {:?}",
@@ -98,6 +104,7 @@ impl RespDecode for RespFrame {
         match iter.peek() {
             Some(b'*') => RespArray::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
             Some(b'$') => BulkString::expect_length(buf),
             Some(b':') => i64::expect_length(buf),
@@ -129,7 +136,85 @@ impl<const N: usize> From<&[u8; N]> for RespFrame {
     }
 }
 
+impl RespFrame {
+    /// The frame as a RESP2 client expects it: RESP3 only types are turned into their RESP2
+    /// counterparts, e.g. a set, a push or a map into an array and null into a null bulk string.
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Array(RespArray(Some(frames))) => RespArray::new(
+                frames
+                    .into_iter()
+                    .map(|f| f.into_resp2())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Set(set) => RespArray::new(
+                set.0
+                    .into_iter()
+                    .map(|f| f.into_resp2())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Push(push) => RespArray::new(
+                push.0
+                    .into_iter()
+                    .map(|f| f.into_resp2())
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Map(map) => {
+                let frames = map
+                    .0
+                    .into_iter()
+                    .flat_map(|(k, v)| [BulkString::from(k).into(), v.into_resp2()])
+                    .collect::<Vec<_>>();
+                RespArray::new(frames).into()
+            }
+            RespFrame::Null(_) => BulkString::new(None).into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Double(n) => BulkString::from(format_double(n)).into(),
+            frame => frame,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // TODO: Add tests
+    use super::*;
+    use crate::RespEncode;
+
+    #[test]
+    fn test_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert("score".to_string(), RespFrame::Double(1.5));
+        let frame: RespFrame = RespArray::new(vec![
+            RespSet::new(vec![RespFrame::Null(RespNull)]).into(),
+            map.into(),
+            true.into(),
+        ])
+        .into();
+
+        let expected: RespFrame = RespArray::new(vec![
+            RespArray::new(vec![BulkString::new(None).into()]).into(),
+            RespArray::new(vec![
+                BulkString::from("score").into(),
+                BulkString::from("1.5").into(),
+            ])
+            .into(),
+            RespFrame::Integer(1),
+        ])
+        .into();
+        assert_eq!(frame.into_resp2(), expected);
+
+        // scores print like redis, not as hundreds of digits
+        let frame: RespFrame = RespArray::new(vec![
+            RespFrame::Double(1e300),
+            RespFrame::Double(f64::INFINITY),
+        ])
+        .into();
+        assert_eq!(
+            frame.into_resp2().encode(),
+            b"*2\r\n$6\r\n1e+300\r\n$3\r\ninf\r\n"
+        );
+    }
 }
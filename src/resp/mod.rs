@@ -6,6 +6,7 @@ mod frame;
 mod integer;
 mod map;
 mod null;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
@@ -18,9 +19,10 @@ const BUF_CAP: usize = 4096;
 const CRLF: &[u8] = b"\r\n";
 const CRLF_LEN: usize = CRLF.len();
 
+pub(crate) use self::double::format_incr_float;
 pub use self::{
     array::RespArray, bulk_string::BulkString, frame::RespFrame, map::RespMap, null::RespNull,
-    set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
+    push::RespPush, set::RespSet, simple_error::SimpleError, simple_string::SimpleString,
 };
 
 #[enum_dispatch]
@@ -116,8 +118,8 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
     let mut total = end + CRLF_LEN;
     let mut data = &buf[total..];
     match prefix {
-        "*" | "~" => {
-            // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
+        "*" | "~" | ">" => {
+            // find nth CRLF in the buffer, for array, set and push, we need to find 1 CRLF for each element
             for _ in 0..len {
                 let len = RespFrame::expect_length(data)?;
                 data = &data[len..];